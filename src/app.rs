@@ -1,9 +1,27 @@
 use std::sync::Arc;
 
-use egui::mutex::Mutex;
+use egui::{mutex::Mutex, Key};
 use nalgebra_glm as glm;
 
-use crate::{camera::Camera, render::Raytracer, scene::Scene, settings::Settings};
+use crate::{
+	camera::{self, Camera, CameraBookmark},
+	eyedropper::Eyedropper,
+	gizmo, guides, hints,
+	hover::{CursorProbe, Hover},
+	inspector::PixelInspector,
+	labels,
+	light_path::LightPaths,
+	mesh,
+	reference::{ReferenceImage, ReferenceSettings},
+	render::Raytracer,
+	render_job::RenderJob,
+	safe_mode::{SafeMode, SafeModeAction},
+	scene::{self, Scene},
+	settings::{RenderSettings, Settings, Status},
+	tutorial::{self, Tutorial},
+	util::Toasts,
+	viewport::ViewportMapping,
+};
 
 pub struct RaytracingApp {
 	pub renderer: Arc<Mutex<Raytracer>>,
@@ -11,6 +29,44 @@ pub struct RaytracingApp {
 
 	default_data: PersistentData,
 	screenshot_time: Option<f32>,
+	toasts: Toasts,
+	hover: Hover,
+	probe: CursorProbe,
+	eyedropper: Eyedropper,
+	inspector: PixelInspector,
+	light_paths: LightPaths,
+	reference: ReferenceImage,
+	render_job: RenderJob,
+	safe_mode: Option<SafeMode>,
+	startup: Option<Startup>,
+	tutorial: Tutorial,
+
+	// read-only, for showing the app to others. the scene and the stored data
+	// can't be edited, only the camera and a few render settings
+	presentation: bool,
+
+	// {{{ save status
+	time: f64,
+	dirty: bool,
+	last_save: Option<f64>,
+
+	// settings from `--set` are stored as they were before, unless the user
+	// saves by hand. autosaves would make them stick to later sessions
+	overridden: crate::overrides::Originals,
+	last_camera: (glm::Vec3, glm::Vec3),
+
+	// whether the window title currently has the unsaved marker
+	#[cfg(not(target_arch = "wasm32"))]
+	title_dirty: bool,
+	// }}}
+
+	// {{{ crash recovery
+	// unsaved data from a crash, offered at launch
+	#[cfg(not(target_arch = "wasm32"))]
+	recovery: Option<PersistentData>,
+	#[cfg(not(target_arch = "wasm32"))]
+	last_snapshot: f64,
+	// }}}
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -18,40 +74,255 @@ pub struct PersistentData {
 	pub settings: Settings,
 	pub camera: Camera,
 	pub scene: Scene,
+	#[serde(default)]
+	pub reference: ReferenceSettings,
+	#[serde(default)]
+	pub bookmarks: Vec<CameraBookmark>,
 }
 
 impl PersistentData {
-	fn new(scr_size: glm::Vec2) -> Self {
+	pub fn new(scr_size: glm::Vec2, gl_renderer: &str) -> Self {
+		let mut settings = Settings::default();
+		settings.render.max_bounces = RenderSettings::default_max_bounces(gl_renderer);
+
 		Self {
-			settings: Settings::default(),
+			settings,
 			camera: Camera::new(scr_size),
 			scene: Scene::default().with_default_scene(),
+			reference: ReferenceSettings::default(),
+			bookmarks: Vec::new(),
+		}
+	}
+
+	// repair stored data before using it, errors mean it can't be used
+	pub fn sanitize(&mut self, default: &Self) -> Result<(), String> {
+		let warnings = self.scene.sanitize()?;
+		self.scene.pending_toasts.extend(warnings);
+		self.settings.sanitize();
+		self.camera.finalize(default.camera.scr_size());
+		if !self.camera.is_valid() {
+			self.camera = default.camera.clone();
+		}
+		self.bookmarks.retain(CameraBookmark::is_valid);
+		Ok(())
+	}
+}
+
+// {{{ edits
+// edits made outside of the windows, by hotkeys and in the viewport
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edit {
+	Select(usize), // a click in the viewport, `usize::MAX` before it's read back
+	LockCamera,
+	Solo,
+	WalkMode,
+	Restart,
+	LookAt,
+	FrameScene,
+	ClearData,
+	Import, // dropped files
+	Gizmo,
+	Eyedropper, // applying a sampled color
+}
+
+// every edit goes through here. presentation mode turns all of them away, a
+// final render the ones that would move the camera or restart the image
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EditGate {
+	pub presentation: bool,
+	pub final_render: bool,
+}
+
+impl EditGate {
+	// `Err` holds what to tell the user, if anything
+	pub fn check(self, edit: Edit) -> Result<(), Option<&'static str>> {
+		if self.presentation {
+			return Err(None);
+		}
+		match edit {
+			Edit::LockCamera | Edit::WalkMode if self.final_render => {
+				Err(Some("The camera is locked during the final render"))
+			},
+			Edit::Select(_) if self.final_render => Err(None),
+			_ => Ok(()),
+		}
+	}
+
+	pub fn allows(self, edit: Edit) -> bool {
+		self.check(edit).is_ok()
+	}
+
+	// makes the edits that only need the data, returns a toast
+	pub fn apply(
+		self,
+		edit: Edit,
+		data: &mut PersistentData,
+	) -> Option<&'static str> {
+		if let Err(text) = self.check(edit) {
+			return text;
+		}
+		match edit {
+			// the selection is uploaded with the scene, so it counts as a scene
+			// change
+			Edit::Select(i) => {
+				if i < data.scene.len() {
+					data.scene.select(i);
+					data.scene.response.changed = true;
+				}
+				None
+			},
+			Edit::LockCamera => {
+				let settings = &mut data.settings;
+				settings.render.lock_camera = !settings.render.lock_camera;
+				settings.response.changed = true;
+				Some(if settings.render.lock_camera {
+					"Camera locked"
+				} else {
+					"Camera unlocked"
+				})
+			},
+			Edit::Solo => {
+				let settings = &mut data.settings;
+				settings.render.solo = !settings.render.solo;
+				settings.response.changed = true;
+				Some(if settings.render.solo {
+					"Soloing the selected object"
+				} else {
+					"Showing all objects"
+				})
+			},
+			Edit::WalkMode => {
+				let controls = &mut data.settings.controls;
+				controls.walk_mode = !controls.walk_mode;
+				Some(if controls.walk_mode {
+					"Walk mode"
+				} else {
+					"Fly mode"
+				})
+			},
+			Edit::Restart => {
+				data.settings.response.restart = true;
+				None
+			},
+			Edit::LookAt => {
+				let i = data.scene.selected()?;
+				if data.settings.render.lock_camera {
+					return Some("Camera is locked");
+				}
+				let target = data.scene.position[i];
+				let clamp_pitch = data.settings.controls.clamp_pitch;
+				data.camera.look_at_point(target, clamp_pitch);
+				None
+			},
+			Edit::FrameScene => {
+				let (center, radius) = crate::geometry::bounding_sphere(&data.scene)?;
+				if data.settings.render.lock_camera {
+					return Some("Camera is locked");
+				}
+				data.camera.frame_sphere(center, radius);
+				None
+			},
+			// these need more than the data, the callers make them after
+			// checking
+			Edit::ClearData | Edit::Import | Edit::Gizmo | Edit::Eyedropper => None,
 		}
 	}
 }
+// }}}
 
 const DATA_KEY: &str = "raytracer_data";
 
+// The stored data is read when the app is created, but only used on the first
+// frame. Until then egui hasn't seen any input, so shift held at launch (which
+// asks for safe mode) can't be told apart from shift not held.
+struct Startup {
+	requested: bool, // safe mode by flag, environment variable or query
+	stored: Option<String>,
+	loaded: Option<PersistentData>,
+	overrides: Vec<String>,
+}
+
+// how often the crash recovery snapshot follows unsaved changes (seconds)
+#[cfg(not(target_arch = "wasm32"))]
+const SNAPSHOT_INTERVAL: f64 = 1.0;
+
 impl RaytracingApp {
 	pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
 		let scr_size = cc.egui_ctx.screen_rect().size();
 		let scr_size = glm::vec2(scr_size.x, scr_size.y);
 
+		// obtain contexts
+		let gl = cc.gl.as_ref().expect("obtaining GL context failed");
+		let egui = &cc.egui_ctx;
+
 		// {{{ initialize persistent data
-		let mut data = PersistentData::new(scr_size);
+		let gl_renderer = crate::render::renderer_name(gl);
+		log::info!("GL renderer: {gl_renderer}");
+
+		let data = PersistentData::new(scr_size, &gl_renderer);
 		let default_data = data.clone();
 
-		if let Some(storage) = cc.storage {
-			if let Some(value) = eframe::get_value(storage, DATA_KEY) {
-				data = value;
-			}
+		// shift held at launch is checked on the first frame
+		#[cfg(not(target_arch = "wasm32"))]
+		let safe_mode_requested = std::env::args().any(|arg| arg == "--safe-mode")
+			|| std::env::var_os("RAYTRACER_SAFE_MODE").is_some_and(|v| v != "0");
+		#[cfg(target_arch = "wasm32")]
+		let safe_mode_requested = cc
+			.integration_info
+			.web_info
+			.location
+			.query_map
+			.get("safe_mode")
+			.is_some_and(|values| values.iter().any(|v| v == "1"));
+
+		#[cfg(not(target_arch = "wasm32"))]
+		let presentation = std::env::args().any(|arg| arg == "--presentation");
+		#[cfg(target_arch = "wasm32")]
+		let presentation = cc
+			.integration_info
+			.web_info
+			.location
+			.query_map
+			.get("presentation")
+			.is_some_and(|values| values.iter().any(|v| v == "1"));
+
+		// pretend the GPU has a small texture size limit, for testing
+		#[cfg(not(target_arch = "wasm32"))]
+		let texture_limit = std::env::args()
+			.find_map(|arg| arg.strip_prefix("--max-texture-size=")?.parse().ok());
+		#[cfg(target_arch = "wasm32")]
+		let texture_limit = cc
+			.integration_info
+			.web_info
+			.location
+			.query_map
+			.get("max_texture_size")
+			.and_then(|values| values.first()?.parse().ok());
+
+		let tutorial = cc
+			.storage
+			.and_then(|storage| eframe::get_value(storage, tutorial::STORAGE_KEY))
+			.unwrap_or_default();
+
+		let startup = Startup {
+			requested: safe_mode_requested,
+			stored: cc.storage.and_then(|storage| storage.get_string(DATA_KEY)),
+			loaded: cc
+				.storage
+				.and_then(|storage| eframe::get_value(storage, DATA_KEY)),
+			// std::env::args is empty on the web, so this is native only
+			overrides: crate::overrides::from_args(),
+		};
+
+		// checked against the defaults right away, so that a typo fails before
+		// anything is shown
+		let mut settings = data.settings.clone();
+		if let Err(e) = crate::overrides::apply(&mut settings, &startup.overrides) {
+			eprintln!("invalid --set: {e}");
+			std::process::exit(2);
 		}
 		// }}}
 
-		// obtain contexts
-		let gl = cc.gl.as_ref().expect("obtaining GL context failed");
-		let egui = &cc.egui_ctx;
-
 		// {{{ reduce window shadow size
 		egui.set_visuals(egui::Visuals {
 			window_shadow: egui::epaint::Shadow {
@@ -64,43 +335,286 @@ impl RaytracingApp {
 		});
 		// }}}
 
-		// reset window positions
-		egui.memory_mut(|mem| mem.reset_areas());
+		// window positions and sizes are kept in egui's memory, which eframe
+		// stores along with the data. windows are constrained to the screen, so
+		// one that was left off-screen on a larger monitor comes back into view
+
+		let last_camera = (data.camera.pos, data.camera.forward_dir);
 
 		Self {
 			renderer: Arc::new(Mutex::new(Raytracer::new(
 				gl,
 				&data.camera, // needed to initialize ray directions texture
 				scr_size,
+				texture_limit,
 			))),
 			data: Arc::new(Mutex::new(data)),
 			default_data,
 			screenshot_time: None,
+			toasts: Toasts::default(),
+			hover: Hover::default(),
+			probe: CursorProbe::default(),
+			eyedropper: Eyedropper::default(),
+			inspector: PixelInspector::default(),
+			light_paths: LightPaths::default(),
+			reference: ReferenceImage::default(),
+			render_job: RenderJob::default(),
+			safe_mode: None,
+			startup: Some(startup),
+			tutorial,
+			presentation,
+			time: 0.0,
+			dirty: false,
+			last_save: None,
+			overridden: Default::default(),
+			last_camera,
+			#[cfg(not(target_arch = "wasm32"))]
+			title_dirty: false,
+			#[cfg(not(target_arch = "wasm32"))]
+			recovery: None,
+			#[cfg(not(target_arch = "wasm32"))]
+			last_snapshot: 0.0,
+		}
+	}
+
+	// loads the stored data on the first frame, or starts in safe mode
+	fn finish_startup(&mut self, egui: &egui::Context) {
+		let Some(Startup {
+			requested,
+			stored,
+			loaded,
+			overrides,
+		}) = self.startup.take()
+		else {
+			return;
+		};
+		let default_data = &self.default_data;
+		let mut data = default_data.clone();
+
+		if requested || egui.input(|i| i.modifiers.shift) {
+			self.safe_mode = Some(SafeMode::new("Requested at launch", stored, loaded));
+		} else if let Some(mut value) = loaded.clone() {
+			match value.sanitize(default_data) {
+				Ok(()) => data = value,
+				Err(e) => {
+					log::warn!("stored data is unusable: {e}");
+					self.safe_mode = Some(SafeMode::new(
+						format!("The stored data is unusable ({e})"),
+						stored,
+						loaded,
+					));
+				},
+			}
+		} else if stored.is_some() {
+			self.safe_mode = Some(SafeMode::new(
+				"The stored data could not be read",
+				stored,
+				None,
+			));
+		}
+
+		// not offered while the stored data is in question or can't be changed
+		#[cfg(not(target_arch = "wasm32"))]
+		if self.safe_mode.is_none() && !self.presentation {
+			self.recovery = crate::crash::find_recovery().and_then(|text| {
+				let recovered = serde_json::from_str::<PersistentData>(&text)
+					.map_err(|e| e.to_string())
+					.and_then(|mut value| value.sanitize(default_data).map(|()| value));
+				if let Err(e) = &recovered {
+					log::warn!("crash recovery data is unusable: {e}");
+					crate::crash::discard_recovery();
+				}
+				recovered.ok()
+			});
+		}
+
+		// already checked against the defaults in `new`
+		match crate::overrides::apply(&mut data.settings, &overrides) {
+			Ok(originals) => self.overridden = originals,
+			Err(e) => {
+				eprintln!("invalid --set: {e}");
+				std::process::exit(2);
+			},
+		}
+
+		self.last_camera = (data.camera.pos, data.camera.forward_dir);
+		*self.data.lock() = data;
+		self.renderer.lock().force_scr_size = true;
+	}
+}
+
+impl RaytracingApp {
+	fn gate(&self) -> EditGate {
+		EditGate {
+			presentation: self.presentation,
+			final_render: self.render_job.running(),
 		}
 	}
 }
 
 impl eframe::App for RaytracingApp {
+	// both the manual save button and eframe's periodic autosave end up here
 	fn save(&mut self, storage: &mut dyn eframe::Storage) {
-		eframe::set_value(storage, DATA_KEY, &*self.data.lock());
+		// nothing was loaded yet, the data is still the defaults
+		if self.startup.is_some() {
+			return;
+		}
+
+		// not part of the data, it's kept even while that isn't stored
+		eframe::set_value(storage, tutorial::STORAGE_KEY, &self.tutorial);
+
+		// the stored data is only replaced once the user decides what to do
+		if self.safe_mode.is_some() {
+			return;
+		}
+
+		// a running final render and `--set` override a few settings, they're
+		// stored as they were before
+		let mut data = self.data.lock().clone();
+		self.render_job.unapply(&mut data.settings.render);
+		data.settings = self.overridden.restore(&data.settings);
+		eframe::set_value(storage, DATA_KEY, &data);
+		self.dirty = false;
+		self.last_save = Some(self.time);
+		#[cfg(not(target_arch = "wasm32"))]
+		crate::crash::clear_snapshot();
 	}
 
 	fn update(&mut self, egui: &egui::Context, frame: &mut eframe::Frame) {
+		self.finish_startup(egui);
+		self.time = egui.input(|i| i.time);
 		let mut data = self.data.lock();
 
 		// {{{ draw windows
 		// draw settings window
-		let frame_index = self.renderer.lock().frame_index;
-		if self.screenshot_time.is_none() {
-			data.settings.window(egui, frame_index);
+		let (frame_index, render_limit, uploads) = {
+			let renderer = self.renderer.lock();
+			let size = renderer.mapping.texture_size();
+			let limit = renderer
+				.mapping
+				.limited()
+				.then_some(([size.x as u32, size.y as u32], renderer.max_texture_size));
+			(renderer.frame_index, limit, renderer.stats.clone())
+		};
+		if self.screenshot_time.is_none() && self.presentation {
+			data.settings.presentation_window(egui);
+		} else if self.screenshot_time.is_none() {
+			let hovered = self.probe.hovered_name(&data).map(str::to_owned);
+			let turntable = data.scene.turntable_running();
+			data.settings.window(
+				egui,
+				Status {
+					frame_index,
+					render_limit,
+					hovered: hovered.as_deref(),
+					unsaved: self.dirty,
+					since_save: self.last_save.map(|t| self.time - t),
+					turntable,
+					uploads: &uploads,
+				},
+			);
 		}
 		let settings_response = data.settings.response;
 
 		// draw scene window
-		if self.screenshot_time.is_none() {
+		if self.screenshot_time.is_none() && !self.presentation {
 			data.scene.window(egui);
 		}
+		let camera_response = if self.screenshot_time.is_none() && !self.presentation {
+			camera::window(egui, &mut data)
+		} else {
+			camera::WindowResponse::default()
+		};
+		// the camera is tracked on its own, bookmarks aren't
+		if camera_response.bookmarks_changed {
+			self.dirty = true;
+		}
+
+		// draw eyedropper result
+		if let Some(color) = self.renderer.lock().sampled_color.take() {
+			self.eyedropper.set_color(color);
+		}
+		if self.screenshot_time.is_none() {
+			let editable = self.gate().allows(Edit::Eyedropper);
+			self.eyedropper.window(egui, &mut data, editable);
+			self.light_paths.window(egui, &data);
+		}
+		if let Some(text) = self.reference.update(egui, &mut data.reference) {
+			self.toasts.add(text);
+		}
+		#[cfg(not(target_arch = "wasm32"))]
+		if camera_response.open_reference {
+			if let Some(text) = self.reference.pick(egui, &mut data.reference) {
+				self.toasts.add(text);
+			}
+		}
+		if self.gate().allows(Edit::Import) {
+			for text in mesh::import_dropped(egui, &mut data.scene) {
+				self.toasts.add(text);
+			}
+			scene::import::import_dropped(egui, &mut data.scene);
+		}
+		if self.screenshot_time.is_none() {
+			self.reference.window(egui, &mut data.reference);
+		}
 		let scene_response = data.scene.response;
+		for text in data.scene.pending_toasts.drain(..) {
+			self.toasts.add(text);
+		}
+		// }}}
+
+		// spin turntables, this marks the objects as moved. a final render
+		// holds everything still, or it would never collect its samples
+		let job_running = self.render_job.running();
+		if !job_running {
+			let dt = egui.input(|i| i.unstable_dt);
+			data.scene.advance_turntables(dt);
+		}
+
+		// {{{ keyboard shortcuts
+		if settings_response.presentation
+			|| (!egui.wants_keyboard_input() && egui.input(|i| i.key_pressed(Key::P)))
+		{
+			self.presentation = !self.presentation;
+			self.toasts.add(if self.presentation {
+				"Presentation mode, press P to exit"
+			} else {
+				"Left presentation mode"
+			});
+		}
+
+		// don't respond to keypresses if text is focused
+		let gate = self.gate();
+		let restart_key = data.settings.controls.restart_key;
+		let keys = [
+			(Key::L, Edit::LockCamera),
+			(Key::I, Edit::Solo),
+			(Key::F, Edit::WalkMode),
+			(restart_key, Edit::Restart),
+			(Key::C, Edit::LookAt),
+		];
+		let mut edits = Vec::new();
+		if !egui.wants_keyboard_input() {
+			egui.input(|i| {
+				edits.extend(
+					keys
+						.iter()
+						.filter(|(key, _)| i.key_pressed(*key))
+						.map(|&(_, edit)| edit),
+				);
+			});
+		}
+		if scene_response.look_at && !edits.contains(&Edit::LookAt) {
+			edits.push(Edit::LookAt);
+		}
+		if scene_response.frame_scene {
+			edits.push(Edit::FrameScene);
+		}
+		for edit in edits {
+			if let Some(text) = gate.apply(edit, &mut data) {
+				self.toasts.add(text);
+			}
+		}
 		// }}}
 
 		// {{{ respond
@@ -108,11 +622,65 @@ impl eframe::App for RaytracingApp {
 		if settings_response.screenshot {
 			self.screenshot_time = Some(0.0);
 		}
+		if settings_response.tutorial {
+			self.tutorial.restart();
+		}
+		if settings_response.reset_stats {
+			self.renderer.lock().stats.reset();
+		}
+		if settings_response.reset_layout {
+			egui.memory_mut(|mem| mem.reset_areas());
+			self.toasts.add("Reset the window layout");
+		}
+
+		// {{{ final render
+		if settings_response.final_render {
+			self.render_job.open();
+		}
+		let max_texture_size = self.renderer.lock().max_texture_size;
+		if let Some(text) = self.render_job.window(
+			egui,
+			&mut data,
+			max_texture_size,
+			frame_index,
+			self.time,
+		) {
+			self.toasts.add(text);
+		}
+		if let Some(readback) = self.renderer.lock().exported.take() {
+			if let Some(text) = self.render_job.finish(readback, &mut data) {
+				self.toasts.add(text);
+			}
+		}
+		let capture = egui.input(|i| {
+			i.events.iter().find_map(|e| match e {
+				egui::Event::Screenshot { image, .. } => Some(image.clone()),
+				_ => None,
+			})
+		});
+		if let Some(image) = capture {
+			if let Some(text) = self.render_job.finish_capture(&image, &mut data) {
+				self.toasts.add(text);
+			}
+		}
+		let job = self.render_job.update(&mut data, frame_index, self.time);
+		if job.reset {
+			self.renderer.lock().force_scr_size = true;
+		}
+		// taken after the next frame is painted, see render_job.rs
+		if job.capture {
+			egui.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+		}
+		if let Some(text) = job.message {
+			self.toasts.add(text);
+		}
+		// }}}
 
 		// clear data if requested
-		if settings_response.clear_data {
+		if settings_response.clear_data && gate.allows(Edit::ClearData) {
 			*data = self.default_data.clone();
 			self.renderer.lock().force_scr_size = true;
+			self.dirty = true;
 		}
 
 		// fixes error with simultaneous mutable borrow of self field
@@ -120,17 +688,235 @@ impl eframe::App for RaytracingApp {
 
 		// save data if requested
 		if settings_response.save_data {
+			if self.safe_mode.is_some() {
+				self.toasts.add("Saving is paused in safe mode");
+			} else {
+				// saving by hand keeps the overrides from then on
+				self.overridden = Default::default();
+				self.save(frame.storage_mut().unwrap());
+			}
+		}
+
+		// {{{ safe mode
+		let action = match self.safe_mode.as_mut() {
+			Some(safe_mode) if self.screenshot_time.is_none() => {
+				safe_mode.banner(egui, &self.default_data)
+			},
+			_ => None,
+		};
+
+		if let Some(action) = action {
+			if let SafeModeAction::Load(data) = action {
+				*self.data.lock() = *data;
+				self.renderer.lock().force_scr_size = true;
+			}
+
+			// both choices replace the stored data right away
+			self.safe_mode = None;
 			self.save(frame.storage_mut().unwrap());
+			self.toasts.add("Left safe mode");
+		}
+		// }}}
+
+		// {{{ crash recovery
+		#[cfg(not(target_arch = "wasm32"))]
+		if self.recovery.is_some() {
+			let mut open = true;
+			let mut restore = false;
+			crate::util::modal(
+				egui,
+				"Recover unsaved changes?",
+				&mut open,
+				|ui| {
+					ui.label("The app didn't close normally last time.");
+					ui.label("Confirm restores the changes that weren't saved.");
+				},
+				crate::util::empty_ui,
+				|| restore = true,
+			);
+
+			if restore {
+				*self.data.lock() = self.recovery.take().unwrap();
+				self.renderer.lock().force_scr_size = true;
+				self.dirty = true;
+				self.toasts.add("Restored unsaved changes");
+			}
+			if !open {
+				self.recovery = None;
+				crate::crash::discard_recovery();
+			}
 		}
 		// }}}
+		// }}}
 
 		// main painting
 		egui::CentralPanel::default().show(egui, |ui| {
-			self.paint(ui, settings_response.focused || scene_response.focused);
+			// everything that maps the pointer goes through the letterboxed
+			// region, the bars around it aren't rendered
+			let bounds = ui.clip_rect();
+			let (aspect, guides, stereo) = {
+				let render = &self.data.lock().settings.render;
+				(
+					render.aspect.ratio(),
+					render.guides,
+					render.stereo_rig().is_some(),
+				)
+			};
+			// a final render has its own size, which was checked against the
+			// limit when it was set
+			let pixels_per_point = egui.pixels_per_point();
+			let mapping = match job.size {
+				Some([w, h]) => ViewportMapping::letterboxed(
+					bounds,
+					Some(w as f32 / h as f32),
+					pixels_per_point,
+				)
+				.with_texture_size([w, h]),
+				None => ViewportMapping::letterboxed(bounds, aspect, pixels_per_point)
+					.with_texture_limit(max_texture_size),
+			}
+			.with_stereo(stereo);
+			{
+				let mut renderer = self.renderer.lock();
+				renderer.mapping = mapping;
+				renderer.exporting = self.screenshot_time.is_some();
+			}
+
+			let sample_region = self.eyedropper.update(ui, &mapping);
+			// text fields in popups and menus don't report focus through the
+			// responses, don't move the camera while typing into them either
+			let ui_focused = settings_response.focused
+				|| scene_response.focused
+				|| camera_response.focused
+				|| egui.wants_keyboard_input();
+			if let Some(readback) = self.renderer.lock().inspected.take() {
+				self.inspector.set(readback);
+			}
+			let inspect_texel = if self.screenshot_time.is_none() {
+				self.inspector.update(ui, &mapping, &self.data.lock())
+			} else {
+				None
+			};
+			// a click would restart a render job that started this frame too
+			let gate = EditGate {
+				final_render: job.size.is_some(),
+				..self.gate()
+			};
+			self.paint(
+				ui,
+				ui_focused,
+				sample_region,
+				inspect_texel,
+				job.read_back,
+				gate,
+			);
+			// after painting, which picks the noise seed for this frame
+			let seed = self.renderer.lock().noise_seed;
+			self
+				.light_paths
+				.update(ui, &mapping, &self.data.lock(), seed);
+			self.hover.update(ui, &mapping, &self.data.lock());
+			self.probe.update(ui, &mapping, &self.data.lock());
+			// left out of screenshots, it's only for matching the render
+			if self.screenshot_time.is_none() {
+				self
+					.reference
+					.draw(ui, &mapping, &self.data.lock().reference);
+				self.light_paths.draw(ui, &mapping, &self.data.lock());
+			}
+			guides::letterbox(ui, bounds, &mapping);
+			if self.screenshot_time.is_none() {
+				guides::draw(ui, &mapping, guides);
+			}
+			{
+				let data = self.data.lock();
+				let render = &data.settings.render;
+				if render.labels
+					&& (self.screenshot_time.is_none() || render.labels_in_screenshots)
+				{
+					labels::draw(ui, &mapping, &data);
+				}
+			}
+			if self.screenshot_time.is_none() && !self.presentation {
+				hints::draw(ui, &mapping, &self.data.lock());
+			}
+			if self.screenshot_time.is_none() && self.gate().allows(Edit::Gizmo) {
+				gizmo::scale_gizmo(ui, &mapping, &mut self.data.lock());
+			}
 		});
 
-		// request repaint so our path tracing continues sampling without activity
-		egui.request_repaint_of(egui.viewport_id());
+		self.toasts.show(egui, self.screenshot_time.is_none());
+
+		// {{{ tutorial
+		// after painting too, the camera only moves while the frame is painted.
+		// it waits for final renders, which lock the camera
+		if self.screenshot_time.is_none()
+			&& !self.presentation
+			&& !self.render_job.running()
+			&& self.tutorial.active()
+		{
+			let data = self.data.lock();
+			let view = self.renderer.lock().mapping.rect;
+			let (scene, settings) = (data.scene.response, data.settings.response);
+			if let Some(text) = self.tutorial.observe(&data, scene, settings) {
+				self.toasts.add(text);
+			}
+			self.tutorial.draw(egui, &data, view);
+		}
+		// }}}
+
+		// {{{ track unsaved changes
+		// checked after painting so that edits made in the viewport (gizmo,
+		// camera movement) are picked up too
+		{
+			let data = self.data.lock();
+			let camera = (data.camera.pos, data.camera.forward_dir);
+			if data.settings.response.changed
+				|| data.scene.response.changed_or_moved()
+				|| camera != self.last_camera
+			{
+				self.dirty = true;
+			}
+			self.last_camera = camera;
+
+			// kept for the panic hook, undoing a running final render's and
+			// `--set` overrides like saving does
+			#[cfg(not(target_arch = "wasm32"))]
+			if self.dirty
+				&& self.safe_mode.is_none()
+				&& self.time - self.last_snapshot >= SNAPSHOT_INTERVAL
+			{
+				let mut data = data.clone();
+				self.render_job.unapply(&mut data.settings.render);
+				data.settings = self.overridden.restore(&data.settings);
+				crate::crash::update_snapshot(&data);
+				self.last_snapshot = self.time;
+			}
+		}
+
+		// the web version only has the indicator in the settings window
+		#[cfg(not(target_arch = "wasm32"))]
+		if self.dirty != self.title_dirty {
+			self.title_dirty = self.dirty;
+			let title = if self.dirty {
+				"● Raytracer"
+			} else {
+				"Raytracer"
+			};
+			egui.send_viewport_cmd(egui::ViewportCommand::Title(title.to_owned()));
+		}
+		// }}}
+
+		// request repaint so our path tracing continues sampling without activity.
+		// a paused or converged image only needs the interface redrawn, which
+		// input does by itself, unless something is counting down
+		let idle = self.data.lock().settings.render.idle(frame_index);
+		let counting = self.toasts.active()
+			|| self.screenshot_time.is_some()
+			|| self.render_job.running();
+		if !idle || counting {
+			egui.request_repaint_of(egui.viewport_id());
+		}
 
 		// count up to 5 seconds. when 5 seconds are over, windows are shown again
 		let dt = egui.input(|i| i.unstable_dt);
@@ -148,3 +934,85 @@ impl eframe::App for RaytracingApp {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::Reset;
+
+	const EDITS: [Edit; 7] = [
+		Edit::Select(0),
+		Edit::LockCamera,
+		Edit::Solo,
+		Edit::WalkMode,
+		Edit::Restart,
+		Edit::LookAt,
+		Edit::FrameScene,
+	];
+
+	// a second object to select, looked at from somewhere else
+	fn data() -> PersistentData {
+		let mut data = PersistentData::new(glm::vec2(64.0, 64.0), "test");
+		data.scene.new_object();
+		data.scene.position[1] = glm::vec3(5.0, 1.0, -3.0);
+		data.scene.select(1);
+		data.scene.response.reset();
+		data.settings.response.reset();
+		data
+	}
+
+	// everything that's stored, and the flags that make the renderer restart
+	fn state(data: &PersistentData) -> (String, bool, bool, bool) {
+		(
+			serde_json::to_string(data).unwrap(),
+			data.scene.response.changed_or_moved(),
+			data.settings.response.changed,
+			data.settings.response.restart,
+		)
+	}
+
+	#[test]
+	fn presentation_mode_leaves_the_data_unchanged() {
+		let gate = EditGate {
+			presentation: true,
+			final_render: false,
+		};
+		let mut data = data();
+		let before = state(&data);
+		for edit in EDITS {
+			assert!(!gate.allows(edit), "{edit:?}");
+			assert_eq!(gate.apply(edit, &mut data), None, "{edit:?}");
+			assert_eq!(state(&data), before, "{edit:?}");
+		}
+		for edit in [Edit::ClearData, Edit::Import, Edit::Gizmo, Edit::Eyedropper] {
+			assert!(!gate.allows(edit), "{edit:?}");
+		}
+	}
+
+	#[test]
+	fn edits_go_through_outside_of_presentation_mode() {
+		let gate = EditGate::default();
+		for edit in EDITS {
+			let mut data = data();
+			let before = state(&data);
+			assert!(gate.allows(edit), "{edit:?}");
+			gate.apply(edit, &mut data);
+			assert_ne!(state(&data), before, "{edit:?}");
+		}
+	}
+
+	#[test]
+	fn final_renders_keep_the_camera_and_selection() {
+		let gate = EditGate {
+			presentation: false,
+			final_render: true,
+		};
+		let mut data = data();
+		let before = state(&data);
+		for edit in [Edit::Select(0), Edit::LockCamera, Edit::WalkMode] {
+			gate.apply(edit, &mut data);
+			assert_eq!(state(&data), before, "{edit:?}");
+		}
+		assert!(gate.allows(Edit::Restart));
+	}
+}
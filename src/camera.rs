@@ -1,9 +1,19 @@
+use std::f32::consts::{PI, TAU};
+
 use egui::Key;
 use nalgebra_glm::{
 	self as glm, inverse, look_at, perspective_fov, quat_angle_axis, Mat4, Vec2,
 	Vec3,
 };
 
+use crate::{
+	app::PersistentData,
+	controls::{self, angle_drag, position_drag},
+	geometry::{self, Ray},
+	scene::Scene,
+	settings::{ControlSettings, WorldSettings},
+};
+
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Camera {
 	vertical_fov: f32,
@@ -13,13 +23,100 @@ pub struct Camera {
 	pub pos: Vec3,
 	pub forward_dir: Vec3,
 
+	// thin lens, set from the render settings. an aperture of zero is a
+	// pinhole camera with everything in focus
+	#[serde(default)]
+	pub aperture: f32, // lens radius in world units
+	#[serde(default = "default_focal_distance")]
+	pub focal_distance: f32, // from the eye to the plane that is in focus
+
+	// derived from the fields above, not stored so that they can't go stale.
+	// `finalize` has to be called after deserializing
+	#[serde(skip)]
 	proj: Mat4,
+	#[serde(skip)]
 	pub inv_proj: Mat4,
+	#[serde(skip)]
 	view: Mat4,
+	#[serde(skip)]
 	pub inv_view: Mat4,
 
+	// set from the render settings like the fov
+	#[serde(skip)]
+	projection: Projection,
+	#[serde(skip)]
+	stereo: Option<Stereo>,
+
+	#[serde(skip)]
 	scr_size: Vec2,
+	#[serde(skip)]
 	pub recalculate_ray_dirs: bool, // actual calculation is offloaded
+
+	// handheld wobble, applied on top of pos and forward_dir without
+	// changing them
+	#[serde(skip)]
+	shake_time: f32,
+	#[serde(skip)]
+	shake: Shake,
+}
+
+// how pixels map to ray directions. the panorama covers the whole sphere,
+// longitude across and latitude up, and ignores the fov
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[repr(u32)]
+pub enum Projection {
+	#[default]
+	Perspective = 0,
+	Panorama = 1,
+}
+
+impl std::fmt::Display for Projection {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Perspective => write!(f, "Perspective"),
+			Self::Panorama => write!(f, "360° panorama"),
+		}
+	}
+}
+
+// Two views side by side, each half as wide as the screen. The left half is
+// seen from an eye half the separation to the left of the camera, the right
+// half from the other side. Both eyes look at the same points on a plane the
+// convergence distance in front of the camera, so that plane has no parallax.
+// A separation of 0 gives the same view twice. Everything that maps the screen
+// onto the scene goes through the left eye.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stereo {
+	pub separation: f32,
+	pub convergence: f32,
+}
+
+// a named viewpoint to come back to
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct CameraBookmark {
+	pub name: String,
+	pub pos: Vec3,
+	pub forward_dir: Vec3,
+	pub vertical_fov: f32,
+}
+
+impl CameraBookmark {
+	// false for bookmarks from corrupt saves
+	pub fn is_valid(&self) -> bool {
+		self.pos.iter().all(|x| x.is_finite())
+			&& self.forward_dir.iter().all(|x| x.is_finite())
+			&& self.forward_dir.norm() > f32::EPSILON
+			&& controls::fov_range().contains(&self.vertical_fov)
+	}
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Shake {
+	offset: Vec3,
+	yaw: f32,
+	pitch: f32,
 }
 
 const UP_DIR: Vec3 = Vec3::new(0.0, 1.0, 0.0);
@@ -29,7 +126,20 @@ const BASE_ROT_SPEED: f32 = 0.005;
 const DEFAULT_POS: Vec3 = Vec3::new(0.0, 0.0, 3.0);
 const DEFAULT_FORWARD_DIR: Vec3 = Vec3::new(0.0, 0.0, -1.0);
 
+const MAX_PITCH_DEG: f32 = 80.0;
+
+// how close the camera gets to surfaces with collision enabled
+const COLLISION_RADIUS: f32 = 0.2;
+
+// rotation of the wobble (radians) per world unit of amplitude
+const SHAKE_ROT_PER_UNIT: f32 = 0.5;
+
 pub const DEFAULT_FOV_DEG: f32 = 70.0_f32;
+pub const DEFAULT_FOCAL_DISTANCE: f32 = 3.0; // the origin, seen from DEFAULT_POS
+
+fn default_focal_distance() -> f32 {
+	DEFAULT_FOCAL_DISTANCE
+}
 
 impl Camera {
 	pub fn new(scr_size: Vec2) -> Self {
@@ -57,29 +167,72 @@ impl Camera {
 			pos,
 			forward_dir,
 
+			aperture: 0.0,
+			focal_distance: DEFAULT_FOCAL_DISTANCE,
+
 			proj,
 			inv_proj: inverse(&proj),
 			view,
 			inv_view: inverse(&view),
 
+			projection: Projection::default(),
+			stereo: None,
+
 			scr_size,
 			recalculate_ray_dirs: false,
+
+			shake_time: 0.0,
+			shake: Shake::default(),
 		}
 	}
 
+	// recompute the derived state of a deserialized camera
+	pub fn finalize(&mut self, scr_size: Vec2) {
+		self.scr_size = scr_size;
+		self.recalc_proj();
+		self.recalc_view();
+	}
+
+	pub fn scr_size(&self) -> Vec2 {
+		self.scr_size
+	}
+
 	// return: whether the camera is moving
-	pub fn update(&mut self, input: egui::InputState) -> bool {
+	// the scene and world are only needed for collision
+	pub fn update(
+		&mut self,
+		input: egui::InputState,
+		controls: ControlSettings,
+		scene: &Scene,
+		world: &WorldSettings,
+	) -> bool {
 		if input.key_pressed(Key::R) {
 			self.pos = DEFAULT_POS;
 			self.forward_dir = DEFAULT_FORWARD_DIR;
+			if controls.walk_mode {
+				self.pos.y = controls.eye_height;
+			}
 			self.recalc_view();
 			return true;
 		}
 
 		let mut moving = false;
+		let start = self.pos;
 		let dt = input.unstable_dt;
 		let right_dir = glm::cross(&self.forward_dir, &UP_DIR);
 
+		// walking moves along the ground, even when looking up or down
+		let move_dir = if controls.walk_mode {
+			let horizontal = glm::vec3(self.forward_dir.x, 0.0, self.forward_dir.z);
+			if horizontal.norm() > f32::EPSILON {
+				glm::normalize(&horizontal)
+			} else {
+				Vec3::zeros()
+			}
+		} else {
+			self.forward_dir
+		};
+
 		let mut speed = BASE_SPEED;
 		let mut rot_speed = BASE_ROT_SPEED;
 
@@ -89,10 +242,10 @@ impl Camera {
 		}
 
 		if input.key_down(Key::W) {
-			self.pos += self.forward_dir * speed * dt;
+			self.pos += move_dir * speed * dt;
 			moving = true;
 		} else if input.key_down(Key::S) {
-			self.pos -= self.forward_dir * speed * dt;
+			self.pos -= move_dir * speed * dt;
 			moving = true;
 		}
 
@@ -104,7 +257,13 @@ impl Camera {
 			moving = true;
 		}
 
-		if input.key_down(Key::Q) {
+		if controls.walk_mode {
+			// also snaps to the eye height when switching to walk mode
+			if self.pos.y != controls.eye_height {
+				self.pos.y = controls.eye_height;
+				moving = true;
+			}
+		} else if input.key_down(Key::Q) {
 			self.pos -= UP_DIR * speed * dt;
 			moving = true;
 		} else if input.key_down(Key::E) {
@@ -121,10 +280,33 @@ impl Camera {
 			));
 
 			self.forward_dir = glm::quat_rotate_vec3(&q, &self.forward_dir);
+			if controls.clamp_pitch {
+				self.forward_dir = clamp_pitch(self.forward_dir);
+			}
 
 			moving = true;
 		}
 
+		if controls.collision && self.pos != start {
+			self.pos = geometry::collide_movement(
+				scene,
+				world,
+				start,
+				self.pos - start,
+				COLLISION_RADIUS,
+			);
+		}
+
+		// the wobble counts as movement so that accumulation restarts
+		if controls.shake {
+			self.shake_time += dt;
+			self.shake = Shake::sample(self.shake_time, controls);
+			moving = true;
+		} else if self.shake != Shake::default() {
+			self.shake = Shake::default();
+			moving = true;
+		}
+
 		if moving {
 			self.recalc_view();
 		}
@@ -132,6 +314,224 @@ impl Camera {
 		moving
 	}
 
+	// position the image is rendered from, including the wobble
+	pub fn eye(&self) -> Vec3 {
+		self.pos + self.shake.offset
+	}
+
+	// ray through a point on the screen, in normalized device coordinates
+	// (same calculation as the ray directions prepass)
+	pub fn ray_through(&self, ndc: Vec2) -> Ray {
+		let mut origin = Vec3::zeros(); // in view space, the eye
+		let mut dir = match self.projection {
+			Projection::Perspective => {
+				let target = self.inv_proj * glm::vec4(ndc.x, ndc.y, 1.0, 1.0);
+				glm::normalize(&(target.xyz() / target.w))
+			},
+			Projection::Panorama => {
+				let (lon, lat) = (ndc.x * PI, ndc.y * PI / 2.0);
+				glm::vec3(lat.cos() * lon.sin(), lat.sin(), -lat.cos() * lon.cos())
+			},
+		};
+		if let Some(stereo) = self.stereo {
+			origin.x = -stereo.separation / 2.0;
+			let target = dir * (stereo.convergence / -dir.z);
+			dir = glm::normalize(&(target - origin));
+		}
+
+		let origin =
+			(self.inv_view * glm::vec4(origin.x, origin.y, origin.z, 1.0)).xyz();
+		let dir = (self.inv_view * glm::vec4(dir.x, dir.y, dir.z, 0.0)).xyz();
+		Ray::new(origin, dir)
+	}
+
+	// world space directions to the right of and above the view, the lens
+	// lies in the plane they span
+	pub fn right_up(&self) -> (Vec3, Vec3) {
+		let right = self.inv_view.column(0).xyz();
+		let up = self.inv_view.column(1).xyz();
+		(right, up)
+	}
+
+	// same as lens_ray in fsh.glsl, `u` is a pair of random numbers
+	pub fn lens_ray(&self, ray: Ray, u: (f32, f32)) -> Ray {
+		let (right, up) = self.right_up();
+		let forward = glm::cross(&up, &right);
+		let focus = ray.at(self.focal_distance / glm::dot(&ray.dir, &forward));
+
+		let angle = std::f32::consts::TAU * u.1;
+		let radius = u.0.sqrt() * self.aperture;
+		let origin =
+			ray.origin + right * (angle.cos() * radius) + up * (angle.sin() * radius);
+		Ray::new(origin, glm::normalize(&(focus - origin)))
+	}
+
+	// world space point -> normalized device coordinates
+	// (none if the point is behind the camera)
+	pub fn project(&self, p: Vec3) -> Option<Vec2> {
+		// nothing is behind a panorama, the inverse of ray_through
+		if self.projection == Projection::Panorama {
+			let v = (self.view * glm::vec4(p.x, p.y, p.z, 1.0)).xyz();
+			if v.norm() <= f32::EPSILON {
+				return None;
+			}
+			let lon = v.x.atan2(-v.z);
+			let lat = (v.y / v.norm()).clamp(-1.0, 1.0).asin();
+			return Some(glm::vec2(lon / PI, lat / (PI / 2.0)));
+		}
+
+		// for the left eye that's where the line from it through the point
+		// meets the convergence plane, as seen from the camera
+		let mut v = self.view * glm::vec4(p.x, p.y, p.z, 1.0);
+		if let Some(stereo) = self.stereo {
+			if v.z >= 0.0 {
+				return None;
+			}
+			let eye = -stereo.separation / 2.0;
+			let t = stereo.convergence / -v.z;
+			v = glm::vec4(eye + (v.x - eye) * t, v.y * t, -stereo.convergence, 1.0);
+		}
+
+		let clip = self.proj * v;
+		if clip.w <= 0.0 {
+			return None;
+		}
+		Some(clip.xy() / clip.w)
+	}
+
+	// false for cameras that can't produce an image, e.g. from corrupt saves
+	pub fn is_valid(&self) -> bool {
+		let finite = |m: &[f32]| m.iter().all(|x| x.is_finite());
+		finite(self.pos.as_slice())
+			&& finite(self.forward_dir.as_slice())
+			&& self.forward_dir.norm() > f32::EPSILON
+			&& finite(self.inv_proj.as_slice())
+			&& finite(self.inv_view.as_slice())
+	}
+
+	pub fn far_clip(&self) -> f32 {
+		self.far_clip
+	}
+
+	// horizontal angle of the view direction, relative to the default one
+	pub fn yaw(&self) -> f32 {
+		let dir = self.forward_dir;
+		let default = DEFAULT_FORWARD_DIR;
+		dir.z.atan2(dir.x) - default.z.atan2(default.x)
+	}
+
+	// view direction from above the horizon, in radians
+	pub fn pitch(&self) -> f32 {
+		self.forward_dir.y.clamp(-1.0, 1.0).asin()
+	}
+
+	// face the direction given by absolute angles, the same ones `yaw` and
+	// `pitch` return. the pitch is kept short of straight up or down
+	pub fn set_yaw_pitch(&mut self, yaw: f32, pitch: f32) {
+		let max_pitch = MAX_PITCH_DEG.to_radians();
+		let pitch = pitch.clamp(-max_pitch, max_pitch);
+		let default_yaw = DEFAULT_FORWARD_DIR.z.atan2(DEFAULT_FORWARD_DIR.x);
+		let yaw = yaw + default_yaw;
+
+		self.forward_dir = glm::normalize(&glm::vec3(
+			yaw.cos() * pitch.cos(),
+			pitch.sin(),
+			yaw.sin() * pitch.cos(),
+		));
+		self.recalc_view();
+	}
+
+	// {{{ numeric input
+	// for placing the camera exactly, e.g. to match a viewpoint from another
+	// render. returns whether a field has focus
+	fn numeric_interface(&mut self, ui: &mut egui::Ui) -> bool {
+		let mut focused = false;
+
+		let mut pos = self.pos;
+		ui.horizontal(|ui| {
+			ui.label("Position:");
+			for (axis, value) in ["X", "Y", "Z"].iter().zip(pos.iter_mut()) {
+				let resp = ui.add(position_drag(ui, value).prefix(*axis));
+				focused |= resp.has_focus();
+			}
+		});
+		if pos != self.pos && pos.iter().all(|x| x.is_finite()) {
+			self.pos = pos;
+			self.recalc_view();
+		}
+
+		// shown from -180 to 180 degrees instead of wherever the turning left it
+		let yaw = self.yaw();
+		let yaw = (yaw + PI).rem_euclid(TAU) - PI;
+		let (mut new_yaw, mut new_pitch) = (yaw, self.pitch());
+		ui.horizontal(|ui| {
+			ui.label("Yaw:");
+			let resp = ui.add(angle_drag(ui, &mut new_yaw));
+			focused |= resp.has_focus();
+
+			ui.label("Pitch:");
+			let max_pitch = MAX_PITCH_DEG.to_radians();
+			let resp =
+				ui.add(angle_drag(ui, &mut new_pitch).clamp_range(-max_pitch..=max_pitch));
+			focused |= resp.has_focus();
+		});
+		if new_yaw != yaw || new_pitch != self.pitch() {
+			self.set_yaw_pitch(new_yaw, new_pitch);
+		}
+
+		focused
+	}
+	// }}}
+
+	// {{{ bookmarks
+	pub fn bookmark(&self, name: String) -> CameraBookmark {
+		CameraBookmark {
+			name,
+			pos: self.pos,
+			forward_dir: self.forward_dir,
+			vertical_fov: self.vertical_fov,
+		}
+	}
+
+	// the projection is rebuilt for the current screen size, so a bookmark
+	// taken at another aspect ratio still has the same position and direction
+	pub fn restore(&mut self, bookmark: &CameraBookmark) {
+		self.pos = bookmark.pos;
+		self.forward_dir = glm::normalize(&bookmark.forward_dir);
+		self.vertical_fov = bookmark.vertical_fov;
+		self.recalc_proj();
+		self.recalc_view();
+	}
+	// }}}
+
+	// turn to face a point without moving. returns false if the point is the
+	// camera position, where there is no direction to face. with
+	// `clamp_pitch`, a point right above or below is faced as far as mouse
+	// look would go
+	pub fn look_at_point(&mut self, target: Vec3, clamp_pitch: bool) -> bool {
+		let dir = target - self.pos;
+		if dir.norm() <= f32::EPSILON {
+			return false;
+		}
+
+		self.forward_dir = glm::normalize(&dir);
+		if clamp_pitch {
+			self.forward_dir = self::clamp_pitch(self.forward_dir);
+		}
+		self.recalc_view();
+		true
+	}
+
+	// move back along the view direction until a sphere fills the view
+	pub fn frame_sphere(&mut self, center: Vec3, radius: f32) {
+		let aspect = self.view_width() / self.scr_size.y;
+		let horizontal_fov = 2.0 * ((self.vertical_fov / 2.0).tan() * aspect).atan();
+		let half_fov = self.vertical_fov.min(horizontal_fov) / 2.0;
+
+		self.pos = center - self.forward_dir * (radius / half_fov.sin());
+		self.recalc_view();
+	}
+
 	pub fn set_fov(&mut self, new_fov: f32) {
 		if (new_fov - self.vertical_fov).abs() <= f32::EPSILON {
 			return;
@@ -141,6 +541,39 @@ impl Camera {
 		self.recalc_proj();
 	}
 
+	pub fn projection(&self) -> Projection {
+		self.projection
+	}
+
+	pub fn set_projection(&mut self, projection: Projection) {
+		if projection != self.projection {
+			self.projection = projection;
+			self.recalculate_ray_dirs = true;
+		}
+	}
+
+	pub fn stereo(&self) -> Option<Stereo> {
+		self.stereo
+	}
+
+	// only the perspective projection has a stereo mode
+	pub fn set_stereo(&mut self, stereo: Option<Stereo>) {
+		let stereo = stereo.filter(|_| self.projection == Projection::Perspective);
+		let halved = stereo.is_some() != self.stereo.is_some();
+		self.stereo = stereo;
+		if halved {
+			self.recalc_proj();
+		}
+	}
+
+	// of a single view
+	fn view_width(&self) -> f32 {
+		match self.stereo {
+			Some(_) => self.scr_size.x / 2.0,
+			None => self.scr_size.x,
+		}
+	}
+
 	pub fn set_scr_size(&mut self, new_scr_size: Vec2) {
 		// no check with existing scr_size is needed because this is done
 		// in the raytracer struct on resize (this logic is also needed there)
@@ -151,7 +584,7 @@ impl Camera {
 	fn recalc_proj(&mut self) {
 		self.proj = perspective_fov(
 			self.vertical_fov,
-			self.scr_size.x,
+			self.view_width(),
 			self.scr_size.y,
 			self.near_clip,
 			self.far_clip,
@@ -161,8 +594,212 @@ impl Camera {
 	}
 
 	fn recalc_view(&mut self) {
-		self.view = look_at(&self.pos, &(self.pos + self.forward_dir), &UP_DIR);
+		let eye = self.eye();
+		let dir = self.shake.rotate(self.forward_dir);
+		self.view = look_at(&eye, &(eye + dir), &UP_DIR);
 		self.inv_view = inverse(&self.view);
 		self.recalculate_ray_dirs = true;
 	}
 }
+
+impl Shake {
+	// deterministic, the same time always gives the same wobble
+	fn sample(time: f32, controls: ControlSettings) -> Self {
+		let t = time * controls.shake_frequency;
+		let amplitude = controls.shake_amplitude;
+		let noise = |channel| crate::util::fractal_noise(channel, t) * amplitude;
+
+		Self {
+			offset: glm::vec3(noise(0), noise(1), noise(2)),
+			yaw: noise(3) * SHAKE_ROT_PER_UNIT,
+			pitch: noise(4) * SHAKE_ROT_PER_UNIT,
+		}
+	}
+
+	fn rotate(&self, dir: Vec3) -> Vec3 {
+		if *self == Self::default() {
+			return dir;
+		}
+
+		let right_dir = glm::cross(&dir, &UP_DIR);
+		let q = glm::quat_normalize(&glm::quat_cross(
+			&quat_angle_axis(self.pitch, &right_dir),
+			&quat_angle_axis(self.yaw, &UP_DIR),
+		));
+		glm::quat_rotate_vec3(&q, &dir)
+	}
+}
+
+// {{{ window
+#[derive(Clone, Copy, Default)]
+pub struct WindowResponse {
+	pub focused: bool,
+	pub bookmarks_changed: bool,
+	#[cfg(not(target_arch = "wasm32"))]
+	pub open_reference: bool, // the app owns the reference image
+}
+
+// camera placement and bookmarks. both move the camera like the controls do,
+// so they're disabled while it's locked
+pub fn window(egui: &egui::Context, data: &mut PersistentData) -> WindowResponse {
+	let mut resp = WindowResponse::default();
+	let locked = data.settings.render.lock_camera;
+
+	egui::Window::new("Camera")
+		.default_open(false)
+		.resizable(false)
+		.show(egui, |ui| {
+			ui.add_enabled_ui(!locked, |ui| {
+				resp.focused |= data.camera.numeric_interface(ui);
+			});
+
+			let fov = data.camera.vertical_fov;
+			ui.label(format!(
+				"Field of view: {:.1}° ({:.1} mm)",
+				fov.to_degrees(),
+				crate::util::fov_to_focal_length(fov),
+			));
+			if locked {
+				ui.label("The camera is locked");
+			}
+
+			// the web only gets dropped images
+			#[cfg(not(target_arch = "wasm32"))]
+			if ui.button("Open reference image…").clicked() {
+				resp.open_reference = true;
+			}
+
+			ui.collapsing("Bookmarks", |ui| {
+				bookmarks_interface(ui, data, locked, &mut resp);
+			});
+		});
+
+	resp
+}
+
+fn bookmarks_interface(
+	ui: &mut egui::Ui,
+	data: &mut PersistentData,
+	locked: bool,
+	resp: &mut WindowResponse,
+) {
+	if ui.button("Add current view").clicked() {
+		let name = format!("View {}", data.bookmarks.len() + 1);
+		data.bookmarks.push(data.camera.bookmark(name));
+		resp.bookmarks_changed = true;
+	}
+
+	let mut restore = None;
+	let mut delete = None;
+	for (i, bookmark) in data.bookmarks.iter_mut().enumerate() {
+		ui.horizontal(|ui| {
+			let go = ui.add_enabled(!locked, egui::Button::new("Go"));
+			if go.clicked() {
+				restore = Some(i);
+			}
+
+			let name =
+				ui.add(egui::TextEdit::singleline(&mut bookmark.name).desired_width(120.0));
+			resp.focused |= name.has_focus();
+			resp.bookmarks_changed |= name.changed();
+
+			ui.scope(|ui| {
+				crate::util::red_hover_button(ui);
+				if ui.button("Delete").clicked() {
+					delete = Some(i);
+				}
+			});
+		});
+	}
+
+	// the fov is driven by the render settings, it has to change there too
+	if let Some(bookmark) = restore.map(|i| &data.bookmarks[i]) {
+		data.camera.restore(bookmark);
+		data.settings.render.fov = bookmark.vertical_fov;
+		resp.bookmarks_changed = true;
+	}
+	if let Some(i) = delete {
+		data.bookmarks.remove(i);
+		resp.bookmarks_changed = true;
+	}
+}
+// }}}
+
+// keeps the view direction away from straight up or down, where the right
+// direction degenerates and the view would flip
+fn clamp_pitch(dir: Vec3) -> Vec3 {
+	let max_pitch = MAX_PITCH_DEG.to_radians();
+	let pitch = dir.y.clamp(-1.0, 1.0).asin();
+	if pitch.abs() <= max_pitch {
+		return dir;
+	}
+
+	let horizontal = glm::vec2(dir.x, dir.z);
+	let horizontal = if horizontal.norm() > f32::EPSILON {
+		glm::normalize(&horizontal)
+	} else {
+		glm::vec2(DEFAULT_FORWARD_DIR.x, DEFAULT_FORWARD_DIR.z)
+	};
+
+	let pitch = pitch.clamp(-max_pitch, max_pitch);
+	glm::vec3(
+		horizontal.x * pitch.cos(),
+		pitch.sin(),
+		horizontal.y * pitch.cos(),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn camera() -> Camera {
+		Camera::new(glm::vec2(400.0, 300.0))
+	}
+
+	#[test]
+	fn look_at_keeps_the_pitch_clamp() {
+		let mut camera = camera();
+		let above = camera.pos + glm::vec3(0.0, 10.0, 0.0);
+		let max_pitch = MAX_PITCH_DEG.to_radians();
+
+		assert!(camera.look_at_point(above, true));
+		assert!(camera.pitch() <= max_pitch + 1e-5);
+		assert!(camera.is_valid());
+
+		assert!(camera.look_at_point(above, false));
+		assert!(camera.pitch() > max_pitch);
+
+		assert!(!camera.look_at_point(camera.pos, true));
+	}
+
+	fn matrices(camera: &Camera) -> [Mat4; 4] {
+		[camera.proj, camera.inv_proj, camera.view, camera.inv_view]
+	}
+
+	#[test]
+	fn finalize_rebuilds_the_matrices() {
+		let mut camera = camera();
+		let expected = matrices(&camera);
+
+		camera.proj = Mat4::from_element(f32::NAN);
+		camera.inv_proj = Mat4::zeros();
+		camera.view = Mat4::from_element(1e30);
+		camera.inv_view = glm::translation(&glm::vec3(5.0, 0.0, 0.0));
+		camera.finalize(camera.scr_size());
+		assert_eq!(matrices(&camera), expected);
+	}
+
+	// the matrices aren't stored, loading leaves them zeroed until finalize
+	#[test]
+	fn stored_camera_is_finalized() {
+		let camera = camera();
+		let json = serde_json::to_string(&camera).unwrap();
+		let mut loaded: Camera = serde_json::from_str(&json).unwrap();
+		assert_eq!(loaded.view, Mat4::zeros());
+
+		loaded.finalize(camera.scr_size());
+		assert_eq!(matrices(&loaded), matrices(&camera));
+		assert!(loaded.is_valid());
+	}
+}
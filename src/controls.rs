@@ -0,0 +1,187 @@
+// Constructors for the numeric widgets, so that the same kind of value gets
+// the same speed, range and formatting in every window. Angles are always
+// stored in radians and shown in degrees.
+use std::ops::RangeInclusive;
+
+use egui::{DragValue, Slider, Ui};
+
+use crate::util::{focal_length_to_fov, fov_to_focal_length};
+
+pub const FOV_RANGE_DEG: RangeInclusive<f32> = 50.0..=120.0;
+
+// decimals shown for angles, enough that re-editing one doesn't change it
+const ANGLE_DECIMALS: usize = 2;
+
+// {{{ drag values
+// world space distances, shift for fine adjustments
+pub fn position_drag<'a>(ui: &Ui, value: &'a mut f32) -> DragValue<'a> {
+	DragValue::new(value).speed(fine(ui, 0.1))
+}
+
+pub fn scale_drag<'a>(ui: &Ui, value: &'a mut f32) -> DragValue<'a> {
+	DragValue::new(value).speed(fine(ui, 0.1)).suffix("×")
+}
+
+// one degree per point, shift for a tenth of that
+pub fn angle_drag<'a>(ui: &Ui, value: &'a mut f32) -> DragValue<'a> {
+	DragValue::new(value)
+		.speed(fine(ui, 1.0_f64.to_radians()))
+		.angle()
+}
+
+fn fine(ui: &Ui, speed: f64) -> f64 {
+	if ui.input(|i| i.modifiers.shift) {
+		speed / 10.0
+	} else {
+		speed
+	}
+}
+// }}}
+
+// {{{ sliders
+// roughness, specular chance and other factors in [0, 1]
+pub fn unit_slider(value: &mut f32) -> Slider<'_> {
+	Slider::new(value, 0.0..=1.0)
+}
+
+pub fn roughness_slider(value: &mut f32) -> Slider<'_> {
+	unit_slider(value)
+}
+
+pub fn angle_slider(value: &mut f32, range: RangeInclusive<f32>) -> Slider<'_> {
+	Slider::new(value, range).angle()
+}
+
+pub fn fov_slider(value: &mut f32) -> Slider<'_> {
+	angle_slider(value, fov_range())
+}
+
+// radians
+pub fn fov_range() -> RangeInclusive<f32> {
+	FOV_RANGE_DEG.start().to_radians()..=FOV_RANGE_DEG.end().to_radians()
+}
+
+// the fov range as focal lengths in mm, a wider view is a shorter lens
+pub fn focal_length_range() -> RangeInclusive<f32> {
+	let fov = fov_range();
+	fov_to_focal_length(*fov.end())..=fov_to_focal_length(*fov.start())
+}
+
+// edits a fov (radians) as a 35mm-equivalent focal length
+pub fn focal_length_slider(ui: &mut Ui, fov: &mut f32) -> egui::Response {
+	let mut focal_length = fov_to_focal_length(*fov);
+	let resp = ui.add(
+		Slider::new(&mut focal_length, focal_length_range())
+			.suffix(" mm")
+			.max_decimals(1),
+	);
+	if resp.changed() {
+		let range = fov_range();
+		*fov = focal_length_to_fov(focal_length).clamp(*range.start(), *range.end());
+	}
+	resp
+}
+// }}}
+
+// {{{ angle formatting
+pub trait AngleControl {
+	fn angle(self) -> Self;
+}
+
+impl AngleControl for Slider<'_> {
+	fn angle(self) -> Self {
+		self
+			.suffix("°")
+			.custom_formatter(|n, _| format_degrees(n))
+			.custom_parser(parse_degrees)
+	}
+}
+
+impl AngleControl for DragValue<'_> {
+	fn angle(self) -> Self {
+		self
+			.suffix("°")
+			.custom_formatter(|n, _| format_degrees(n))
+			.custom_parser(parse_degrees)
+	}
+}
+
+// radians -> degrees, without trailing zeros
+fn format_degrees(radians: f64) -> String {
+	let degrees = format!("{:.*}", ANGLE_DECIMALS, radians.to_degrees());
+	let degrees = degrees.trim_end_matches('0').trim_end_matches('.');
+	match degrees {
+		"-0" => "0".to_owned(),
+		_ => degrees.to_owned(),
+	}
+}
+
+// degrees (with or without the sign) -> radians
+fn parse_degrees(text: &str) -> Option<f64> {
+	let text = text.trim().trim_end_matches('°').trim_end();
+	let degrees: f64 = text.parse().ok()?;
+	degrees.is_finite().then(|| degrees.to_radians()) // "nan" and "inf" parse
+}
+// }}}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// {{{ degrees
+	#[test]
+	fn degrees_round_trip() {
+		for degrees in [0.0, 1.0, -1.0, 12.5, -45.25, 90.0, 180.0, -359.99, 720.0] {
+			let text = format_degrees(f64::to_radians(degrees));
+			let parsed = parse_degrees(&text).unwrap().to_degrees();
+			assert!(
+				(parsed - degrees).abs() < 1e-9,
+				"{degrees} -> {text} -> {parsed}"
+			);
+		}
+	}
+
+	#[test]
+	fn degrees_are_rounded_and_trimmed() {
+		assert_eq!(format_degrees(0.0), "0");
+		assert_eq!(format_degrees(f64::to_radians(90.0)), "90");
+		assert_eq!(format_degrees(f64::to_radians(12.5)), "12.5");
+		assert_eq!(format_degrees(f64::to_radians(-33.333)), "-33.33");
+		assert_eq!(format_degrees(f64::to_radians(-0.001)), "0");
+	}
+
+	#[test]
+	fn degrees_parse_with_suffix_and_whitespace() {
+		for text in ["45", "45°", " 45 ", "\t45 °", "+45", "45.0°  "] {
+			let parsed = parse_degrees(text).map(f64::to_degrees);
+			assert!(parsed.is_some_and(|d| (d - 45.0).abs() < 1e-9), "{text:?}");
+		}
+		let parsed = parse_degrees("-12.5°").unwrap().to_degrees();
+		assert!((parsed + 12.5).abs() < 1e-9);
+	}
+
+	#[test]
+	fn garbage_degrees_are_rejected() {
+		for text in [
+			"", "°", "abc", "12deg", "1 2", "°45", "--5", "nan", "inf", "-inf",
+		] {
+			assert_eq!(parse_degrees(text), None, "{text:?}");
+		}
+	}
+	// }}}
+
+	// {{{ focal length
+	// the slider's ends are the fov range's, swapped
+	#[test]
+	fn focal_length_range_matches_the_fov_range() {
+		let (fov, mm) = (fov_range(), focal_length_range());
+		assert!(mm.start() < mm.end());
+		assert!((focal_length_to_fov(*mm.start()) - fov.end()).abs() < 1e-5);
+		assert!((focal_length_to_fov(*mm.end()) - fov.start()).abs() < 1e-5);
+
+		// 120° to 50°
+		assert!((mm.start() - 6.93).abs() < 0.01, "{mm:?}");
+		assert!((mm.end() - 25.73).abs() < 0.01, "{mm:?}");
+	}
+	// }}}
+}
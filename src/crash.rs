@@ -0,0 +1,80 @@
+// Crash recovery, native only. While there are unsaved changes the app keeps
+// the data encoded here, and a panic hook writes those bytes next to eframe's
+// storage. The next launch offers them back, unless the storage was saved
+// after the crash.
+use std::{path::PathBuf, sync::Mutex};
+
+use crate::app::PersistentData;
+
+// the title given to eframe, it names the storage directory
+const APP_ID: &str = "Raytracer";
+const STORAGE_FILE: &str = "app.ron"; // eframe's
+const RECOVERY_FILE: &str = "crash-recovery.json";
+
+// encoded ahead of time, a panic can happen anywhere (even in the middle of
+// painting with the data locked) so the hook only writes bytes out
+static SNAPSHOT: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+
+fn recovery_path() -> Option<PathBuf> {
+	Some(eframe::storage_dir(APP_ID)?.join(RECOVERY_FILE))
+}
+
+// runs before the default hook, which prints the message
+pub fn install_hook() {
+	let Some(path) = recovery_path() else {
+		return;
+	};
+
+	let previous = std::panic::take_hook();
+	std::panic::set_hook(Box::new(move |info| {
+		// a panic while the snapshot was being replaced leaves it locked or
+		// poisoned, there's nothing reliable to write then
+		if let Ok(snapshot) = SNAPSHOT.try_lock() {
+			if let Some(bytes) = snapshot.as_deref() {
+				if std::fs::write(&path, bytes).is_ok() {
+					eprintln!("unsaved changes were written to {}", path.display());
+				}
+			}
+		}
+		previous(info);
+	}));
+}
+
+// called while there are unsaved changes
+pub(crate) fn update_snapshot(data: &PersistentData) {
+	let bytes = serde_json::to_vec(data).ok();
+	if let Ok(mut snapshot) = SNAPSHOT.lock() {
+		*snapshot = bytes;
+	}
+}
+
+// called after saving, there's nothing to recover then
+pub(crate) fn clear_snapshot() {
+	if let Ok(mut snapshot) = SNAPSHOT.lock() {
+		*snapshot = None;
+	}
+}
+
+// the text a crash left behind. a file older than the storage is out of date
+// and gets removed right away
+pub(crate) fn find_recovery() -> Option<String> {
+	let dir = eframe::storage_dir(APP_ID)?;
+	let path = dir.join(RECOVERY_FILE);
+	let modified = |path: PathBuf| std::fs::metadata(path).and_then(|m| m.modified());
+
+	let crashed = modified(path.clone()).ok()?;
+	if modified(dir.join(STORAGE_FILE)).is_ok_and(|stored| stored > crashed) {
+		discard_recovery();
+		return None;
+	}
+	std::fs::read_to_string(path).ok()
+}
+
+// once the user restored or declined it
+pub(crate) fn discard_recovery() {
+	if let Some(path) = recovery_path() {
+		if let Err(e) = std::fs::remove_file(path) {
+			log::warn!("removing the crash recovery file failed: {e}");
+		}
+	}
+}
@@ -0,0 +1,112 @@
+use egui::{Color32, Id, LayerId, Order, Pos2, Rect, Rgba, Stroke, Ui, Vec2};
+
+use crate::{app::PersistentData, util::UpdateResponse, viewport::ViewportMapping};
+
+// side length of the region sampled by a single click (points)
+const CLICK_REGION_SIZE: f32 = 5.0;
+
+// alt + click or drag over the viewport samples the average color of the
+// render, the result is shown in a small window
+#[derive(Default)]
+pub struct Eyedropper {
+	drag_start: Option<Pos2>,
+
+	// linear color of the last sample, the window is open while this is set
+	color: Option<[f32; 3]>,
+}
+
+impl Eyedropper {
+	// returns the region to sample once the click or drag is finished
+	pub fn update(&mut self, ui: &Ui, mapping: &ViewportMapping) -> Option<Rect> {
+		let (pointer, alt, pressed, released) = ui.input(|i| {
+			(
+				i.pointer.latest_pos(), // still known after leaving the window
+				i.modifiers.alt,
+				i.pointer.primary_pressed(),
+				i.pointer.primary_released(),
+			)
+		});
+		let pointer = pointer?;
+
+		if pressed
+			&& alt && ui.rect_contains_pointer(mapping.rect)
+			&& ui.ctx().dragged_id().is_none()
+		{
+			self.drag_start = Some(pointer);
+		}
+
+		let start = self.drag_start?;
+		let mut region = Rect::from_two_pos(start, pointer);
+		if region.width() < CLICK_REGION_SIZE && region.height() < CLICK_REGION_SIZE {
+			region = Rect::from_center_size(pointer, Vec2::splat(CLICK_REGION_SIZE));
+		}
+
+		if !released {
+			ui.ctx()
+				.layer_painter(LayerId::new(Order::Foreground, Id::new("eyedropper")))
+				.rect_stroke(region, 0.0, Stroke::new(1.0, Color32::WHITE));
+			return None;
+		}
+
+		self.drag_start = None;
+		Some(region)
+	}
+
+	pub fn set_color(&mut self, color: [f32; 3]) {
+		self.color = Some(color);
+	}
+
+	// `editable` is false when the scene must not be changed
+	pub fn window(
+		&mut self,
+		egui: &egui::Context,
+		data: &mut PersistentData,
+		editable: bool,
+	) {
+		let Some(color) = self.color else {
+			return;
+		};
+
+		let mut open = true;
+		egui::Window::new("Eyedropper")
+			.open(&mut open)
+			.collapsible(false)
+			.resizable(false)
+			.show(egui, |ui| {
+				let srgb = Color32::from(Rgba::from_rgb(color[0], color[1], color[2]));
+				let hex = format!("#{:02x}{:02x}{:02x}", srgb.r(), srgb.g(), srgb.b());
+				let linear = format!("{:.4}, {:.4}, {:.4}", color[0], color[1], color[2]);
+
+				ui.horizontal(|ui| {
+					let (rect, _) =
+						ui.allocate_exact_size(Vec2::splat(32.0), egui::Sense::hover());
+					ui.painter().rect_filled(rect, 2.0, srgb);
+
+					ui.vertical(|ui| {
+						ui.label(format!("Linear: {linear}"));
+						ui.label(format!("sRGB: {hex}"));
+					});
+				});
+
+				ui.horizontal(|ui| {
+					if ui.button("Copy").clicked() {
+						ui.output_mut(|o| o.copied_text = linear);
+					}
+
+					let selected = data.scene.selected().filter(|_| editable);
+					let assign = ui.add_enabled(
+						selected.is_some(),
+						egui::Button::new("Assign to selected material"),
+					);
+					if let Some(i) = selected.filter(|_| assign.clicked()) {
+						data.scene.mat_color[i] = color.map(|c| c.clamp(0.0, 1.0));
+						data.scene.set_changed(true);
+					}
+				});
+			});
+
+		if !open {
+			self.color = None;
+		}
+	}
+}
@@ -1 +1,697 @@
+// CPU-side ray casting against the scene, mirrors the intersection code in
+// fsh.glsl so that the results agree with what is on screen
+use nalgebra_glm::{self as glm, vec3, vec4, Mat4, Vec3};
 
+pub mod bvh;
+
+use crate::{
+	scene::{CsgOp, ObjectType, Scene},
+	settings::WorldSettings,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+	pub origin: Vec3,
+	pub dir: Vec3,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+	pub obj: HitObject,
+	pub pos: Vec3,
+	pub normal: Vec3,
+	pub distance: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitObject {
+	Object(usize),
+	Ground,
+}
+
+impl Ray {
+	pub fn new(origin: Vec3, dir: Vec3) -> Self {
+		Self {
+			origin,
+			dir: glm::normalize(&dir),
+		}
+	}
+
+	pub fn at(&self, t: f32) -> Vec3 {
+		self.origin + self.dir * t
+	}
+
+	pub fn transform(&self, m: &Mat4) -> Self {
+		Self {
+			origin: transform_point(&self.origin, m),
+			// the zero is needed to transform directions without translation
+			dir: glm::normalize(
+				&(m * vec4(self.dir.x, self.dir.y, self.dir.z, 0.0)).xyz(),
+			),
+		}
+	}
+}
+
+pub fn transform_point(p: &Vec3, m: &Mat4) -> Vec3 {
+	(m * vec4(p.x, p.y, p.z, 1.0)).xyz()
+}
+
+fn transform_normal(n: &Vec3, m: &Mat4) -> Vec3 {
+	glm::normalize(&(m * vec4(n.x, n.y, n.z, 0.0)).xyz())
+}
+
+// {{{ bounds
+// corners of the unit cube, every object fits inside it in local space
+const UNIT_CUBE_CORNERS: [Vec3; 8] = [
+	Vec3::new(-1.0, -1.0, -1.0),
+	Vec3::new(-1.0, -1.0, 1.0),
+	Vec3::new(-1.0, 1.0, -1.0),
+	Vec3::new(-1.0, 1.0, 1.0),
+	Vec3::new(1.0, -1.0, -1.0),
+	Vec3::new(1.0, -1.0, 1.0),
+	Vec3::new(1.0, 1.0, -1.0),
+	Vec3::new(1.0, 1.0, 1.0),
+];
+
+// world space corners of an object's bounds, planes and discs are flat so
+// their scale on Y doesn't count
+fn object_corners(scene: &Scene, i: usize) -> impl Iterator<Item = Vec3> + '_ {
+	let flat = matches!(scene.ty[i], ObjectType::Plane | ObjectType::Disc);
+	UNIT_CUBE_CORNERS.iter().map(move |c| {
+		let c = if flat { vec3(c.x, 0.0, c.z) } else { *c };
+		transform_point(&c, &scene.transform[i])
+	})
+}
+
+// world space axis-aligned bounding box of all objects (min, max). the ground
+// plane is infinite and not included
+pub fn scene_bounds(scene: &Scene) -> Option<(Vec3, Vec3)> {
+	(0..scene.len())
+		.flat_map(|i| object_corners(scene, i))
+		.fold(None, |bounds, p| match bounds {
+			None => Some((p, p)),
+			Some((min, max)) => Some((min.inf(&p), max.sup(&p))),
+		})
+}
+
+// sphere around `scene_bounds` (center, radius)
+pub fn bounding_sphere(scene: &Scene) -> Option<(Vec3, f32)> {
+	let (min, max) = scene_bounds(scene)?;
+	Some(((min + max) / 2.0, glm::distance(&min, &max) / 2.0))
+}
+
+// distance from an object's center to the farthest corner of its bounds
+pub fn bounding_radius(scene: &Scene, i: usize) -> f32 {
+	let center = transform_point(&Vec3::zeros(), &scene.transform[i]);
+	object_corners(scene, i)
+		.map(|c| glm::distance(&c, &center))
+		.fold(0.0, f32::max)
+}
+
+pub fn bounds_corners(min: &Vec3, max: &Vec3) -> [Vec3; 8] {
+	UNIT_CUBE_CORNERS.map(|c| {
+		vec3(
+			if c.x < 0.0 { min.x } else { max.x },
+			if c.y < 0.0 { min.y } else { max.y },
+			if c.z < 0.0 { min.z } else { max.z },
+		)
+	})
+}
+// }}}
+
+// {{{ intersection tests
+// local positions of hits, the caller transforms them back to world space
+fn intersect_sphere(local_ray: &Ray) -> Option<(Vec3, Vec3)> {
+	let b = glm::dot(&local_ray.origin, &local_ray.dir);
+	let c = glm::dot(&local_ray.origin, &local_ray.origin) - 1.0;
+
+	let d = b * b - c;
+	if d < 0.0 {
+		return None;
+	}
+
+	let tn = -b - d.sqrt();
+	if tn < 0.0 {
+		return None;
+	}
+
+	let pos = local_ray.at(tn);
+	Some((pos, pos))
+}
+
+// enter and exit distances of a box centered on the origin
+fn box_interval(local_ray: &Ray, half_extent: &Vec3) -> Option<(f32, f32, Vec3)> {
+	let inv = vec3(1.0, 1.0, 1.0).component_div(&local_ray.dir);
+	let n = inv.component_mul(&local_ray.origin);
+	let k = inv.abs().component_mul(half_extent);
+	let t1 = -n - k;
+	let t2 = -n + k;
+
+	let tn = t1.x.max(t1.y).max(t1.z);
+	let tx = t2.x.min(t2.y).min(t2.z);
+
+	if tn > tx || tx < 0.0 || tn < 0.0 {
+		return None;
+	}
+
+	Some((tn, tx, t1))
+}
+
+fn intersect_box(local_ray: &Ray) -> Option<(Vec3, Vec3)> {
+	let (tn, _, t1) = box_interval(local_ray, &vec3(1.0, 1.0, 1.0))?;
+
+	let normal = t1
+		.map(|t| if t >= tn { 1.0 } else { 0.0 })
+		.component_mul(&-local_ray.dir.map(f32::signum));
+	Some((local_ray.at(tn), normal))
+}
+
+// the XZ square of the unit cube or the circle inside it, single-sided ones
+// are only hit from local +Y
+fn intersect_plane(
+	local_ray: &Ray,
+	disc: bool,
+	double_sided: bool,
+) -> Option<(Vec3, Vec3)> {
+	if local_ray.dir.y == 0.0 || (!double_sided && local_ray.dir.y > 0.0) {
+		return None;
+	}
+
+	let t = -local_ray.origin.y / local_ray.dir.y;
+	let pos = local_ray.at(t);
+	let outside = if disc {
+		pos.x * pos.x + pos.z * pos.z > 1.0
+	} else {
+		pos.x.abs() > 1.0 || pos.z.abs() > 1.0
+	};
+	if t < 0.0 || outside {
+		return None;
+	}
+
+	Some((pos, vec3(0.0, -local_ray.dir.y.signum(), 0.0)))
+}
+
+// radius 1 around the Y axis, capped at y = ±1
+fn intersect_cylinder(local_ray: &Ray) -> Option<(Vec3, Vec3)> {
+	let (o, d) = (&local_ray.origin, &local_ray.dir);
+
+	// side, skipped for rays along the axis
+	let a = d.x * d.x + d.z * d.z;
+	let b = o.x * d.x + o.z * d.z;
+	let c = o.x * o.x + o.z * o.z - 1.0;
+	if a > 1e-12 {
+		let h = b * b - a * c;
+		if h < 0.0 {
+			return None;
+		}
+
+		let t = (-b - h.sqrt()) / a;
+		let pos = local_ray.at(t);
+		if t >= 0.0 && pos.y.abs() <= 1.0 {
+			return Some((pos, vec3(pos.x, 0.0, pos.z)));
+		}
+	} else if c > 0.0 {
+		return None;
+	}
+
+	// the cap facing the ray
+	if d.y == 0.0 {
+		return None;
+	}
+	let t = (-d.y.signum() - o.y) / d.y;
+	let pos = local_ray.at(t);
+	if t < 0.0 || pos.x * pos.x + pos.z * pos.z > 1.0 {
+		return None;
+	}
+	Some((pos, vec3(0.0, -d.y.signum(), 0.0)))
+}
+
+// caps of radius `r` centered at y = ±(1 - r), the nearest of the side and caps
+fn intersect_capsule(local_ray: &Ray, r: f32) -> Option<(Vec3, Vec3)> {
+	let (o, d) = (&local_ray.origin, &local_ray.dir);
+	let h = 1.0 - r;
+
+	let mut nearest = f32::MAX;
+
+	// side, skipped for rays along the axis
+	let a = d.x * d.x + d.z * d.z;
+	let b = o.x * d.x + o.z * d.z;
+	let c = o.x * o.x + o.z * o.z - r * r;
+	if a > 1e-12 {
+		let e = b * b - a * c;
+		if e < 0.0 {
+			return None;
+		}
+
+		let t = (-b - e.sqrt()) / a;
+		if t >= 0.0 && (o.y + t * d.y).abs() <= h {
+			nearest = t;
+		}
+	} else if c > 0.0 {
+		return None;
+	}
+
+	// caps
+	for y in [-h, h] {
+		let oc = o - vec3(0.0, y, 0.0);
+		let cb = oc.dot(d);
+		let e = cb * cb - oc.dot(&oc) + r * r;
+		if e < 0.0 {
+			continue;
+		}
+
+		let t = -cb - e.sqrt();
+		if t >= 0.0 && t < nearest {
+			nearest = t;
+		}
+	}
+	if nearest == f32::MAX {
+		return None;
+	}
+
+	let pos = local_ray.at(nearest);
+	Some((pos, (pos - vec3(0.0, pos.y.clamp(-h, h), 0.0)) / r))
+}
+
+fn sd_rounded_box(p: &Vec3, b: &Vec3, r: f32) -> f32 {
+	let q = p.abs() - b.add_scalar(-r);
+	q.sup(&Vec3::zeros()).norm() + q.max().min(0.0) - r
+}
+
+// `local_ray` has rotation and translation removed but not scale, `b` is the
+// scale of the object. returns the hit position and normal in the same space
+fn intersect_rounded_box(
+	local_ray: &Ray,
+	b: &Vec3,
+	r: f32,
+) -> Option<(Vec3, Vec3)> {
+	let (tn, tx, _) = box_interval(local_ray, b)?;
+
+	let mut t = tn;
+	let mut pos = local_ray.at(t);
+	let mut hit = false;
+	for _ in 0..64 {
+		pos = local_ray.at(t);
+		let d = sd_rounded_box(&pos, b, r);
+		if d < t.max(1.0) * 0.00001 {
+			hit = true;
+			break;
+		}
+		t += d;
+		if t > tx {
+			break;
+		}
+	}
+	if !hit {
+		return None;
+	}
+
+	let q = pos.abs() - b.add_scalar(-r);
+	let mut normal = pos.map(f32::signum).component_mul(&q.sup(&Vec3::zeros()));
+	if q.max() <= 0.0 {
+		let max = q.max();
+		normal =
+			pos
+				.map(f32::signum)
+				.component_mul(&q.map(|q| if q >= max { 1.0 } else { 0.0 }));
+	}
+
+	Some((pos, glm::normalize(&normal)))
+}
+
+// {{{ constructive solid geometry
+// where the line of a ray enters and leaves a sphere or a box with sharp
+// corners, as distances along the world ray, with outward world normals
+struct Interval {
+	tn: f32,
+	tx: f32,
+	nn: Vec3,
+	nx: Vec3,
+}
+
+fn csg_interval(scene: &Scene, i: usize, ray: &Ray) -> Option<Interval> {
+	let local_ray = ray.transform(&scene.inv_transform[i]);
+	let (o, d) = (&local_ray.origin, &local_ray.dir);
+
+	let (tn, tx, nn, nx) = if scene.ty[i] == ObjectType::Sphere {
+		let b = glm::dot(o, d);
+		let h = b * b - glm::dot(o, o) + 1.0;
+		if h < 0.0 {
+			return None;
+		}
+		let (tn, tx) = (-b - h.sqrt(), -b + h.sqrt());
+		(tn, tx, local_ray.at(tn), local_ray.at(tx))
+	} else {
+		// per axis, written out so that rays parallel to a face and starting
+		// on the center plane don't turn into 0 * inf
+		let lo = (vec3(-1.0, -1.0, -1.0) - o).component_div(d);
+		let hi = (vec3(1.0, 1.0, 1.0) - o).component_div(d);
+		let t1 = lo.inf(&hi);
+		let t2 = lo.sup(&hi);
+		let (tn, tx) = (t1.max(), t2.min());
+		if tn > tx {
+			return None;
+		}
+		let sign = d.map(f32::signum);
+		let nn = t1
+			.map(|t| if t >= tn { 1.0 } else { 0.0 })
+			.component_mul(&-sign);
+		let nx = t2
+			.map(|t| if t <= tx { 1.0 } else { 0.0 })
+			.component_mul(&sign);
+		(tn, tx, nn, nx)
+	};
+
+	let world_t = |t| {
+		let pos = transform_point(&local_ray.at(t), &scene.transform[i]);
+		glm::dot(&(pos - ray.origin), &ray.dir)
+	};
+	Some(Interval {
+		tn: world_t(tn),
+		tx: world_t(tx),
+		nn: transform_normal(&nn, &scene.normal_transform[i]),
+		nx: transform_normal(&nx, &scene.normal_transform[i]),
+	})
+}
+
+fn csg_inside(op: CsgOp, a: bool, b: bool) -> bool {
+	match op {
+		CsgOp::None => a,
+		CsgOp::Union => a || b,
+		CsgOp::Intersect => a && b,
+		CsgOp::Subtract => a && !b,
+	}
+}
+
+// the nearest boundary of either object where the ray goes in or out of the
+// result. the normal is turned to face the ray where it goes in and away from
+// it where it comes out, so cuts get the inverted normals of the partner
+fn intersect_csg(
+	scene: &Scene,
+	i: usize,
+	op: CsgOp,
+	j: usize,
+	ray: &Ray,
+) -> Option<(f32, Vec3)> {
+	let a = csg_interval(scene, i, ray);
+	let b = csg_interval(scene, j, ray);
+	let inside = |iv: &Option<Interval>, t: f32| {
+		iv.as_ref().is_some_and(|iv| iv.tn < t && t < iv.tx)
+	};
+
+	let mut nearest: Option<(f32, Vec3)> = None;
+	for (iv, other, first) in [(&a, &b, true), (&b, &a, false)] {
+		let Some(iv) = iv else {
+			continue;
+		};
+		for (t, normal, entering) in [(iv.tn, iv.nn, true), (iv.tx, iv.nx, false)] {
+			if t < 0.0 || nearest.is_some_and(|(best, _)| t >= best) {
+				continue;
+			}
+
+			let o = inside(other, t);
+			let result = |inside: bool| {
+				if first {
+					csg_inside(op, inside, o)
+				} else {
+					csg_inside(op, o, inside)
+				}
+			};
+			let (before, after) = (result(!entering), result(entering));
+			if before == after {
+				continue;
+			}
+
+			let facing = glm::dot(&normal, &ray.dir) < 0.0;
+			nearest = Some((t, if after == facing { normal } else { -normal }));
+		}
+	}
+	nearest
+}
+// }}}
+
+pub fn intersect_object(scene: &Scene, i: usize, ray: &Ray) -> Option<RayHit> {
+	// the orientation of the normal follows from going in or out of the
+	// result, so it's never inside out and isn't flipped
+	if let Some((op, j)) = scene.csg_link(i) {
+		let (t, normal) = intersect_csg(scene, i, op, j, ray)?;
+		return Some(RayHit {
+			obj: HitObject::Object(i),
+			pos: ray.at(t),
+			normal,
+			distance: t,
+		});
+	}
+
+	let local_ray = ray.transform(&scene.inv_transform[i]);
+
+	let (pos, normal) = match scene.ty[i] {
+		ObjectType::Sphere => {
+			let (local_pos, local_normal) = intersect_sphere(&local_ray)?;
+			(
+				transform_point(&local_pos, &scene.transform[i]),
+				transform_normal(&local_normal, &scene.normal_transform[i]),
+			)
+		},
+		ObjectType::Box if scene.corner_radius[i] > 0.0 => {
+			let b = scene.scale[i].abs();
+			let r = scene.corner_radius[i] * b.min();
+			let unscaled_ray = Ray::new(
+				local_ray.origin.component_mul(&b),
+				local_ray.dir.component_mul(&b),
+			);
+			let (local_pos, local_normal) = intersect_rounded_box(&unscaled_ray, &b, r)?;
+			(
+				transform_point(&local_pos.component_div(&b), &scene.transform[i]),
+				transform_normal(
+					&local_normal.component_mul(&b),
+					&scene.normal_transform[i],
+				),
+			)
+		},
+		ObjectType::Box => {
+			let (local_pos, local_normal) = intersect_box(&local_ray)?;
+			(
+				transform_point(&local_pos, &scene.transform[i]),
+				transform_normal(&local_normal, &scene.normal_transform[i]),
+			)
+		},
+		ty @ (ObjectType::Plane | ObjectType::Disc) => {
+			let disc = ty == ObjectType::Disc;
+			let (local_pos, local_normal) =
+				intersect_plane(&local_ray, disc, scene.double_sided[i])?;
+			(
+				transform_point(&local_pos, &scene.transform[i]),
+				transform_normal(&local_normal, &scene.normal_transform[i]),
+			)
+		},
+		ObjectType::Cylinder => {
+			let (local_pos, local_normal) = intersect_cylinder(&local_ray)?;
+			(
+				transform_point(&local_pos, &scene.transform[i]),
+				transform_normal(&local_normal, &scene.normal_transform[i]),
+			)
+		},
+		ObjectType::Capsule => {
+			let (local_pos, local_normal) =
+				intersect_capsule(&local_ray, scene.cap_radius[i])?;
+			(
+				transform_point(&local_pos, &scene.transform[i]),
+				transform_normal(&local_normal, &scene.normal_transform[i]),
+			)
+		},
+		ObjectType::Mesh => {
+			let (local_pos, local_normal) = scene.mesh[i].intersect(&local_ray)?;
+			(
+				transform_point(&local_pos, &scene.transform[i]),
+				transform_normal(&local_normal, &scene.normal_transform[i]),
+			)
+		},
+	};
+
+	let normal = if scene.flip_normals[i] {
+		-normal
+	} else {
+		normal
+	};
+
+	Some(RayHit {
+		obj: HitObject::Object(i),
+		pos,
+		normal,
+		distance: glm::distance(&ray.origin, &pos),
+	})
+}
+
+// whether the normal where the ray from `eye` to the object's center enters it
+// points away from the ray. the object then shades as if inside out, usually
+// because a negative scale mirrored it
+pub fn normals_inverted(scene: &Scene, i: usize, eye: &Vec3) -> bool {
+	let ray = Ray::new(*eye, scene.position[i] - eye);
+	intersect_object(scene, i, &ray)
+		.is_some_and(|hit| glm::dot(&ray.dir, &hit.normal) > 0.0)
+}
+
+pub fn intersect_ground(world: &WorldSettings, ray: &Ray) -> Option<RayHit> {
+	if !world.ground_plane || ray.dir.y == 0.0 {
+		return None;
+	}
+
+	let t = (world.ground_height - ray.origin.y) / ray.dir.y;
+	if t < 0.0 {
+		return None;
+	}
+
+	Some(RayHit {
+		obj: HitObject::Ground,
+		pos: ray.at(t),
+		normal: vec3(0.0, -ray.dir.y.signum(), 0.0),
+		distance: t,
+	})
+}
+
+// {{{ collision
+// movement left over after hitting a surface is projected onto it this many
+// times, enough to slide into a corner and stop there
+const MAX_SLIDES: usize = 2;
+
+// Moves a sphere of `radius` from `from` by `delta`. It stops where it would
+// touch the first surface in the way and slides along it with the rest of the
+// movement. Only the center ray is cast, so at glancing angles the sphere can
+// still graze thin objects. Surfaces that face away are ignored, so it can't
+// get stuck inside an object it already overlaps.
+pub fn collide_movement(
+	scene: &Scene,
+	world: &WorldSettings,
+	from: Vec3,
+	delta: Vec3,
+	radius: f32,
+) -> Vec3 {
+	let mut pos = from;
+	let mut delta = delta;
+
+	for _ in 0..=MAX_SLIDES {
+		let len = delta.norm();
+		if len <= f32::EPSILON {
+			break;
+		}
+
+		let ray = Ray::new(pos, delta);
+		let hit = intersect_world(scene, world, &ray)
+			.filter(|hit| glm::dot(&ray.dir, &hit.normal) < 0.0);
+		let Some(hit) = hit else {
+			return pos + delta;
+		};
+
+		// the distance to the surface's plane shrinks by `facing` per unit
+		// travelled, stop when it's down to the radius
+		let facing = -glm::dot(&ray.dir, &hit.normal);
+		let travel = hit.distance - radius / facing;
+		if travel >= len {
+			return pos + delta;
+		}
+
+		let travel = travel.max(0.0);
+		pos += ray.dir * travel;
+		let rest = ray.dir * (len - travel);
+		delta = rest - hit.normal * glm::dot(&rest, &hit.normal);
+	}
+
+	pos
+}
+// }}}
+
+// closest hit among all objects and the ground plane
+pub fn intersect_world(
+	scene: &Scene,
+	world: &WorldSettings,
+	ray: &Ray,
+) -> Option<RayHit> {
+	(0..scene.len())
+		.filter(|&i| !scene.csg_partner(i))
+		.filter_map(|i| intersect_object(scene, i, ray))
+		.chain(intersect_ground(world, ray))
+		.min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+// }}}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RADIUS: f32 = 0.5;
+
+	// boxes from their centers and half extents
+	fn walls(boxes: &[(Vec3, Vec3)]) -> Scene {
+		let mut scene = Scene::default();
+		for (i, &(center, half_extents)) in boxes.iter().enumerate() {
+			scene.new_object();
+			scene.ty[i] = ObjectType::Box;
+			scene.position[i] = center;
+			scene.scale[i] = half_extents;
+		}
+		scene.recalc_transforms();
+		scene
+	}
+
+	fn assert_near(a: Vec3, b: Vec3) {
+		assert!((a - b).norm() < 1e-3, "{a:?} is not {b:?}");
+	}
+
+	// {{{ movement
+	#[test]
+	fn moves_freely_without_objects() {
+		let world = WorldSettings::default();
+		let delta = vec3(3.0, 0.0, -4.0);
+		let to =
+			collide_movement(&Scene::default(), &world, Vec3::zeros(), delta, RADIUS);
+		assert_near(to, delta);
+	}
+
+	// a wall with its face at x = 4.5, stopping the sphere at x = 4
+	#[test]
+	fn slides_along_a_wall() {
+		let scene = walls(&[(vec3(5.0, 1.0, 0.0), vec3(0.5, 5.0, 50.0))]);
+		let world = WorldSettings::default();
+		let from = vec3(0.0, 1.0, 0.0);
+
+		// diagonally, the part towards the wall is lost
+		let to = collide_movement(&scene, &world, from, vec3(10.0, 0.0, 10.0), RADIUS);
+		assert_near(to, vec3(4.0, 1.0, 10.0));
+
+		// head on, it stops
+		let to = collide_movement(&scene, &world, from, vec3(10.0, 0.0, 0.0), RADIUS);
+		assert_near(to, vec3(4.0, 1.0, 0.0));
+	}
+
+	#[test]
+	fn stops_in_an_inside_corner() {
+		let scene = walls(&[
+			(vec3(5.0, 1.0, 0.0), vec3(0.5, 5.0, 50.0)),
+			(vec3(0.0, 1.0, 5.0), vec3(50.0, 5.0, 0.5)),
+		]);
+		let world = WorldSettings::default();
+		let from = vec3(0.0, 1.0, 0.0);
+
+		for delta in [
+			vec3(10.0, 0.0, 10.0),
+			vec3(10.0, 0.0, 6.0),
+			vec3(6.0, 0.0, 30.0),
+		] {
+			let to = collide_movement(&scene, &world, from, delta, RADIUS);
+			assert_near(to, vec3(4.0, 1.0, 4.0));
+		}
+	}
+
+	// the whole movement is cast at once, so a thin wall can't be skipped
+	#[test]
+	fn fast_movement_does_not_tunnel() {
+		let scene = walls(&[(vec3(5.0, 1.0, 0.0), vec3(0.01, 5.0, 5.0))]);
+		let world = WorldSettings::default();
+
+		let delta = vec3(1e4, 0.0, 0.0);
+		let to = collide_movement(&scene, &world, vec3(0.0, 1.0, 0.0), delta, RADIUS);
+		assert_near(to, vec3(4.49, 1.0, 0.0));
+	}
+	// }}}
+}
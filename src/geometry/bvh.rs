@@ -0,0 +1,120 @@
+// Bounding volume hierarchy over the triangles of a mesh, traversed by
+// intersect_mesh in fsh.glsl. It's built in the mesh's own space and the ray
+// is brought into that space before the traversal, like for every other
+// primitive, so moving, rotating or scaling the object (even non-uniformly)
+// never needs a rebuild. Only importing or loading a mesh does.
+use std::ops::Range;
+
+use nalgebra_glm::Vec3;
+
+use crate::mesh::MeshData;
+
+// leaves hold up to this many triangles
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+// deeper nodes are made leaves no matter their size. also the size of the
+// traversal stack in fsh.glsl, which holds at most one node per level
+pub const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct BvhNode {
+	pub min: Vec3,
+	pub max: Vec3,
+	// leaves: the first triangle and how many there are. other nodes have a
+	// count of 0, their left child comes right after them and `offset` is the
+	// right child
+	pub offset: u32,
+	pub count: u32,
+}
+
+pub struct Bvh {
+	pub nodes: Vec<BvhNode>,      // the root is the first one
+	pub triangles: Vec<[u32; 3]>, // the mesh's triangles in leaf order
+}
+
+impl Bvh {
+	// splits at the median centroid along the longest axis of the centroids
+	pub fn build(mesh: &MeshData) -> Self {
+		let corners = |t: &[u32; 3]| t.map(|i| Vec3::from(mesh.positions[i as usize]));
+		let mut items: Vec<Item> = mesh
+			.triangles
+			.iter()
+			.map(|t| {
+				let [a, b, c] = corners(t);
+				Item {
+					triangle: *t,
+					min: a.inf(&b).inf(&c),
+					max: a.sup(&b).sup(&c),
+					centroid: (a + b + c) / 3.0,
+				}
+			})
+			.collect();
+
+		let mut nodes = Vec::new();
+		if !items.is_empty() {
+			let len = items.len();
+			build_node(&mut items, 0..len, 0, &mut nodes);
+		}
+
+		Self {
+			nodes,
+			triangles: items.iter().map(|item| item.triangle).collect(),
+		}
+	}
+}
+
+struct Item {
+	triangle: [u32; 3],
+	min: Vec3,
+	max: Vec3,
+	centroid: Vec3,
+}
+
+// returns the index of the new node
+fn build_node(
+	items: &mut [Item],
+	range: Range<usize>,
+	depth: usize,
+	nodes: &mut Vec<BvhNode>,
+) -> usize {
+	let slice = &mut items[range.clone()];
+	let (min, max) = slice.iter().fold(
+		(Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)),
+		|(min, max), item| (min.inf(&item.min), max.sup(&item.max)),
+	);
+	let (cmin, cmax) = slice.iter().fold(
+		(Vec3::repeat(f32::MAX), Vec3::repeat(f32::MIN)),
+		|(min, max), item| (min.inf(&item.centroid), max.sup(&item.centroid)),
+	);
+
+	let index = nodes.len();
+	nodes.push(BvhNode {
+		min,
+		max,
+		offset: range.start as u32,
+		count: range.len() as u32,
+	});
+
+	// identical centroids can't be split
+	let extent = cmax - cmin;
+	let axis = extent.imax();
+	if range.len() <= MAX_LEAF_TRIANGLES
+		|| depth + 1 >= MAX_DEPTH
+		|| extent[axis] <= 0.0
+	{
+		return index;
+	}
+
+	let mid = slice.len() / 2;
+	slice.select_nth_unstable_by(mid, |a, b| {
+		a.centroid[axis].total_cmp(&b.centroid[axis])
+	});
+	let mid = range.start + mid;
+
+	build_node(items, range.start..mid, depth + 1, nodes);
+	let right = build_node(items, mid..range.end, depth + 1, nodes);
+	nodes[index].offset = right as u32;
+	nodes[index].count = 0;
+
+	index
+}
@@ -0,0 +1,104 @@
+use egui::{Color32, Id, Rect, Sense, Stroke, Ui, Vec2};
+use nalgebra_glm as glm;
+
+use crate::{app::PersistentData, util::UpdateResponse, viewport::ViewportMapping};
+
+const HANDLE_SIZE: f32 = 10.0;
+const AXIS_COLORS: [Color32; 3] = [
+	Color32::from_rgb(240, 84, 84),
+	Color32::from_rgb(84, 200, 84),
+	Color32::from_rgb(84, 128, 240),
+];
+
+// axis length as a fraction of the distance to the camera, so the gizmo keeps
+// roughly the same size on screen
+const AXIS_LENGTH: f32 = 0.15;
+
+// pixels of horizontal drag on the center handle that double the scale
+const UNIFORM_DRAG_PIXELS: f32 = 100.0;
+
+// smallest allowed scale, zero would make the transform non-invertible and
+// negative values flip normals
+const MIN_SCALE: f32 = 0.001;
+
+// axis handles scale along one local axis, the center handle scales uniformly
+pub fn scale_gizmo(
+	ui: &mut Ui,
+	mapping: &ViewportMapping,
+	data: &mut PersistentData,
+) {
+	if !data.settings.render.show_gizmo {
+		return;
+	}
+	let Some(i) = data.scene.selected() else {
+		return;
+	};
+
+	let center_world = data.scene.position[i];
+	let Some(center) = mapping.project(&data.camera, center_world) else {
+		return;
+	};
+
+	let painter = ui.painter_at(mapping.rect);
+	let length = glm::distance(&data.camera.pos, &center_world) * AXIS_LENGTH;
+
+	// rotation and inverse scale, the normalized columns are the local axes
+	let normal_transform = data.scene.normal_transform[i];
+	let mut factor = glm::vec3(1.0, 1.0, 1.0);
+
+	// {{{ per-axis handles
+	for (axis, color) in AXIS_COLORS.iter().enumerate() {
+		let dir = glm::normalize(&normal_transform.column(axis).xyz());
+		let Some(end) = mapping.project(&data.camera, center_world + dir * length)
+		else {
+			continue;
+		};
+
+		let screen_axis = end - center;
+		if screen_axis.length() < HANDLE_SIZE {
+			// pointing straight at the camera, can't be dragged meaningfully
+			continue;
+		}
+
+		let handle = Rect::from_center_size(end, Vec2::splat(HANDLE_SIZE));
+		let resp = ui.interact(handle, Id::new(("scale_gizmo", axis)), Sense::drag());
+
+		let color = if resp.hovered() || resp.dragged() {
+			Color32::WHITE
+		} else {
+			*color
+		};
+		painter.line_segment([center, end], Stroke::new(2.0, color));
+		painter.rect_filled(handle, 0.0, color);
+
+		if resp.dragged() {
+			let delta = resp.drag_delta().dot(screen_axis.normalized());
+			factor[axis] = 1.0 + delta / screen_axis.length();
+		}
+	}
+	// }}}
+
+	// {{{ uniform handle
+	let handle = Rect::from_center_size(center, Vec2::splat(HANDLE_SIZE * 1.5));
+	let resp =
+		ui.interact(handle, Id::new(("scale_gizmo", "uniform")), Sense::drag());
+
+	let color = if resp.hovered() || resp.dragged() {
+		Color32::WHITE
+	} else {
+		Color32::LIGHT_GRAY
+	};
+	painter.circle_filled(center, HANDLE_SIZE * 0.75, color);
+
+	if resp.dragged() {
+		factor *= 1.0 + resp.drag_delta().x / UNIFORM_DRAG_PIXELS;
+	}
+	// }}}
+
+	if factor != glm::vec3(1.0, 1.0, 1.0) {
+		let scale = &mut data.scene.scale[i];
+		*scale = scale.component_mul(&factor).map(|s| s.max(MIN_SCALE));
+		data.scene.recalc_transforms();
+		data.scene.set_changed(true);
+	}
+}
@@ -0,0 +1,53 @@
+use egui::{Color32, Rect, Stroke, Ui};
+
+use crate::{settings::CompositionGuides, viewport::ViewportMapping};
+
+// fraction of each side that lies outside the safe area
+const SAFE_AREA_MARGIN: f32 = 0.05;
+
+// dims the parts of `bounds` that aren't rendered when the aspect is locked
+pub fn letterbox(ui: &Ui, bounds: Rect, mapping: &ViewportMapping) {
+	let painter = ui.painter_at(bounds);
+	let fill = Color32::from_black_alpha(160);
+	let rect = mapping.rect;
+
+	let bars = [
+		Rect::from_x_y_ranges(bounds.x_range(), bounds.top()..=rect.top()),
+		Rect::from_x_y_ranges(bounds.x_range(), rect.bottom()..=bounds.bottom()),
+		Rect::from_x_y_ranges(bounds.left()..=rect.left(), rect.y_range()),
+		Rect::from_x_y_ranges(rect.right()..=bounds.right(), rect.y_range()),
+	];
+	for bar in bars {
+		if bar.is_positive() {
+			painter.rect_filled(bar, 0.0, fill);
+		}
+	}
+}
+
+// composition guides over the rendered region
+pub fn draw(ui: &Ui, mapping: &ViewportMapping, guides: CompositionGuides) {
+	let rect = mapping.rect;
+	let painter = ui.painter_at(rect);
+	let stroke = Stroke::new(1.0, Color32::from_white_alpha(96));
+
+	if guides.thirds {
+		for t in [1.0 / 3.0, 2.0 / 3.0] {
+			let x = rect.left() + rect.width() * t;
+			let y = rect.top() + rect.height() * t;
+			painter.vline(x, rect.y_range(), stroke);
+			painter.hline(rect.x_range(), y, stroke);
+		}
+	}
+
+	if guides.center {
+		let c = rect.center();
+		let r = rect.size().min_elem() * 0.03;
+		painter.hline((c.x - r)..=(c.x + r), c.y, stroke);
+		painter.vline(c.x, (c.y - r)..=(c.y + r), stroke);
+	}
+
+	if guides.safe_area {
+		let safe = rect.shrink2(rect.size() * SAFE_AREA_MARGIN);
+		painter.rect_stroke(safe, 0.0, stroke);
+	}
+}
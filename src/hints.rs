@@ -0,0 +1,42 @@
+// Warnings drawn over the viewport on objects that probably don't look the
+// way they were meant to
+use egui::{Align2, Color32, FontId, Rect, Ui, Vec2};
+
+use crate::{app::PersistentData, geometry, viewport::ViewportMapping};
+
+const PADDING: Vec2 = Vec2::new(4.0, 2.0);
+const FONT_SIZE: f32 = 12.0;
+
+pub fn draw(ui: &Ui, mapping: &ViewportMapping, data: &PersistentData) {
+	let scene = &data.scene;
+	let painter = ui.painter_at(mapping.rect);
+	let eye = data.camera.eye();
+
+	for i in 0..scene.len() {
+		if !geometry::normals_inverted(scene, i, &eye) {
+			continue;
+		}
+		let Some(center) = mapping.project(&data.camera, scene.position[i]) else {
+			continue;
+		};
+		if !mapping.contains(center) {
+			continue;
+		}
+
+		let color = ui.visuals().warn_fg_color;
+		let galley = painter.layout_no_wrap(
+			"⚠ Inside out, try Flip normals".to_owned(),
+			FontId::proportional(FONT_SIZE),
+			color,
+		);
+		let rect = Rect::from_center_size(center, galley.size() + PADDING * 2.0);
+		painter.rect_filled(rect, 2.0, Color32::from_black_alpha(160));
+		painter.galley(
+			Align2::CENTER_CENTER
+				.align_size_within_rect(galley.size(), rect)
+				.min,
+			galley,
+			color,
+		);
+	}
+}
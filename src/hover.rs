@@ -0,0 +1,157 @@
+use egui::{Pos2, Ui};
+use nalgebra_glm::Vec3;
+
+use crate::{
+	app::PersistentData,
+	geometry::{self, HitObject, RayHit},
+	viewport::ViewportMapping,
+};
+
+// how long the pointer has to rest before the tooltip appears (seconds)
+const REST_TIME: f32 = 0.5;
+
+// minimum time between two raycasts of the cursor probe (seconds)
+const PROBE_INTERVAL: f32 = 0.1;
+
+// object info tooltip shown when the pointer rests over the viewport
+#[derive(Default)]
+pub struct Hover {
+	pointer: Option<Pos2>,
+	rest_time: f32,
+
+	// camera position and direction at the time of the raycast
+	camera: (Vec3, Vec3),
+
+	// outer option is whether the ray has been cast yet
+	hit: Option<Option<RayHit>>,
+}
+
+impl Hover {
+	fn reset(&mut self, pointer: Option<Pos2>) {
+		self.pointer = pointer;
+		self.rest_time = 0.0;
+		self.hit = None;
+	}
+
+	pub fn update(
+		&mut self,
+		ui: &Ui,
+		mapping: &ViewportMapping,
+		data: &PersistentData,
+	) {
+		let (pointer, busy, dt) = ui.input(|i| {
+			(
+				i.pointer.hover_pos(),
+				i.pointer.any_down(), // dragging or looking around
+				i.unstable_dt,
+			)
+		});
+
+		let enabled = data.settings.render.hover_tooltip
+			&& !busy
+			&& !data.scene.modal_open()
+			&& !data.settings.modal_open()
+			&& ui.ctx().dragged_id().is_none()
+			&& pointer_over_viewport(ui, mapping, pointer);
+
+		let camera = (data.camera.pos, data.camera.forward_dir);
+
+		if !enabled || pointer != self.pointer || camera != self.camera {
+			self.reset(pointer);
+			self.camera = camera;
+			return;
+		}
+
+		self.rest_time += dt;
+		if self.rest_time < REST_TIME {
+			return;
+		}
+
+		let pointer = pointer.unwrap();
+
+		// only cast once per rest, not on every frame
+		let hit = *self.hit.get_or_insert_with(|| cast(mapping, pointer, data));
+
+		if let Some(hit) = hit {
+			egui::show_tooltip_at_pointer(
+				ui.ctx(),
+				egui::Id::new("hover_tooltip"),
+				|ui| {
+					match hit.obj {
+						HitObject::Object(i) => {
+							ui.strong(&data.scene.name[i]);
+							ui.label(format!("Type: {}", data.scene.ty[i]));
+							ui.label(format!("Material: {}", data.scene.mat_ty[i]));
+						},
+						HitObject::Ground => {
+							ui.strong("Ground plane");
+						},
+					}
+					ui.label(format!("Distance: {:.3}", hit.distance));
+				},
+			);
+		}
+	}
+}
+
+// object under the cursor, shown in the settings window. updated continuously
+// but throttled, unlike the tooltip which waits for the pointer to rest
+#[derive(Default)]
+pub struct CursorProbe {
+	since_cast: f32,
+	hit: Option<HitObject>,
+}
+
+impl CursorProbe {
+	pub fn update(
+		&mut self,
+		ui: &Ui,
+		mapping: &ViewportMapping,
+		data: &PersistentData,
+	) {
+		let (pointer, dt) = ui.input(|i| (i.pointer.hover_pos(), i.unstable_dt));
+
+		// nothing when the cursor is over a window or outside the viewport
+		if !pointer_over_viewport(ui, mapping, pointer) {
+			self.hit = None;
+			self.since_cast = PROBE_INTERVAL; // cast right away on return
+			return;
+		}
+		let pointer = pointer.unwrap();
+
+		self.since_cast += dt;
+		if self.since_cast < PROBE_INTERVAL {
+			return;
+		}
+		self.since_cast = 0.0;
+
+		self.hit = cast(mapping, pointer, data).map(|hit| hit.obj);
+	}
+
+	// name of the hovered object, the object may have been deleted since the
+	// last cast
+	pub fn hovered_name<'a>(&self, data: &'a PersistentData) -> Option<&'a str> {
+		match self.hit? {
+			HitObject::Object(i) => data.scene.name.get(i).map(String::as_str),
+			HitObject::Ground => Some("Ground plane"),
+		}
+	}
+}
+
+fn pointer_over_viewport(
+	ui: &Ui,
+	mapping: &ViewportMapping,
+	pointer: Option<Pos2>,
+) -> bool {
+	pointer
+		.is_some_and(|p| ui.rect_contains_pointer(mapping.rect) && mapping.contains(p))
+}
+
+fn cast(
+	mapping: &ViewportMapping,
+	pointer: Pos2,
+	data: &PersistentData,
+) -> Option<RayHit> {
+	let ray = data.camera.ray_through(mapping.point_to_ndc(pointer));
+	geometry::intersect_world(&data.scene, &data.settings.world, &ray)
+}
@@ -0,0 +1,60 @@
+use egui::{Color32, Rgba, Ui};
+
+use crate::{app::PersistentData, render::Readback, viewport::ViewportMapping};
+
+// the rendered value under the pointer, shown next to it. the value is read
+// back asynchronously, so it lags the pointer by a frame or two
+#[derive(Default)]
+pub struct PixelInspector {
+	last: Option<Readback>,
+}
+
+impl PixelInspector {
+	// returns the texel to read back this frame, if any
+	pub fn update(
+		&mut self,
+		ui: &Ui,
+		mapping: &ViewportMapping,
+		data: &PersistentData,
+	) -> Option<[i32; 2]> {
+		let pointer = ui.input(|i| i.pointer.hover_pos());
+		let pointer = pointer.filter(|&p| {
+			data.settings.render.pixel_inspector
+				&& mapping.contains(p)
+				&& ui.rect_contains_pointer(mapping.rect)
+		});
+		let Some(pointer) = pointer else {
+			self.last = None;
+			return None;
+		};
+
+		if let Some(readback) = &self.last {
+			let [x, y, ..] = readback.region;
+			let [r, g, b, _] = readback.texels[0];
+			egui::show_tooltip_at_pointer(
+				ui.ctx(),
+				egui::Id::new("pixel_inspector"),
+				|ui| {
+					ui.horizontal(|ui| {
+						let (rect, _) =
+							ui.allocate_exact_size(egui::Vec2::splat(16.0), egui::Sense::hover());
+						let srgb = Color32::from(Rgba::from_rgb(r, g, b));
+						ui.painter().rect_filled(rect, 2.0, srgb);
+						ui.label(format!("{r:.4}, {g:.4}, {b:.4}"));
+					});
+					ui.label(format!("Pixel: {x}, {y}"));
+					ui.label(format!("Samples: {}", readback.samples));
+				},
+			);
+		}
+
+		Some(mapping.point_to_texel(pointer))
+	}
+
+	// empty readbacks (a texel outside of the image) are ignored
+	pub fn set(&mut self, readback: Readback) {
+		if !readback.texels.is_empty() {
+			self.last = Some(readback);
+		}
+	}
+}
@@ -0,0 +1,110 @@
+// Object names drawn over the viewport, above each object with a short
+// leader line. Labels are placed nearest first and pushed up until they
+// don't overlap the ones already placed.
+use egui::{Align2, Color32, FontId, Rect, Stroke, Ui, Vec2};
+use nalgebra_glm as glm;
+
+use crate::{
+	app::PersistentData,
+	geometry::{self, HitObject, Ray},
+	viewport::ViewportMapping,
+};
+
+// length of the leader line (points)
+const LEADER_LENGTH: f32 = 12.0;
+const PADDING: Vec2 = Vec2::new(4.0, 2.0);
+const FONT_SIZE: f32 = 12.0;
+
+// labels that can't be placed after this many attempts overlap
+const MAX_PUSHES: usize = 8;
+
+pub fn draw(ui: &Ui, mapping: &ViewportMapping, data: &PersistentData) {
+	let scene = &data.scene;
+	let camera = &data.camera;
+	let occlusion = data.settings.render.label_occlusion;
+	let painter = ui.painter_at(mapping.rect);
+	let eye = camera.eye();
+
+	let mut order: Vec<usize> = (0..scene.len()).collect();
+	order.sort_by(|&a, &b| {
+		let da = glm::distance(&scene.position[a], &eye);
+		let db = glm::distance(&scene.position[b], &eye);
+		da.total_cmp(&db)
+	});
+
+	let mut placed: Vec<Rect> = Vec::new();
+	for i in order {
+		let center = scene.position[i];
+		let Some(center_point) = mapping.project(camera, center) else {
+			continue;
+		};
+		if !mapping.contains(center_point) {
+			continue;
+		}
+
+		// top of the bounding sphere, clamped so the leader stays on screen
+		let top = center + glm::vec3(0.0, geometry::bounding_radius(scene, i), 0.0);
+		let anchor = mapping
+			.project(camera, top)
+			.unwrap_or(center_point)
+			.clamp(mapping.rect.min, mapping.rect.max);
+
+		let hidden = occlusion && occluded(data, i);
+		let (text_color, line_color) = if hidden {
+			(Color32::from_gray(140), Color32::from_white_alpha(48))
+		} else {
+			(Color32::WHITE, Color32::from_white_alpha(128))
+		};
+
+		let galley = painter.layout_no_wrap(
+			scene.name[i].clone(),
+			FontId::proportional(FONT_SIZE),
+			text_color,
+		);
+		let size = galley.size() + PADDING * 2.0;
+
+		// {{{ place
+		let mut rect = Rect::from_center_size(
+			anchor - Vec2::new(0.0, LEADER_LENGTH + size.y / 2.0),
+			size,
+		);
+		for _ in 0..MAX_PUSHES {
+			rect = keep_inside(rect, mapping.rect);
+			let Some(other) = placed.iter().find(|r| r.intersects(rect)) else {
+				break;
+			};
+			rect = rect.translate(Vec2::new(0.0, other.top() - rect.bottom() - 2.0));
+		}
+		rect = keep_inside(rect, mapping.rect);
+		placed.push(rect);
+		// }}}
+
+		painter
+			.line_segment([anchor, rect.center_bottom()], Stroke::new(1.0, line_color));
+		painter.rect_filled(rect, 2.0, Color32::from_black_alpha(160));
+		painter.galley(
+			Align2::CENTER_CENTER
+				.align_size_within_rect(galley.size(), rect)
+				.min,
+			galley,
+			text_color,
+		);
+	}
+}
+
+// whether something is in between the camera and the object's center
+fn occluded(data: &PersistentData, i: usize) -> bool {
+	let eye = data.camera.eye();
+	let ray = Ray::new(eye, data.scene.position[i] - eye);
+	match geometry::intersect_world(&data.scene, &data.settings.world, &ray) {
+		Some(hit) => hit.obj != HitObject::Object(i),
+		None => false,
+	}
+}
+
+// moves a rect inside the bounds, keeping its size where possible
+fn keep_inside(rect: Rect, bounds: Rect) -> Rect {
+	let min = rect.min.max(bounds.min);
+	let min = min.min(bounds.max - rect.size()).max(bounds.min);
+	Rect::from_min_size(min, rect.size())
+}
@@ -1,9 +1,29 @@
 mod app;
 mod camera;
+mod controls;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod crash;
+mod eyedropper;
 mod geometry;
+mod gizmo;
+mod guides;
+mod hints;
+mod hover;
+mod inspector;
+mod labels;
+mod light_path;
+mod lod;
+mod mesh;
+mod overrides;
+mod reference;
 mod render;
+mod render_job;
+mod safe_mode;
 mod scene;
 mod settings;
+mod sky;
+mod tutorial;
 mod util;
+mod viewport;
 
 pub use app::RaytracingApp;
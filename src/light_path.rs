@@ -0,0 +1,518 @@
+// Paths of a single pixel traced on the CPU, for seeing where its light comes
+// from. Ctrl + click on the viewport traces the first few samples of the
+// clicked pixel and draws them over the render, one color per bounce.
+// This is a port of path_trace in fsh.glsl that uses the same random streams
+// as the first frames after a reset, but the intersections come from
+// geometry.rs and LODs, solo and the selection highlight are left out, so the
+// paths are close to what the shader traces rather than exactly the same.
+use egui::{Color32, Grid, Slider, Stroke, Ui};
+use nalgebra_glm::{self as glm, vec3, Vec3};
+
+use crate::{
+	app::PersistentData,
+	geometry::{self, HitObject, Ray, RayHit},
+	render::{BIAS_ABSOLUTE, BIAS_MAX_OBJECT_FRACTION, BIAS_RELATIVE},
+	scene::MaterialType,
+	util::pcg_hash,
+	viewport::ViewportMapping,
+};
+
+// samples traced per click, each one is what the pixel gets on that frame
+const PATH_COUNT: u32 = 4;
+
+// length of the segment drawn for rays that escape to the sky
+const ESCAPE_LENGTH: f32 = 100.0;
+
+// segments are drawn in these colors by bounce index, the last one repeats
+const BOUNCE_COLORS: [Color32; 6] = [
+	Color32::from_rgb(255, 255, 255),
+	Color32::from_rgb(255, 200, 60),
+	Color32::from_rgb(255, 110, 60),
+	Color32::from_rgb(230, 60, 140),
+	Color32::from_rgb(150, 70, 230),
+	Color32::from_rgb(70, 110, 255),
+];
+
+// {{{ random streams
+// ported from noise.glsl and fsh.glsl, see the notes there
+const STREAM_SALT: u32 = 0x9e37_79b9;
+const STREAM_INCREMENT: [u32; 4] = [0, 0x68e3_1da4, 0xb529_7a4d, 0x1b56_c4e9];
+const RECIP_UINT_MAX: f32 = 1.0 / u32::MAX as f32;
+
+struct Rng {
+	state: [u32; 4],
+	counter: u32,
+}
+
+impl Rng {
+	// the noise prepass output for a pixel on a frame, counted from 1 after a
+	// reset. `coords` is the pixel index in the render targets
+	fn new(coords: u32, seed: u32, frame: u32) -> Self {
+		let state = std::array::from_fn(|i| {
+			let salt = STREAM_SALT.wrapping_mul(i as u32 + 1);
+			let mut v = pcg_hash(coords ^ seed ^ salt);
+			for _ in 0..frame {
+				v = pcg_hash(v.wrapping_add(STREAM_INCREMENT[i])).max(1);
+			}
+			v
+		});
+		Self { state, counter: 0 }
+	}
+
+	fn next(&mut self) -> f32 {
+		let i = self.counter;
+		self.counter = self.counter.wrapping_add(1);
+		let lane = self.state[(i & 3) as usize];
+		pcg_hash(lane ^ pcg_hash(i)) as f32 * RECIP_UINT_MAX
+	}
+
+	fn next3(&mut self) -> Vec3 {
+		let x = self.next();
+		let y = self.next();
+		vec3(x, y, self.next())
+	}
+}
+// }}}
+
+// {{{ scattering
+// the material accessors of the shader, with the ground's defaults
+struct Material {
+	ty: MaterialType,
+	color: Vec3,
+	ior: f32,
+	specular: f32,
+	roughness: f32,
+	metallic: f32,
+	emissive_strength: f32,
+}
+
+impl Material {
+	fn of(data: &PersistentData, obj: HitObject) -> Self {
+		let multiplier = data.settings.render.roughness_multiplier;
+		match obj {
+			HitObject::Object(i) => {
+				let scene = &data.scene;
+				let group = scene.mat_light_group[i] as usize;
+				Self {
+					ty: scene.mat_ty[i],
+					color: Vec3::from(scene.mat_color[i]),
+					ior: scene.mat_ior[i],
+					specular: scene.mat_specular[i],
+					roughness: (scene.mat_roughness[i] * multiplier).clamp(0.0, 1.0),
+					metallic: scene.mat_metallic[i],
+					emissive_strength: scene.mat_emissive_strength[i]
+						* scene.light_group_scales().get(group).unwrap_or(&1.0),
+				}
+			},
+			HitObject::Ground => {
+				let world = &data.settings.world;
+				Self {
+					ty: MaterialType::Solid,
+					color: Vec3::from(world.ground_color),
+					ior: 1.333,
+					specular: 1.0,
+					roughness: (world.ground_roughness * multiplier).clamp(0.0, 1.0),
+					metallic: 0.0,
+					emissive_strength: 0.0,
+				}
+			},
+		}
+	}
+}
+
+fn hit_bias(data: &PersistentData, hit: &RayHit) -> f32 {
+	let magnitude = hit.pos.abs().max().max(hit.distance);
+	let bias =
+		(BIAS_ABSOLUTE + BIAS_RELATIVE * magnitude) * data.settings.render.ray_bias;
+	match hit.obj {
+		HitObject::Object(i) => {
+			bias.min(data.scene.scale[i].abs().min() * BIAS_MAX_OBJECT_FRACTION)
+		},
+		HitObject::Ground => bias,
+	}
+}
+
+fn reflect(dir: &Vec3, normal: &Vec3) -> Vec3 {
+	dir - normal * 2.0 * glm::dot(normal, dir)
+}
+
+fn cos_dist_in_hemi(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+	let res = glm::normalize(&(normal + (rng.next3() * 2.0).add_scalar(-1.0)));
+	if glm::dot(&res, normal) < 0.0 {
+		-res
+	} else {
+		res
+	}
+}
+
+fn ggx_normal(normal: &Vec3, alpha: f32, rng: &mut Rng) -> Vec3 {
+	let (u, v) = (rng.next(), rng.next());
+	let a2 = alpha * alpha;
+	let cos_theta = ((1.0 - u) / (1.0 + (a2 - 1.0) * u)).sqrt();
+	let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+	let phi = std::f32::consts::TAU * v;
+
+	let helper = if normal.x.abs() > 0.5 {
+		vec3(0.0, 1.0, 0.0)
+	} else {
+		vec3(1.0, 0.0, 0.0)
+	};
+	let tangent = glm::normalize(&glm::cross(&helper, normal));
+	let bitangent = glm::cross(normal, &tangent);
+
+	glm::normalize(
+		&((tangent * phi.cos() + bitangent * phi.sin()) * sin_theta
+			+ normal * cos_theta),
+	)
+}
+
+// same as metal_fresnel in the shader
+fn metal_fresnel(f0: &Vec3, incident: &Vec3, normal: &Vec3) -> Vec3 {
+	let x = 1.0 - (-glm::dot(normal, incident)).clamp(0.0, 1.0);
+	f0 + (vec3(1.0, 1.0, 1.0) - f0) * x.powi(5)
+}
+
+fn schlick_fresnel(
+	ior_hit: f32,
+	incident: &Vec3,
+	normal: &Vec3,
+	min_refl: f32,
+) -> f32 {
+	let r0 = ((1.0 - ior_hit) / (1.0 + ior_hit)).powi(2);
+	let mut cos_x = -glm::dot(normal, incident);
+
+	if 1.0 > ior_hit {
+		let n = 1.0 / ior_hit;
+		let sin_t2 = n * n * (1.0 - cos_x * cos_x);
+		if sin_t2 > 1.0 {
+			return 1.0;
+		}
+		cos_x = (1.0 - sin_t2).sqrt();
+	}
+
+	let x = 1.0 - cos_x;
+	glm::lerp_scalar(min_refl, 1.0, r0 + (1.0 - r0) * x.powi(5))
+}
+// }}}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Lobe {
+	Diffuse,
+	Specular,
+	Glossy,
+}
+
+// one ray of a path, from where it starts to what it hits
+struct Segment {
+	start: Vec3,
+	end: Vec3,
+	hit: Option<RayHit>, // none for the sky
+	sun: bool,           // the sky in the direction of the sun
+	throughput: Vec3,    // after the surface that was hit
+	lobe: Option<Lobe>,  // how the ray leaves the surface, none at the end
+}
+
+// the ray through the pixel and its index in the render targets
+#[derive(Clone, Copy)]
+struct Pixel {
+	ray: Ray,
+	texel: [i32; 2],
+	coords: u32,
+}
+
+#[derive(Default)]
+pub struct LightPaths {
+	pixel: Option<Pixel>,
+	paths: Vec<Vec<Segment>>,
+	shown: u32, // path listed in the window, from 1
+}
+
+impl LightPaths {
+	// ctrl + click traces the pixel under the pointer. `seed` is the
+	// renderer's noise seed
+	pub fn update(
+		&mut self,
+		ui: &Ui,
+		mapping: &ViewportMapping,
+		data: &PersistentData,
+		seed: u32,
+	) {
+		let (pointer, command, clicked) = ui.input(|i| {
+			(
+				i.pointer.interact_pos(),
+				i.modifiers.command,
+				i.pointer.primary_clicked(),
+			)
+		});
+
+		if let Some(pointer) = pointer.filter(|&p| {
+			clicked
+				&& command
+				&& mapping.contains(p)
+				&& ui.rect_contains_pointer(mapping.rect)
+		}) {
+			// the noise prepass numbers pixels by their centers, within the
+			// view in stereo
+			let texel = mapping.point_to_texel(pointer);
+			let width = mapping.view_texture_size().x;
+			let coords = (texel[1] as f32 + 0.5) * width + texel[0] as f32 + 0.5;
+
+			self.pixel = Some(Pixel {
+				ray: data.camera.ray_through(mapping.point_to_ndc(pointer)),
+				texel,
+				coords: coords as u32,
+			});
+			self.shown = 1;
+			self.trace(data, seed);
+		} else if data.scene.response.changed_or_moved()
+			|| data.settings.response.changed
+		{
+			// the pixel stays, the paths follow the scene
+			self.trace(data, seed);
+		}
+	}
+
+	fn trace(&mut self, data: &PersistentData, seed: u32) {
+		let Some(pixel) = self.pixel else {
+			return;
+		};
+
+		self.paths = (1..=PATH_COUNT)
+			.map(|frame| {
+				let mut rng = Rng::new(pixel.coords, seed, frame);
+				trace_path(data, pixel.ray, &mut rng)
+			})
+			.collect();
+	}
+
+	// closing the window removes the paths
+	pub fn window(&mut self, egui: &egui::Context, data: &PersistentData) {
+		let Some(pixel) = self.pixel else {
+			return;
+		};
+
+		let mut open = true;
+		egui::Window::new("Light path")
+			.open(&mut open)
+			.resizable(false)
+			.show(egui, |ui| {
+				ui.label(format!("Pixel: {}, {}", pixel.texel[0], pixel.texel[1]));
+				ui.add(Slider::new(&mut self.shown, 1..=PATH_COUNT).text("Sample"));
+
+				let Some(path) = self.paths.get(self.shown as usize - 1) else {
+					return;
+				};
+
+				Grid::new("light_path_bounces")
+					.striped(true)
+					.show(ui, |ui| {
+						ui.strong("Bounce");
+						ui.strong("Hit");
+						ui.strong("Distance");
+						ui.strong("Throughput");
+						ui.strong("Leaves");
+						ui.end_row();
+
+						for (n, segment) in path.iter().enumerate() {
+							ui.colored_label(bounce_color(n), n.to_string());
+							match segment.hit {
+								Some(hit) => {
+									ui.label(match hit.obj {
+										HitObject::Object(i) => data.scene.name[i].as_str(),
+										HitObject::Ground => "Ground plane",
+									});
+									ui.label(format!("{:.3}", hit.distance));
+								},
+								None => {
+									ui.label(if segment.sun { "Sun" } else { "Sky" });
+									ui.label("∞");
+								},
+							}
+							let t = segment.throughput;
+							ui.label(format!("{:.3}, {:.3}, {:.3}", t.x, t.y, t.z));
+							ui.label(match segment.lobe {
+								Some(Lobe::Diffuse) => "Diffuse",
+								Some(Lobe::Specular) => "Specular",
+								Some(Lobe::Glossy) => "Glossy",
+								None => "",
+							});
+							ui.end_row();
+						}
+					});
+			});
+
+		if !open {
+			self.pixel = None;
+			self.paths.clear();
+		}
+	}
+
+	// the path listed in the window is drawn on top of the others
+	pub fn draw(&self, ui: &Ui, mapping: &ViewportMapping, data: &PersistentData) {
+		if self.paths.is_empty() {
+			return;
+		}
+
+		let painter = ui.painter_at(mapping.rect);
+		let camera = &data.camera;
+		let eye = camera.eye();
+
+		let shown = self.shown as usize - 1;
+		let order = (0..self.paths.len()).filter(|&p| p != shown).chain([shown]);
+		for p in order {
+			let Some(path) = self.paths.get(p) else {
+				continue;
+			};
+
+			let (width, alpha) = if p == shown { (2.0, 1.0) } else { (1.0, 0.3) };
+			for (n, segment) in path.iter().enumerate() {
+				let Some((start, end)) =
+					clip_to_front(segment.start, segment.end, &eye, &camera.forward_dir)
+				else {
+					continue;
+				};
+				let (Some(start), Some(end)) =
+					(mapping.project(camera, start), mapping.project(camera, end))
+				else {
+					continue;
+				};
+
+				let color = bounce_color(n).gamma_multiply(alpha);
+				painter.line_segment([start, end], Stroke::new(width, color));
+				if segment.hit.is_some() {
+					painter.circle_filled(end, width + 1.0, color);
+				}
+			}
+		}
+	}
+}
+
+fn bounce_color(n: usize) -> Color32 {
+	BOUNCE_COLORS[n.min(BOUNCE_COLORS.len() - 1)]
+}
+
+// the part of a segment in front of the camera, so that both ends project
+fn clip_to_front(
+	start: Vec3,
+	end: Vec3,
+	eye: &Vec3,
+	forward: &Vec3,
+) -> Option<(Vec3, Vec3)> {
+	const NEAR: f32 = 0.01;
+	let (ds, de) = (
+		glm::dot(&(start - eye), forward),
+		glm::dot(&(end - eye), forward),
+	);
+	if ds < NEAR && de < NEAR {
+		return None;
+	}
+
+	let cut = |from: Vec3, to: Vec3, df: f32, dt: f32| {
+		from + (to - from) * ((NEAR - df) / (dt - df))
+	};
+	Some(match (ds < NEAR, de < NEAR) {
+		(true, _) => (cut(start, end, ds, de), end),
+		(_, true) => (start, cut(end, start, de, ds)),
+		_ => (start, end),
+	})
+}
+
+// port of path_trace, one segment per bounce
+fn trace_path(data: &PersistentData, primary: Ray, rng: &mut Rng) -> Vec<Segment> {
+	let world = &data.settings.world;
+	let sun_dir = world.sun_dir(data.camera.yaw());
+	let sun_cos_radius = world.sun_cos_radius();
+
+	// antialiasing offset
+	let forward = data.camera.forward_dir;
+	let up = vec3(0.0, 1.0, 0.0);
+	let ofs = (rng.next(), rng.next());
+	let ofs = ((ofs.0 * 2.0 - 1.0) / 1000.0, (ofs.1 * 2.0 - 1.0) / 1000.0);
+	let dir = primary.dir + glm::cross(&forward, &up) * ofs.0 + up * ofs.1;
+	let mut ray = Ray::new(primary.origin, dir);
+
+	if data.camera.aperture > 0.0 {
+		ray = data.camera.lens_ray(ray, (rng.next(), rng.next()));
+	}
+
+	let mut contribution = vec3(1.0, 1.0, 1.0);
+	let mut path = Vec::new();
+
+	for _ in 0..=data.settings.render.max_bounces {
+		let Some(hit) = geometry::intersect_world(&data.scene, world, &ray) else {
+			// same test as the sun disc in the shader
+			path.push(Segment {
+				start: ray.origin,
+				end: ray.at(ESCAPE_LENGTH),
+				hit: None,
+				sun: glm::dot(&ray.dir, &sun_dir) >= sun_cos_radius,
+				throughput: contribution,
+				lobe: None,
+			});
+			break;
+		};
+
+		let mat = Material::of(data, hit.obj);
+		// see-through like in the shader, the shadows on it aren't traced
+		if mat.ty == MaterialType::ShadowCatcher {
+			ray = Ray::new(hit.pos + ray.dir * hit_bias(data, &hit), ray.dir);
+			continue;
+		}
+		if mat.ty == MaterialType::Emissive {
+			path.push(Segment {
+				start: ray.origin,
+				end: hit.pos,
+				hit: Some(hit),
+				sun: false,
+				throughput: contribution * mat.emissive_strength,
+				lobe: None,
+			});
+			break;
+		}
+		// metal reflections are tinted by fresnel instead, below
+		if mat.ty != MaterialType::Metal {
+			contribution.component_mul_assign(&mat.color);
+		}
+
+		let r = mat.roughness * mat.roughness;
+
+		let diffuse = cos_dist_in_hemi(&hit.normal, rng);
+		let specular = reflect(&ray.dir, &hit.normal);
+		let specular = glm::normalize(&glm::lerp(&specular, &diffuse, r * r));
+
+		let specular_chance = if mat.specular > 0.0 {
+			schlick_fresnel(mat.ior, &ray.dir, &hit.normal, mat.specular)
+		} else {
+			mat.specular
+		};
+
+		let (dir, lobe) = if mat.ty == MaterialType::Metal {
+			let facet = ggx_normal(&hit.normal, r, rng);
+			let glossy = reflect(&ray.dir, &facet);
+			if rng.next() < mat.metallic && glm::dot(&glossy, &hit.normal) > 0.0 {
+				let fresnel = metal_fresnel(&mat.color, &ray.dir, &facet);
+				contribution.component_mul_assign(&fresnel);
+				(glossy, Lobe::Glossy)
+			} else {
+				contribution.component_mul_assign(&mat.color);
+				(diffuse, Lobe::Diffuse)
+			}
+		} else if rng.next() < specular_chance {
+			(specular, Lobe::Specular)
+		} else {
+			(diffuse, Lobe::Diffuse)
+		};
+
+		path.push(Segment {
+			start: ray.origin,
+			end: hit.pos,
+			hit: Some(hit),
+			sun: false,
+			throughput: contribution,
+			lobe: Some(lobe),
+		});
+		ray = Ray::new(hit.pos + hit.normal * hit_bias(data, &hit), dir);
+	}
+
+	path
+}
@@ -0,0 +1,46 @@
+// Level of detail for objects that are tiny on screen. Their contribution to
+// indirect light is negligible, so they can be skipped by secondary rays, and
+// at higher aggressiveness left out entirely. The classification is done on
+// the CPU from the projected size of each object's bounding sphere.
+use crate::{app::PersistentData, geometry};
+
+// must match the LOD_* constants in fsh.glsl
+pub const LOD_FULL: u32 = 0;
+pub const LOD_PRIMARY_ONLY: u32 = 1;
+pub const LOD_SKIP: u32 = 2;
+
+// projected radii (pixels) below which objects are simplified, at full
+// aggressiveness
+const PRIMARY_ONLY_RADIUS: f32 = 4.0;
+const SKIP_RADIUS: f32 = 0.5;
+
+// one LOD_* value per object
+pub fn classify(data: &PersistentData, scr_height: f32) -> Vec<u32> {
+	let aggressiveness = data.settings.render.lod_aggressiveness;
+	let scene = &data.scene;
+	if aggressiveness <= 0.0 {
+		return vec![LOD_FULL; scene.len()];
+	}
+
+	let eye = data.camera.eye();
+	let pixels_per_unit = scr_height / 2.0 / (data.settings.render.fov / 2.0).tan();
+
+	(0..scene.len())
+		.map(|i| {
+			let radius = geometry::bounding_radius(scene, i);
+			let distance = nalgebra_glm::distance(&scene.position[i], &eye);
+			if distance <= radius {
+				return LOD_FULL;
+			}
+
+			let projected = radius / distance * pixels_per_unit;
+			if projected < SKIP_RADIUS * aggressiveness {
+				LOD_SKIP
+			} else if projected < PRIMARY_ONLY_RADIUS * aggressiveness {
+				LOD_PRIMARY_ONLY
+			} else {
+				LOD_FULL
+			}
+		})
+		.collect()
+}
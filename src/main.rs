@@ -6,6 +6,7 @@ use raytracer::RaytracingApp;
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
 	env_logger::init();
+	raytracer::crash::install_hook();
 
 	let native_options = eframe::NativeOptions {
 		viewport: egui::ViewportBuilder::default()
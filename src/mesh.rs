@@ -0,0 +1,308 @@
+// Triangle meshes loaded from Wavefront OBJ files. They're fitted into the
+// unit cube on import like every other primitive, so the object's scale sets
+// their size. Only vertex positions and faces are read, the normals are flat
+// and face the ray, so the winding order doesn't matter.
+use nalgebra_glm::{self as glm, Vec3};
+
+use crate::{geometry::Ray, scene::Scene, util::dropped_file_bytes};
+
+// triangles of all meshes in the scene together. the shader tests every
+// triangle of a mesh, so this keeps it from getting too slow
+pub const MAX_MESH_TRIANGLES: usize = 4096;
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct MeshData {
+	pub positions: Vec<[f32; 3]>,
+	pub triangles: Vec<[u32; 3]>, // indices into `positions`
+}
+
+impl MeshData {
+	pub fn from_obj(text: &str) -> Result<Self, String> {
+		let mut mesh = Self::default();
+
+		for (n, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or("");
+			let mut words = line.split_whitespace();
+			let error = |e: &str| format!("line {}: {e}", n + 1);
+
+			match words.next() {
+				Some("v") => {
+					let mut p = [0.0; 3];
+					for c in &mut p {
+						*c = words
+							.next()
+							.and_then(|w| w.parse().ok())
+							.ok_or_else(|| error("expected three coordinates"))?;
+					}
+					mesh.positions.push(p);
+				},
+				Some("f") => {
+					// `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indices count
+					// back from the last vertex
+					let corners = words
+						.map(|w| {
+							let index: i64 = w
+								.split('/')
+								.next()
+								.and_then(|v| v.parse().ok())
+								.ok_or_else(|| error("invalid face"))?;
+							let len = mesh.positions.len() as i64;
+							let index = if index < 0 { len + index } else { index - 1 };
+							if !(0..len).contains(&index) {
+								return Err(error("face refers to a missing vertex"));
+							}
+							Ok(index as u32)
+						})
+						.collect::<Result<Vec<_>, _>>()?;
+					if corners.len() < 3 {
+						return Err(error("faces need at least three corners"));
+					}
+
+					// polygons are split into a fan
+					for pair in corners[1..].windows(2) {
+						mesh.triangles.push([corners[0], pair[0], pair[1]]);
+					}
+				},
+				_ => {},
+			}
+		}
+
+		mesh.check()?;
+		mesh.fit_unit_cube()?;
+		Ok(mesh)
+	}
+
+	// binary or ASCII STL. the stored normals are ignored, the faces get flat
+	// normals from their corners like those of every other mesh
+	pub fn from_stl(bytes: &[u8]) -> Result<Self, String> {
+		// ASCII files start with "solid", but so do some binary ones. the size
+		// of a binary file follows from its triangle count
+		let binary_size = bytes
+			.get(80..84)
+			.map(|n| u32::from_le_bytes(n.try_into().unwrap()) as usize)
+			.and_then(|count| count.checked_mul(50)?.checked_add(84));
+		let mut mesh =
+			if binary_size == Some(bytes.len()) || !bytes.starts_with(b"solid") {
+				Self::from_binary_stl(bytes)?
+			} else {
+				let text = std::str::from_utf8(bytes)
+					.map_err(|_| "the file is neither binary nor ASCII STL")?;
+				Self::from_ascii_stl(text)?
+			};
+
+		mesh.check()?;
+		mesh.fit_unit_cube()?;
+		Ok(mesh)
+	}
+
+	// an 80 byte header, the triangle count and 50 bytes per triangle: the
+	// normal, the corners and two unused bytes
+	fn from_binary_stl(bytes: &[u8]) -> Result<Self, String> {
+		let Some(count) = bytes.get(80..84) else {
+			return Err("the file is too short for an STL header".into());
+		};
+		let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+		if count > MAX_MESH_TRIANGLES {
+			return Err(format!(
+				"{count} triangles, the limit is {MAX_MESH_TRIANGLES}"
+			));
+		}
+		let body = &bytes[84..];
+		if body.len() < count * 50 {
+			return Err(format!(
+				"the file is truncated, it should have {count} triangles"
+			));
+		}
+
+		let mut mesh = Self::default();
+		for triangle in body.chunks_exact(50).take(count) {
+			let first = mesh.positions.len() as u32;
+			for corner in triangle[12..48].chunks_exact(12) {
+				let c = |i: usize| {
+					f32::from_le_bytes(corner[i * 4..i * 4 + 4].try_into().unwrap())
+				};
+				mesh.positions.push([c(0), c(1), c(2)]);
+			}
+			mesh.triangles.push([first, first + 1, first + 2]);
+		}
+		Ok(mesh)
+	}
+
+	fn from_ascii_stl(text: &str) -> Result<Self, String> {
+		let mut mesh = Self::default();
+		let mut corners = Vec::new();
+
+		for (n, line) in text.lines().enumerate() {
+			let mut words = line.split_whitespace();
+			let error = |e: &str| format!("line {}: {e}", n + 1);
+
+			match words.next() {
+				Some("outer") => corners.clear(),
+				Some("vertex") => {
+					let mut p = [0.0; 3];
+					for c in &mut p {
+						*c = words
+							.next()
+							.and_then(|w| w.parse().ok())
+							.ok_or_else(|| error("expected three coordinates"))?;
+					}
+					corners.push(p);
+				},
+				Some("endloop") => {
+					if corners.len() != 3 {
+						return Err(error("facets need exactly three vertices"));
+					}
+					let first = mesh.positions.len() as u32;
+					mesh.positions.append(&mut corners);
+					mesh.triangles.push([first, first + 1, first + 2]);
+				},
+				_ => {},
+			}
+		}
+		Ok(mesh)
+	}
+
+	// limits that every imported mesh is held to
+	fn check(&self) -> Result<(), String> {
+		if self.triangles.is_empty() {
+			return Err("the file has no faces".into());
+		}
+		if self.triangles.len() > MAX_MESH_TRIANGLES {
+			return Err(format!(
+				"{} triangles, the limit is {MAX_MESH_TRIANGLES}",
+				self.triangles.len()
+			));
+		}
+		if !self.is_valid() {
+			return Err("the file has invalid coordinates".into());
+		}
+		Ok(())
+	}
+
+	// centered, with the largest dimension spanning [-1, 1]. returns the old
+	// center and half of the largest dimension, which undo the fit
+	pub fn fit_unit_cube(&mut self) -> Result<(Vec3, f32), String> {
+		let mut min = Vec3::repeat(f32::MAX);
+		let mut max = Vec3::repeat(f32::MIN);
+		for p in &self.positions {
+			min = min.inf(&Vec3::from(*p));
+			max = max.sup(&Vec3::from(*p));
+		}
+
+		let center = (min + max) / 2.0;
+		let half = ((max - min) / 2.0).max();
+		if half <= 0.0 {
+			return Err("the mesh has no size".into());
+		}
+
+		for p in &mut self.positions {
+			*p = ((Vec3::from(*p) - center) / half).into();
+		}
+		Ok((center, half))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.triangles.is_empty()
+	}
+
+	// finite coordinates and indices that point at vertices
+	pub fn is_valid(&self) -> bool {
+		let len = self.positions.len() as u32;
+		self.positions.iter().flatten().all(|c| c.is_finite())
+			&& self.triangles.iter().flatten().all(|&i| i < len)
+	}
+
+	// the three corners of every triangle
+	pub fn corners(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+		self
+			.triangles
+			.iter()
+			.flatten()
+			.map(|&i| self.positions[i as usize])
+	}
+
+	// nearest triangle, Möller-Trumbore like intersect_mesh in fsh.glsl
+	pub fn intersect(&self, local_ray: &Ray) -> Option<(Vec3, Vec3)> {
+		let (o, d) = (&local_ray.origin, &local_ray.dir);
+		let mut nearest: Option<(f32, Vec3)> = None;
+
+		for tri in &self.triangles {
+			let [v0, v1, v2] = tri.map(|i| Vec3::from(self.positions[i as usize]));
+			let e1 = v1 - v0;
+			let e2 = v2 - v0;
+
+			let p = glm::cross(d, &e2);
+			let det = glm::dot(&e1, &p);
+			if det.abs() < 1e-12 {
+				continue;
+			}
+			let inv = 1.0 / det;
+
+			let s = o - v0;
+			let u = glm::dot(&s, &p) * inv;
+			if !(0.0..=1.0).contains(&u) {
+				continue;
+			}
+			let q = glm::cross(&s, &e1);
+			let v = glm::dot(d, &q) * inv;
+			if v < 0.0 || u + v > 1.0 {
+				continue;
+			}
+
+			let t = glm::dot(&e2, &q) * inv;
+			if t >= 0.0 && nearest.map_or(true, |(best, _)| t < best) {
+				nearest = Some((t, glm::cross(&e1, &e2)));
+			}
+		}
+
+		let (t, normal) = nearest?;
+		let normal = glm::normalize(&normal);
+		let normal = if glm::dot(&normal, d) > 0.0 {
+			-normal
+		} else {
+			normal
+		};
+		Some((local_ray.at(t), normal))
+	}
+}
+
+// {{{ importing
+// turns OBJ files dropped onto the window into objects, returns messages about
+// the results
+pub fn import_dropped(egui: &egui::Context, scene: &mut Scene) -> Vec<String> {
+	let files = egui.input(|i| i.raw.dropped_files.clone());
+	files
+		.iter()
+		.filter(|f| is_obj(f))
+		.map(|file| {
+			let name = file_name(file);
+			let name = name.get(..name.len() - ".obj".len()).unwrap_or(&name);
+			let imported = dropped_file_bytes(file)
+				.and_then(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()))
+				.and_then(|text| MeshData::from_obj(&text))
+				.and_then(|mesh| scene.import_mesh(name, mesh));
+			match imported {
+				Ok(()) => format!("Imported mesh '{name}'"),
+				Err(e) => format!("Mesh '{name}' not imported: {e}"),
+			}
+		})
+		.collect()
+}
+
+// dropped files with this extension are imported as meshes
+pub fn is_obj(file: &egui::DroppedFile) -> bool {
+	file_name(file).to_lowercase().ends_with(".obj")
+}
+
+// these too, see scene::import
+pub fn is_stl(file: &egui::DroppedFile) -> bool {
+	file_name(file).to_lowercase().ends_with(".stl")
+}
+
+pub fn file_name(file: &egui::DroppedFile) -> String {
+	match file.path.as_ref().and_then(|p| p.file_name()) {
+		Some(name) => name.to_string_lossy().into_owned(),
+		None => file.name.clone(),
+	}
+}
+// }}}
@@ -0,0 +1,198 @@
+// Settings given on the command line as `--set path=value`, for example
+// `--set render.max_bounces=12` or `--set world.sun_elevation=10deg`. They are
+// applied to the serialized settings, so every number, bool and enum variant
+// can be set by its field name without listing the fields here. They only last
+// for the session, what gets stored has the overridden settings as they were.
+use serde_json::Value;
+
+use crate::settings::Settings;
+
+// `path=value` pairs in the order they were given
+pub fn from_args() -> Vec<String> {
+	let mut overrides = Vec::new();
+	let mut args = std::env::args();
+	while let Some(arg) = args.next() {
+		if arg == "--set" {
+			overrides.extend(args.next());
+		} else if let Some(set) = arg.strip_prefix("--set=") {
+			overrides.push(set.to_owned());
+		}
+	}
+	overrides
+}
+
+// the overridden settings as they were before `apply`
+#[derive(Default)]
+pub struct Originals(Vec<(String, Value)>);
+
+impl Originals {
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	// `settings` with the overridden ones put back, everything else stays as
+	// it is now
+	pub fn restore(&self, settings: &Settings) -> Settings {
+		if self.is_empty() {
+			return settings.clone();
+		}
+
+		let mut value = serde_json::to_value(settings).expect("settings serialize");
+		for (path, original) in &self.0 {
+			if let Some(field) = field_mut(&mut value, path) {
+				*field = original.clone();
+			}
+		}
+		serde_json::from_value(value).unwrap_or_else(|e| {
+			log::warn!("overridden settings could not be restored: {e}");
+			settings.clone()
+		})
+	}
+}
+
+pub fn apply(
+	settings: &mut Settings,
+	overrides: &[String],
+) -> Result<Originals, String> {
+	let mut originals = Originals::default();
+	if overrides.is_empty() {
+		return Ok(originals);
+	}
+
+	let original = serde_json::to_value(&*settings).map_err(|e| e.to_string())?;
+	let mut value = original.clone();
+
+	for set in overrides {
+		let (path, text) = set
+			.split_once('=')
+			.ok_or_else(|| format!("expected `path=value`, got `{set}`"))?;
+
+		let field = field_mut(&mut value, path)
+			.filter(|v| v.is_number() || v.is_boolean() || v.is_string())
+			.ok_or_else(|| {
+				let mut paths = Vec::new();
+				leaf_paths(&original, String::new(), &mut paths);
+				format!(
+					"unknown setting `{path}`, the valid ones are:\n  {}",
+					paths.join("\n  "),
+				)
+			})?;
+		let parsed = parse(field, text)
+			.ok_or_else(|| format!("`{text}` is not a valid value for `{path}`"))?;
+		// a path given twice still goes back to the value from before both
+		if !originals.0.iter().any(|(p, _)| p == path) {
+			originals.0.push((path.to_owned(), field.clone()));
+		}
+		*field = parsed;
+
+		// checked one at a time so that the error names the setting
+		serde_json::from_value::<Settings>(value.clone())
+			.map_err(|e| format!("`{text}` is not a valid value for `{path}`: {e}"))?;
+	}
+
+	*settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+	settings.sanitize();
+	Ok(originals)
+}
+
+fn field_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+	path.split('.').try_fold(value, |v, key| v.get_mut(key))
+}
+
+// a new value of the same kind as `current`. numbers can end in `deg` to be
+// converted to radians (or `rad`, which changes nothing), enum variants are
+// given by name
+fn parse(current: &Value, text: &str) -> Option<Value> {
+	match current {
+		Value::Bool(_) => text.parse::<bool>().ok().map(Value::from),
+		Value::Number(n) if n.is_u64() => text.parse::<u64>().ok().map(Value::from),
+		Value::Number(_) => {
+			let (number, factor) = if let Some(deg) = text.strip_suffix("deg") {
+				(deg, 1.0_f64.to_radians())
+			} else {
+				(text.strip_suffix("rad").unwrap_or(text), 1.0)
+			};
+			let number = number.trim().parse::<f64>().ok()?;
+			number.is_finite().then(|| Value::from(number * factor))
+		},
+		Value::String(_) => Some(Value::from(text)),
+		_ => None,
+	}
+}
+
+fn leaf_paths(value: &Value, prefix: String, paths: &mut Vec<String>) {
+	match value {
+		Value::Object(map) => {
+			for (key, value) in map {
+				let path = if prefix.is_empty() {
+					key.clone()
+				} else {
+					format!("{prefix}.{key}")
+				};
+				leaf_paths(value, path, paths);
+			}
+		},
+		Value::Number(_) | Value::Bool(_) | Value::String(_) => paths.push(prefix),
+		_ => {},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn set(overrides: &[&str]) -> (Settings, Originals) {
+		let mut settings = Settings::default();
+		let overrides: Vec<String> = overrides.iter().map(|&s| s.to_owned()).collect();
+		let originals = apply(&mut settings, &overrides).unwrap();
+		(settings, originals)
+	}
+
+	#[test]
+	fn only_overridden_settings_are_restored() {
+		let defaults = Settings::default();
+		let (mut settings, originals) = set(&["render.max_bounces=3"]);
+		assert_eq!(settings.render.max_bounces, 3);
+
+		// changed in the UI during the session, that one is kept
+		settings.render.samples_per_frame = defaults.render.samples_per_frame + 1;
+
+		let stored = originals.restore(&settings);
+		assert_eq!(stored.render.max_bounces, defaults.render.max_bounces);
+		assert_eq!(
+			stored.render.samples_per_frame,
+			settings.render.samples_per_frame
+		);
+	}
+
+	#[test]
+	fn repeated_paths_restore_the_first_value() {
+		let defaults = Settings::default();
+		let (settings, originals) =
+			set(&["render.max_bounces=3", "render.max_bounces=4"]);
+		assert_eq!(settings.render.max_bounces, 4);
+		assert_eq!(
+			originals.restore(&settings).render.max_bounces,
+			defaults.render.max_bounces
+		);
+	}
+
+	#[test]
+	fn overrides_are_sanitized() {
+		let (settings, _) = set(&["render.samples_per_frame=1000"]);
+		assert_eq!(settings.render.samples_per_frame, 32);
+	}
+
+	#[test]
+	fn unknown_and_invalid_overrides_fail() {
+		let mut settings = Settings::default();
+		for set in [
+			"render.nope=1",
+			"render.max_bounces=many",
+			"max_bounces",
+			"render=1",
+		] {
+			assert!(apply(&mut settings, &[set.to_owned()]).is_err(), "{set}");
+		}
+	}
+}
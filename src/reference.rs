@@ -0,0 +1,258 @@
+use egui::{pos2, Color32, DragValue, Rect, Slider, Stroke, TextureHandle, Ui};
+
+use crate::{mesh, scene, util::dropped_file_bytes, viewport::ViewportMapping};
+
+// {{{ settings
+// placement of the reference image, stored with the rest of the data. the
+// image itself is only remembered by its path, which the web doesn't have
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ReferenceSettings {
+	pub path: Option<String>,
+	pub opacity: f32,
+	pub split: bool,      // opaque, left of `split_pos` only
+	pub split_pos: f32,   // fraction of the viewport width
+	pub offset: [f32; 2], // fraction of the viewport size
+	pub scale: f32,       // 1 fits the viewport
+}
+
+impl Default for ReferenceSettings {
+	fn default() -> Self {
+		Self {
+			path: None,
+			opacity: 0.5,
+			split: false,
+			split_pos: 0.5,
+			offset: [0.0, 0.0],
+			scale: 1.0,
+		}
+	}
+}
+// }}}
+
+// a PNG or JPEG dropped onto the window (or picked from the camera window) is
+// drawn over the render, for matching a photo. it's only an overlay,
+// screenshots leave it out
+#[derive(Default)]
+pub struct ReferenceImage {
+	texture: Option<TextureHandle>,
+
+	// the stored path is only tried once
+	#[cfg(not(target_arch = "wasm32"))]
+	restored: bool,
+}
+
+impl ReferenceImage {
+	// picks up dropped files, returns a message about the result
+	pub fn update(
+		&mut self,
+		egui: &egui::Context,
+		settings: &mut ReferenceSettings,
+	) -> Option<String> {
+		#[cfg(not(target_arch = "wasm32"))]
+		if !self.restored {
+			self.restored = true;
+			if let Some(path) = &settings.path {
+				let loaded = std::fs::read(path)
+					.map_err(|e| e.to_string())
+					.and_then(|bytes| self.load(egui, &bytes));
+				if let Err(e) = loaded {
+					log::warn!("reference image {path} could not be loaded: {e}");
+					settings.path = None;
+				}
+			}
+		}
+
+		// OBJ, STL and glTF files are meshes, not images
+		let file = egui.input(|i| {
+			i.raw
+				.dropped_files
+				.iter()
+				.find(|f| {
+					!mesh::is_obj(f) && !mesh::is_stl(f) && !scene::import::is_gltf(f)
+				})
+				.cloned()
+		})?;
+
+		let loaded =
+			dropped_file_bytes(&file).and_then(|bytes| self.load(egui, &bytes));
+		Some(loaded_message(loaded, file.path, settings))
+	}
+
+	// asks for the image with a file dialog, returns a message like `update`
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn pick(
+		&mut self,
+		egui: &egui::Context,
+		settings: &mut ReferenceSettings,
+	) -> Option<String> {
+		let path = rfd::FileDialog::new()
+			.set_title("Reference image")
+			.add_filter("Images", &["png", "jpg", "jpeg"])
+			.pick_file()?;
+		let loaded = std::fs::read(&path)
+			.map_err(|e| e.to_string())
+			.and_then(|bytes| self.load(egui, &bytes));
+		Some(loaded_message(loaded, Some(path), settings))
+	}
+
+	fn load(&mut self, egui: &egui::Context, bytes: &[u8]) -> Result<(), String> {
+		// by content, dropped files on the web don't always have a name
+		let format = image::guess_format(bytes).map_err(|e| e.to_string())?;
+		if !matches!(format, image::ImageFormat::Png | image::ImageFormat::Jpeg) {
+			return Err("only PNG and JPEG images are supported".to_owned());
+		}
+		let image = image::load_from_memory_with_format(bytes, format)
+			.map_err(|e| e.to_string())?
+			.to_rgba8();
+
+		let size = [image.width() as usize, image.height() as usize];
+		let image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+		self.texture = Some(egui.load_texture(
+			"reference_image",
+			image,
+			egui::TextureOptions::LINEAR,
+		));
+		Ok(())
+	}
+
+	// closing the window removes the image
+	pub fn window(&mut self, egui: &egui::Context, settings: &mut ReferenceSettings) {
+		if self.texture.is_none() {
+			return;
+		}
+
+		let mut open = true;
+		egui::Window::new("Reference image")
+			.open(&mut open)
+			.resizable(false)
+			.show(egui, |ui| {
+				ui.add_enabled(
+					!settings.split,
+					Slider::new(&mut settings.opacity, 0.0..=1.0).text("Opacity"),
+				);
+
+				ui.checkbox(&mut settings.split, "Split view");
+				ui.add_enabled(
+					settings.split,
+					Slider::new(&mut settings.split_pos, 0.0..=1.0).text("Split position"),
+				);
+
+				ui.separator();
+
+				ui.horizontal(|ui| {
+					ui.label("Offset:");
+					for offset in &mut settings.offset {
+						ui.add(DragValue::new(offset).speed(0.002).fixed_decimals(3));
+					}
+				});
+				ui.add(
+					Slider::new(&mut settings.scale, 0.1..=10.0)
+						.logarithmic(true)
+						.text("Scale"),
+				);
+				if ui.button("Reset placement").clicked() {
+					settings.offset = [0.0, 0.0];
+					settings.scale = 1.0;
+				}
+			});
+
+		if !open {
+			self.texture = None;
+			settings.path = None;
+		}
+	}
+
+	// fitted into the letterboxed viewport, keeping the image's aspect ratio
+	pub fn draw(
+		&self,
+		ui: &Ui,
+		mapping: &ViewportMapping,
+		settings: &ReferenceSettings,
+	) {
+		let Some(texture) = &self.texture else {
+			return;
+		};
+
+		let rect = mapping.rect;
+		let size = texture.size_vec2();
+		let fit = (rect.width() / size.x).min(rect.height() / size.y) * settings.scale;
+		let offset = egui::vec2(settings.offset[0], settings.offset[1]) * rect.size();
+		let image_rect = Rect::from_center_size(rect.center() + offset, size * fit);
+
+		let split_x = rect.left() + rect.width() * settings.split_pos;
+		let (clip, opacity) = if settings.split {
+			(Rect::from_min_max(rect.min, pos2(split_x, rect.max.y)), 1.0)
+		} else {
+			(rect, settings.opacity)
+		};
+
+		ui.painter_at(clip).image(
+			texture.id(),
+			image_rect,
+			Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+			Color32::WHITE.gamma_multiply(opacity),
+		);
+
+		if settings.split {
+			ui.painter_at(rect).vline(
+				split_x,
+				rect.y_range(),
+				Stroke::new(1.0, Color32::WHITE),
+			);
+		}
+	}
+}
+
+// remembers where a loaded image came from, so that it's loaded again next time
+fn loaded_message(
+	loaded: Result<(), String>,
+	path: Option<std::path::PathBuf>,
+	settings: &mut ReferenceSettings,
+) -> String {
+	match loaded {
+		Ok(()) => {
+			settings.path = path.map(|p| p.display().to_string());
+			"Reference image loaded".to_owned()
+		},
+		Err(e) => format!("Reference image not loaded: {e}"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn encoded(format: image::ImageFormat) -> Vec<u8> {
+		let image = image::RgbImage::from_pixel(4, 2, image::Rgb([200, 100, 50]));
+		let mut bytes = std::io::Cursor::new(Vec::new());
+		image.write_to(&mut bytes, format).unwrap();
+		bytes.into_inner()
+	}
+
+	#[test]
+	fn png_and_jpeg_are_loaded() {
+		let egui = egui::Context::default();
+		for format in [image::ImageFormat::Png, image::ImageFormat::Jpeg] {
+			let mut reference = ReferenceImage::default();
+			assert_eq!(reference.load(&egui, &encoded(format)), Ok(()));
+			let texture = reference.texture.unwrap();
+			assert_eq!(texture.size(), [4, 2]);
+		}
+	}
+
+	#[test]
+	fn other_files_are_rejected() {
+		let egui = egui::Context::default();
+		let mut reference = ReferenceImage::default();
+		for bytes in [
+			&b"GIF89a"[..],
+			b"not an image",
+			b"",
+			&encoded(image::ImageFormat::Png)[..20],
+		] {
+			assert!(reference.load(&egui, bytes).is_err());
+		}
+		assert!(reference.texture.is_none());
+	}
+}
@@ -5,11 +5,24 @@ use eframe::{
 use nalgebra_glm as glm;
 
 use crate::{
-	app::{PersistentData, RaytracingApp},
-	camera::Camera,
-	util::{fill_50, flatten_matrices, Reset},
+	app::{Edit, EditGate, PersistentData, RaytracingApp},
+	camera::{Camera, Projection},
+	geometry::{self, bvh::Bvh},
+	lod,
+	mesh::MAX_MESH_TRIANGLES,
+	scene::{Scene, MAX_OBJECTS},
+	settings::{PostSettings, RenderMode, Tonemap},
+	util::Reset,
+	viewport::ViewportMapping,
 };
 
+// texels per row of the mesh and BVH textures, MESH_TEXTURE_WIDTH in fsh.glsl
+const MESH_TEXTURE_WIDTH: usize = 1024;
+
+// texels per object in the object texture, OBJECT_TEXELS in fsh.glsl. the
+// last one is the level of detail
+const OBJECT_TEXELS: usize = 22;
+
 pub struct Raytracer {
 	clear_fbo: Framebuffer,
 
@@ -17,7 +30,6 @@ pub struct Raytracer {
 	ray_dirs_fbo: Framebuffer,
 	ray_dirs_texture: Texture,
 	ray_dirs_program: Program,
-	ray_dirs_verts: VertexArray,
 
 	// prepass calculates noise based on the noise of the previous frame
 	// this prevents large seeds leading to less randomness
@@ -25,33 +37,459 @@ pub struct Raytracer {
 	noise_texture_0: Texture,
 	noise_texture_1: Texture,
 	noise_program: Program,
-	noise_verts: VertexArray,
 
 	accumulation_fbo: Framebuffer,
 	accumulation_texture_0: Texture,
 	accumulation_texture_1: Texture,
 	program: Program,
-	verts: VertexArray,
-
 	final_program: Program,
-	final_verts: VertexArray,
+
+	// a single texel that the main program writes the index of the object
+	// under a clicked pixel into, see `pick`
+	pick_fbo: Framebuffer,
+	pick_texture: Texture,
+
+	// triangles of every mesh and their BVHs, see `upload_meshes`
+	mesh_texture: Texture,
+	bvh_texture: Texture,
+
+	// transforms and materials, see `object_texels`
+	object_texture: Texture,
+
+	// shared by every pass
+	fullscreen: FullscreenTriangle,
 
 	scr_size: glm::Vec2,
+	pub max_texture_size: u32,
 	first_frame: bool,
+
+	// of the camera the ray directions were calculated for. the noise prepass
+	// numbers pixels within each view, the main program picks the eye
+	stereo: bool,
+
 	rendering_to_texture_0: bool,
 	pub frame_index: u32,
 
+	// multiplier for the accumulated image when adding the next sample
+	history_scale: f32,
+
+	// mixed into the noise on every reset, the light path tracer starts its
+	// random streams from it too
+	pub noise_seed: u32,
+
+	// per-object lod::LOD_* values, reclassified when the camera comes to rest.
+	// the last column of the object texture holds the uploaded ones
+	lod: Vec<u32>,
+	lod_stale: bool,
+	uploaded_lod: Vec<f32>,
+
+	// object shown by solo mode, the selection only affects the image while
+	// this is set
+	solo: Option<usize>,
+
+	// low latency camera movement, the frame skips the noise prepass and
+	// takes one sample per pixel
+	fast_frame: bool,
+
+	// set while the windows are hidden for a screenshot, the final pass then
+	// leaves out what exports don't keep
+	pub exporting: bool,
+
+	// result of the last eyedropper readback, taken by the app
+	pub sampled_color: Option<[f32; 3]>,
+
+	// readbacks that don't stall, see AsyncReadback. finished inspector and
+	// render job readbacks are kept here until the app takes them
+	readback: AsyncReadback,
+	pub inspected: Option<Readback>,
+	pub exported: Option<Readback>, // the whole image, for the render job
+
+	// set by the app every frame before painting
+	pub mapping: ViewportMapping,
+
 	pub force_scr_size: bool,
+
+	// bytes sent to the GPU, shown by the settings window
+	pub stats: RenderStats,
+}
+
+// {{{ upload stats
+// what the bytes sent to the GPU were for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Upload {
+	Frame,    // camera and everything else set on every frame
+	Scene,    // object and material uniforms
+	Settings, // world and render setting uniforms
+	Meshes,   // triangle and BVH textures
+	Textures, // render targets, reallocated when the size changes
+}
+
+const UPLOAD_KINDS: usize = 5;
+
+impl Upload {
+	pub const ALL: [Self; UPLOAD_KINDS] = [
+		Self::Frame,
+		Self::Scene,
+		Self::Settings,
+		Self::Meshes,
+		Self::Textures,
+	];
+}
+
+impl std::fmt::Display for Upload {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let name = match self {
+			Self::Frame => "Per frame",
+			Self::Scene => "Scene",
+			Self::Settings => "Settings",
+			Self::Meshes => "Meshes",
+			Self::Textures => "Render targets",
+		};
+		write!(f, "{name}")
+	}
+}
+
+// bytes uploaded of each kind since the last reset. a change is a frame that
+// uploaded anything of that kind, so per change is what e.g. one step of a
+// slider drag costs
+#[derive(Clone, Default, Debug)]
+pub struct RenderStats {
+	current: [usize; UPLOAD_KINDS], // this frame, until it's done
+	pub last_frame: [usize; UPLOAD_KINDS],
+	pub total: [usize; UPLOAD_KINDS],
+	pub peak: [usize; UPLOAD_KINDS],
+	pub changes: [usize; UPLOAD_KINDS],
+	pub frames: usize,
+}
+
+impl RenderStats {
+	pub fn reset(&mut self) {
+		*self = Self::default();
+	}
+
+	fn add(&mut self, upload: Upload, bytes: usize) {
+		self.current[upload as usize] += bytes;
+	}
+
+	// uniforms set through the returned context count as `upload`
+	fn counter<'a>(&'a mut self, gl: &'a Context, upload: Upload) -> Counted<'a> {
+		Counted {
+			gl,
+			bytes: &mut self.current[upload as usize],
+		}
+	}
+
+	fn end_frame(&mut self) {
+		for i in 0..UPLOAD_KINDS {
+			let bytes = std::mem::take(&mut self.current[i]);
+			self.last_frame[i] = bytes;
+			self.total[i] += bytes;
+			self.peak[i] = self.peak[i].max(bytes);
+			self.changes[i] += (bytes > 0) as usize;
+		}
+		self.frames += 1;
+	}
+
+	pub fn per_frame(&self, upload: Upload) -> usize {
+		self.total[upload as usize] / self.frames.max(1)
+	}
+
+	pub fn per_change(&self, upload: Upload) -> usize {
+		let i = upload as usize;
+		self.total[i] / self.changes[i].max(1)
+	}
+}
+
+// adds up the size of the uniforms set through it, everything else goes
+// straight to the context
+struct Counted<'a> {
+	gl: &'a Context,
+	bytes: &'a mut usize,
+}
+
+type Location = <Context as HasContext>::UniformLocation;
+
+impl std::ops::Deref for Counted<'_> {
+	type Target = Context;
+
+	fn deref(&self) -> &Context {
+		self.gl
+	}
+}
+
+impl Counted<'_> {
+	unsafe fn uniform_1_u32(&mut self, location: Option<&Location>, x: u32) {
+		*self.bytes += 4;
+		self.gl.uniform_1_u32(location, x);
+	}
+
+	unsafe fn uniform_1_f32(&mut self, location: Option<&Location>, x: f32) {
+		*self.bytes += 4;
+		self.gl.uniform_1_f32(location, x);
+	}
+
+	unsafe fn uniform_3_f32(
+		&mut self,
+		location: Option<&Location>,
+		x: f32,
+		y: f32,
+		z: f32,
+	) {
+		*self.bytes += 12;
+		self.gl.uniform_3_f32(location, x, y, z);
+	}
+
+	unsafe fn uniform_1_f32_slice(&mut self, location: Option<&Location>, v: &[f32]) {
+		*self.bytes += std::mem::size_of_val(v);
+		self.gl.uniform_1_f32_slice(location, v);
+	}
 }
+// }}}
+
+// {{{ async readback
+// how many readbacks of each kind can wait for the GPU at once, more are
+// refused
+const MAX_READBACKS_IN_FLIGHT: usize = 2;
+
+// who asked for a readback, the result goes back to them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadbackKind {
+	Eyedropper,
+	Inspector,
+	RenderJob,
+}
+
+// part of the image to read, in texels with rows going bottom to top.
+// clamped to the image, so a large enough rectangle reads all of it
+#[derive(Clone, Copy, Debug)]
+pub enum ReadbackRegion {
+	Rect([i32; 4]), // x, y, width, height
+	Pixel([i32; 2]),
+}
+
+impl ReadbackRegion {
+	fn texels(self, size: [i32; 2]) -> [i32; 4] {
+		let [x, y, w, h] = match self {
+			Self::Rect(rect) => rect,
+			Self::Pixel([x, y]) => [x, y, 1, 1],
+		};
+		let (x0, y0) = (x.clamp(0, size[0]), y.clamp(0, size[1]));
+		let (x1, y1) = ((x + w).clamp(x0, size[0]), (y + h).clamp(y0, size[1]));
+		[x0, y0, x1 - x0, y1 - y0]
+	}
+}
+
+// a finished readback, in linear color averaged over the accumulated samples
+pub struct Readback {
+	pub kind: ReadbackKind,
+	pub region: [i32; 4],
+	pub samples: u32,
+	pub texels: Vec<[f32; 4]>, // rows go bottom to top
+}
+
+struct PendingReadback {
+	kind: ReadbackKind,
+	region: [i32; 4],
+	samples: u32,
+	buffer: glow::Buffer,
+	fence: glow::Fence,
+}
+
+// native fences are raw pointers. they are only touched from the paint
+// callback, which always runs on the thread that owns the GL context
+unsafe impl Send for PendingReadback {}
+
+// Reads the image back without waiting for the GPU. The pixels are copied
+// into a pixel buffer, and the data is only fetched once a fence says that
+// the copy is done, usually a frame or two later. WebGL2 has the same buffers
+// and fences, so both sides work the same way.
+#[derive(Default)]
+pub struct AsyncReadback {
+	pending: Vec<PendingReadback>,
+	free: Vec<glow::Buffer>, // buffers of finished readbacks, for reuse
+}
+
+impl AsyncReadback {
+	// the framebuffer to read from must be bound. false if the region is
+	// empty or too many readbacks of this kind are in flight
+	unsafe fn request(
+		&mut self,
+		gl: &Context,
+		kind: ReadbackKind,
+		region: [i32; 4],
+		samples: u32,
+	) -> bool {
+		let [x, y, w, h] = region;
+		let in_flight = self.pending.iter().filter(|p| p.kind == kind).count();
+		if w <= 0 || h <= 0 || in_flight >= MAX_READBACKS_IN_FLIGHT {
+			return false;
+		}
+
+		let Some(buffer) = self.free.pop().or_else(|| gl.create_buffer().ok()) else {
+			return false;
+		};
+		gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(buffer));
+		gl.buffer_data_size(glow::PIXEL_PACK_BUFFER, w * h * 16, glow::STREAM_READ);
+		gl.read_pixels(
+			x,
+			y,
+			w,
+			h,
+			glow::RGBA_INTEGER,
+			glow::UNSIGNED_INT,
+			glow::PixelPackData::BufferOffset(0),
+		);
+		gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+
+		match gl.fence_sync(glow::SYNC_GPU_COMMANDS_COMPLETE, 0) {
+			Ok(fence) => {
+				self.pending.push(PendingReadback {
+					kind,
+					region,
+					samples,
+					buffer,
+					fence,
+				});
+				true
+			},
+			Err(e) => {
+				log::warn!("readback fence failed: {e}");
+				self.free.push(buffer);
+				false
+			},
+		}
+	}
+
+	// the readbacks that finished since the last poll, oldest first
+	fn poll(&mut self, gl: &Context) -> Vec<Readback> {
+		let mut done = Vec::new();
+		let mut i = 0;
+		while i < self.pending.len() {
+			let fence = self.pending[i].fence;
+			if unsafe { gl.get_sync_status(fence) } != glow::SIGNALED {
+				i += 1;
+				continue;
+			}
+
+			let pending = self.pending.remove(i);
+			let [_, _, w, h] = pending.region;
+			let mut texels = vec![0_u32; (w * h * 4) as usize];
+			unsafe {
+				gl.delete_sync(pending.fence);
+				gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(pending.buffer));
+				gl.get_buffer_sub_data(
+					glow::PIXEL_PACK_BUFFER,
+					0,
+					bytemuck::cast_slice_mut(&mut texels),
+				);
+				gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+			}
+			self.free.push(pending.buffer);
+
+			// the image stores float bits summed over the samples
+			let scale = 1.0 / pending.samples.max(1) as f32;
+			done.push(Readback {
+				kind: pending.kind,
+				region: pending.region,
+				samples: pending.samples,
+				texels: texels
+					.chunks_exact(4)
+					.map(|t| [0, 1, 2, 3].map(|c| f32::from_bits(t[c]) * scale))
+					.collect(),
+			});
+		}
+		done
+	}
+
+	// readbacks still in flight are dropped
+	fn destroy(&self, gl: &Context) {
+		unsafe {
+			for pending in &self.pending {
+				gl.delete_sync(pending.fence);
+				gl.delete_buffer(pending.buffer);
+			}
+			for &buffer in &self.free {
+				gl.delete_buffer(buffer);
+			}
+		}
+	}
+}
+
+// average color of the texels, for the eyedropper
+fn average_color(texels: &[[f32; 4]]) -> [f32; 3] {
+	let mut sum = [0.0; 3];
+	for texel in texels {
+		for (sum, c) in sum.iter_mut().zip(texel) {
+			*sum += c;
+		}
+	}
+	sum.map(|c| c / texels.len().max(1) as f32)
+}
+// }}}
+
+// {{{ fullscreen pass
+// Every pass draws a single triangle that covers the screen. Its corners come
+// from a vertex buffer rather than gl_VertexID, because some drivers (older
+// Mali) draw glitchy triangles from vertex arrays without any enabled
+// attributes.
+struct FullscreenTriangle {
+	verts: VertexArray,
+	buffer: glow::Buffer,
+}
+
+// the two corners outside of the screen get clipped away
+const FULLSCREEN_TRIANGLE: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+
+impl FullscreenTriangle {
+	unsafe fn new(gl: &Context) -> Self {
+		let verts = gl
+			.create_vertex_array()
+			.expect("create vertex array failed");
+		let buffer = gl.create_buffer().expect("create buffer failed");
+
+		gl.bind_vertex_array(Some(verts));
+		gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+		gl.buffer_data_u8_slice(
+			glow::ARRAY_BUFFER,
+			bytemuck::cast_slice(&FULLSCREEN_TRIANGLE),
+			glow::STATIC_DRAW,
+		);
+		// `position` in vsh.glsl
+		gl.enable_vertex_attrib_array(0);
+		gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
+
+		gl.bind_vertex_array(None);
+		gl.bind_buffer(glow::ARRAY_BUFFER, None);
+
+		Self { verts, buffer }
+	}
+
+	// into the bound framebuffer, with the current program
+	unsafe fn draw(&self, gl: &Context) {
+		gl.bind_vertex_array(Some(self.verts));
+		gl.draw_arrays(glow::TRIANGLES, 0, 3);
+		gl.bind_vertex_array(None);
+	}
+
+	unsafe fn destroy(&self, gl: &Context) {
+		gl.delete_vertex_array(self.verts);
+		gl.delete_buffer(self.buffer);
+	}
+}
+// }}}
 
 // {{{ shader compilation boilerplate
+// GLSL has no includes, shared files are pasted in front of the shader
 macro_rules! fragment_shader {
-	($location:literal) => {
+	($location:literal $(, include $include:literal)* $(,)?) => {
 		&[
 			(glow::VERTEX_SHADER, include_str!("shaders/vsh.glsl")),
 			(
 				glow::FRAGMENT_SHADER,
-				include_str!(concat!("shaders/", $location)),
+				concat!(
+					$(include_str!(concat!("shaders/", $include)), "\n",)*
+					include_str!(concat!("shaders/", $location)),
+				),
 			),
 		]
 	};
@@ -63,16 +501,34 @@ const SHADER_VERSION: &str = "#version 330";
 #[cfg(target_arch = "wasm32")]
 const SHADER_VERSION: &str = "#version 300 es";
 
+// the self-intersection bias, see fsh.glsl. the light paths offset their rays
+// by the same amount
+pub const BIAS_ABSOLUTE: f32 = 0.00005;
+pub const BIAS_RELATIVE: f32 = 0.00005;
+pub const BIAS_MAX_OBJECT_FRACTION: f32 = 0.01;
+
+// put in front of every shader after the version, as defines because GLSL ES
+// won't take float constants before the precision is set. `{:?}` always
+// writes a decimal point or an exponent, GLSL would read `1` as an int
+fn shader_constants() -> String {
+	format!(
+		"#define BIAS_ABSOLUTE {BIAS_ABSOLUTE:?}\n\
+		 #define BIAS_RELATIVE {BIAS_RELATIVE:?}\n\
+		 #define BIAS_MAX_OBJECT_FRACTION {BIAS_MAX_OBJECT_FRACTION:?}"
+	)
+}
+
 unsafe fn compile_shaders(
 	gl: &Context,
 	program: Program,
 	srcs: &[(u32, &'static str)],
 ) {
+	let constants = shader_constants();
 	let shaders: Vec<_> = srcs
 		.iter()
 		.map(|(ty, src)| {
 			let shader = gl.create_shader(*ty).expect("create shader failed");
-			gl.shader_source(shader, &format!("{SHADER_VERSION}\n{src}"));
+			gl.shader_source(shader, &format!("{SHADER_VERSION}\n{constants}\n{src}"));
 			gl.compile_shader(shader);
 			assert!(
 				gl.get_shader_compile_status(shader),
@@ -98,59 +554,234 @@ unsafe fn compile_shaders(
 }
 // }}}
 
-#[cfg(not(target = "wasm32"))]
-fn scale() -> f32 {
-	1.0
-}
-
-#[cfg(target = "wasm32")]
-#[wasm_bindgen]
-fn scale() -> f32 {
-	web_sys::window().unwrap().device_pixel_ratio() as f32
+// moves the camera from this frame's input, returns whether it moved. keys
+// are ignored while text is focused
+fn update_camera(
+	data: &mut PersistentData,
+	input: &egui::InputState,
+	ui_focused: bool,
+) -> bool {
+	data.camera.set_fov(data.settings.render.fov);
+	data.camera.set_projection(data.settings.render.projection);
+	data.camera.set_stereo(data.settings.render.stereo_rig());
+	!ui_focused
+		&& data.camera.update(
+			input.clone(),
+			data.settings.controls,
+			&data.scene,
+			&data.settings.world,
+		)
 }
 
 impl RaytracingApp {
-	pub fn paint(&mut self, ui: &mut egui::Ui, ui_focused: bool) {
-		let scr = ui.clip_rect();
-		let scr_size = glm::vec2(scr.size().x, scr.size().y) / scale();
+	// `sample_region` is a region of the viewport (in points) to average for
+	// the eyedropper, `inspect_texel` is read back for the pixel inspector,
+	// `export` reads back the whole image for the render job and `gate` decides
+	// whether a click selects the object under the pointer
+	pub fn paint(
+		&mut self,
+		ui: &mut egui::Ui,
+		ui_focused: bool,
+		sample_region: Option<egui::Rect>,
+		inspect_texel: Option<[i32; 2]>,
+		export: bool,
+		gate: EditGate,
+	) {
+		let mapping = self.renderer.lock().mapping;
+		let scr = mapping.rect;
+		let scr_size = mapping.texture_size();
+
+		// plain left clicks pick, alt and ctrl clicks belong to the eyedropper
+		// and the light paths. the camera doesn't matter, so this works while
+		// it's locked too
+		let pick_texel = {
+			let data = self.data.lock();
+			let modal_open = data.scene.modal_open() || data.settings.modal_open();
+			ui.input(|i| {
+				let plain = !i.modifiers.alt && !i.modifiers.command;
+				// the object isn't known until the click is read back
+				let pick = gate.allows(Edit::Select(usize::MAX));
+				let clicked = pick && plain && !modal_open && i.pointer.primary_clicked();
+				i.pointer.interact_pos().filter(|_| clicked)
+			})
+			.filter(|&p| mapping.contains(p) && ui.rect_contains_pointer(mapping.rect))
+			.map(|p| mapping.point_to_texel(p))
+		};
 
 		let raytracer_mutex = self.renderer.clone();
 		let data_mutex = self.data.clone();
 		let input = ui.input(|i| i.clone());
 
+		// in low latency mode the camera moves before the callback is queued
+		// and the frame is drawn from the new view. otherwise it's drawn from
+		// the old one and the camera moves after, showing up a frame later
+		let moved_early = {
+			let mut data = self.data.lock();
+			let controls = data.settings.controls;
+			let early = controls.low_latency && !data.settings.render.lock_camera;
+			early.then(|| update_camera(&mut data, &input, ui_focused))
+		};
+
 		// {{{ paint callback
 		let callback = egui::PaintCallback {
 			rect: scr,
 			callback: std::sync::Arc::new(egui_glow::CallbackFn::new(
-				move |_, painter| {
+				move |info, painter| {
 					let mut raytracer = raytracer_mutex.lock();
 					let mut data = data_mutex.lock();
 
 					let gl = painter.gl();
 
+					// from the image on screen, before anything changes it
+					if let Some(i) = pick_texel.and_then(|t| raytracer.pick(gl, t)) {
+						gate.apply(Edit::Select(i), &mut data);
+					}
+
 					raytracer.set_scr_size(gl, &mut data.camera, scr_size);
+					raytracer.use_targets_viewport(gl);
+
+					let accumulating = data.settings.render.accumulating();
+					let mut camera_moved = false;
+
+					// the lens doesn't move the camera, so it's refocused even
+					// while the camera is locked
+					// the lens is a plane in front of the camera, a panorama
+					// has no front
+					data.camera.aperture = match data.settings.render.projection {
+						Projection::Perspective => data.settings.render.aperture,
+						Projection::Panorama => 0.0,
+					};
+					data.camera.focal_distance = data.settings.render.focal_distance;
+
+					// the ray directions are recalculated before the main pass
+					// uses them
+					if let Some(moved) = moved_early {
+						camera_moved = raytracer.apply_camera_change(
+							gl,
+							&mut data.camera,
+							moved,
+							accumulating,
+						);
+					}
+					raytracer.fast_frame = camera_moved;
+
+					let viewport = info.viewport_in_pixels();
+					raytracer.paint(
+						gl,
+						&data,
+						[
+							viewport.left_px,
+							viewport.from_bottom_px,
+							viewport.width_px,
+							viewport.height_px,
+						],
+					);
+
+					raytracer.poll_readbacks(gl);
+					let accumulated = data.settings.render.accumulating();
+					if let Some(region) = sample_region {
+						// texel rows go bottom to top, so the bottom left corner
+						// comes first
+						let mapping = raytracer.mapping;
+						let [x0, y0] = mapping.point_to_texel(region.left_bottom());
+						let [x1, y1] = mapping.point_to_texel(region.right_top());
+						raytracer.request_readback(
+							gl,
+							ReadbackKind::Eyedropper,
+							ReadbackRegion::Rect([x0, y0, x1 - x0 + 1, y1 - y0 + 1]),
+							accumulated,
+						);
+					}
+					if let Some(texel) = inspect_texel {
+						// skipped while both buffers are still in flight
+						raytracer.request_readback(
+							gl,
+							ReadbackKind::Inspector,
+							ReadbackRegion::Pixel(texel),
+							accumulated,
+						);
+					}
+					if export {
+						// a lost request times out in the job
+						let size = raytracer.scr_size;
+						let queued = raytracer.request_readback(
+							gl,
+							ReadbackKind::RenderJob,
+							ReadbackRegion::Rect([0, 0, size.x as i32, size.y as i32]),
+							accumulated,
+						);
+						if !queued {
+							log::warn!("final render readback wasn't queued");
+						}
+					}
+
+					if !data.settings.render.lock_camera && moved_early.is_none() {
+						let moved = update_camera(&mut data, &input, ui_focused);
+						camera_moved = raytracer.apply_camera_change(
+							gl,
+							&mut data.camera,
+							moved,
+							accumulating,
+						);
+					}
 
-					raytracer.paint(gl, &data);
-
-					if !data.settings.render.lock_camera {
-						// {{{ update camera
-						let fov = data.settings.render.fov;
-						data.camera.set_fov(fov);
-						if !ui_focused && data.camera.update(input.clone()) {
-							// don't respond to keypresses if text is focused
-							raytracer.frame_index = 1;
-							raytracer.clear_textures(gl);
-						};
-						if data.camera.recalculate_ray_dirs {
-							raytracer.calculate_ray_dirs(gl, &data.camera);
-							data.camera.recalculate_ray_dirs = false;
+					// {{{ level of detail
+					// reclassified once the camera comes to rest, so a change
+					// only restarts accumulation that the movement restarted
+					// anyway. scene edits need it right away, the object indices
+					// may have changed
+					if camera_moved {
+						raytracer.lod_stale = true;
+					}
+					if (raytracer.lod_stale && !camera_moved)
+						|| data.scene.response.changed_or_moved()
+						|| data.settings.response.changed
+					{
+						let lod = lod::classify(&data, scr_size.y);
+						if lod != raytracer.lod {
+							raytracer.lod = lod;
+							raytracer.invalidate(gl, accumulating);
 						}
-						// }}}
+						raytracer.lod_stale = camera_moved;
+					}
+					// }}}
+
+					// {{{ solo
+					// picking another object while soloing changes the image
+					let solo_enabled = data.settings.render.solo;
+					let solo = data.scene.selected().filter(|_| solo_enabled);
+					if solo != raytracer.solo {
+						raytracer.solo = solo;
+						raytracer.invalidate(gl, accumulating);
+					}
+					// }}}
+
+					// anything that restarts the image resumes it too
+					let response = data.settings.response;
+					if camera_moved
+						|| response.restart
+						|| response.changed
+						|| data.scene.response.changed_or_moved()
+					{
+						data.settings.render.paused = false;
 					}
 
-					if data.settings.response.changed || data.scene.response.changed {
-						raytracer.frame_index = 1;
-						raytracer.clear_textures(gl);
+					let render = &data.settings.render;
+					let freeze = render.freeze_and_continue && accumulating;
+
+					if response.restart {
+						// explicit, so always start over (this also reseeds the
+						// noise view)
+						raytracer.invalidate(gl, true);
+					} else if response.changed {
+						raytracer.invalidate(gl, accumulating);
+					} else if data.scene.response.changed_or_moved() {
+						// the camera might have reset accumulation already
+						if freeze && raytracer.frame_index > 1 {
+							raytracer.continue_accumulation(render.freeze_weight);
+						} else {
+							raytracer.invalidate(gl, accumulating);
+						}
 					}
 
 					data.settings.response.reset();
@@ -164,8 +795,20 @@ impl RaytracingApp {
 }
 
 impl Raytracer {
-	pub fn new(gl: &Context, camera: &Camera, scr_size: glm::Vec2) -> Self {
+	// `texture_limit` lowers the maximum texture size below what the GPU
+	// supports, to try out the clamping on any hardware
+	pub fn new(
+		gl: &Context,
+		camera: &Camera,
+		scr_size: glm::Vec2,
+		texture_limit: Option<u32>,
+	) -> Self {
 		unsafe {
+			let max_texture_size = gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) as u32;
+			let max_texture_size =
+				texture_limit.map_or(max_texture_size, |l| l.min(max_texture_size));
+			let scr_size = scr_size * (max_texture_size as f32 / scr_size.max()).min(1.0);
+
 			// {{{ create shader programs
 			let ray_dirs_program = gl.create_program().expect("create program failed");
 			let noise_program = gl.create_program().expect("create program failed");
@@ -174,21 +817,18 @@ impl Raytracer {
 
 			compile_shaders(gl, ray_dirs_program, fragment_shader!("ray_dirs.glsl"));
 			compile_shaders(gl, noise_program, fragment_shader!("noise.glsl"));
-			compile_shaders(gl, program, fragment_shader!("fsh.glsl"));
-			compile_shaders(gl, final_program, fragment_shader!("final.glsl"));
-
-			let ray_dirs_verts = gl
-				.create_vertex_array()
-				.expect("create vertex array failed");
-			let noise_verts = gl
-				.create_vertex_array()
-				.expect("create vertex array failed");
-			let verts = gl
-				.create_vertex_array()
-				.expect("create vertex array failed");
-			let final_verts = gl
-				.create_vertex_array()
-				.expect("create vertex array failed");
+			compile_shaders(
+				gl,
+				program,
+				fragment_shader!("fsh.glsl", include "palette.glsl"),
+			);
+			compile_shaders(
+				gl,
+				final_program,
+				fragment_shader!("final.glsl", include "color.glsl"),
+			);
+
+			let fullscreen = FullscreenTriangle::new(gl);
 			// }}}
 
 			// {{{ create prepass (ray dirs) FBO and texture
@@ -257,39 +897,80 @@ impl Raytracer {
 			);
 			// }}}
 
+			// {{{ create picking FBO and texture
+			let pick_fbo = gl.create_framebuffer().expect("create FBO failed");
+			let pick_texture = gl.create_texture().expect("create texture failed");
+
+			gl.bind_texture(glow::TEXTURE_2D, Some(pick_texture));
+			screen_sized_texture(gl, glm::vec2(1.0, 1.0), true);
+
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(pick_fbo));
+			framebuffer_texture(gl, pick_texture);
+
+			gl.bind_texture(glow::TEXTURE_2D, None);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+			let fbo_status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+			assert!(
+				fbo_status == glow::FRAMEBUFFER_COMPLETE,
+				"framebuffer incomplete: {fbo_status}"
+			);
+			// }}}
+
 			let mut this = Self {
 				clear_fbo: gl.create_framebuffer().expect("create FBO failed"),
 
 				ray_dirs_fbo,
 				ray_dirs_texture,
 				ray_dirs_program,
-				ray_dirs_verts,
 
 				noise_fbo,
 				noise_texture_0,
 				noise_texture_1,
 				noise_program,
-				noise_verts,
 
 				accumulation_fbo,
 				accumulation_texture_0,
 				accumulation_texture_1,
 				program,
-				verts,
-
 				final_program,
-				final_verts,
+
+				pick_fbo,
+				pick_texture,
+
+				mesh_texture: gl.create_texture().expect("create texture failed"),
+				bvh_texture: gl.create_texture().expect("create texture failed"),
+				object_texture: gl.create_texture().expect("create texture failed"),
+
+				fullscreen,
 
 				scr_size,
+				max_texture_size,
 				first_frame: true,
+				stereo: false,
 				rendering_to_texture_0: true,
 
 				// this starts at one to avoid division by zero
 				frame_index: 1,
+				history_scale: 1.0,
+				noise_seed: 0,
+				lod: Vec::new(),
+				lod_stale: true,
+				uploaded_lod: Vec::new(),
+				solo: None,
+				fast_frame: false,
 
 				force_scr_size: false,
+				exporting: false,
+				sampled_color: None,
+				readback: AsyncReadback::default(),
+				inspected: None,
+				exported: None,
+				mapping: ViewportMapping::default(),
+				stats: RenderStats::default(),
 			};
 			// initial ray direction calculation
+			this.use_targets_viewport(gl);
 			this.calculate_ray_dirs(gl, camera);
 			this
 		}
@@ -303,16 +984,21 @@ impl Raytracer {
 			gl.delete_framebuffer(self.ray_dirs_fbo);
 			gl.delete_texture(self.ray_dirs_texture);
 			gl.delete_program(self.ray_dirs_program);
-			gl.delete_vertex_array(self.ray_dirs_verts);
 
 			gl.delete_framebuffer(self.accumulation_fbo);
 			gl.delete_texture(self.accumulation_texture_0);
 			gl.delete_texture(self.accumulation_texture_1);
 			gl.delete_program(self.program);
-			gl.delete_vertex_array(self.verts);
+			gl.delete_texture(self.mesh_texture);
+			gl.delete_texture(self.bvh_texture);
+			gl.delete_texture(self.object_texture);
 
 			gl.delete_program(self.final_program);
-			gl.delete_vertex_array(self.final_verts);
+			gl.delete_framebuffer(self.pick_fbo);
+			gl.delete_texture(self.pick_texture);
+			self.fullscreen.destroy(gl);
+
+			self.readback.destroy(gl);
 		}
 	}
 	// }}}
@@ -336,10 +1022,91 @@ impl Raytracer {
 		self.frame_index = 1;
 		self.realloc_textures(gl, new_scr_size);
 	}
+
+	// Everything except the final pass draws into the render targets, which
+	// start at the origin and can be smaller than the viewport (see
+	// `ViewportMapping::with_texture_limit`). egui sets up the viewport and
+	// scissor for the window instead, and restores both after the callback.
+	fn use_targets_viewport(&self, gl: &Context) {
+		unsafe {
+			gl.disable(glow::SCISSOR_TEST);
+			gl.viewport(0, 0, self.scr_size.x as i32, self.scr_size.y as i32);
+		}
+	}
+	// }}}
+
+	// {{{ object picking
+	// index of the object that the camera ray through `texel` hits first, none
+	// for the ground and the sky. the main program traces only that ray, into
+	// the picking texel, and it's read back right away. this waits for the GPU,
+	// but only once per click
+	fn pick(&self, gl: &Context, texel: [i32; 2]) -> Option<usize> {
+		// the samplers are only assigned to their units on the first frame
+		if self.first_frame {
+			return None;
+		}
+
+		let mut id = [u32::MAX; 4];
+		unsafe {
+			gl.use_program(Some(self.program));
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.program, "picking").as_ref(),
+				1,
+			);
+			gl.uniform_2_i32(
+				gl.get_uniform_location(self.program, "pick_texel").as_ref(),
+				texel[0],
+				texel[1],
+			);
+
+			// only the ray directions and meshes are read, but every sampler
+			// needs a texture of its kind
+			gl.active_texture(glow::TEXTURE0);
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.ray_dirs_texture));
+			gl.active_texture(glow::TEXTURE1);
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.noise_texture_0));
+			gl.active_texture(glow::TEXTURE2);
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.accumulation_texture_0));
+			gl.active_texture(glow::TEXTURE3);
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.mesh_texture));
+			gl.active_texture(glow::TEXTURE4);
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.bvh_texture));
+			gl.active_texture(glow::TEXTURE5);
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.object_texture));
+
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.pick_fbo));
+			gl.viewport(0, 0, 1, 1);
+			self.fullscreen.draw(gl);
+			gl.read_pixels(
+				0,
+				0,
+				1,
+				1,
+				glow::RGBA_INTEGER,
+				glow::UNSIGNED_INT,
+				glow::PixelPackData::Slice(bytemuck::cast_slice_mut(&mut id)),
+			);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.program, "picking").as_ref(),
+				0,
+			);
+		}
+		self.use_targets_viewport(gl);
+
+		// u32::MAX when nothing was hit
+		let id = id[0] as usize;
+		(id < MAX_OBJECTS).then_some(id)
+	}
 	// }}}
 
 	// {{{ reset textures
-	fn realloc_textures(&self, gl: &Context, scr_size: glm::Vec2) {
+	fn realloc_textures(&mut self, gl: &Context, scr_size: glm::Vec2) {
+		// five RGBA32UI targets
+		let texels = scr_size.x as usize * scr_size.y as usize;
+		self.stats.add(Upload::Textures, 5 * 16 * texels);
+
 		unsafe {
 			gl.bind_texture(glow::TEXTURE_2D, Some(self.ray_dirs_texture));
 			screen_sized_texture(gl, scr_size, false);
@@ -354,16 +1121,13 @@ impl Raytracer {
 		}
 	}
 
-	fn clear_textures(&self, gl: &Context) {
+	fn clear_textures(&mut self, gl: &Context) {
+		// the noise textures must never be all zero, the RNG would degenerate
+		self.seed_noise(gl);
+
 		unsafe {
 			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.clear_fbo));
 
-			framebuffer_texture(gl, self.noise_texture_0);
-			gl.draw_buffers(&[glow::COLOR_ATTACHMENT0]);
-			gl.clear_buffer_u32_slice(glow::COLOR, 0, &[0, 0, 0, 0]);
-			framebuffer_texture(gl, self.noise_texture_1);
-			gl.draw_buffers(&[glow::COLOR_ATTACHMENT0]);
-			gl.clear_buffer_u32_slice(glow::COLOR, 0, &[0, 0, 0, 0]);
 			framebuffer_texture(gl, self.accumulation_texture_0);
 			gl.draw_buffers(&[glow::COLOR_ATTACHMENT0]);
 			gl.clear_buffer_u32_slice(glow::COLOR, 0, &[0, 0, 0, 0]);
@@ -373,160 +1137,352 @@ impl Raytracer {
 			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
 		}
 	}
-	// }}}
 
-	// {{{ calculate ray directions
-	fn calculate_ray_dirs(&mut self, gl: &Context, camera: &Camera) {
-		unsafe {
-			gl.use_program(Some(self.ray_dirs_program));
+	// fill both noise textures with non-zero values derived from the pixel
+	// coordinates and a seed that changes on every reset
+	fn seed_noise(&mut self, gl: &Context) {
+		self.noise_seed = self.noise_seed.wrapping_add(0x9e37_79b9);
 
-			// {{{ bind uniforms for ray direction calculation
-			self.apply_uniforms_common(gl, self.ray_dirs_program);
+		unsafe {
+			gl.use_program(Some(self.noise_program));
+			self.apply_uniforms_common(gl, self.noise_program);
 
-			gl.uniform_matrix_4_f32_slice(
-				gl.get_uniform_location(self.ray_dirs_program, "inv_proj")
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.noise_program, "reseed")
 					.as_ref(),
-				false, // no transpose, it's already in column-major order
-				camera.inv_proj.as_slice(),
+				1,
 			);
-			gl.uniform_matrix_4_f32_slice(
-				gl.get_uniform_location(self.ray_dirs_program, "inv_view")
-					.as_ref(),
-				false, // no transpose, it's already in column-major order
-				camera.inv_view.as_slice(),
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.noise_program, "seed").as_ref(),
+				self.noise_seed,
 			);
-			// }}}
 
-			// draw into framebuffer
-			gl.bind_vertex_array(Some(self.ray_dirs_verts));
-			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.ray_dirs_fbo));
-			gl.bind_texture(glow::TEXTURE_2D, Some(self.ray_dirs_texture));
-			gl.draw_buffers(&[glow::COLOR_ATTACHMENT0]);
-			gl.clear_buffer_u32_slice(glow::COLOR, 0, &[0, 0, 0, 0]);
-			gl.draw_arrays(glow::TRIANGLES, 0, 3);
+			// nothing is sampled when reseeding, avoid a feedback loop
+			gl.active_texture(glow::TEXTURE0);
+			gl.bind_texture(glow::TEXTURE_2D, None);
+
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.noise_fbo));
+			for texture in [self.noise_texture_0, self.noise_texture_1] {
+				framebuffer_texture(gl, texture);
+				self.fullscreen.draw(gl);
+			}
+
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.noise_program, "reseed")
+					.as_ref(),
+				0,
+			);
 
 			// unbind
-			gl.bind_vertex_array(None);
 			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-			gl.bind_texture(glow::TEXTURE_2D, None);
 			gl.use_program(Some(self.program));
 		}
 	}
 	// }}}
 
-	// {{{ call on every frame to render
-	pub fn paint(&mut self, gl: &Context, data: &PersistentData) {
-		unsafe {
-			// {{{ calculate noise texture
-			gl.use_program(Some(self.noise_program));
-			gl.active_texture(glow::TEXTURE0);
-			gl.bind_texture(
-				glow::TEXTURE_2D,
-				Some(if self.rendering_to_texture_0 {
-					self.noise_texture_1
-				} else {
-					self.noise_texture_0
-				}),
-			);
-
-			// {{{ uniforms
-			self.apply_uniforms_common(gl, self.noise_program);
+	// restarts accumulation after the camera moved and recalculates the ray
+	// directions if the view changed. the view can also change outside of
+	// camera movement (field of view, looking at an object). returns whether
+	// anything changed
+	fn apply_camera_change(
+		&mut self,
+		gl: &Context,
+		camera: &mut Camera,
+		moved: bool,
+		accumulating: bool,
+	) -> bool {
+		if moved {
+			self.invalidate(gl, accumulating);
+		}
+		if camera.recalculate_ray_dirs {
+			self.calculate_ray_dirs(gl, camera);
+			camera.recalculate_ray_dirs = false;
+			self.invalidate(gl, accumulating);
+			return true;
+		}
+		moved
+	}
 
-			// texture sampler
-			gl.uniform_1_i32(
-				gl.get_uniform_location(self.noise_program, "noise")
-					.as_ref(),
-				0,
-			);
-			// }}}
+	// Throws away the accumulated image after something that affects the render
+	// changed. Modes that don't accumulate redraw every pixel each frame and
+	// never read the previous image (the noise textures carry over on their
+	// own), so there is nothing to throw away and clearing would only cause a
+	// hitch. Settings changes that switch to an accumulating mode come through
+	// here with `accumulating` already set, so that still starts clean.
+	fn invalidate(&mut self, gl: &Context, accumulating: bool) {
+		if accumulating {
+			self.frame_index = 1;
+			self.clear_textures(gl);
+		}
+	}
 
-			gl.bind_vertex_array(Some(self.noise_verts));
-			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.noise_fbo));
+	// Keeps the accumulated image after an edit, but weighted as if it were
+	// `weight` samples of the new scene. The image refines from the old result
+	// towards the new one; it is biased during the transition, but the old
+	// samples' share shrinks with every frame so it still converges correctly.
+	fn continue_accumulation(&mut self, weight: u32) {
+		// the last frame holds `frame_index - 1` samples
+		self.history_scale = weight as f32 / (self.frame_index - 1) as f32;
+		self.frame_index = weight + 1;
+	}
 
-			// unbind the other texture (the one that is being sampled)
+	// {{{ readback
+	// queues a readback of the last rendered image, false if too many are
+	// already in flight. the result comes out of `poll_readbacks` later
+	fn request_readback(
+		&mut self,
+		gl: &Context,
+		kind: ReadbackKind,
+		region: ReadbackRegion,
+		accumulated: bool,
+	) -> bool {
+		let samples = if accumulated { self.frame_index - 1 } else { 1 };
+		let size = [self.scr_size.x as i32, self.scr_size.y as i32];
+		unsafe {
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.clear_fbo));
 			framebuffer_texture(
 				gl,
 				if self.rendering_to_texture_0 {
-					self.noise_texture_0
+					self.accumulation_texture_1
 				} else {
-					self.noise_texture_1
+					self.accumulation_texture_0
 				},
 			);
+			let queued = self
+				.readback
+				.request(gl, kind, region.texels(size), samples);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			queued
+		}
+	}
 
-			gl.draw_arrays(glow::TRIANGLES, 0, 3);
+	// hands finished readbacks to their consumers
+	fn poll_readbacks(&mut self, gl: &Context) {
+		for readback in self.readback.poll(gl) {
+			match readback.kind {
+				ReadbackKind::Eyedropper => {
+					self.sampled_color = Some(average_color(&readback.texels));
+				},
+				ReadbackKind::Inspector => self.inspected = Some(readback),
+				ReadbackKind::RenderJob => self.exported = Some(readback),
+			}
+		}
+	}
+	// }}}
+
+	// {{{ calculate ray directions
+	fn calculate_ray_dirs(&mut self, gl: &Context, camera: &Camera) {
+		self.stereo = camera.stereo().is_some();
+
+		unsafe {
+			gl.use_program(Some(self.ray_dirs_program));
+
+			// {{{ bind uniforms for ray direction calculation
+			self.apply_uniforms_common(gl, self.ray_dirs_program);
+
+			gl.uniform_matrix_4_f32_slice(
+				gl.get_uniform_location(self.ray_dirs_program, "inv_proj")
+					.as_ref(),
+				false, // no transpose, it's already in column-major order
+				camera.inv_proj.as_slice(),
+			);
+			gl.uniform_matrix_4_f32_slice(
+				gl.get_uniform_location(self.ray_dirs_program, "inv_view")
+					.as_ref(),
+				false, // no transpose, it's already in column-major order
+				camera.inv_view.as_slice(),
+			);
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.ray_dirs_program, "projection")
+					.as_ref(),
+				camera.projection() as u32,
+			);
+			// }}}
+
+			// draw into framebuffer
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.ray_dirs_fbo));
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.ray_dirs_texture));
+			gl.draw_buffers(&[glow::COLOR_ATTACHMENT0]);
+			gl.clear_buffer_u32_slice(glow::COLOR, 0, &[0, 0, 0, 0]);
+			self.fullscreen.draw(gl);
 
 			// unbind
-			gl.bind_vertex_array(None);
 			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
 			gl.bind_texture(glow::TEXTURE_2D, None);
-			// }}}
-
-			// {{{ draw ray traced image into accumulation buffer
 			gl.use_program(Some(self.program));
+		}
+	}
+	// }}}
 
-			self.apply_uniforms(gl, data);
+	// {{{ call on every frame to render
+	// `viewport` is where the final image goes, in pixels (left, bottom,
+	// width, height)
+	pub fn paint(&mut self, gl: &Context, data: &PersistentData, viewport: [i32; 4]) {
+		// only the final pass runs once the image has all its samples or while
+		// rendering is paused. changes restart accumulation after this, their
+		// uniforms go up now
+		let idle = !self.first_frame
+			&& self.frame_index > 1
+			&& !data.scene.response.changed_or_moved()
+			&& !data.settings.response.changed
+			&& data.settings.render.idle(self.frame_index);
+		if idle {
+			// step back to the frame that was rendered last, so that the final
+			// pass shows it. the end of the frame steps forward again
+			self.frame_index -= 1;
+			self.rendering_to_texture_0 = !self.rendering_to_texture_0;
+		}
 
-			// {{{ bind textures
-			if self.first_frame {
-				gl.uniform_1_i32(
-					gl.get_uniform_location(self.program, "ray_dirs").as_ref(),
-					0, // ray directions texture
+		unsafe {
+			// {{{ calculate noise texture
+			// fast frames reuse older noise, it's only seen for a frame
+			if !self.fast_frame && !idle {
+				gl.use_program(Some(self.noise_program));
+				gl.active_texture(glow::TEXTURE0);
+				gl.bind_texture(
+					glow::TEXTURE_2D,
+					Some(if self.rendering_to_texture_0 {
+						self.noise_texture_1
+					} else {
+						self.noise_texture_0
+					}),
 				);
+
+				// {{{ uniforms
+				self.apply_uniforms_common(gl, self.noise_program);
+
+				// texture sampler
 				gl.uniform_1_i32(
-					gl.get_uniform_location(self.program, "noise").as_ref(),
-					1, // noise texture, one of two buffers
+					gl.get_uniform_location(self.noise_program, "noise")
+						.as_ref(),
+					0,
 				);
-				gl.uniform_1_i32(
-					gl.get_uniform_location(self.program, "image").as_ref(),
-					2, // accumulation texture, one of two buffers
+				// }}}
+
+				gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.noise_fbo));
+
+				// unbind the other texture (the one that is being sampled)
+				framebuffer_texture(
+					gl,
+					if self.rendering_to_texture_0 {
+						self.noise_texture_0
+					} else {
+						self.noise_texture_1
+					},
 				);
-			}
-			gl.active_texture(glow::TEXTURE0);
-			gl.bind_texture(glow::TEXTURE_2D, Some(self.ray_dirs_texture));
 
-			// sample from the noise that just got generated
-			gl.active_texture(glow::TEXTURE1);
-			gl.bind_texture(
-				glow::TEXTURE_2D,
-				Some(if self.rendering_to_texture_0 {
-					self.noise_texture_0
-				} else {
-					self.noise_texture_1
-				}),
-			);
+				self.fullscreen.draw(gl);
 
-			// sample from the one that isn't being rendered to
-			gl.active_texture(glow::TEXTURE2);
-			gl.bind_texture(
-				glow::TEXTURE_2D,
-				Some(if self.rendering_to_texture_0 {
-					self.accumulation_texture_1
-				} else {
-					self.accumulation_texture_0
-				}),
-			);
+				// unbind
+				gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+				gl.bind_texture(glow::TEXTURE_2D, None);
+			}
 			// }}}
 
-			// draw into accumulation buffer
-			gl.bind_vertex_array(Some(self.verts));
-			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.accumulation_fbo));
+			// {{{ draw ray traced image into accumulation buffer
+			gl.use_program(Some(self.program));
+			if !idle {
+				self.apply_uniforms(gl, data);
 
-			// unbind the other texture (the one that is being sampled)
-			framebuffer_texture(
-				gl,
-				if self.rendering_to_texture_0 {
-					self.accumulation_texture_0
+				let samples = if self.fast_frame {
+					1
 				} else {
-					self.accumulation_texture_1
-				},
-			);
+					data.settings.render.samples_per_frame
+				};
+				gl.uniform_1_u32(
+					gl.get_uniform_location(self.program, "samples_per_frame")
+						.as_ref(),
+					samples,
+				);
 
-			gl.draw_arrays(glow::TRIANGLES, 0, 3);
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "history_scale")
+						.as_ref(),
+					self.history_scale,
+				);
+				self.history_scale = 1.0;
+
+				// {{{ bind textures
+				if self.first_frame {
+					gl.uniform_1_i32(
+						gl.get_uniform_location(self.program, "ray_dirs").as_ref(),
+						0, // ray directions texture
+					);
+					gl.uniform_1_i32(
+						gl.get_uniform_location(self.program, "noise").as_ref(),
+						1, // noise texture, one of two buffers
+					);
+					gl.uniform_1_i32(
+						gl.get_uniform_location(self.program, "image").as_ref(),
+						2, // accumulation texture, one of two buffers
+					);
+					gl.uniform_1_i32(
+						gl.get_uniform_location(self.program, "mesh_triangles")
+							.as_ref(),
+						3, // mesh triangles texture
+					);
+					gl.uniform_1_i32(
+						gl.get_uniform_location(self.program, "mesh_bvh").as_ref(),
+						4, // mesh BVH texture
+					);
+					gl.uniform_1_i32(
+						gl.get_uniform_location(self.program, "scene_objects")
+							.as_ref(),
+						5, // object texture
+					);
+				}
+				gl.active_texture(glow::TEXTURE0);
+				gl.bind_texture(glow::TEXTURE_2D, Some(self.ray_dirs_texture));
+
+				// sample from the noise that just got generated
+				gl.active_texture(glow::TEXTURE1);
+				gl.bind_texture(
+					glow::TEXTURE_2D,
+					Some(if self.rendering_to_texture_0 {
+						self.noise_texture_0
+					} else {
+						self.noise_texture_1
+					}),
+				);
 
-			// unbind
-			gl.bind_vertex_array(None);
-			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-			gl.bind_texture(glow::TEXTURE_2D, None);
+				gl.active_texture(glow::TEXTURE3);
+				gl.bind_texture(glow::TEXTURE_2D, Some(self.mesh_texture));
+				gl.active_texture(glow::TEXTURE4);
+				gl.bind_texture(glow::TEXTURE_2D, Some(self.bvh_texture));
+				gl.active_texture(glow::TEXTURE5);
+				gl.bind_texture(glow::TEXTURE_2D, Some(self.object_texture));
+
+				// sample from the one that isn't being rendered to
+				gl.active_texture(glow::TEXTURE2);
+				gl.bind_texture(
+					glow::TEXTURE_2D,
+					Some(if self.rendering_to_texture_0 {
+						self.accumulation_texture_1
+					} else {
+						self.accumulation_texture_0
+					}),
+				);
+				// }}}
+
+				// draw into accumulation buffer
+				gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.accumulation_fbo));
+
+				// unbind the other texture (the one that is being sampled)
+				framebuffer_texture(
+					gl,
+					if self.rendering_to_texture_0 {
+						self.accumulation_texture_0
+					} else {
+						self.accumulation_texture_1
+					},
+				);
+
+				self.fullscreen.draw(gl);
+
+				// unbind
+				gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+				gl.bind_texture(glow::TEXTURE_2D, None);
+			}
 			// }}}
 
 			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
@@ -547,7 +1503,48 @@ impl Raytracer {
 			gl.uniform_1_u32(
 				gl.get_uniform_location(self.final_program, "accumulate")
 					.as_ref(),
-				data.settings.render.accumulate as u32,
+				data.settings.render.accumulating() as u32,
+			);
+
+			// the noise view shows the RNG state as is
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.final_program, "display_raw")
+					.as_ref(),
+				(data.settings.render.mode == RenderMode::Noise) as u32,
+			);
+
+			// debug views show their values without exposure or tonemapping
+			let render = &data.settings.render;
+			let (tonemap, exposure, grading) = if render.mode.is_debug() {
+				(Tonemap::None, 0.0, PostSettings::NEUTRAL)
+			} else {
+				let grading = data.settings.post.uniform(self.exporting);
+				(render.tonemap, render.exposure, grading)
+			};
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.final_program, "tonemap")
+					.as_ref(),
+				tonemap as u32,
+			);
+			gl.uniform_1_f32(
+				gl.get_uniform_location(self.final_program, "exposure")
+					.as_ref(),
+				exposure.exp2(),
+			);
+			gl.uniform_4_f32_slice(
+				gl.get_uniform_location(self.final_program, "grading")
+					.as_ref(),
+				&grading,
+			);
+
+			let [x, y, w, h] = viewport;
+			gl.uniform_4_f32(
+				gl.get_uniform_location(self.final_program, "viewport")
+					.as_ref(),
+				x as f32,
+				y as f32,
+				w as f32,
+				h as f32,
 			);
 			// }}}
 
@@ -561,32 +1558,98 @@ impl Raytracer {
 					self.accumulation_texture_1
 				}),
 			);
-			gl.bind_vertex_array(Some(self.final_verts));
-			gl.draw_arrays(glow::TRIANGLES, 0, 3);
+			gl.viewport(x, y, w, h);
+			self.fullscreen.draw(gl);
+			self.use_targets_viewport(gl);
 
 			gl.bind_texture(glow::TEXTURE_2D, None);
-			gl.bind_vertex_array(None);
 			gl.use_program(Some(self.program));
 
 			self.first_frame = false;
 			self.frame_index += 1;
+			self.stats.end_frame();
 			self.rendering_to_texture_0 = !self.rendering_to_texture_0;
 			// }}}
 		}
 	}
 	// }}}
 
+	// {{{ upload meshes
+	// builds a BVH for every mesh and writes the results into two RGBA32F
+	// textures. the mesh texture has the corners of the triangles in leaf
+	// order, one texel each (alpha unused). the BVH texture has two texels per
+	// node: (min, offset) and (max, count), with the indices as floats. returns
+	// the nodes that belong to each object as (root, count)
+	unsafe fn upload_meshes(&mut self, gl: &Context, scene: &Scene) -> Vec<[u32; 2]> {
+		let mut corners: Vec<[f32; 4]> = Vec::new();
+		let mut nodes: Vec<[f32; 4]> = Vec::new();
+		let ranges = scene
+			.mesh
+			.iter()
+			.map(|mesh| {
+				// the scene keeps to the limit, this only guards the texture size
+				let first = corners.len() / 3;
+				if first + mesh.triangles.len() > MAX_MESH_TRIANGLES {
+					return [0, 0];
+				}
+
+				let bvh = Bvh::build(mesh);
+				corners.extend(bvh.triangles.iter().flatten().map(|&i| {
+					let [x, y, z] = mesh.positions[i as usize];
+					[x, y, z, 0.0]
+				}));
+
+				// offsets become indices into the whole texture
+				let root = nodes.len() / 2;
+				for node in &bvh.nodes {
+					let offset = if node.count > 0 {
+						node.offset as usize + first
+					} else {
+						node.offset as usize + root
+					};
+					let (min, max) = (node.min, node.max);
+					nodes.push([min.x, min.y, min.z, offset as f32]);
+					nodes.push([max.x, max.y, max.z, node.count as f32]);
+				}
+				[root as u32, bvh.nodes.len() as u32]
+			})
+			.collect();
+
+		gl.active_texture(glow::TEXTURE3);
+		gl.bind_texture(glow::TEXTURE_2D, Some(self.mesh_texture));
+		let mut bytes = float_texture(gl, corners, MESH_TEXTURE_WIDTH);
+		gl.active_texture(glow::TEXTURE4);
+		gl.bind_texture(glow::TEXTURE_2D, Some(self.bvh_texture));
+		bytes += float_texture(gl, nodes, MESH_TEXTURE_WIDTH);
+		self.stats.add(Upload::Meshes, bytes);
+		gl.bind_texture(glow::TEXTURE_2D, None);
+		gl.active_texture(glow::TEXTURE3);
+		gl.bind_texture(glow::TEXTURE_2D, None);
+		gl.active_texture(glow::TEXTURE0);
+
+		ranges
+	}
+	// }}}
+
 	// apply uniforms to main program
-	fn apply_uniforms(&mut self, gl: &Context, data: &PersistentData) {
+	fn apply_uniforms(&mut self, context: &Context, data: &PersistentData) {
 		unsafe {
-			self.apply_uniforms_common(gl, self.program);
+			self.apply_uniforms_common(context, self.program);
+
+			// meshes are uploaded with the scene, but counted on their own
+			let scene_changed = self.first_frame || data.scene.response.changed;
+			let mesh_ranges =
+				scene_changed.then(|| self.upload_meshes(context, &data.scene));
+
+			let mut gl = self.stats.counter(context, Upload::Frame);
 
 			// {{{ camera
+			let eye = data.camera.eye();
 			gl.uniform_3_f32(
 				gl.get_uniform_location(self.program, "camera_pos").as_ref(),
-				data.camera.pos.x,
-				data.camera.pos.y,
-				data.camera.pos.z,
+				eye.x,
+				eye.y,
+				eye.z,
 			);
 
 			gl.uniform_3_f32(
@@ -595,15 +1658,95 @@ impl Raytracer {
 				data.camera.forward_dir.y,
 				data.camera.forward_dir.z,
 			);
+
+			let (right, up) = data.camera.right_up();
+			gl.uniform_3_f32(
+				gl.get_uniform_location(self.program, "camera_right")
+					.as_ref(),
+				right.x,
+				right.y,
+				right.z,
+			);
+			gl.uniform_3_f32(
+				gl.get_uniform_location(self.program, "camera_up").as_ref(),
+				up.x,
+				up.y,
+				up.z,
+			);
+
+			gl.uniform_1_f32(
+				gl.get_uniform_location(self.program, "aperture").as_ref(),
+				data.camera.aperture,
+			);
+			gl.uniform_1_f32(
+				gl.get_uniform_location(self.program, "focal_distance")
+					.as_ref(),
+				data.camera.focal_distance,
+			);
+
+			let stereo = data.camera.stereo();
+			gl.uniform_1_f32(
+				gl.get_uniform_location(self.program, "stereo_separation")
+					.as_ref(),
+				stereo.map_or(0.0, |s| s.separation),
+			);
+			gl.uniform_1_f32(
+				gl.get_uniform_location(self.program, "stereo_convergence")
+					.as_ref(),
+				stereo.map_or(1.0, |s| s.convergence),
+			);
+			// }}}
+
+			// {{{ debug view scaling
+			// position maps the scene bounds to [0, 1], depth maps the farthest
+			// corner of the bounds (at most the far clip) to 1. without objects,
+			// fall back to a fixed range around the origin
+			let (bounds_min, bounds_max) = geometry::scene_bounds(&data.scene)
+				.unwrap_or((glm::vec3(-1.0, -1.0, -1.0), glm::vec3(1.0, 1.0, 1.0)));
+			let max_depth = geometry::bounds_corners(&bounds_min, &bounds_max)
+				.iter()
+				.map(|c| glm::distance(c, &data.camera.pos))
+				.fold(0.0, f32::max)
+				.min(data.camera.far_clip());
+
+			gl.uniform_3_f32(
+				gl.get_uniform_location(self.program, "bounds_min").as_ref(),
+				bounds_min.x,
+				bounds_min.y,
+				bounds_min.z,
+			);
+
+			gl.uniform_3_f32(
+				gl.get_uniform_location(self.program, "bounds_max").as_ref(),
+				bounds_max.x,
+				bounds_max.y,
+				bounds_max.z,
+			);
+
+			gl.uniform_1_f32(
+				gl.get_uniform_location(self.program, "max_depth").as_ref(),
+				max_depth,
+			);
 			// }}}
 
-			if self.first_frame || data.scene.response.changed {
+			// never matches an object when nothing is soloed
+			gl.uniform_1_u32(
+				gl.get_uniform_location(self.program, "solo_object")
+					.as_ref(),
+				self.solo.map_or(u32::MAX, |i| i as u32),
+			);
+
+			if let Some(mesh_ranges) = mesh_ranges {
 				// {{{ scene
+				let mut gl = self.stats.counter(context, Upload::Scene);
+
 				// general
+				// out of range when there's nothing to select, never highlights
+				let selected = data.scene.selected().unwrap_or(data.scene.len());
 				gl.uniform_1_u32(
 					gl.get_uniform_location(self.program, "scene_selected")
 						.as_ref(),
-					data.scene.selected.try_into().unwrap(),
+					selected.try_into().unwrap(),
 				);
 
 				gl.uniform_1_u32(
@@ -611,109 +1754,164 @@ impl Raytracer {
 					data.scene.len().try_into().unwrap(),
 				);
 
-				gl.uniform_1_u32_slice(
-					gl.get_uniform_location(self.program, "scene_obj_type")
+				gl.uniform_1_f32_slice(
+					gl.get_uniform_location(self.program, "scene_light_group_scale")
 						.as_ref(),
-					&fill_50(bytemuck::cast_slice(&data.scene.ty)),
+					&data.scene.light_group_scales(),
 				);
 
-				// materials
-				gl.uniform_1_u32_slice(
-					gl.get_uniform_location(self.program, "scene_mat_type")
-						.as_ref(),
-					&fill_50(bytemuck::cast_slice(&data.scene.mat_ty)),
-				);
+				// everything else about the objects
+				context.active_texture(glow::TEXTURE5);
+				context.bind_texture(glow::TEXTURE_2D, Some(self.object_texture));
+				let texels = object_texels(&data.scene, &mesh_ranges);
+				let bytes = float_texture(context, texels, OBJECT_TEXELS);
+				self.stats.add(Upload::Scene, bytes);
+				context.bind_texture(glow::TEXTURE_2D, None);
+				context.active_texture(glow::TEXTURE0);
+				// starts out with every object at full detail
+				self.uploaded_lod.clear();
+			// }}}
+			} else if !data.scene.response.moved.is_empty() {
+				// {{{ moved objects
+				// only the transforms of the objects that turntables spun
+				context.active_texture(glow::TEXTURE5);
+				context.bind_texture(glow::TEXTURE_2D, Some(self.object_texture));
+				let moved = data.scene.response.moved;
+				for i in (0..data.scene.len()).filter(|&i| moved.contains(i)) {
+					let texels = transform_texels(&data.scene, i);
+					context.tex_sub_image_2d(
+						glow::TEXTURE_2D,
+						0,
+						0,
+						i as i32,
+						texels.len() as i32,
+						1,
+						glow::RGBA,
+						glow::FLOAT,
+						glow::PixelUnpackData::Slice(bytemuck::cast_slice(&texels)),
+					);
+					self
+						.stats
+						.add(Upload::Scene, std::mem::size_of_val(&texels));
+				}
+				context.bind_texture(glow::TEXTURE_2D, None);
+				context.active_texture(glow::TEXTURE0);
+				// }}}
+			}
 
-				gl.uniform_3_f32_slice(
-					gl.get_uniform_location(self.program, "scene_mat_color")
-						.as_ref(),
-					bytemuck::cast_slice(&fill_50(&data.scene.mat_color)),
+			// {{{ level of detail
+			// changes with the camera, not only with the scene. it's the last
+			// column of the object texture, which has a row for every object
+			let lod: Vec<f32> = (0..data.scene.len())
+				.map(|i| self.lod.get(i).map_or(lod::LOD_FULL, |&l| l) as f32)
+				.collect();
+			if lod != self.uploaded_lod {
+				let texels: Vec<[f32; 4]> =
+					lod.iter().map(|&l| [l, 0.0, 0.0, 0.0]).collect();
+				context.active_texture(glow::TEXTURE5);
+				context.bind_texture(glow::TEXTURE_2D, Some(self.object_texture));
+				context.tex_sub_image_2d(
+					glow::TEXTURE_2D,
+					0,
+					(OBJECT_TEXELS - 1) as i32,
+					0,
+					1,
+					texels.len() as i32,
+					glow::RGBA,
+					glow::FLOAT,
+					glow::PixelUnpackData::Slice(bytemuck::cast_slice(&texels)),
 				);
+				self
+					.stats
+					.add(Upload::Frame, std::mem::size_of_val(texels.as_slice()));
+				context.bind_texture(glow::TEXTURE_2D, None);
+				context.active_texture(glow::TEXTURE0);
+				self.uploaded_lod = lod;
+			}
+			// }}}
 
-				gl.uniform_1_f32_slice(
-					gl.get_uniform_location(self.program, "scene_mat_ior")
-						.as_ref(),
-					&fill_50(&data.scene.mat_ior),
+			if self.first_frame || data.settings.response.changed {
+				// {{{ world settings
+				let mut gl = self.stats.counter(context, Upload::Settings);
+
+				let sky = data.settings.world.sky_colors();
+
+				// sky color
+				gl.uniform_3_f32(
+					gl.get_uniform_location(self.program, "sky_color").as_ref(),
+					sky.zenith[0],
+					sky.zenith[1],
+					sky.zenith[2],
 				);
 
-				gl.uniform_1_f32_slice(
-					gl.get_uniform_location(self.program, "scene_mat_specular")
+				gl.uniform_3_f32(
+					gl.get_uniform_location(self.program, "horizon_color")
 						.as_ref(),
-					&fill_50(&data.scene.mat_specular),
+					sky.horizon[0],
+					sky.horizon[1],
+					sky.horizon[2],
 				);
 
-				gl.uniform_1_f32_slice(
-					gl.get_uniform_location(self.program, "scene_mat_roughness")
-						.as_ref(),
-					&fill_50(&data.scene.mat_roughness),
+				// sun color
+				gl.uniform_3_f32(
+					gl.get_uniform_location(self.program, "sun_color").as_ref(),
+					sky.sun[0],
+					sky.sun[1],
+					sky.sun[2],
 				);
 
-				gl.uniform_1_f32_slice(
-					gl.get_uniform_location(self.program, "scene_mat_emissive_strength")
+				// sun strength
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "sun_strength")
 						.as_ref(),
-					&fill_50(&data.scene.mat_emissive_strength),
+					data.settings.world.sun_strength,
 				);
 
-				// transforms
-				gl.uniform_matrix_4_f32_slice(
-					gl.get_uniform_location(self.program, "scene_transform")
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "sun_cos_radius")
 						.as_ref(),
-					false, // no transpose, it's already in column-major order
-					flatten_matrices(&fill_50(&data.scene.transform)),
+					data.settings.world.sun_cos_radius(),
 				);
 
-				gl.uniform_matrix_4_f32_slice(
-					gl.get_uniform_location(self.program, "scene_inv_transform")
-						.as_ref(),
-					false, // no transpose, it's already in column-major order
-					flatten_matrices(&fill_50(&data.scene.inv_transform)),
+				// constant fill light
+				gl.uniform_3_f32(
+					gl.get_uniform_location(self.program, "fill_color").as_ref(),
+					data.settings.world.fill_color[0],
+					data.settings.world.fill_color[1],
+					data.settings.world.fill_color[2],
 				);
 
-				gl.uniform_matrix_4_f32_slice(
-					gl.get_uniform_location(self.program, "scene_normal_transform")
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "fill_strength")
 						.as_ref(),
-					false, // no transpose, it's already in column-major order
-					flatten_matrices(&fill_50(&data.scene.normal_transform)),
+					data.settings.world.fill_strength,
 				);
-				// }}}
-			}
 
-			if self.first_frame || data.settings.response.changed {
-				// {{{ world settings
-				// sky color
-				gl.uniform_3_f32(
-					gl.get_uniform_location(self.program, "sky_color").as_ref(),
-					data.settings.world.sky_color[0],
-					data.settings.world.sky_color[1],
-					data.settings.world.sky_color[2],
+				// ground plane
+				gl.uniform_1_u32(
+					gl.get_uniform_location(self.program, "ground_plane")
+						.as_ref(),
+					data.settings.world.ground_plane as u32,
 				);
 
-				// sun color
-				gl.uniform_3_f32(
-					gl.get_uniform_location(self.program, "sun_color").as_ref(),
-					data.settings.world.sun_color[0],
-					data.settings.world.sun_color[1],
-					data.settings.world.sun_color[2],
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "ground_height")
+						.as_ref(),
+					data.settings.world.ground_height,
 				);
 
-				// sun direction
-				let beta_cos = data.settings.world.sun_elevation.cos();
-				let x = data.settings.world.sun_rotation.cos() * beta_cos;
-				let y = data.settings.world.sun_elevation.sin();
-				let z = data.settings.world.sun_rotation.sin() * beta_cos;
-				let mag = (x.powi(2) + y.powi(2) + z.powi(2)).sqrt();
 				gl.uniform_3_f32(
-					gl.get_uniform_location(self.program, "sun_dir").as_ref(),
-					x / mag,
-					y / mag,
-					z / mag,
+					gl.get_uniform_location(self.program, "ground_color")
+						.as_ref(),
+					data.settings.world.ground_color[0],
+					data.settings.world.ground_color[1],
+					data.settings.world.ground_color[2],
 				);
 
-				// sun strength
 				gl.uniform_1_f32(
-					gl.get_uniform_location(self.program, "sun_strength")
+					gl.get_uniform_location(self.program, "ground_roughness")
 						.as_ref(),
-					data.settings.world.sun_strength,
+					data.settings.world.ground_roughness,
 				);
 				// }}}
 
@@ -727,20 +1925,22 @@ impl Raytracer {
 
 				gl.uniform_1_u32(
 					gl.get_uniform_location(self.program, "accumulate").as_ref(),
-					data.settings.render.accumulate as u32,
+					data.settings.render.accumulating() as u32,
 				);
 
+				// highlight selected object. panoramas are captures, so they're
+				// left as they are
+				let panorama = data.settings.render.projection == Projection::Panorama;
 				gl.uniform_1_u32(
-					gl.get_uniform_location(self.program, "samples_per_frame")
+					gl.get_uniform_location(self.program, "highlight_selected")
 						.as_ref(),
-					data.settings.render.samples_per_frame,
+					(data.settings.render.highlight && !panorama) as u32,
 				);
 
-				// highlight selected object
 				gl.uniform_1_u32(
-					gl.get_uniform_location(self.program, "highlight_selected")
+					gl.get_uniform_location(self.program, "solo_lighting")
 						.as_ref(),
-					data.settings.render.highlight as u32,
+					data.settings.render.solo_lighting as u32,
 				);
 
 				// maximum light bounces
@@ -749,6 +1949,46 @@ impl Raytracer {
 						.as_ref(),
 					data.settings.render.max_bounces,
 				);
+
+				// global roughness multiplier
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "roughness_multiplier")
+						.as_ref(),
+					data.settings.render.roughness_multiplier,
+				);
+
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "ray_bias").as_ref(),
+					data.settings.render.ray_bias,
+				);
+
+				gl.uniform_1_f32(
+					gl.get_uniform_location(self.program, "firefly_clamp")
+						.as_ref(),
+					data.settings.render.firefly_clamp,
+				);
+
+				gl.uniform_1_u32(
+					gl.get_uniform_location(self.program, "debug_palette")
+						.as_ref(),
+					data.settings.render.debug_palette as u32,
+				);
+				// }}}
+			}
+
+			// a sun locked to the camera follows its yaw, so it has to be updated
+			// on camera movement too (which resets accumulation by itself)
+			let sun_locked = data.settings.world.lock_sun_to_camera;
+			if self.first_frame || data.settings.response.changed || sun_locked {
+				// {{{ sun direction
+				let mut gl = self.stats.counter(context, Upload::Settings);
+				let sun_dir = data.settings.world.sun_dir(data.camera.yaw());
+				gl.uniform_3_f32(
+					gl.get_uniform_location(self.program, "sun_dir").as_ref(),
+					sun_dir.x,
+					sun_dir.y,
+					sun_dir.z,
+				);
 				// }}}
 			}
 		}
@@ -766,11 +2006,31 @@ impl Raytracer {
 				gl.get_uniform_location(program, "frame_index").as_ref(),
 				self.frame_index,
 			);
+			gl.uniform_1_u32(
+				gl.get_uniform_location(program, "stereo").as_ref(),
+				self.stereo as u32,
+			);
 		}
 	}
 }
 
 // {{{ gl helpers
+// WEBGL_debug_renderer_info, browsers mask the regular RENDERER string
+const UNMASKED_RENDERER_WEBGL: u32 = 0x9246;
+
+pub fn renderer_name(gl: &Context) -> String {
+	unsafe {
+		if gl
+			.supported_extensions()
+			.contains("WEBGL_debug_renderer_info")
+		{
+			gl.get_parameter_string(UNMASKED_RENDERER_WEBGL)
+		} else {
+			gl.get_parameter_string(glow::RENDERER)
+		}
+	}
+}
+
 unsafe fn screen_sized_texture(gl: &Context, scr_size: glm::Vec2, params: bool) {
 	gl.tex_image_2d(
 		glow::TEXTURE_2D,
@@ -798,6 +2058,108 @@ unsafe fn screen_sized_texture(gl: &Context, scr_size: glm::Vec2, params: bool)
 	}
 }
 
+// {{{ object texture
+// one row per object, read by the scene_* functions in fsh.glsl. the transform,
+// its inverse and the normal transform come first, column by column
+fn transform_texels(scene: &Scene, i: usize) -> [[f32; 4]; 12] {
+	let mut texels = [[0.0; 4]; 12];
+	let matrices = [
+		&scene.transform[i],
+		&scene.inv_transform[i],
+		&scene.normal_transform[i],
+	];
+	for (columns, matrix) in texels.chunks_exact_mut(4).zip(matrices) {
+		columns.copy_from_slice(bytemuck::cast_slice(matrix.as_slice()));
+	}
+	texels
+}
+
+// every object, with the rows past the end of the scene left at zero.
+// integers are exact as floats at these sizes
+fn object_texels(scene: &Scene, mesh_ranges: &[[u32; 2]]) -> Vec<[f32; 4]> {
+	let uv_matrices = scene.uv_matrices();
+	let checkers = scene.checker_uniforms();
+
+	// the level of detail is left at LOD_FULL, 0
+	let mut texels = vec![[0.0; 4]; scene.len() * OBJECT_TEXELS];
+	for (i, row) in texels.chunks_exact_mut(OBJECT_TEXELS).enumerate() {
+		let (op, other) = scene
+			.csg_link(i)
+			.map_or((0.0, 0.0), |(op, j)| (op as u32 as f32, j as f32));
+		let partner = scene.csg_partner(i) as u32 as f32;
+		let [root, count] = mesh_ranges[i].map(|n| n as f32);
+		let [r, g, b] = scene.mat_color[i];
+		let [er, eg, eb] = scene.mat_emissive_color[i];
+		let uv_offset = scene.uv_offset[i];
+
+		row[..12].copy_from_slice(&transform_texels(scene, i));
+		row[12..].copy_from_slice(&[
+			[
+				scene.ty[i] as u32 as f32,
+				scene.flip_normals[i] as u32 as f32,
+				scene.double_sided[i] as u32 as f32,
+				scene.corner_radius[i],
+			],
+			[
+				scene.cap_radius[i],
+				root,
+				count,
+				scene.mat_light_group[i] as f32,
+			],
+			[op, other, partner, scene.mat_ty[i] as u32 as f32],
+			uv_matrices[i],
+			[
+				uv_offset.x,
+				uv_offset.y,
+				scene.mat_ior[i],
+				scene.mat_specular[i],
+			],
+			[r, g, b, scene.mat_roughness[i]],
+			[er, eg, eb, scene.mat_emissive_strength[i]],
+			checkers[i],
+			[scene.mat_metallic[i], 0.0, 0.0, 0.0],
+			[0.0; 4],
+		]);
+	}
+	texels
+}
+// }}}
+
+// `width` texels per row, always at least one row and full rows only. float
+// textures can't be filtered everywhere, texelFetch ignores the filter anyway.
+// returns the size of the upload in bytes
+unsafe fn float_texture(
+	gl: &Context,
+	mut texels: Vec<[f32; 4]>,
+	width: usize,
+) -> usize {
+	let rows = texels.len().div_ceil(width).max(1);
+	texels.resize(rows * width, [0.0; 4]);
+
+	gl.tex_image_2d(
+		glow::TEXTURE_2D,
+		0,
+		glow::RGBA32F as i32,
+		width as i32,
+		rows as i32,
+		0,
+		glow::RGBA,
+		glow::FLOAT,
+		Some(bytemuck::cast_slice(&texels)),
+	);
+	gl.tex_parameter_i32(
+		glow::TEXTURE_2D,
+		glow::TEXTURE_MIN_FILTER,
+		glow::NEAREST as i32,
+	);
+	gl.tex_parameter_i32(
+		glow::TEXTURE_2D,
+		glow::TEXTURE_MAG_FILTER,
+		glow::NEAREST as i32,
+	);
+	std::mem::size_of_val(texels.as_slice())
+}
+
 unsafe fn framebuffer_texture(gl: &Context, texture: Texture) {
 	gl.framebuffer_texture_2d(
 		glow::FRAMEBUFFER,
@@ -808,3 +2170,766 @@ unsafe fn framebuffer_texture(gl: &Context, texture: Texture) {
 	);
 }
 // }}}
+
+#[cfg(test)]
+pub mod tests {
+	use khronos_egl as egl;
+
+	use super::*;
+	use crate::{
+		scene::Scene,
+		util::{self, pcg_hash},
+	};
+
+	const SIZE: i32 = 64;
+
+	// a GL 3.3 core context drawing into a pbuffer, through Mesa's surfaceless
+	// platform so that no window system is needed. none without EGL or a
+	// driver, tests that need it then skip. the instance keeps libEGL loaded
+	pub fn headless() -> Option<(egl::DynamicInstance<egl::EGL1_5>, Context)> {
+		const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+		let egl =
+			unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }.ok()?;
+		let display = unsafe {
+			egl.get_platform_display(
+				PLATFORM_SURFACELESS_MESA,
+				egl::DEFAULT_DISPLAY,
+				&[egl::ATTRIB_NONE],
+			)
+		}
+		.ok()?;
+		egl.initialize(display).ok()?;
+		egl.bind_api(egl::OPENGL_API).ok()?;
+
+		#[rustfmt::skip]
+		let config = egl.choose_first_config(display, &[
+			egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+			egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+			egl::RED_SIZE, 8,
+			egl::GREEN_SIZE, 8,
+			egl::BLUE_SIZE, 8,
+			egl::NONE,
+		]).ok()??;
+		#[rustfmt::skip]
+		let context = egl.create_context(display, config, None, &[
+			egl::CONTEXT_MAJOR_VERSION, 3,
+			egl::CONTEXT_MINOR_VERSION, 3,
+			egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+			egl::NONE,
+		]).ok()?;
+		#[rustfmt::skip]
+		let surface = egl.create_pbuffer_surface(display, config, &[
+			egl::WIDTH, SIZE,
+			egl::HEIGHT, SIZE,
+			egl::NONE,
+		]).ok()?;
+		egl
+			.make_current(display, Some(surface), Some(surface), Some(context))
+			.ok()?;
+
+		let gl = unsafe {
+			Context::from_loader_function(|name| {
+				egl
+					.get_proc_address(name)
+					.map_or(std::ptr::null(), |f| f as *const _)
+			})
+		};
+		Some((egl, gl))
+	}
+
+	// creating the renderer compiles and links every shader, failures panic
+	#[test]
+	fn renders_a_frame() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let scr_size = glm::vec2(SIZE as f32, SIZE as f32);
+		let data = PersistentData::new(scr_size, &renderer_name(&gl));
+		let mut raytracer = Raytracer::new(&gl, &data.camera, scr_size, None);
+		raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+
+		let mut pixels = vec![0u8; (SIZE * SIZE * 4) as usize];
+		unsafe {
+			assert_eq!(gl.get_error(), glow::NO_ERROR);
+			gl.read_pixels(
+				0,
+				0,
+				SIZE,
+				SIZE,
+				glow::RGBA,
+				glow::UNSIGNED_BYTE,
+				glow::PixelPackData::Slice(&mut pixels),
+			);
+		}
+		// the default scene fills the view, some of it is lit
+		assert!(pixels.chunks(4).any(|p| p[..3] != [0, 0, 0]));
+
+		raytracer.destroy(&gl);
+	}
+
+	// the object texture as the shader sees it, a row for each object
+	fn read_objects(
+		gl: &Context,
+		raytracer: &Raytracer,
+		rows: usize,
+	) -> Vec<[f32; 4]> {
+		let mut texels = vec![[0.0f32; 4]; rows * OBJECT_TEXELS];
+		unsafe {
+			let fbo = gl.create_framebuffer().unwrap();
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+			framebuffer_texture(gl, raytracer.object_texture);
+			gl.read_pixels(
+				0,
+				0,
+				OBJECT_TEXELS as i32,
+				rows as i32,
+				glow::RGBA,
+				glow::FLOAT,
+				glow::PixelPackData::Slice(bytemuck::cast_slice_mut(&mut texels)),
+			);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			gl.delete_framebuffer(fbo);
+			assert_eq!(gl.get_error(), glow::NO_ERROR);
+		}
+		texels
+	}
+
+	// mesh ranges don't change when objects move, they're taken from the
+	// texture
+	fn assert_matches_full_upload(texels: &[[f32; 4]], scene: &Scene) {
+		let ranges: Vec<_> = texels
+			.chunks_exact(OBJECT_TEXELS)
+			.map(|row| [row[13][1] as u32, row[13][2] as u32])
+			.collect();
+		let mut expected = object_texels(scene, &ranges);
+		for (row, expected) in texels
+			.chunks_exact(OBJECT_TEXELS)
+			.zip(expected.chunks_exact_mut(OBJECT_TEXELS))
+		{
+			// the level of detail comes from the renderer
+			expected[OBJECT_TEXELS - 1] = row[OBJECT_TEXELS - 1];
+		}
+		assert_eq!(texels, expected);
+	}
+
+	// turntables only upload the transforms, the rest of the row has to stay
+	#[test]
+	fn moved_objects_match_a_full_upload() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let scr_size = glm::vec2(SIZE as f32, SIZE as f32);
+		let mut data = PersistentData::new(scr_size, &renderer_name(&gl));
+		data.scene.new_object();
+		data.scene.new_object();
+		data.scene.recalc_transforms();
+		let mut raytracer = Raytracer::new(&gl, &data.camera, scr_size, None);
+		raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+
+		data.scene.response.reset();
+		let last = data.scene.len() - 1;
+		data.scene.rotation[last].y += 0.5;
+		data.scene.position[0].x += 1.0;
+		data.scene.recalc_transforms();
+		data.scene.response.moved.insert(0);
+		data.scene.response.moved.insert(last);
+		raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+
+		let texels = read_objects(&gl, &raytracer, data.scene.len());
+		assert_matches_full_upload(&texels, &data.scene);
+
+		raytracer.destroy(&gl);
+	}
+
+	// past the 64 bits the moved objects used to fit in. the object in the
+	// middle of the view is picked, everything else is off to the side
+	#[test]
+	fn scenes_past_64_objects_are_rendered() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		const COUNT: usize = 100;
+		let center = [SIZE / 2, SIZE / 2];
+		let aside = glm::vec3(-100.0, 0.0, 0.0);
+		let scr_size = glm::vec2(SIZE as f32, SIZE as f32);
+		let mut data = PersistentData::new(scr_size, &renderer_name(&gl));
+		while data.scene.len() < COUNT {
+			data.scene.new_object();
+		}
+		data.scene.position.fill(aside);
+		data.scene.position[COUNT - 1] = glm::vec3(0.0, 0.0, 0.0);
+		data.scene.recalc_transforms();
+		let mut raytracer = Raytracer::new(&gl, &data.camera, scr_size, None);
+		raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+		assert_eq!(raytracer.pick(&gl, center), Some(COUNT - 1));
+
+		// turntables moving objects on both sides of the old limit
+		data.scene.response.reset();
+		data.scene.position[COUNT - 1] = aside;
+		data.scene.position[70] = glm::vec3(0.0, 0.0, 0.0);
+		data.scene.recalc_transforms();
+		data.scene.response.moved.insert(70);
+		data.scene.response.moved.insert(COUNT - 1);
+		raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+		assert_eq!(raytracer.pick(&gl, center), Some(70));
+		let texels = read_objects(&gl, &raytracer, COUNT);
+		assert_matches_full_upload(&texels, &data.scene);
+
+		// skipped objects aren't picked either
+		data.scene.response.reset();
+		raytracer.lod = vec![lod::LOD_FULL; COUNT];
+		raytracer.lod[70] = lod::LOD_SKIP;
+		raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+		assert_eq!(raytracer.pick(&gl, center), None);
+		let texels = read_objects(&gl, &raytracer, COUNT);
+		assert_eq!(texels[71 * OBJECT_TEXELS - 1][0], lod::LOD_SKIP as f32);
+
+		raytracer.destroy(&gl);
+	}
+
+	// acne shows on planes, a ray leaving one starts right on it. the bias test
+	// scene's floor far from the origin, lit by a low sun alone, has to be lit
+	// evenly
+	#[test]
+	fn bias_test_scene_has_no_acne() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let scr_size = glm::vec2(SIZE as f32, SIZE as f32);
+		let mut data = PersistentData::new(scr_size, &renderer_name(&gl));
+		data.scene = Scene::default().with_bias_test_scene();
+		let world = &mut data.settings.world;
+		world.sun_color = [1.0, 1.0, 1.0];
+		world.sky_color = [0.0, 0.0, 0.0];
+		world.sun_elevation = 0.3;
+		world.ground_plane = false;
+		data.camera.pos = glm::vec3(400.0, 1.0, 400.0);
+		data
+			.camera
+			.look_at_point(glm::vec3(405.0, 0.0, 405.0), false);
+
+		let mut raytracer = Raytracer::new(&gl, &data.camera, scr_size, None);
+		for _ in 0..4 {
+			raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+			data.scene.response.reset();
+			data.settings.response.reset();
+		}
+
+		let mut pixels = vec![0u8; (SIZE * SIZE * 4) as usize];
+		unsafe {
+			gl.read_pixels(
+				0,
+				0,
+				SIZE,
+				SIZE,
+				glow::RGBA,
+				glow::UNSIGNED_BYTE,
+				glow::PixelPackData::Slice(&mut pixels),
+			);
+			assert_eq!(gl.get_error(), glow::NO_ERROR);
+		}
+
+		// the bottom half is all floor
+		let mut floor: Vec<u8> = pixels[..pixels.len() / 2]
+			.chunks(4)
+			.map(|p| p[..3].iter().copied().max().unwrap())
+			.collect();
+		floor.sort_unstable();
+		let median = floor[floor.len() / 2];
+		assert!(median > 64, "floor isn't lit ({median})");
+		assert!(
+			floor[0] + 8 >= median,
+			"acne: {} against {median}",
+			floor[0]
+		);
+
+		raytracer.destroy(&gl);
+	}
+
+	// {{{ async readback
+	// a few accumulated frames of the default scene
+	fn accumulated(gl: &Context) -> Raytracer {
+		let scr_size = glm::vec2(SIZE as f32, SIZE as f32);
+		let mut data = PersistentData::new(scr_size, &renderer_name(gl));
+		let mut raytracer = Raytracer::new(gl, &data.camera, scr_size, None);
+		for _ in 0..4 {
+			raytracer.paint(gl, &data, [0, 0, SIZE, SIZE]);
+			data.scene.response.reset();
+			data.settings.response.reset();
+		}
+		raytracer
+	}
+
+	// the last frame read the usual, stalling way. float bits summed over the
+	// samples, like the pixel buffers get them
+	fn read_blocking(gl: &Context, raytracer: &Raytracer) -> Vec<[u32; 4]> {
+		let [w, h] = [raytracer.scr_size.x as i32, raytracer.scr_size.y as i32];
+		let mut texels = vec![[0_u32; 4]; (w * h) as usize];
+		unsafe {
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(raytracer.clear_fbo));
+			framebuffer_texture(
+				gl,
+				if raytracer.rendering_to_texture_0 {
+					raytracer.accumulation_texture_1
+				} else {
+					raytracer.accumulation_texture_0
+				},
+			);
+			gl.read_pixels(
+				0,
+				0,
+				w,
+				h,
+				glow::RGBA_INTEGER,
+				glow::UNSIGNED_INT,
+				glow::PixelPackData::Slice(bytemuck::cast_slice_mut(&mut texels)),
+			);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			assert_eq!(gl.get_error(), glow::NO_ERROR);
+		}
+		texels
+	}
+
+	fn average(texels: &[[u32; 4]], samples: u32) -> Vec<[f32; 4]> {
+		let scale = 1.0 / samples as f32;
+		texels
+			.iter()
+			.map(|t| t.map(|c| f32::from_bits(c) * scale))
+			.collect()
+	}
+
+	// polls like the paint callback does, until nothing is in flight. a fence
+	// that never signals fails instead of hanging
+	fn poll_until_done(gl: &Context, raytracer: &mut Raytracer) {
+		let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+		while !raytracer.readback.pending.is_empty() {
+			assert!(std::time::Instant::now() < deadline, "readback timed out");
+			unsafe { gl.flush() };
+			raytracer.poll_readbacks(gl);
+		}
+	}
+
+	#[test]
+	fn readbacks_match_a_blocking_read() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let mut raytracer = accumulated(&gl);
+		let expected = read_blocking(&gl, &raytracer);
+		let samples = raytracer.frame_index - 1;
+		assert!(samples > 1);
+
+		// the rectangle and the full frame go through `Rect`, which the render
+		// job uses for the whole image
+		let rect = ReadbackRegion::Rect([20, 10, 8, 4]);
+		let pixel = ReadbackRegion::Pixel([37, 51]);
+		let frame = ReadbackRegion::Rect([0, 0, SIZE, SIZE]);
+		for (kind, region) in [
+			(ReadbackKind::Eyedropper, rect),
+			(ReadbackKind::Inspector, pixel),
+			(ReadbackKind::RenderJob, frame),
+		] {
+			assert!(raytracer.request_readback(&gl, kind, region, true));
+		}
+		poll_until_done(&gl, &mut raytracer);
+
+		let rows = expected.chunks_exact(SIZE as usize);
+		let rect_texels: Vec<_> = rows
+			.skip(10)
+			.take(4)
+			.flat_map(|row| &row[20..28])
+			.copied()
+			.collect();
+		let sampled = raytracer.sampled_color.take().unwrap();
+		assert_eq!(sampled, average_color(&average(&rect_texels, samples)));
+
+		let inspected = raytracer.inspected.take().unwrap();
+		assert_eq!(inspected.region, [37, 51, 1, 1]);
+		assert_eq!(inspected.samples, samples);
+		let texel = expected[(51 * SIZE + 37) as usize];
+		assert_eq!(inspected.texels, average(&[texel], samples));
+
+		let exported = raytracer.exported.take().unwrap();
+		assert_eq!(exported.region, [0, 0, SIZE, SIZE]);
+		assert_eq!(exported.texels, average(&expected, samples));
+
+		raytracer.destroy(&gl);
+	}
+
+	// refused readbacks never come back, the render job times out waiting
+	#[test]
+	fn readbacks_in_flight_are_limited() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let mut raytracer = accumulated(&gl);
+		let frame = ReadbackRegion::Rect([0, 0, SIZE, SIZE]);
+		let kind = ReadbackKind::RenderJob;
+		for _ in 0..MAX_READBACKS_IN_FLIGHT {
+			assert!(raytracer.request_readback(&gl, kind, frame, true));
+		}
+		assert!(!raytracer.request_readback(&gl, kind, frame, true));
+		// other kinds have their own limit, empty regions are refused
+		let outside = ReadbackRegion::Pixel([SIZE, 0]);
+		assert!(!raytracer.request_readback(
+			&gl,
+			ReadbackKind::Inspector,
+			outside,
+			true
+		));
+		assert!(raytracer.request_readback(&gl, ReadbackKind::Inspector, frame, true));
+
+		let mut done = Vec::new();
+		let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+		while !raytracer.readback.pending.is_empty() {
+			assert!(std::time::Instant::now() < deadline, "readback timed out");
+			unsafe { gl.flush() };
+			done.extend(raytracer.readback.poll(&gl).into_iter().map(|r| r.kind));
+		}
+		done.sort_by_key(|&kind| kind == ReadbackKind::RenderJob);
+		assert_eq!(done, [ReadbackKind::Inspector, kind, kind]);
+
+		// the buffers are reused afterwards
+		let free = raytracer.readback.free.clone();
+		assert!(raytracer.request_readback(&gl, kind, frame, true));
+		assert!(free.contains(&raytracer.readback.pending[0].buffer));
+		poll_until_done(&gl, &mut raytracer);
+
+		raytracer.destroy(&gl);
+	}
+
+	// buffers and fences are deleted, the ones still in flight too
+	#[test]
+	fn destroying_readbacks_deletes_everything() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let mut raytracer = accumulated(&gl);
+		let frame = ReadbackRegion::Rect([0, 0, SIZE, SIZE]);
+		assert!(raytracer.request_readback(&gl, ReadbackKind::RenderJob, frame, true));
+		poll_until_done(&gl, &mut raytracer);
+		assert!(raytracer.request_readback(&gl, ReadbackKind::Inspector, frame, true));
+		assert!(raytracer.request_readback(&gl, ReadbackKind::RenderJob, frame, true));
+
+		let readback = std::mem::take(&mut raytracer.readback);
+		let buffers: Vec<_> = readback
+			.pending
+			.iter()
+			.map(|p| p.buffer)
+			.chain(readback.free.iter().copied())
+			.collect();
+		let fences: Vec<_> = readback.pending.iter().map(|p| p.fence).collect();
+		assert_eq!((buffers.len(), fences.len()), (2, 2));
+		unsafe {
+			assert!(buffers.iter().all(|&b| gl.is_buffer(b)));
+			assert!(fences.iter().all(|&f| gl.is_sync(f)));
+			readback.destroy(&gl);
+			assert!(!buffers.iter().any(|&b| gl.is_buffer(b)));
+			assert!(!fences.iter().any(|&f| gl.is_sync(f)));
+			assert_eq!(gl.get_error(), glow::NO_ERROR);
+		}
+
+		raytracer.destroy(&gl);
+	}
+	// }}}
+
+	// without any separation both eyes see what the camera sees, down to the
+	// noise
+	#[test]
+	fn stereo_without_separation_is_mono_twice() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let render = |stereo: bool| {
+			let width = if stereo { 2 * SIZE } else { SIZE };
+			let scr_size = glm::vec2(width as f32, SIZE as f32);
+			let mut data = PersistentData::new(scr_size, &renderer_name(&gl));
+			data.settings.render.stereo = stereo;
+			data.settings.render.stereo_separation = 0.0;
+			data.camera.set_stereo(data.settings.render.stereo_rig());
+
+			let mut raytracer = Raytracer::new(&gl, &data.camera, scr_size, None);
+			for _ in 0..3 {
+				raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+				data.scene.response.reset();
+				data.settings.response.reset();
+			}
+			let texels = read_blocking(&gl, &raytracer);
+			raytracer.destroy(&gl);
+			texels
+		};
+
+		let mono = render(false);
+		let stereo = render(true);
+		let rows = stereo
+			.chunks(2 * SIZE as usize)
+			.zip(mono.chunks(SIZE as usize));
+		for (y, (stereo, mono)) in rows.enumerate() {
+			let (left, right) = stereo.split_at(SIZE as usize);
+			assert!(left == mono, "left view differs in row {y}");
+			assert!(right == mono, "right view differs in row {y}");
+		}
+	}
+
+	// {{{ random streams
+	// chi-square over equally likely bins, 0.001 significance for up to 15
+	// degrees of freedom. the noise is deterministic for a seed, so this either
+	// always passes or always fails
+	fn assert_uniform(what: &str, counts: &[u32]) {
+		const CRITICAL: [f32; 16] = [
+			0.0, 10.83, 13.82, 16.27, 18.47, 20.52, 22.46, 24.32, 26.12, 27.88, 29.59,
+			31.26, 32.91, 34.53, 36.12, 37.70,
+		];
+		let total: u32 = counts.iter().sum();
+		let expected = total as f32 / counts.len() as f32;
+		let chi_square: f32 = counts
+			.iter()
+			.map(|&c| (c as f32 - expected).powi(2) / expected)
+			.sum();
+		let critical = CRITICAL[counts.len() - 1];
+		assert!(
+			chi_square < critical,
+			"{what}: chi-square {chi_square} over {critical}, {counts:?}",
+		);
+	}
+
+	// the last states the noise prepass wrote, four per pixel
+	unsafe fn read_noise(gl: &Context, raytracer: &Raytracer) -> Vec<[u32; 4]> {
+		let mut texels = vec![[0u32; 4]; (SIZE * SIZE) as usize];
+		gl.bind_framebuffer(glow::FRAMEBUFFER, Some(raytracer.noise_fbo));
+		gl.read_pixels(
+			0,
+			0,
+			SIZE,
+			SIZE,
+			glow::RGBA_INTEGER,
+			glow::UNSIGNED_INT,
+			glow::PixelPackData::Slice(bytemuck::cast_slice_mut(&mut texels)),
+		);
+		gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+		texels
+	}
+
+	// rng_next in fsh.glsl
+	fn rng_next(state: [u32; 4], i: u32) -> u32 {
+		pcg_hash(state[i as usize & 3] ^ pcg_hash(i))
+	}
+
+	// each stream on its own, then the numbers a pixel takes one after another,
+	// which mix the streams. pairs are binned together to catch correlation
+	#[test]
+	fn random_streams_are_uniform() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+
+		let scr_size = glm::vec2(SIZE as f32, SIZE as f32);
+		let data = PersistentData::new(scr_size, &renderer_name(&gl));
+		let mut raytracer = Raytracer::new(&gl, &data.camera, scr_size, None);
+
+		// the first frame starts the streams, the second steps them
+		for frame in 1..=2 {
+			raytracer.paint(&gl, &data, [0, 0, SIZE, SIZE]);
+			let noise = unsafe { read_noise(&gl, &raytracer) };
+			assert!(noise.iter().flatten().all(|&v| v != 0));
+
+			for lane in 0..4 {
+				let mut counts = [0; 16];
+				for state in &noise {
+					counts[(state[lane] >> 28) as usize] += 1;
+				}
+				assert_uniform(&format!("frame {frame} stream {lane}"), &counts);
+			}
+
+			for i in 0..8 {
+				let mut counts = [0; 16];
+				let mut pairs = [0; 16];
+				for &state in &noise {
+					let a = rng_next(state, i);
+					let b = rng_next(state, i + 1);
+					counts[(a >> 28) as usize] += 1;
+					pairs[(a >> 30 << 2 | b >> 30) as usize] += 1;
+				}
+				assert_uniform(&format!("frame {frame} number {i}"), &counts);
+				assert_uniform(&format!("frame {frame} pair {i}"), &pairs);
+			}
+		}
+
+		raytracer.destroy(&gl);
+	}
+	// }}}
+
+	// {{{ color grading
+	// runs one of color.glsl's functions on every texel of `inputs`
+	const COLOR_SHADER: &str = concat!(
+		include_str!("shaders/color.glsl"),
+		"\n",
+		r#"
+uniform highp sampler2D inputs;
+uniform uint function; // 0 grade, 1 rgb_to_hsv, 2 hsv_to_rgb
+uniform vec4 grading;
+
+out vec4 out_color;
+
+void main() {
+	vec3 c = texelFetch(inputs, ivec2(gl_FragCoord.xy), 0).rgb;
+	if (function == 0u) {
+		c = grade(c, grading);
+	} else if (function == 1u) {
+		c = rgb_to_hsv(c);
+	} else {
+		c = hsv_to_rgb(c);
+	}
+	out_color = vec4(c, 1.0);
+}
+"#
+	);
+
+	unsafe fn run_color_shader(
+		gl: &Context,
+		function: u32,
+		grading: [f32; 4],
+		inputs: &[[f32; 4]],
+	) -> Vec<[f32; 4]> {
+		let program = gl.create_program().unwrap();
+		compile_shaders(
+			gl,
+			program,
+			&[
+				(glow::VERTEX_SHADER, include_str!("shaders/vsh.glsl")),
+				(glow::FRAGMENT_SHADER, COLOR_SHADER),
+			],
+		);
+		let fullscreen = FullscreenTriangle::new(gl);
+
+		let input = gl.create_texture().unwrap();
+		gl.active_texture(glow::TEXTURE0);
+		gl.bind_texture(glow::TEXTURE_2D, Some(input));
+		float_texture(gl, inputs.to_vec(), SIZE as usize);
+
+		let output = gl.create_texture().unwrap();
+		gl.bind_texture(glow::TEXTURE_2D, Some(output));
+		gl.tex_image_2d(
+			glow::TEXTURE_2D,
+			0,
+			glow::RGBA32F as i32,
+			SIZE,
+			SIZE,
+			0,
+			glow::RGBA,
+			glow::FLOAT,
+			None,
+		);
+		let fbo = gl.create_framebuffer().unwrap();
+		gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+		framebuffer_texture(gl, output);
+
+		gl.bind_texture(glow::TEXTURE_2D, Some(input));
+		gl.use_program(Some(program));
+		let uniform = |name| gl.get_uniform_location(program, name);
+		gl.uniform_1_i32(uniform("inputs").as_ref(), 0);
+		gl.uniform_1_u32(uniform("function").as_ref(), function);
+		gl.uniform_4_f32_slice(uniform("grading").as_ref(), &grading);
+		gl.viewport(0, 0, SIZE, SIZE);
+		fullscreen.draw(gl);
+
+		let mut texels = vec![[0.0f32; 4]; (SIZE * SIZE) as usize];
+		gl.read_pixels(
+			0,
+			0,
+			SIZE,
+			SIZE,
+			glow::RGBA,
+			glow::FLOAT,
+			glow::PixelPackData::Slice(bytemuck::cast_slice_mut(&mut texels)),
+		);
+		assert_eq!(gl.get_error(), glow::NO_ERROR);
+
+		gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+		gl.delete_framebuffer(fbo);
+		gl.delete_texture(input);
+		gl.delete_texture(output);
+		fullscreen.destroy(gl);
+		gl.delete_program(program);
+		texels
+	}
+
+	// 16 levels per channel, 16³ colors fill the 64² texture
+	fn color_inputs() -> Vec<[f32; 4]> {
+		(0..SIZE * SIZE)
+			.map(|i| {
+				let level = |shift: i32| (i >> shift & 15) as f32 / 15.0;
+				[level(0), level(4), level(8), 1.0]
+			})
+			.collect()
+	}
+
+	// hues are compared around the circle, 0.9999 is next to 0
+	fn assert_colors_match(gpu: &[[f32; 4]], cpu: &[[f32; 3]], hues: bool) {
+		for (i, (gpu, cpu)) in gpu.iter().zip(cpu).enumerate() {
+			let matches = (0..3).all(|c| {
+				let d = (gpu[c] - cpu[c]).abs();
+				let d = if hues && c == 0 { d.min(1.0 - d) } else { d };
+				d < 1e-4
+			});
+			assert!(matches, "texel {i}: GLSL {gpu:?}, Rust {cpu:?}");
+		}
+	}
+
+	// util.rs mirrors color.glsl for the images saved on the CPU
+	#[test]
+	fn color_grading_matches_the_shader() {
+		let Some((_egl, gl)) = headless() else {
+			eprintln!("no headless GL context, skipped");
+			return;
+		};
+		let inputs = color_inputs();
+		let rgb = |t: &[f32; 4]| [t[0], t[1], t[2]];
+
+		for grading in [
+			[0.0, 1.0, 1.0, 1.0],
+			[0.3, 1.5, 0.8, 1.2],
+			[-0.7, 0.5, 1.2, 0.8],
+			[2.25, 0.0, 1.0, 3.0],
+		] {
+			let gpu = unsafe { run_color_shader(&gl, 0, grading, &inputs) };
+			let cpu: Vec<_> = inputs
+				.iter()
+				.map(|t| util::grade(rgb(t), grading))
+				.collect();
+			assert_colors_match(&gpu, &cpu, false);
+		}
+
+		let gpu = unsafe { run_color_shader(&gl, 1, [0.0; 4], &inputs) };
+		let cpu: Vec<_> = inputs.iter().map(|t| util::rgb_to_hsv(rgb(t))).collect();
+		assert_colors_match(&gpu, &cpu, true);
+
+		// hues from -1.5 to 1.5 turns, GLSL's fract rounds down below zero
+		let hsv: Vec<_> = inputs
+			.iter()
+			.map(|&[h, s, v, a]| [h * 3.0 - 1.5, s, v, a])
+			.collect();
+		let gpu = unsafe { run_color_shader(&gl, 2, [0.0; 4], &hsv) };
+		let cpu: Vec<_> = hsv.iter().map(|t| util::hsv_to_rgb(rgb(t))).collect();
+		assert_colors_match(&gpu, &cpu, false);
+	}
+	// }}}
+}
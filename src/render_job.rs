@@ -0,0 +1,935 @@
+// Final renders: the image is rendered at a set size until it has enough
+// samples, then saved as PNG or EXR. While the job runs it takes over the view
+// (the render size, the realistic mode and a locked camera), and it puts the
+// interactive state back once it's done, has failed or was cancelled.
+//
+// How a frame is put together, and what each capture takes from it:
+// 1. the path tracer accumulates into textures of its own (see render.rs)
+// 2. the final pass tonemaps and grades that into egui's paint callback, in
+//    the window's framebuffer
+// 3. egui draws the overlays and the windows on top, then eframe presents
+//
+// - final renders read back the accumulation texture from step 1 and encode
+//   it on the CPU, so they have the job's size and nothing from steps 2 and 3
+//   but the tonemapping and (if it's kept in exports) the grading
+// - "Include the interface" captures the framebuffer after step 3 instead,
+//   through eframe's screenshot command. that's the window as it's shown, at
+//   its size in pixels, without the job's own window
+// - temporarily hiding the windows (from the settings) doesn't capture
+//   anything, it leaves out the interface for a screenshot taken with another
+//   tool
+use std::fmt::{Display, Formatter};
+
+use egui::{DragValue, ProgressBar};
+
+use crate::{
+	app::PersistentData,
+	render::Readback,
+	settings::{PostSettings, RenderMode, RenderSettings, Tonemap},
+	util::{grade, UpdateResponse},
+};
+
+// the image only goes missing if the GPU fails, so waiting this long for it
+// means it's not coming
+const READBACK_TIMEOUT: f64 = 10.0; // seconds
+
+const MAX_SAMPLES: u32 = 1 << 16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+	Png, // tonemapped like the view
+	Exr, // linear, only the exposure applies
+}
+
+impl OutputFormat {
+	fn extension(self) -> &'static str {
+		match self {
+			Self::Png => "png",
+			Self::Exr => "exr",
+		}
+	}
+}
+
+impl Display for OutputFormat {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Png => write!(f, "PNG"),
+			Self::Exr => write!(f, "EXR (HDR)"),
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+	Idle,
+	Setup,    // the dialog is open
+	Starting, // the view is taken over on the next update
+	Rendering { started: f64, last_frame: u32 },
+	Reading { since: f64 },   // waiting for the image from the GPU
+	Capturing { since: f64 }, // waiting for the window capture from eframe
+}
+
+// interactive settings that the job overrides
+struct Saved {
+	mode: RenderMode,
+	accumulate: bool,
+	lock_camera: bool,
+	max_samples: u32,
+	roughness_multiplier: f32,
+}
+
+// what the app does for the job this frame
+#[derive(Default)]
+pub struct JobFrame {
+	pub size: Option<[u32; 2]>, // render targets of exactly this size
+	pub reset: bool,            // start accumulating from scratch
+	pub read_back: bool,        // read the image back for saving
+	pub capture: bool,          // capture the window, interface included
+	pub message: Option<String>,
+}
+
+pub struct RenderJob {
+	width: u32,
+	height: u32,
+	samples: u32,
+	format: OutputFormat,
+	path: String,
+	include_ui: bool, // save the window instead, PNG only
+
+	stage: Stage,
+	saved: Option<Saved>,
+}
+
+impl Default for RenderJob {
+	fn default() -> Self {
+		Self {
+			width: 1920,
+			height: 1080,
+			samples: 1024,
+			format: OutputFormat::Png,
+			path: "render.png".to_owned(),
+			include_ui: false,
+			stage: Stage::Idle,
+			saved: None,
+		}
+	}
+}
+
+impl RenderJob {
+	// {{{ state
+	pub fn open(&mut self) {
+		if self.stage == Stage::Idle {
+			self.stage = Stage::Setup;
+		}
+	}
+
+	pub fn running(&self) -> bool {
+		matches!(
+			self.stage,
+			Stage::Starting
+				| Stage::Rendering { .. }
+				| Stage::Reading { .. }
+				| Stage::Capturing { .. }
+		)
+	}
+
+	// accumulated samples, the first frame after a reset has none yet
+	fn samples_done(frame_index: u32, render: &RenderSettings) -> u32 {
+		frame_index.saturating_sub(1) * render.samples_per_frame
+	}
+
+	// advances the job, called once per frame before painting
+	pub fn update(
+		&mut self,
+		data: &mut PersistentData,
+		frame_index: u32,
+		time: f64,
+	) -> JobFrame {
+		let mut frame = JobFrame {
+			size: self.running().then_some([self.width, self.height]),
+			..Default::default()
+		};
+
+		match self.stage {
+			Stage::Idle | Stage::Setup => {},
+			Stage::Starting => {
+				let render = &mut data.settings.render;
+				self.saved = Some(Saved {
+					mode: render.mode,
+					accumulate: render.accumulate,
+					lock_camera: render.lock_camera,
+					max_samples: render.max_samples,
+					roughness_multiplier: render.roughness_multiplier,
+				});
+				Self::take_over(render);
+				data.settings.set_changed(true);
+
+				frame.reset = true;
+				self.stage = Stage::Rendering {
+					started: time,
+					last_frame: 0,
+				};
+			},
+			Stage::Rendering {
+				mut started,
+				last_frame,
+			} => {
+				// the settings window and the shortcuts can't have the view back
+				// until the job is done
+				if Self::take_over(&mut data.settings.render) {
+					data.settings.set_changed(true);
+				}
+
+				// editing the scene starts over, so does the estimate
+				if frame_index < last_frame {
+					started = time;
+				}
+				self.stage = Stage::Rendering {
+					started,
+					last_frame: frame_index,
+				};
+
+				if Self::samples_done(frame_index, &data.settings.render) >= self.samples {
+					if self.include_ui {
+						frame.capture = true;
+						self.stage = Stage::Capturing { since: time };
+					} else {
+						frame.read_back = true;
+						self.stage = Stage::Reading { since: time };
+					}
+				}
+			},
+			Stage::Reading { since } | Stage::Capturing { since } => {
+				if time - since > READBACK_TIMEOUT {
+					self.restore(data);
+					frame.message =
+						Some("Final render failed: the GPU didn't return the image".into());
+				}
+			},
+		}
+
+		frame
+	}
+
+	// saves the image the job asked for, returns a message about the result
+	pub fn finish(
+		&mut self,
+		readback: Readback,
+		data: &mut PersistentData,
+	) -> Option<String> {
+		if !matches!(self.stage, Stage::Reading { .. }) {
+			return None;
+		}
+
+		let result = self.save(&readback, &data.settings.render, &data.settings.post);
+		self.restore(data);
+		Some(match result {
+			Ok(()) => format!("Saved {} ({} samples)", self.path, self.samples),
+			Err(e) => format!("Final render failed: {e}"),
+		})
+	}
+
+	// saves the window capture the job asked for, like `finish`
+	pub fn finish_capture(
+		&mut self,
+		image: &egui::ColorImage,
+		data: &mut PersistentData,
+	) -> Option<String> {
+		if !matches!(self.stage, Stage::Capturing { .. }) {
+			return None;
+		}
+
+		let result = encode_capture(image).and_then(|bytes| {
+			std::fs::write(self.path.trim(), bytes).map_err(|e| e.to_string())
+		});
+		self.restore(data);
+		Some(match result {
+			Ok(()) => format!(
+				"Saved {} ({} samples, with the interface)",
+				self.path, self.samples
+			),
+			Err(e) => format!("Final render failed: {e}"),
+		})
+	}
+
+	fn cancel(&mut self, data: &mut PersistentData) {
+		self.restore(data);
+	}
+
+	fn restore(&mut self, data: &mut PersistentData) {
+		if self.saved.is_some() {
+			self.unapply(&mut data.settings.render);
+			self.saved = None;
+			data.settings.set_changed(true);
+		}
+		self.stage = Stage::Idle;
+	}
+
+	// the view the job renders in, returns whether it changed anything. the
+	// roughness multiplier is only a viewing aid, exports get the real
+	// materials
+	fn take_over(render: &mut RenderSettings) -> bool {
+		let before = (
+			render.mode,
+			render.accumulate,
+			render.lock_camera,
+			render.max_samples,
+			render.roughness_multiplier,
+		);
+		render.mode = RenderMode::Realistic;
+		render.accumulate = true;
+		render.lock_camera = true;
+		render.max_samples = 0; // the job counts its own
+		render.roughness_multiplier = 1.0;
+		before != (RenderMode::Realistic, true, true, 0, 1.0)
+	}
+
+	// puts back the settings the job overrides, if it's running
+	pub fn unapply(&self, render: &mut RenderSettings) {
+		if let Some(saved) = &self.saved {
+			render.mode = saved.mode;
+			render.accumulate = saved.accumulate;
+			render.lock_camera = saved.lock_camera;
+			render.max_samples = saved.max_samples;
+			render.roughness_multiplier = saved.roughness_multiplier;
+		}
+	}
+	// }}}
+
+	// {{{ interface
+	// returns a message for the user, if there is one
+	pub fn window(
+		&mut self,
+		egui: &egui::Context,
+		data: &mut PersistentData,
+		max_texture_size: u32,
+		frame_index: u32,
+		time: f64,
+	) -> Option<String> {
+		match self.stage {
+			Stage::Idle => None,
+			Stage::Setup => {
+				self.setup_window(egui, max_texture_size);
+				None
+			},
+			// kept out of the capture
+			Stage::Capturing { .. } => None,
+			_ => self.progress_window(egui, data, frame_index, time),
+		}
+	}
+
+	fn setup_window(&mut self, egui: &egui::Context, max_texture_size: u32) {
+		let mut open = true;
+		egui::Window::new("Final render")
+			.collapsible(false)
+			.resizable(false)
+			.anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+			.show(egui, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("Size:");
+					let range = 16..=max_texture_size;
+					ui.add(DragValue::new(&mut self.width).clamp_range(range.clone()));
+					ui.label("×");
+					ui.add(DragValue::new(&mut self.height).clamp_range(range));
+				});
+
+				ui.horizontal(|ui| {
+					ui.label("Samples per pixel:");
+					ui.add(
+						DragValue::new(&mut self.samples)
+							.clamp_range(1..=MAX_SAMPLES)
+							.speed(8.0),
+					);
+				});
+
+				ui.horizontal(|ui| {
+					ui.label("Format:");
+					for format in [OutputFormat::Png, OutputFormat::Exr] {
+						if ui
+							.selectable_value(&mut self.format, format, format.to_string())
+							.changed()
+						{
+							self.fix_extension();
+							self.include_ui &= self.format == OutputFormat::Png;
+						}
+					}
+				});
+
+				ui.add_enabled(
+					self.format == OutputFormat::Png,
+					egui::Checkbox::new(&mut self.include_ui, "Include the interface"),
+				)
+				.on_hover_text(
+					"Saves the whole window as it's shown instead of the render",
+				);
+
+				ui.horizontal(|ui| {
+					ui.label("File:");
+					ui.text_edit_singleline(&mut self.path);
+				});
+
+				let native = cfg!(not(target_arch = "wasm32"));
+				if !native {
+					ui.label("Saving files needs the desktop version.");
+				}
+
+				ui.horizontal(|ui| {
+					if ui.button("Cancel").highlight().clicked() {
+						open = false;
+					}
+					let start = ui.add_enabled(
+						native && !self.path.trim().is_empty(),
+						egui::Button::new("Start"),
+					);
+					if start.clicked() {
+						self.stage = Stage::Starting;
+					}
+				});
+			});
+
+		if !open {
+			self.stage = Stage::Idle;
+		}
+	}
+
+	fn progress_window(
+		&mut self,
+		egui: &egui::Context,
+		data: &mut PersistentData,
+		frame_index: u32,
+		time: f64,
+	) -> Option<String> {
+		let mut cancel = false;
+		egui::Window::new("Final render")
+			.collapsible(false)
+			.resizable(false)
+			.anchor(egui::Align2::CENTER_BOTTOM, [0.0, -16.0])
+			.show(egui, |ui| {
+				let done =
+					Self::samples_done(frame_index, &data.settings.render).min(self.samples);
+				let (text, eta) = match self.stage {
+					Stage::Rendering { started, .. } => (
+						format!("{done} / {} samples", self.samples),
+						estimate(done, self.samples, time - started),
+					),
+					Stage::Reading { .. } => ("Saving".to_owned(), None),
+					_ => ("Starting".to_owned(), None),
+				};
+
+				ui.add(
+					ProgressBar::new(done as f32 / self.samples as f32)
+						.text(text)
+						.desired_width(240.0),
+				);
+				ui.horizontal(|ui| {
+					ui.label(format!("{}×{}", self.width, self.height));
+					if let Some(eta) = eta {
+						ui.label(format!("about {} left", format_duration(eta)));
+					}
+				});
+				if ui.button("Cancel").clicked() {
+					cancel = true;
+				}
+			});
+
+		if cancel {
+			self.cancel(data);
+			return Some("Final render cancelled".to_owned());
+		}
+		None
+	}
+
+	fn fix_extension(&mut self) {
+		let path = std::path::Path::new(self.path.trim());
+		self.path = path
+			.with_extension(self.format.extension())
+			.to_string_lossy()
+			.into_owned();
+	}
+	// }}}
+
+	// {{{ saving
+	fn save(
+		&self,
+		readback: &Readback,
+		render: &RenderSettings,
+		post: &PostSettings,
+	) -> Result<(), String> {
+		let [_, _, w, h] = readback.region;
+		if [w as u32, h as u32] != [self.width, self.height] {
+			return Err("the render size changed".into());
+		}
+
+		let exposure = render.exposure.exp2();
+		let bytes = match self.format {
+			OutputFormat::Png => {
+				let grading = post.uniform(true);
+				encode_png(readback, render.tonemap, exposure, grading)?
+			},
+			OutputFormat::Exr => encode_exr(readback, exposure),
+		};
+		std::fs::write(self.path.trim(), bytes).map_err(|e| e.to_string())
+	}
+	// }}}
+}
+
+// seconds left at the current rate
+fn estimate(done: u32, target: u32, elapsed: f64) -> Option<f64> {
+	(done > 0 && elapsed > 1.0)
+		.then(|| (target - done) as f64 * elapsed / done as f64)
+}
+
+fn format_duration(secs: f64) -> String {
+	let secs = secs.ceil() as u64;
+	match secs {
+		0..=59 => format!("{secs} s"),
+		60..=3599 => format!("{} min {} s", secs / 60, secs % 60),
+		_ => format!("{} h {} min", secs / 3600, secs / 60 % 60),
+	}
+}
+
+// {{{ encoding
+// the readback's rows go bottom to top, image files go top to bottom
+fn rows(readback: &Readback) -> impl Iterator<Item = &[[f32; 4]]> {
+	let [_, _, w, _] = readback.region;
+	readback.texels.chunks_exact(w as usize).rev()
+}
+
+// same as final.glsl
+fn display_color(
+	color: [f32; 3],
+	tonemap: Tonemap,
+	exposure: f32,
+	grading: [f32; 4],
+) -> [u8; 3] {
+	let color = color.map(|c| {
+		let c = c * exposure;
+		let c = match tonemap {
+			Tonemap::None => c.clamp(0.0, 1.0),
+			Tonemap::Reinhard => c / (1.0 + c),
+			Tonemap::AcesFilmic => {
+				let (a, b, c2, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+				((c * (a * c + b)) / (c * (c2 * c + d) + e)).clamp(0.0, 1.0)
+			},
+		};
+		c.max(0.0).powf(1.0 / 2.2)
+	});
+	grade(color, grading).map(|c| (c * 255.0).round() as u8)
+}
+
+fn encode_png(
+	readback: &Readback,
+	tonemap: Tonemap,
+	exposure: f32,
+	grading: [f32; 4],
+) -> Result<Vec<u8>, String> {
+	let [_, _, w, h] = readback.region;
+	let pixels: Vec<u8> = rows(readback)
+		.flatten()
+		.flat_map(|&[r, g, b, _]| display_color([r, g, b], tonemap, exposure, grading))
+		.collect();
+
+	let mut bytes = Vec::new();
+	image::write_buffer_with_format(
+		&mut std::io::Cursor::new(&mut bytes),
+		&pixels,
+		w as u32,
+		h as u32,
+		image::ColorType::Rgb8,
+		image::ImageFormat::Png,
+	)
+	.map_err(|e| e.to_string())?;
+	Ok(bytes)
+}
+
+// the captured window, its alpha isn't meaningful
+fn encode_capture(image: &egui::ColorImage) -> Result<Vec<u8>, String> {
+	let [w, h] = image.size;
+	let pixels: Vec<u8> = image
+		.pixels
+		.iter()
+		.flat_map(|c| [c.r(), c.g(), c.b()])
+		.collect();
+
+	let mut bytes = Vec::new();
+	image::write_buffer_with_format(
+		&mut std::io::Cursor::new(&mut bytes),
+		&pixels,
+		w as u32,
+		h as u32,
+		image::ColorType::Rgb8,
+		image::ImageFormat::Png,
+	)
+	.map_err(|e| e.to_string())?;
+	Ok(bytes)
+}
+
+// uncompressed scanline OpenEXR with 32-bit float channels
+fn encode_exr(readback: &Readback, exposure: f32) -> Vec<u8> {
+	let [_, _, w, h] = readback.region;
+	let mut out = Vec::new();
+	out.extend(20000630_u32.to_le_bytes()); // magic number
+	out.extend(2_u32.to_le_bytes()); // version 2, single part scanline file
+
+	// {{{ header
+	// channels are sorted by name. float, linear, no subsampling
+	let mut channels = Vec::new();
+	for name in ["B", "G", "R"] {
+		channels.extend(name.as_bytes());
+		channels.push(0);
+		channels.extend(2_i32.to_le_bytes());
+		channels.extend([0; 4]);
+		channels.extend(1_i32.to_le_bytes());
+		channels.extend(1_i32.to_le_bytes());
+	}
+	channels.push(0);
+
+	let window: Vec<u8> = [0, 0, w - 1, h - 1]
+		.iter()
+		.flat_map(|v| v.to_le_bytes())
+		.collect();
+
+	exr_attribute(&mut out, "channels", "chlist", &channels);
+	exr_attribute(&mut out, "compression", "compression", &[0]);
+	exr_attribute(&mut out, "dataWindow", "box2i", &window);
+	exr_attribute(&mut out, "displayWindow", "box2i", &window);
+	exr_attribute(&mut out, "lineOrder", "lineOrder", &[0]); // top to bottom
+	exr_attribute(&mut out, "pixelAspectRatio", "float", &1_f32.to_le_bytes());
+	exr_attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+	exr_attribute(&mut out, "screenWindowWidth", "float", &1_f32.to_le_bytes());
+	out.push(0);
+	// }}}
+
+	// offsets of the scanlines, then the scanlines with one channel after the
+	// other
+	let line_size = w as usize * 3 * 4;
+	let first_line = out.len() + h as usize * 8;
+	for y in 0..h as usize {
+		out.extend(((first_line + y * (8 + line_size)) as u64).to_le_bytes());
+	}
+	for (y, row) in rows(readback).enumerate() {
+		out.extend((y as i32).to_le_bytes());
+		out.extend((line_size as i32).to_le_bytes());
+		for channel in [2, 1, 0] {
+			for texel in row {
+				out.extend((texel[channel] * exposure).to_le_bytes());
+			}
+		}
+	}
+
+	out
+}
+
+fn exr_attribute(out: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+	for text in [name, ty] {
+		out.extend(text.as_bytes());
+		out.push(0);
+	}
+	out.extend((value.len() as u32).to_le_bytes());
+	out.extend(value);
+}
+// }}}
+
+#[cfg(test)]
+mod tests {
+	use nalgebra_glm as glm;
+
+	use super::*;
+	use crate::render::ReadbackKind;
+
+	const WIDTH: u32 = 4;
+	const HEIGHT: u32 = 2;
+
+	// {{{ helpers
+	// an interactive view that the job has to put back
+	fn data() -> PersistentData {
+		let mut data = PersistentData::new(glm::vec2(64.0, 64.0), "test");
+		let render = &mut data.settings.render;
+		render.mode = RenderMode::Preview;
+		render.accumulate = false;
+		render.lock_camera = false;
+		render.max_samples = 100;
+		render.roughness_multiplier = 0.5;
+		render.samples_per_frame = 1;
+		data
+	}
+
+	fn assert_restored(data: &PersistentData) {
+		let render = &data.settings.render;
+		assert!(render.mode == RenderMode::Preview);
+		assert!(!render.accumulate);
+		assert!(!render.lock_camera);
+		assert_eq!(render.max_samples, 100);
+		assert_eq!(render.roughness_multiplier, 0.5);
+	}
+
+	fn job(path: &str) -> RenderJob {
+		RenderJob {
+			width: WIDTH,
+			height: HEIGHT,
+			samples: 4,
+			path: path.to_owned(),
+			..Default::default()
+		}
+	}
+
+	// a job that took over the view and renders
+	fn started(path: &str) -> (RenderJob, PersistentData) {
+		let mut job = job(path);
+		let mut data = data();
+		job.open();
+		job.stage = Stage::Starting;
+		job.update(&mut data, 0, 0.0);
+		data.settings.response.changed = false;
+		(job, data)
+	}
+
+	fn readback(width: u32) -> Readback {
+		Readback {
+			kind: ReadbackKind::RenderJob,
+			region: [0, 0, width as i32, HEIGHT as i32],
+			samples: 4,
+			texels: vec![[0.5, 0.25, 1.0, 1.0]; (width * HEIGHT) as usize],
+		}
+	}
+
+	// files in the temporary directory, unique to the test
+	fn temp_path(name: &str) -> String {
+		let name = format!("raytracer-{}-{name}", std::process::id());
+		std::env::temp_dir()
+			.join(name)
+			.to_string_lossy()
+			.into_owned()
+	}
+	// }}}
+
+	#[test]
+	fn the_dialog_does_nothing_until_started() {
+		let mut job = job("render.png");
+		let mut data = data();
+		assert!(job.stage == Stage::Idle);
+
+		job.open();
+		assert!(job.stage == Stage::Setup);
+		assert!(!job.running());
+
+		let frame = job.update(&mut data, 3, 1.0);
+		assert!(frame.size.is_none() && !frame.reset && !frame.read_back);
+		assert!(job.stage == Stage::Setup);
+		assert_restored(&data);
+	}
+
+	#[test]
+	fn starting_takes_over_the_view() {
+		let mut job = job("render.png");
+		let mut data = data();
+		job.open();
+		job.stage = Stage::Starting;
+		assert!(job.running());
+
+		let frame = job.update(&mut data, 7, 2.0);
+		assert!(frame.reset);
+		assert_eq!(frame.size, Some([WIDTH, HEIGHT]));
+		assert!(
+			job.stage
+				== Stage::Rendering {
+					started: 2.0,
+					last_frame: 0
+				}
+		);
+
+		let render = &data.settings.render;
+		assert!(render.mode == RenderMode::Realistic);
+		assert!(render.accumulate && render.lock_camera);
+		assert_eq!(render.max_samples, 0);
+		assert_eq!(render.roughness_multiplier, 1.0);
+		assert!(data.settings.response.changed);
+
+		// saving in the meantime stores the interactive view
+		let mut stored = data.settings.render.clone();
+		job.unapply(&mut stored);
+		assert!(stored.mode == RenderMode::Preview);
+		assert!(!stored.lock_camera);
+		assert_eq!(stored.roughness_multiplier, 0.5);
+	}
+
+	#[test]
+	fn opening_a_running_job_does_nothing() {
+		let (mut job, _) = started("render.png");
+		job.open();
+		assert!(matches!(job.stage, Stage::Rendering { .. }));
+	}
+
+	#[test]
+	fn the_view_stays_taken_over() {
+		let (mut job, mut data) = started("render.png");
+
+		// like pressing L, or the settings window
+		data.settings.render.lock_camera = false;
+		data.settings.render.mode = RenderMode::Normal;
+		data.settings.render.roughness_multiplier = 2.0;
+		job.update(&mut data, 2, 0.1);
+		assert!(data.settings.render.lock_camera);
+		assert!(data.settings.render.mode == RenderMode::Realistic);
+		assert_eq!(data.settings.render.roughness_multiplier, 1.0);
+		assert!(data.settings.response.changed);
+
+		// nothing to put back, so nothing restarts
+		data.settings.response.changed = false;
+		job.update(&mut data, 3, 0.2);
+		assert!(!data.settings.response.changed);
+	}
+
+	#[test]
+	fn rendering_reads_back_at_the_sample_target() {
+		let (mut job, mut data) = started("render.png");
+
+		// the first frame after the reset has no samples yet
+		for frame_index in 1..=4 {
+			let frame = job.update(&mut data, frame_index, frame_index as f64);
+			assert!(!frame.read_back && !frame.capture, "frame {frame_index}");
+			assert_eq!(frame.size, Some([WIDTH, HEIGHT]));
+		}
+		let frame = job.update(&mut data, 5, 5.0);
+		assert!(frame.read_back && !frame.capture);
+		assert!(job.stage == Stage::Reading { since: 5.0 });
+	}
+
+	#[test]
+	fn frames_count_samples_per_frame() {
+		let (mut job, mut data) = started("render.png");
+		data.settings.render.samples_per_frame = 2;
+		assert!(!job.update(&mut data, 2, 1.0).read_back);
+		assert!(job.update(&mut data, 3, 2.0).read_back);
+	}
+
+	#[test]
+	fn the_interface_is_captured_instead() {
+		let (mut job, mut data) = started("render.png");
+		job.include_ui = true;
+		let frame = job.update(&mut data, 5, 5.0);
+		assert!(frame.capture && !frame.read_back);
+		assert!(job.stage == Stage::Capturing { since: 5.0 });
+		assert_eq!(
+			job.window(&egui::Context::default(), &mut data, 4096, 5, 5.0),
+			None
+		);
+	}
+
+	#[test]
+	fn restarts_reset_the_estimate() {
+		let (mut job, mut data) = started("render.png");
+		job.update(&mut data, 3, 1.0);
+		job.update(&mut data, 4, 2.0);
+		assert!(
+			job.stage
+				== Stage::Rendering {
+					started: 0.0,
+					last_frame: 4
+				}
+		);
+
+		// an edit starts accumulating over
+		job.update(&mut data, 1, 3.0);
+		assert!(
+			job.stage
+				== Stage::Rendering {
+					started: 3.0,
+					last_frame: 1
+				}
+		);
+	}
+
+	#[test]
+	fn missing_images_time_out() {
+		for include_ui in [false, true] {
+			let (mut job, mut data) = started("render.png");
+			job.include_ui = include_ui;
+			job.update(&mut data, 5, 1.0);
+
+			let frame = job.update(&mut data, 6, 1.0 + READBACK_TIMEOUT);
+			assert!(frame.message.is_none());
+			assert!(job.running());
+
+			let frame = job.update(&mut data, 7, 1.5 + READBACK_TIMEOUT);
+			assert!(frame.message.unwrap().contains("failed"));
+			assert!(job.stage == Stage::Idle && !job.running());
+			assert_restored(&data);
+		}
+	}
+
+	#[test]
+	fn cancelling_restores_the_view() {
+		let (mut job, mut data) = started("render.png");
+		job.update(&mut data, 2, 1.0);
+		data.settings.response.changed = false;
+
+		job.cancel(&mut data);
+		assert!(job.stage == Stage::Idle);
+		assert!(data.settings.response.changed);
+		assert_restored(&data);
+
+		// and only once
+		data.settings.response.changed = false;
+		job.cancel(&mut data);
+		assert!(!data.settings.response.changed);
+	}
+
+	#[test]
+	fn finished_renders_are_saved() {
+		let path = temp_path("finished.png");
+		let (mut job, mut data) = started(&path);
+
+		// only the image the job waits for is taken
+		assert_eq!(job.finish(readback(WIDTH), &mut data), None);
+		job.update(&mut data, 5, 1.0);
+		let message = job.finish(readback(WIDTH), &mut data).unwrap();
+		assert!(message.starts_with("Saved"), "{message}");
+		assert!(job.stage == Stage::Idle);
+		assert_restored(&data);
+
+		let image = image::open(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!([image.width(), image.height()], [WIDTH, HEIGHT]);
+	}
+
+	#[test]
+	fn failed_saves_restore_the_view() {
+		// resized while reading back
+		let (mut job, mut data) = started(&temp_path("resized.png"));
+		job.update(&mut data, 5, 1.0);
+		let message = job.finish(readback(WIDTH + 1), &mut data).unwrap();
+		assert!(message.contains("size changed"), "{message}");
+		assert_restored(&data);
+
+		// a directory that doesn't exist
+		let missing = temp_path("missing");
+		let (mut job, mut data) = started(&format!("{missing}/render.png"));
+		job.update(&mut data, 5, 1.0);
+		let message = job.finish(readback(WIDTH), &mut data).unwrap();
+		assert!(message.starts_with("Final render failed"), "{message}");
+		assert!(job.stage == Stage::Idle);
+		assert_restored(&data);
+	}
+
+	#[test]
+	fn captures_are_saved() {
+		let path = temp_path("capture.png");
+		let (mut job, mut data) = started(&path);
+		job.include_ui = true;
+		let capture = egui::ColorImage::new([3, 5], egui::Color32::RED);
+
+		assert_eq!(job.finish_capture(&capture, &mut data), None);
+		job.update(&mut data, 5, 1.0);
+		// not what the job waits for
+		assert_eq!(job.finish(readback(WIDTH), &mut data), None);
+
+		let message = job.finish_capture(&capture, &mut data).unwrap();
+		assert!(message.contains("with the interface"), "{message}");
+		assert_restored(&data);
+
+		let image = image::open(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!([image.width(), image.height()], [3, 5]);
+	}
+}
@@ -0,0 +1,136 @@
+use egui::Ui;
+
+use crate::app::PersistentData;
+
+// Started with default data instead of the stored data, either on request
+// (shift held at launch, `--safe-mode` or `RAYTRACER_SAFE_MODE`) or because the
+// stored data failed to load. The stored data isn't overwritten until the user
+// picks what to do.
+pub struct SafeMode {
+	reason: String,
+	stored: Option<String>,
+	loaded: Option<PersistentData>,
+
+	view_stored: bool,
+	reset_modal: bool,
+	load_error: Option<String>,
+}
+
+pub enum SafeModeAction {
+	Load(Box<PersistentData>),
+	Reset,
+}
+
+impl SafeMode {
+	// `stored` is the raw stored text, `loaded` is the same data if it could be
+	// deserialized
+	pub fn new(
+		reason: impl Into<String>,
+		stored: Option<String>,
+		loaded: Option<PersistentData>,
+	) -> Self {
+		Self {
+			reason: reason.into(),
+			stored,
+			loaded,
+			view_stored: false,
+			reset_modal: false,
+			load_error: None,
+		}
+	}
+
+	// banner at the top of the screen, has to be shown before the central panel
+	pub fn banner(
+		&mut self,
+		egui: &egui::Context,
+		default: &PersistentData,
+	) -> Option<SafeModeAction> {
+		let mut action = None;
+
+		egui::TopBottomPanel::top("safe_mode").show(egui, |ui| {
+			ui.horizontal_wrapped(|ui| {
+				ui.colored_label(ui.visuals().warn_fg_color, "Safe mode:");
+				ui.label(format!(
+					"{}. Started with default data, the stored data is left untouched.",
+					self.reason,
+				));
+			});
+
+			ui.horizontal(|ui| {
+				let view = ui.add_enabled(
+					self.stored.is_some(),
+					egui::Button::new("View stored data"),
+				);
+				if view.clicked() {
+					self.view_stored = !self.view_stored;
+				}
+
+				let load = ui.add_enabled(
+					self.loaded.is_some(),
+					egui::Button::new("Try sanitized load"),
+				);
+				if load.clicked() {
+					action = self.sanitized_load(default);
+				}
+
+				if ui.button("Reset stored data").clicked() {
+					self.reset_modal = true;
+				}
+			});
+
+			if let Some(e) = &self.load_error {
+				ui.colored_label(ui.visuals().error_fg_color, e);
+			}
+		});
+
+		crate::util::modal(
+			egui,
+			"Reset stored data?",
+			&mut self.reset_modal,
+			|ui| {
+				ui.label("The stored data will be replaced by the defaults.");
+			},
+			crate::util::red_hover_button,
+			|| action = Some(SafeModeAction::Reset),
+		);
+
+		self.stored_data_window(egui);
+
+		action
+	}
+
+	fn sanitized_load(&mut self, default: &PersistentData) -> Option<SafeModeAction> {
+		let mut data = self.loaded.clone()?;
+		match data.sanitize(default) {
+			Ok(()) => Some(SafeModeAction::Load(Box::new(data))),
+			Err(e) => {
+				self.load_error = Some(format!("Sanitized load failed: {e}"));
+				None
+			},
+		}
+	}
+
+	fn stored_data_window(&mut self, egui: &egui::Context) {
+		let Some(stored) = &self.stored else {
+			return;
+		};
+
+		egui::Window::new("Stored data")
+			.open(&mut self.view_stored)
+			.default_height(300.0)
+			.show(egui, |ui: &mut Ui| {
+				if ui.button("Copy").clicked() {
+					ui.output_mut(|o| o.copied_text.clone_from(stored));
+				}
+
+				egui::ScrollArea::vertical().show(ui, |ui| {
+					// read-only, `&str` can't be edited
+					ui.add(
+						egui::TextEdit::multiline(&mut stored.as_str())
+							.code_editor()
+							.desired_width(f32::INFINITY),
+					);
+				});
+			});
+	}
+}
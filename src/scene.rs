@@ -1,18 +1,23 @@
 use std::fmt::{Display, Formatter};
 
 use egui::{ComboBox, DragValue, Slider, Ui};
-use glm::{identity, inverse, vec3, Mat4, Vec3};
+use glm::{identity, inverse, vec2, vec3, Mat4, Vec2, Vec3};
 use nalgebra_glm as glm;
 
 use crate::{
+	controls,
+	mesh::{MeshData, MAX_MESH_TRIANGLES},
 	selectable_values,
-	util::{modal, AngleControl, Reset, UpdateResponse},
+	util::{modal, Reset, UpdateResponse},
 };
 
+pub mod import;
+
 // {{{ state
 #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Scene {
-	pub selected: usize,
+	// always a valid index unless the scene is empty, go through `select`
+	selected: usize,
 
 	// object properties
 	pub name: Vec<String>,
@@ -21,13 +26,64 @@ pub struct Scene {
 	pub rotation: Vec<Vec3>,
 	pub scale: Vec<Vec3>,
 
+	// object geometry properties
+	#[serde(default)]
+	pub corner_radius: Vec<f32>, // fraction of the smallest half-extent
+	#[serde(default)]
+	pub flip_normals: Vec<bool>, // for mirrored (negatively scaled) objects
+	#[serde(default)]
+	pub cap_radius: Vec<f32>, // capsules, 1 is a sphere
+	#[serde(default)]
+	pub double_sided: Vec<bool>, // planes and discs, otherwise only local +Y
+	#[serde(default)]
+	pub mesh: Vec<MeshData>, // empty unless the object is a mesh
+
+	// constructive solid geometry, combines a sphere or box with another one.
+	// the partner is then only drawn as part of the result, which has the
+	// material of the object that holds the link
+	#[serde(default)]
+	pub csg_op: Vec<CsgOp>,
+	#[serde(default)]
+	pub csg_other: Vec<Option<usize>>,
+
+	// slow spin around the Y axis, for looking at materials
+	#[serde(default)]
+	pub turntable: Vec<bool>,
+	#[serde(default)]
+	pub turntable_speed: Vec<f32>, // radians per second
+
+	// texture coordinate transform, applied as rotate(uv * scale) + offset.
+	// spheres use latitude/longitude coordinates, boxes project each face
+	#[serde(default)]
+	pub uv_offset: Vec<Vec2>,
+	#[serde(default)]
+	pub uv_scale: Vec<Vec2>,
+	#[serde(default)]
+	pub uv_rotation: Vec<f32>,
+
 	// object material properties
 	pub mat_ty: Vec<MaterialType>,
 	pub mat_color: Vec<[f32; 3]>,
+	#[serde(default)]
+	pub mat_albedo_mode: Vec<AlbedoMode>,
+	#[serde(default)]
+	pub mat_checker_color: Vec<[f32; 3]>, // the other squares, mat_color is the first
+	#[serde(default)]
+	pub mat_checker_scale: Vec<f32>, // squares across the object
 	pub mat_ior: Vec<f32>,
 	pub mat_specular: Vec<f32>,
 	pub mat_roughness: Vec<f32>,
+	#[serde(default)]
+	pub mat_metallic: Vec<f32>, // chance of a metal reflection, the rest is diffuse
 	pub mat_emissive_strength: Vec<f32>,
+	#[serde(default)]
+	pub mat_emissive_color: Vec<[f32; 3]>, // light color, mat_color is the surface
+	#[serde(default)]
+	pub mat_light_group: Vec<u32>, // index into `light_groups`
+
+	// the first group is the default one and can't be deleted
+	#[serde(default)]
+	pub light_groups: Vec<LightGroup>,
 
 	// cached object transforms
 	pub transform: Vec<Mat4>,
@@ -36,11 +92,62 @@ pub struct Scene {
 
 	#[serde(skip)]
 	pub response: SceneResponse,
+	#[serde(skip)]
+	pub pending_toasts: Vec<String>,
 
+	#[serde(skip)]
 	rename_modal: bool,
+	#[serde(skip)]
 	delete_modal: bool,
+	#[serde(skip)]
+	paste_modal: bool,
+	#[serde(skip)]
+	fit_modal: bool,
+	#[serde(skip)]
+	fit_rescale: bool,
+	#[serde(skip)]
+	import_modal: bool,
+	#[serde(skip)]
+	import_error: String,
+	#[serde(skip)]
+	mesh_modal: bool,
+	#[serde(skip)]
+	arc_modal: bool,
+	#[serde(skip)]
+	arc: ArcArray,
+	#[serde(skip)]
 	pending_rename: String,
-	pending_rename_selected: usize,
+	#[serde(skip)]
+	pending_paste: String,
+}
+
+// MAX_SCENE_SIZE in fsh.glsl, the object texture has a row for each
+pub const MAX_OBJECTS: usize = 1024;
+pub const MAX_LIGHT_GROUPS: usize = 8;
+
+const DEFAULT_TURNTABLE_SPEED: f32 = std::f32::consts::PI / 6.0; // 30°/s
+
+const DEFAULT_CHECKER_COLOR: [f32; 3] = [0.1, 0.1, 0.1];
+const DEFAULT_CHECKER_SCALE: f32 = 8.0;
+
+// bounding radius that fitting rescales the scene to
+const FIT_RADIUS: f32 = 4.0;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct LightGroup {
+	pub name: String,
+	pub enabled: bool,
+	pub intensity: f32,
+}
+
+impl LightGroup {
+	fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			enabled: true,
+			intensity: 1.0,
+		}
+	}
 }
 
 #[derive(
@@ -57,6 +164,11 @@ pub struct Scene {
 pub enum ObjectType {
 	Sphere = 0,
 	Box = 1,
+	Plane = 2,    // bounded by scale.x and scale.z, scale.y is ignored
+	Cylinder = 3, // around Y, scale.y is half the height
+	Capsule = 4,  // around Y, the caps are part of the unit cube
+	Disc = 5,     // the circle inside a plane
+	Mesh = 6,     // imported triangles in `Scene::mesh`
 }
 
 impl Display for ObjectType {
@@ -79,6 +191,9 @@ impl Display for ObjectType {
 pub enum MaterialType {
 	Solid = 0,
 	Emissive = 1,
+	Metal = 2,
+	// invisible apart from the shadows it receives, for compositing
+	ShadowCatcher = 3,
 }
 
 impl Display for MaterialType {
@@ -86,6 +201,145 @@ impl Display for MaterialType {
 		match self {
 			MaterialType::Solid => write!(f, "Solid"),
 			MaterialType::Emissive => write!(f, "Light source"),
+			MaterialType::Metal => write!(f, "Metal"),
+			MaterialType::ShadowCatcher => write!(f, "Shadow catcher"),
+		}
+	}
+}
+
+// where the surface color comes from, ALBEDO_* in fsh.glsl
+#[derive(
+	Clone,
+	Copy,
+	Debug,
+	Default,
+	PartialEq,
+	Eq,
+	bytemuck::NoUninit,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[repr(u32)]
+pub enum AlbedoMode {
+	#[default]
+	Solid = 0,
+	// squares in object space, alternating with the checker color
+	Checker = 1,
+}
+
+impl Display for AlbedoMode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AlbedoMode::Solid => write!(f, "Solid color"),
+			AlbedoMode::Checker => write!(f, "Checkerboard"),
+		}
+	}
+}
+
+#[derive(
+	Clone,
+	Copy,
+	Debug,
+	Default,
+	PartialEq,
+	Eq,
+	bytemuck::NoUninit,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[repr(u32)]
+pub enum CsgOp {
+	#[default]
+	None = 0,
+	Union = 1,
+	Intersect = 2,
+	Subtract = 3, // the partner is cut out of the object
+}
+
+// only these have intervals along a ray that are cheap to find
+fn csg_capable(ty: ObjectType) -> bool {
+	matches!(ty, ObjectType::Sphere | ObjectType::Box)
+}
+
+impl Display for CsgOp {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{self:?}")
+	}
+}
+
+// copies of the selected object spread along a circle, see `array_on_arc`
+#[derive(Clone, Copy)]
+pub struct ArcArray {
+	pub count: usize,
+	pub center: Vec3,
+	pub radius: f32,
+	pub start_angle: f32, // radians, a whole turn between them is a full circle
+	pub end_angle: f32,
+	pub axis: ArcAxis,
+	pub facing: ArcFacing,
+}
+
+impl Default for ArcArray {
+	fn default() -> Self {
+		Self {
+			count: 8,
+			center: Vec3::zeros(),
+			radius: 2.0,
+			start_angle: 0.0,
+			end_angle: std::f32::consts::TAU,
+			axis: ArcAxis::Y,
+			facing: ArcFacing::Keep,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArcAxis {
+	X,
+	Y,
+	Z,
+}
+
+impl ArcAxis {
+	fn dir(self) -> Vec3 {
+		match self {
+			ArcAxis::X => vec3(1.0, 0.0, 0.0),
+			ArcAxis::Y => vec3(0.0, 1.0, 0.0),
+			ArcAxis::Z => vec3(0.0, 0.0, 1.0),
+		}
+	}
+
+	// where the angle 0 is, going around the next axis
+	fn start(self) -> Vec3 {
+		match self {
+			ArcAxis::X => vec3(0.0, 1.0, 0.0),
+			ArcAxis::Y => vec3(0.0, 0.0, 1.0),
+			ArcAxis::Z => vec3(1.0, 0.0, 0.0),
+		}
+	}
+}
+
+impl Display for ArcAxis {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{self:?}")
+	}
+}
+
+// how the copies are rotated. the local +Y axis is the one that's turned, it's
+// the lit side of planes and discs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArcFacing {
+	Keep,    // the rotation of the original
+	Center,  // +Y towards the center
+	Tangent, // +Y along the circle, in the direction of the angle
+}
+
+impl Display for ArcFacing {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ArcFacing::Keep => write!(f, "Keep rotation"),
+			ArcFacing::Center => write!(f, "Face center"),
+			ArcFacing::Tangent => write!(f, "Along the circle"),
 		}
 	}
 }
@@ -96,6 +350,11 @@ impl Display for MaterialType {
 pub struct SceneResponse {
 	pub focused: bool,
 	pub changed: bool,
+	pub look_at: bool,
+	pub frame_scene: bool, // move the camera to see every object
+	// objects whose transform is all that changed. turntables set these
+	// instead of `changed`, so that only their transforms are uploaded
+	pub moved: ObjectSet,
 }
 
 impl Default for SceneResponse {
@@ -103,10 +362,38 @@ impl Default for SceneResponse {
 		Self {
 			focused: false,
 			changed: true,
+			look_at: false,
+			frame_scene: false,
+			moved: ObjectSet::default(),
 		}
 	}
 }
 
+impl SceneResponse {
+	// whether the image is out of date
+	pub fn changed_or_moved(&self) -> bool {
+		self.changed || !self.moved.is_empty()
+	}
+}
+
+// object indices, a bit each. fixed size so that the response stays `Copy`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ObjectSet([u64; MAX_OBJECTS.div_ceil(64)]);
+
+impl ObjectSet {
+	pub fn insert(&mut self, i: usize) {
+		self.0[i / 64] |= 1 << (i % 64);
+	}
+
+	pub fn contains(&self, i: usize) -> bool {
+		self.0[i / 64] & (1 << (i % 64)) != 0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.iter().all(|&bits| bits == 0)
+	}
+}
+
 impl Reset for SceneResponse {
 	fn reset_state() -> Self {
 		Self {
@@ -125,18 +412,14 @@ macro_rules! transform_ui_for {
 				&mut self,
 				ui: &mut Ui,
 				label: &'static str,
-				speed: f64,
 				obj_changed: &mut bool,
-				extra: impl Fn(DragValue) -> DragValue,
+				drag: for<'a> fn(&Ui, &'a mut f32) -> DragValue<'a>,
 			) {
 				ui.label(label);
 				ui.horizontal(|ui| {
 					for (i, axis) in (0..3).zip("XYZ".chars()) {
-						let drag = ui.add(extra(
-							DragValue::new(&mut self.$prop[self.selected].as_mut_slice()[i])
-								.prefix(format!("{axis}: "))
-								.speed(speed),
-						));
+						let value = &mut self.$prop[self.selected].as_mut_slice()[i];
+						let drag = ui.add(drag(ui, value).prefix(format!("{axis}: ")));
 						*obj_changed |= drag.changed();
 						self.update_response(drag);
 					}
@@ -152,16 +435,150 @@ impl Scene {
 		self.name.len()
 	}
 
+	// none if there are no objects
+	pub fn selected(&self) -> Option<usize> {
+		(self.len() > 0).then(|| self.selected.min(self.len() - 1))
+	}
+
+	// clamped to the objects that exist
+	pub fn select(&mut self, i: usize) {
+		self.selected = i.min(self.len().saturating_sub(1));
+		self.check_selection();
+	}
+
+	fn check_selection(&self) {
+		debug_assert!(
+			self.selected < self.len().max(1),
+			"selected object {} out of range ({} objects)",
+			self.selected,
+			self.len(),
+		);
+	}
+
+	// {{{ invariants
+	// what every edit has to keep true. checked in debug builds after the UI
+	// changed the scene, a failure means some path forgot a column
+	pub fn check_invariants(&self) -> Result<(), String> {
+		let len = self.len();
+		let lengths = [
+			("ty", self.ty.len()),
+			("position", self.position.len()),
+			("rotation", self.rotation.len()),
+			("scale", self.scale.len()),
+			("corner_radius", self.corner_radius.len()),
+			("flip_normals", self.flip_normals.len()),
+			("cap_radius", self.cap_radius.len()),
+			("double_sided", self.double_sided.len()),
+			("mesh", self.mesh.len()),
+			("csg_op", self.csg_op.len()),
+			("csg_other", self.csg_other.len()),
+			("turntable", self.turntable.len()),
+			("turntable_speed", self.turntable_speed.len()),
+			("uv_offset", self.uv_offset.len()),
+			("uv_scale", self.uv_scale.len()),
+			("uv_rotation", self.uv_rotation.len()),
+			("mat_ty", self.mat_ty.len()),
+			("mat_color", self.mat_color.len()),
+			("mat_albedo_mode", self.mat_albedo_mode.len()),
+			("mat_checker_color", self.mat_checker_color.len()),
+			("mat_checker_scale", self.mat_checker_scale.len()),
+			("mat_ior", self.mat_ior.len()),
+			("mat_specular", self.mat_specular.len()),
+			("mat_roughness", self.mat_roughness.len()),
+			("mat_metallic", self.mat_metallic.len()),
+			("mat_emissive_strength", self.mat_emissive_strength.len()),
+			("mat_emissive_color", self.mat_emissive_color.len()),
+			("mat_light_group", self.mat_light_group.len()),
+			("transform", self.transform.len()),
+			("inv_transform", self.inv_transform.len()),
+			("normal_transform", self.normal_transform.len()),
+		];
+		if let Some((column, l)) = lengths.iter().find(|(_, l)| *l != len) {
+			return Err(format!("{column} has {l} entries for {len} objects"));
+		}
+		if len > MAX_OBJECTS {
+			return Err(format!("{len} objects, the limit is {MAX_OBJECTS}"));
+		}
+		if len > 0 && self.selected >= len {
+			return Err(format!("selected object {} of {len}", self.selected));
+		}
+
+		for i in 0..len {
+			let cached = [
+				self.transform[i],
+				self.inv_transform[i],
+				self.normal_transform[i],
+			];
+			if cached.iter().any(|m| m.iter().any(|x| !x.is_finite())) {
+				return Err(format!("object {i} has a transform that isn't finite"));
+			}
+			let stale = cached
+				.iter()
+				.zip(self.compute_transform(i))
+				.any(|(a, b)| (a - b).abs().max() > 1e-4 * (1.0 + b.abs().max()));
+			if stale {
+				return Err(format!("object {i} has a stale transform"));
+			}
+
+			if self.csg_other[i].is_some_and(|j| j >= len || j == i) {
+				return Err(format!("object {i} has a broken CSG link"));
+			}
+			if self.mat_light_group[i] as usize >= self.light_groups.len() {
+				return Err(format!("object {i} is in a light group that doesn't exist"));
+			}
+		}
+
+		// what's stored has to come back the same
+		let json = serde_json::to_value(self).map_err(|e| e.to_string())?;
+		let back: Self =
+			serde_json::from_value(json.clone()).map_err(|e| e.to_string())?;
+		if serde_json::to_value(back).map_err(|e| e.to_string())? != json {
+			return Err("the scene changes when it's stored and loaded".into());
+		}
+
+		Ok(())
+	}
+	// }}}
+
+	pub fn modal_open(&self) -> bool {
+		self.rename_modal
+			|| self.delete_modal
+			|| self.paste_modal
+			|| self.fit_modal
+			|| self.import_modal
+			|| self.mesh_modal
+			|| self.arc_modal
+	}
+
 	pub fn window(&mut self, egui: &egui::Context) {
 		egui::Window::new("Scene").show(egui, |ui| {
-			let modal_open = self.rename_modal || self.delete_modal;
+			let modal_open = self.modal_open();
 
+			self.clipboard_interface(egui, ui, modal_open);
+			self.arc_interface(egui);
+			self.fit_interface(egui);
+			self.import_interface(egui);
+			self.mesh_interface(egui);
 			self.object_management_interface(ui, modal_open);
 
 			if self.len() > 0 {
-				if ui.button("Duplicate").clicked() {
-					self.duplicate_object();
-				}
+				ui.horizontal(|ui| {
+					if ui.button("Duplicate").clicked() {
+						self.duplicate_object();
+					}
+
+					if ui.button("Array on arc").clicked() && !modal_open {
+						self.arc_modal = true;
+					}
+
+					if ui.button("Look at (C)").clicked() {
+						self.response.look_at = true;
+					}
+
+					if ui.button("Fit scene").clicked() && !modal_open {
+						self.open_fit_modal();
+					}
+				});
 
 				ui.separator();
 
@@ -169,10 +586,412 @@ impl Scene {
 				self.object_renaming_button(egui, ui, modal_open);
 				self.object_deletion_button(egui, ui, modal_open);
 				self.transformation_interface(ui);
+				self.csg_interface(ui);
 				self.material_interface(ui);
 			}
+
+			self.light_group_interface(ui);
+		});
+
+		if self.response.changed {
+			debug_assert_eq!(self.check_invariants(), Ok(()));
+		}
+	}
+
+	// {{{ clipboard
+	fn clipboard_interface(
+		&mut self,
+		egui: &egui::Context,
+		ui: &mut Ui,
+		modal_open: bool,
+	) {
+		ui.horizontal(|ui| {
+			if ui.button("Copy scene").clicked() {
+				match serde_json::to_string(self) {
+					Ok(json) => {
+						ui.output_mut(|o| o.copied_text = json);
+						self.pending_toasts.push("Scene copied to clipboard".into());
+					},
+					Err(e) => self.pending_toasts.push(format!("Copy failed: {e}")),
+				}
+			}
+
+			// the clipboard can't be read directly (especially on the web),
+			// so the text is pasted into a text box instead
+			if ui.button("Paste scene").clicked() && !modal_open {
+				self.paste_modal = true;
+				self.pending_paste.clear();
+			}
+
+			ui.menu_button("Test scenes", |ui| {
+				if ui.button("Huge floor and tiny sphere").clicked() {
+					self.replace_objects(Self::default().with_bias_test_scene());
+					self.pending_toasts.push("Test scene loaded".into());
+					ui.close_menu();
+				}
+			});
 		});
+
+		let mut do_paste = false;
+
+		modal(
+			egui,
+			"Paste scene",
+			&mut self.paste_modal,
+			|ui| {
+				ui.label("Paste the scene JSON below.");
+				ui.label("This will replace the current scene.");
+				egui::ScrollArea::vertical()
+					.max_height(200.0)
+					.show(ui, |ui| {
+						self.response.focused |=
+							ui.text_edit_multiline(&mut self.pending_paste).has_focus();
+					});
+			},
+			crate::util::red_hover_button,
+			|| do_paste = true,
+		);
+
+		if do_paste {
+			match Self::from_json(&self.pending_paste) {
+				Ok((scene, warnings)) => {
+					self.replace_objects(scene);
+					self
+						.pending_toasts
+						.push("Scene pasted from clipboard".into());
+					self.pending_toasts.extend(warnings);
+					self.open_fit_modal();
+				},
+				Err(e) => self.pending_toasts.push(format!("Invalid scene: {e}")),
+			}
+			self.pending_paste.clear();
+		}
+	}
+
+	// }}}
+
+	// {{{ fitting
+	// imported scenes can be at any scale, this offers to bring them to a
+	// common size and to move the camera back far enough to see all of it
+	fn open_fit_modal(&mut self) {
+		let Some(factor) = self.fit_factor() else {
+			return;
+		};
+		self.fit_modal = true;
+		self.fit_rescale = !(0.5..=2.0).contains(&factor);
+	}
+
+	fn fit_factor(&self) -> Option<f32> {
+		let (_, radius) = crate::geometry::bounding_sphere(self)?;
+		(radius > 0.0 && radius.is_finite()).then(|| FIT_RADIUS / radius)
+	}
+
+	fn fit_interface(&mut self, egui: &egui::Context) {
+		let Some(factor) = self.fit_factor() else {
+			self.fit_modal = false;
+			return;
+		};
+
+		let mut do_fit = false;
+		let fit_rescale = &mut self.fit_rescale;
+
+		modal(
+			egui,
+			"Fit scene",
+			&mut self.fit_modal,
+			|ui| {
+				ui.label("The camera will be moved to show the whole scene.");
+				ui.checkbox(
+					fit_rescale,
+					format!("Also scale all objects by {factor:.3}×"),
+				)
+				.on_hover_text(
+					"Positions and sizes are scaled around the origin, so that the \
+					 scene is about the same size as the default one",
+				);
+			},
+			|_| {},
+			|| do_fit = true,
+		);
+
+		if do_fit {
+			if self.fit_rescale {
+				self.rescale(factor);
+			}
+			self.response.frame_scene = true;
+			self.set_changed(true);
+		}
+	}
+
+	// scales positions and sizes of all objects around the origin
+	pub fn rescale(&mut self, factor: f32) {
+		for i in 0..self.len() {
+			self.position[i] *= factor;
+			self.scale[i] *= factor;
+		}
+		self.recalc_transforms();
+	}
+	// }}}
+
+	// {{{ import
+	// the scene and warnings about anything that had to be changed
+	pub fn from_json(json: &str) -> Result<(Self, Vec<String>), String> {
+		let mut scene: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+		let warnings = scene.sanitize()?;
+		Ok((scene, warnings))
+	}
+
+	// checks a scene that came from outside (pasted or stored) and repairs what
+	// can be repaired. errors mean the scene can't be used at all, otherwise
+	// the returned warnings describe the repairs that lose data
+	pub fn sanitize(&mut self) -> Result<Vec<String>, String> {
+		let mut warnings = Vec::new();
+		self.migrate();
+
+		let lengths = [
+			self.ty.len(),
+			self.position.len(),
+			self.rotation.len(),
+			self.scale.len(),
+			self.mat_ty.len(),
+			self.mat_color.len(),
+			self.mat_ior.len(),
+			self.mat_specular.len(),
+			self.mat_roughness.len(),
+			self.mat_emissive_strength.len(),
+		];
+		if lengths.iter().any(|&l| l != self.len()) {
+			return Err("object properties have mismatched lengths".into());
+		}
+
+		// keep the first objects instead of rejecting the whole scene
+		let dropped = self.len().saturating_sub(MAX_OBJECTS);
+		if dropped > 0 {
+			self.truncate_objects(MAX_OBJECTS);
+			warnings.push(format!(
+				"Dropped {dropped} objects, the limit is {MAX_OBJECTS}"
+			));
+		}
+		let len = self.len();
+
+		// non-finite values would turn every pixel they touch into NaN
+		let finite_or = |v: &mut Vec3, default: f32| {
+			if !v.iter().all(|c| c.is_finite()) {
+				*v = Vec3::repeat(default);
+			}
+		};
+		for i in 0..len {
+			finite_or(&mut self.position[i], 0.0);
+			finite_or(&mut self.rotation[i], 0.0);
+			finite_or(&mut self.scale[i], 1.0);
+		}
+
+		// broken meshes and those over the triangle limit become spheres
+		let mut triangles = 0;
+		for i in 0..len {
+			if self.ty[i] != ObjectType::Mesh {
+				self.mesh[i] = MeshData::default();
+				continue;
+			}
+
+			let count = self.mesh[i].triangles.len();
+			let fits = triangles + count <= MAX_MESH_TRIANGLES;
+			if self.mesh[i].is_empty() || !self.mesh[i].is_valid() || !fits {
+				warnings.push(format!(
+					"Replaced mesh '{}' with a sphere, its data was invalid or over \
+					 the limit of {MAX_MESH_TRIANGLES} triangles",
+					self.name[i],
+				));
+				self.ty[i] = ObjectType::Sphere;
+				self.mesh[i] = MeshData::default();
+			} else {
+				triangles += count;
+			}
+		}
+
+		// links to objects that were dropped or to the object itself
+		for i in 0..len {
+			if self.csg_other[i].is_some_and(|j| j >= len || j == i) {
+				self.csg_other[i] = None;
+			}
+		}
+
+		// don't trust the cached transforms
+		self.transform = vec![identity(); len];
+		self.inv_transform = vec![identity(); len];
+		self.normal_transform = vec![identity(); len];
+		self.recalc_transforms();
+
+		self.select(self.selected);
+
+		Ok(warnings)
+	}
+
+	// replace all objects while keeping UI state
+	fn replace_objects(&mut self, scene: Self) {
+		let response = self.response;
+		let pending_toasts = std::mem::take(&mut self.pending_toasts);
+
+		*self = scene;
+
+		self.response = response;
+		self.pending_toasts = pending_toasts;
+		self.set_changed(true);
+		self.check_selection();
 	}
+	// }}}
+
+	// {{{ arc array
+	fn arc_interface(&mut self, egui: &egui::Context) {
+		if self.len() == 0 {
+			self.arc_modal = false;
+			return;
+		}
+
+		let mut do_array = false;
+		let arc = &mut self.arc;
+		let focused = &mut self.response.focused;
+
+		modal(
+			egui,
+			"Array on arc",
+			&mut self.arc_modal,
+			|ui| {
+				ui.label("Copies of the selected object are spread along a circle.");
+				ui.horizontal(|ui| {
+					ui.label("Copies:");
+					*focused |= ui
+						.add(Slider::new(&mut arc.count, 1..=MAX_OBJECTS - 1))
+						.has_focus();
+				});
+				ui.horizontal(|ui| {
+					ui.label("Center:");
+					for value in arc.center.iter_mut() {
+						*focused |= ui.add(controls::position_drag(ui, value)).has_focus();
+					}
+				});
+				ui.horizontal(|ui| {
+					ui.label("Radius:");
+					*focused |= ui
+						.add(
+							controls::position_drag(ui, &mut arc.radius)
+								.clamp_range(0.0..=f32::INFINITY),
+						)
+						.has_focus();
+				});
+				ui.horizontal(|ui| {
+					ui.label("Angles:");
+					*focused |= ui
+						.add(controls::angle_drag(ui, &mut arc.start_angle))
+						.has_focus();
+					ui.label("to");
+					*focused |= ui
+						.add(controls::angle_drag(ui, &mut arc.end_angle))
+						.has_focus();
+				})
+				.response
+				.on_hover_text("A whole turn leaves the same gap after the last copy");
+
+				let mut changed = false;
+				ui.horizontal(|ui| {
+					ui.label("Axis:");
+					ComboBox::new("scene_arc_axis_selector", "")
+						.selected_text(format!("{}", arc.axis))
+						.show_ui(
+							ui,
+							selectable_values! {
+								target = arc.axis,
+								focused = *focused,
+								changed = changed,
+								[ArcAxis::X, ArcAxis::Y, ArcAxis::Z],
+							},
+						);
+				});
+				ui.horizontal(|ui| {
+					ui.label("Rotation:");
+					ComboBox::new("scene_arc_facing_selector", "")
+						.selected_text(format!("{}", arc.facing))
+						.show_ui(
+							ui,
+							selectable_values! {
+								target = arc.facing,
+								focused = *focused,
+								changed = changed,
+								[ArcFacing::Keep, ArcFacing::Center, ArcFacing::Tangent],
+							},
+						);
+				});
+			},
+			|_| {},
+			|| do_array = true,
+		);
+
+		if do_array {
+			if let Err(e) = self.array_on_arc(self.arc) {
+				self
+					.pending_toasts
+					.push(format!("Can't make the array: {e}"));
+			}
+		}
+	}
+
+	// duplicates the selected object `count` times along the arc. the copies
+	// are made on a copy of the scene that replaces it in one go, so the
+	// array is a single change and nothing is added if it fails. the original
+	// stays where it is and stays selected
+	pub fn array_on_arc(&mut self, arc: ArcArray) -> Result<(), String> {
+		if self.len() == 0 {
+			return Err("nothing is selected".into());
+		}
+		if self.len() + arc.count > MAX_OBJECTS {
+			return Err(format!("the limit is {MAX_OBJECTS} objects"));
+		}
+		let triangles = self.mesh[self.selected].triangles.len() * arc.count;
+		if self.mesh_triangles() + triangles > MAX_MESH_TRIANGLES {
+			return Err(format!(
+				"meshes are limited to {MAX_MESH_TRIANGLES} triangles"
+			));
+		}
+
+		// a full circle would put the last copy on top of the first
+		let span = arc.end_angle - arc.start_angle;
+		let full = span.abs() >= std::f32::consts::TAU - 1e-4;
+		let steps = if full || arc.count == 1 {
+			arc.count
+		} else {
+			arc.count - 1
+		};
+		let step = span / steps as f32;
+
+		let original = self.selected;
+		let name = self.name[original].clone();
+		let axis = arc.axis.dir();
+
+		let mut scene = self.clone();
+		for k in 0..arc.count {
+			let angle = arc.start_angle + step * k as f32;
+			let outward = glm::rotate_vec3(&arc.axis.start(), angle, &axis);
+			let tangent = glm::cross(&axis, &outward);
+
+			scene.select(original);
+			scene.duplicate_object();
+			let i = scene.len() - 1;
+
+			scene.name[i] = format!("{name} {}", k + 1);
+			scene.position[i] = arc.center + outward * arc.radius;
+			scene.rotation[i] = match arc.facing {
+				ArcFacing::Keep => scene.rotation[original],
+				ArcFacing::Center => rotation_from_axes(-outward, tangent),
+				ArcFacing::Tangent => rotation_from_axes(tangent, outward),
+			};
+		}
+		scene.select(original);
+		scene.recalc_transforms();
+
+		self.replace_objects(scene);
+		Ok(())
+	}
+	// }}}
 
 	// {{{ select and add
 	fn object_management_interface(&mut self, ui: &mut Ui, modal_open: bool) {
@@ -183,10 +1002,9 @@ impl Scene {
 					.selected_text(&self.name[self.selected])
 					.show_ui(ui, |ui| {
 						for i in 0..self.len() {
-							let value =
-								ui.selectable_value(&mut &self.selected, &i, &self.name[i]);
+							let value = self.object_row(ui, i);
 							if !modal_open && value.clicked() {
-								self.selected = i;
+								self.select(i);
 							}
 							self.update_response(value);
 						}
@@ -194,10 +1012,43 @@ impl Scene {
 			});
 		}
 
-		if ui.button("New object").clicked() {
-			self.new_object();
-			self.set_changed(true);
-		}
+		ui.horizontal(|ui| {
+			if ui.button("New object").clicked() {
+				self.new_object();
+				self.set_changed(true);
+			}
+			if ui.button("Import mesh").clicked() && !modal_open {
+				#[cfg(not(target_arch = "wasm32"))]
+				self.pick_mesh();
+				#[cfg(target_arch = "wasm32")]
+				{
+					self.mesh_modal = true;
+				}
+			}
+		});
+	}
+
+	// selectable row with a swatch of the material color in front of the name,
+	// light sources also get a sun glyph
+	fn object_row(&self, ui: &mut Ui, i: usize) -> egui::Response {
+		ui.horizontal(|ui| {
+			let size = ui.text_style_height(&egui::TextStyle::Body);
+			let (rect, _) =
+				ui.allocate_exact_size(egui::Vec2::splat(size), egui::Sense::hover());
+
+			let [r, g, b] = self.mat_color[i];
+			let color = egui::Color32::from(egui::Rgba::from_rgb(r, g, b));
+			ui.painter().rect_filled(rect.shrink(1.0), 2.0, color);
+
+			let text = match self.mat_ty[i] {
+				MaterialType::Emissive => format!("☀ {}", self.name[i]),
+				MaterialType::Solid | MaterialType::Metal | MaterialType::ShadowCatcher => {
+					self.name[i].clone()
+				},
+			};
+			ui.selectable_label(i == self.selected, text)
+		})
+		.inner
 	}
 	// }}}
 
@@ -205,6 +1056,14 @@ impl Scene {
 	fn object_type_menu(&mut self, ui: &mut Ui) {
 		ui.horizontal(|ui| {
 			ui.label("Object type:");
+
+			// meshes come from files, they can't be turned into something else
+			if self.ty[self.selected] == ObjectType::Mesh {
+				let triangles = self.mesh[self.selected].triangles.len();
+				ui.label(format!("Mesh ({triangles} triangles)"));
+				return;
+			}
+
 			ComboBox::new("scene_object_type_selector", "")
 				.selected_text(format!("{:?}", self.ty[self.selected]))
 				.show_ui(
@@ -216,6 +1075,10 @@ impl Scene {
 						[
 							ObjectType::Sphere,
 							ObjectType::Box,
+							ObjectType::Plane,
+							ObjectType::Cylinder,
+							ObjectType::Capsule,
+							ObjectType::Disc,
 						]
 					},
 				);
@@ -297,16 +1160,71 @@ impl Scene {
 		}
 
 		ui.collapsing("Transform", |ui| {
-			let speed = ui.input(|i| if i.modifiers.shift { 0.01 } else { 0.1 });
-
 			let mut changed = false;
 
-			self.transform_position(ui, "Position", speed, &mut changed, |drag| drag);
-			self.transform_rotation(ui, "Rotation", speed, &mut changed, |drag| {
-				drag.angle()
+			self.transform_position(
+				ui,
+				"Position",
+				&mut changed,
+				controls::position_drag,
+			);
+			self.transform_rotation(ui, "Rotation", &mut changed, controls::angle_drag);
+			self.transform_scale(ui, "Scale", &mut changed, controls::scale_drag);
+
+			if self.ty[self.selected] == ObjectType::Box {
+				ui.horizontal(|ui| {
+					ui.label("Corner radius:");
+					let slider = ui.add(Slider::new(
+						&mut self.corner_radius[self.selected],
+						0.0..=0.5,
+					));
+					self.update_response(slider);
+				});
+			}
+
+			if self.ty[self.selected] == ObjectType::Capsule {
+				ui.horizontal(|ui| {
+					ui.label("Cap radius:");
+					let slider = ui
+						.add(Slider::new(&mut self.cap_radius[self.selected], 0.05..=1.0))
+						.on_hover_text("The rest of the height is the straight part");
+					self.update_response(slider);
+				});
+			}
+
+			if matches!(self.ty[self.selected], ObjectType::Plane | ObjectType::Disc) {
+				let checkbox = ui
+					.checkbox(&mut self.double_sided[self.selected], "Double-sided")
+					.on_hover_text(
+						"Otherwise only the side facing local +Y is visible and \
+						 gives off light",
+					);
+				self.update_response(checkbox);
+			}
+
+			let checkbox = ui
+				.checkbox(&mut self.flip_normals[self.selected], "Flip normals")
+				.on_hover_text(
+					"For objects that look inside out, usually because of a \
+					 negative scale",
+				);
+			self.update_response(checkbox);
+
+			ui.horizontal(|ui| {
+				let checkbox = ui.checkbox(&mut self.turntable[self.selected], "Turntable");
+				self.update_response(checkbox);
+
+				if self.turntable[self.selected] {
+					let slider = ui.add(
+						controls::angle_slider(
+							&mut self.turntable_speed[self.selected],
+							-std::f32::consts::PI..=std::f32::consts::PI,
+						)
+						.suffix("°/s"),
+					);
+					self.update_response(slider);
+				}
 			});
-			self
-				.transform_scale(ui, "Scale", speed, &mut changed, |drag| drag.suffix("×"));
 
 			if changed {
 				self.recalc_transforms();
@@ -315,6 +1233,23 @@ impl Scene {
 		});
 	}
 
+	// spin objects with the turntable enabled, stopping leaves them where they
+	// are
+	pub fn advance_turntables(&mut self, dt: f32) {
+		for i in 0..self.len() {
+			if self.turntable[i] {
+				let y = self.rotation[i].y + self.turntable_speed[i] * dt;
+				self.rotation[i].y = y % std::f32::consts::TAU;
+				self.recalc_transform(i);
+				self.response.moved.insert(i);
+			}
+		}
+	}
+
+	pub fn turntable_running(&self) -> bool {
+		self.turntable.contains(&true)
+	}
+
 	transform_ui_for!(position);
 	transform_ui_for!(rotation);
 	transform_ui_for!(scale);
@@ -341,35 +1276,39 @@ impl Scene {
 							changed = self.response.changed,
 							[
 								MaterialType::Solid,
+								MaterialType::Metal,
 								MaterialType::Emissive,
+								MaterialType::ShadowCatcher,
 							],
 						},
 					);
 			});
 			// }}}
 
-			ui.horizontal(|ui| {
-				ui.label("Color:");
-				let color = ui.color_edit_button_rgb(&mut self.mat_color[self.selected]);
-				self.update_response(color);
-			});
+			// a shadow catcher only shows the background
+			if self.mat_ty[self.selected] != MaterialType::ShadowCatcher {
+				ui.horizontal(|ui| {
+					ui.label("Color:");
+					let color =
+						crate::util::color_edit(ui, &mut self.mat_color[self.selected]);
+					self.update_response(color);
+				});
+				self.albedo_interface(ui);
+			}
 
 			match self.mat_ty[self.selected] {
 				MaterialType::Solid => {
 					ui.horizontal(|ui| {
 						ui.label("Specular:");
-						let slider = ui.add(Slider::new(
-							&mut self.mat_specular[self.selected],
-							0.0..=1.0,
-						));
+						let slider =
+							ui.add(controls::unit_slider(&mut self.mat_specular[self.selected]));
 						self.update_response(slider);
 					});
 					if self.mat_specular[self.selected] > 0.0 {
 						ui.horizontal(|ui| {
 							ui.label("Roughness:");
-							let slider = ui.add(Slider::new(
+							let slider = ui.add(controls::roughness_slider(
 								&mut self.mat_roughness[self.selected],
-								0.0..=1.0,
 							));
 							self.update_response(slider);
 						});
@@ -383,7 +1322,26 @@ impl Scene {
 						self.update_response(slider);
 					});
 				},
+				MaterialType::Metal => {
+					ui.horizontal(|ui| {
+						ui.label("Metallic:");
+						let slider =
+							ui.add(controls::unit_slider(&mut self.mat_metallic[self.selected]));
+						self.update_response(slider);
+					});
+					ui.horizontal(|ui| {
+						ui.label("Roughness:");
+						let slider = ui.add(controls::roughness_slider(
+							&mut self.mat_roughness[self.selected],
+						));
+						self.update_response(slider);
+					});
+				},
 				MaterialType::Emissive => {
+					ui.horizontal(|ui| {
+						ui.label("Light color:");
+						self.hdr_color_edit(ui);
+					});
 					ui.horizontal(|ui| {
 						ui.label("Light strength:");
 						let slider = ui.add(Slider::new(
@@ -392,15 +1350,334 @@ impl Scene {
 						));
 						self.update_response(slider);
 					});
+
+					ui.horizontal(|ui| {
+						ui.label("Light group:");
+						let group = self.mat_light_group[self.selected] as usize;
+						ComboBox::new("scene_light_group_selector", "")
+							.selected_text(&self.light_groups[group].name)
+							.show_ui(ui, |ui| {
+								for i in 0..self.light_groups.len() {
+									let value =
+										ui.selectable_label(i == group, &self.light_groups[i].name);
+									if value.clicked() {
+										self.mat_light_group[self.selected] = i as u32;
+									}
+									self.update_response(value);
+								}
+							});
+					});
+				},
+				MaterialType::ShadowCatcher => {
+					ui.label("Only shows the shadows cast onto it, e.g. for compositing.");
 				},
 			}
+
+			self.uv_interface(ui);
+		});
+	}
+
+	fn uv_interface(&mut self, ui: &mut Ui) {
+		let i = self.selected;
+
+		ui.label("UV mapping:");
+		ui.horizontal(|ui| {
+			ui.label("Offset:");
+			for (axis, label) in "UV".chars().enumerate() {
+				let value = &mut self.uv_offset[i][axis];
+				let drag =
+					ui.add(controls::position_drag(ui, value).prefix(format!("{label}: ")));
+				self.update_response(drag);
+			}
+		});
+		ui.horizontal(|ui| {
+			ui.label("Scale:");
+			for (axis, label) in "UV".chars().enumerate() {
+				let value = &mut self.uv_scale[i][axis];
+				let drag =
+					ui.add(controls::scale_drag(ui, value).prefix(format!("{label}: ")));
+				self.update_response(drag);
+			}
+		});
+		ui.horizontal(|ui| {
+			ui.label("Rotation:");
+			let drag = ui.add(controls::angle_drag(ui, &mut self.uv_rotation[i]));
+			self.update_response(drag);
+		});
+	}
+
+	// rotation and scale of the UV transform as a column-major 2x2 matrix
+	pub fn uv_matrices(&self) -> Vec<[f32; 4]> {
+		(0..self.len())
+			.map(|i| {
+				let (sin, cos) = self.uv_rotation[i].sin_cos();
+				let scale = self.uv_scale[i];
+				[cos * scale.x, sin * scale.x, -sin * scale.y, cos * scale.y]
+			})
+			.collect()
+	}
+	// }}}
+
+	// optional pattern on top of the color
+	fn albedo_interface(&mut self, ui: &mut Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Pattern:");
+			ComboBox::new("scene_albedo_mode_selector", "")
+				.selected_text(format!("{}", self.mat_albedo_mode[self.selected]))
+				.show_ui(
+					ui,
+					selectable_values! {
+						target = self.mat_albedo_mode[self.selected],
+						focused = self.response.focused,
+						changed = self.response.changed,
+						[AlbedoMode::Solid, AlbedoMode::Checker],
+					},
+				);
+		});
+
+		if self.mat_albedo_mode[self.selected] == AlbedoMode::Checker {
+			ui.horizontal(|ui| {
+				ui.label("Second color:");
+				let color =
+					crate::util::color_edit(ui, &mut self.mat_checker_color[self.selected]);
+				self.update_response(color);
+			});
+			ui.horizontal(|ui| {
+				ui.label("Squares:");
+				let slider = ui
+					.add(
+						Slider::new(&mut self.mat_checker_scale[self.selected], 1.0..=64.0)
+							.logarithmic(true),
+					)
+					.on_hover_text("Across the object, they follow its transform");
+				self.update_response(slider);
+			});
+		}
+	}
+
+	// per object, the second color and the number of squares. no squares is a
+	// solid color, see scene_mat_checker in fsh.glsl
+	pub fn checker_uniforms(&self) -> Vec<[f32; 4]> {
+		(0..self.len())
+			.map(|i| {
+				let [r, g, b] = self.mat_checker_color[i];
+				let squares = match self.mat_albedo_mode[i] {
+					AlbedoMode::Solid => 0.0,
+					AlbedoMode::Checker => self.mat_checker_scale[i],
+				};
+				[r, g, b, squares]
+			})
+			.collect()
+	}
+
+	// light colors can go above 1. the picker only works in [0, 1], so it edits
+	// the color divided by its brightest channel and the result is scaled back
+	// up, keeping the ratio between the channels
+	fn hdr_color_edit(&mut self, ui: &mut Ui) {
+		let color = &mut self.mat_emissive_color[self.selected];
+		let peak = color.iter().copied().fold(1.0, f32::max);
+
+		let mut normalized = color.map(|c| c / peak);
+		let picker = crate::util::color_edit(ui, &mut normalized);
+		if picker.changed() {
+			*color = normalized.map(|c| c * peak);
+		}
+		self.update_response(picker);
+
+		for (i, channel) in "RGB".chars().enumerate() {
+			let drag = ui.add(
+				DragValue::new(&mut self.mat_emissive_color[self.selected][i])
+					.prefix(format!("{channel}: "))
+					.speed(0.01)
+					.clamp_range(0.0..=f32::MAX),
+			);
+			self.update_response(drag);
+		}
+	}
+
+	// {{{ constructive solid geometry
+	fn csg_interface(&mut self, ui: &mut Ui) {
+		let i = self.selected;
+		if !csg_capable(self.ty[i]) {
+			return;
+		}
+
+		ui.collapsing("Combine", |ui| {
+			ui.horizontal(|ui| {
+				ui.label("Operation:");
+				ComboBox::new("scene_csg_op_selector", "")
+					.selected_text(format!("{}", self.csg_op[i]))
+					.show_ui(
+						ui,
+						selectable_values! {
+							target = self.csg_op[i],
+							focused = self.response.focused,
+							changed = self.response.changed,
+							[CsgOp::None, CsgOp::Union, CsgOp::Intersect, CsgOp::Subtract],
+						},
+					);
+			});
+
+			if self.csg_op[i] == CsgOp::None {
+				return;
+			}
+
+			ui.horizontal(|ui| {
+				ui.label("With:");
+				let selected = match self.csg_other[i] {
+					Some(j) if j < self.len() => self.name[j].as_str(),
+					_ => "Nothing",
+				};
+				ComboBox::new("scene_csg_other_selector", "")
+					.selected_text(selected)
+					.show_ui(ui, |ui| {
+						let value = ui.selectable_label(self.csg_other[i].is_none(), "Nothing");
+						if value.clicked() {
+							self.csg_other[i] = None;
+						}
+						self.update_response(value);
+
+						for j in 0..self.len() {
+							if j == i || !csg_capable(self.ty[j]) {
+								continue;
+							}
+							let value =
+								ui.selectable_label(self.csg_other[i] == Some(j), &self.name[j]);
+							if value.clicked() {
+								self.csg_other[i] = Some(j);
+							}
+							self.update_response(value);
+						}
+					});
+			});
+
+			if self.csg_link(i).is_some() && self.corner_radius[i] > 0.0 {
+				ui.label("Rounded corners are ignored while combined");
+			}
+		});
+	}
+
+	// the operation and partner of an object's CSG link, if it has a usable one
+	pub fn csg_link(&self, i: usize) -> Option<(CsgOp, usize)> {
+		let op = self.csg_op[i];
+		let j = self.csg_other[i]?;
+		let usable = op != CsgOp::None
+			&& j < self.len()
+			&& j != i
+			&& csg_capable(self.ty[i])
+			&& csg_capable(self.ty[j]);
+		usable.then_some((op, j))
+	}
+
+	// partners are only drawn as part of the objects that link to them
+	pub fn csg_partner(&self, i: usize) -> bool {
+		(0..self.len()).any(|k| self.csg_link(k).is_some_and(|(_, j)| j == i))
+	}
+	// }}}
+
+	// {{{ light groups
+	fn light_group_interface(&mut self, ui: &mut Ui) {
+		ui.collapsing("Light groups", |ui| {
+			let mut delete = None;
+
+			for i in 0..self.light_groups.len() {
+				ui.horizontal(|ui| {
+					let group = &mut self.light_groups[i];
+
+					let checkbox =
+						ui.checkbox(&mut group.enabled, "").on_hover_text("Enabled");
+					let intensity = ui
+						.add(
+							DragValue::new(&mut group.intensity)
+								.speed(0.01)
+								.clamp_range(0.0..=f32::MAX)
+								.suffix("×"),
+						)
+						.on_hover_text("Intensity");
+
+					// the default group keeps its name
+					if i == 0 {
+						ui.label(&group.name);
+					} else {
+						// the name doesn't change the image, it's edited in a copy
+						// that's written back once the field loses focus instead of
+						// restarting accumulation on every key
+						let id = ui.make_persistent_id(("light_group_name", i));
+						let mut name = ui
+							.data_mut(|d| d.get_temp::<String>(id))
+							.unwrap_or_else(|| group.name.clone());
+						let edit =
+							ui.add(egui::TextEdit::singleline(&mut name).desired_width(100.0));
+
+						if edit.has_focus() {
+							ui.data_mut(|d| d.insert_temp(id, name));
+						} else {
+							ui.data_mut(|d| d.remove::<String>(id));
+							if edit.lost_focus() && name != group.name {
+								group.name = name;
+								self.set_changed(true);
+							}
+						}
+						self.set_focused(edit.has_focus());
+
+						if ui.button("Delete").clicked() {
+							delete = Some(i);
+						}
+					}
+
+					self.update_response(checkbox);
+					self.update_response(intensity);
+				});
+			}
+
+			if let Some(i) = delete {
+				self.delete_light_group(i);
+				self.set_changed(true);
+			}
+
+			let add = ui.add_enabled(
+				self.light_groups.len() < MAX_LIGHT_GROUPS,
+				egui::Button::new("New light group"),
+			);
+			if add.clicked() {
+				let name = format!("Group {}", self.light_groups.len());
+				self.light_groups.push(LightGroup::new(name));
+				self.set_changed(true);
+			}
 		});
 	}
+
+	// members of the deleted group go back to the default group
+	fn delete_light_group(&mut self, group: usize) {
+		if group == 0 || group >= self.light_groups.len() {
+			return;
+		}
+
+		self.light_groups.remove(group);
+
+		let group = group as u32;
+		for member in &mut self.mat_light_group {
+			match (*member).cmp(&group) {
+				std::cmp::Ordering::Equal => *member = 0,
+				std::cmp::Ordering::Greater => *member -= 1,
+				std::cmp::Ordering::Less => {},
+			}
+		}
+	}
+
+	// emission multiplier for each group, disabled groups emit nothing
+	pub fn light_group_scales(&self) -> [f32; MAX_LIGHT_GROUPS] {
+		let mut scales = [1.0; MAX_LIGHT_GROUPS];
+		for (scale, group) in scales.iter_mut().zip(&self.light_groups) {
+			*scale = if group.enabled { group.intensity } else { 0.0 };
+		}
+		scales
+	}
 	// }}}
 
 	// {{{ create, duplicate, and delete objects
 	pub fn new_object(&mut self) {
-		if self.len() >= 50 {
+		if self.len() >= MAX_OBJECTS {
 			return;
 		}
 
@@ -412,26 +1689,54 @@ impl Scene {
 		self.rotation.push(vec3(0.0, 0.0, 0.0));
 		self.scale.push(vec3(1.0, 1.0, 1.0));
 
+		self.corner_radius.push(0.0);
+		self.flip_normals.push(false);
+		self.cap_radius.push(0.5);
+		self.double_sided.push(true);
+		self.mesh.push(MeshData::default());
+		self.csg_op.push(CsgOp::None);
+		self.csg_other.push(None);
+		self.turntable.push(false);
+		self.turntable_speed.push(DEFAULT_TURNTABLE_SPEED);
+
+		self.uv_offset.push(vec2(0.0, 0.0));
+		self.uv_scale.push(vec2(1.0, 1.0));
+		self.uv_rotation.push(0.0);
+
 		self.mat_ty.push(MaterialType::Solid);
 		self.mat_color.push([0.9, 0.9, 0.9]);
+		self.mat_albedo_mode.push(AlbedoMode::Solid);
+		self.mat_checker_color.push(DEFAULT_CHECKER_COLOR);
+		self.mat_checker_scale.push(DEFAULT_CHECKER_SCALE);
 		self.mat_ior.push(1.333);
 		self.mat_specular.push(1.0);
 		self.mat_roughness.push(1.0);
+		self.mat_metallic.push(1.0);
 		self.mat_emissive_strength.push(1.0);
+		self.mat_emissive_color.push([1.0, 1.0, 1.0]);
+		self.mat_light_group.push(0);
 
 		self.transform.push(glm::identity());
 		self.inv_transform.push(glm::identity());
 		self.normal_transform.push(glm::identity());
 
-		self.selected = self.len() - 1;
+		self.select(self.len() - 1);
 	}
 
 	pub fn duplicate_object(&mut self) {
-		if self.len() < 1 || self.len() >= 50 {
+		if self.len() < 1 || self.len() >= MAX_OBJECTS {
 			return;
 		}
 
 		let i = self.selected;
+		let triangles = self.mesh[i].triangles.len();
+		if self.mesh_triangles() + triangles > MAX_MESH_TRIANGLES {
+			self.pending_toasts.push(format!(
+				"Can't duplicate, meshes are limited to {MAX_MESH_TRIANGLES} triangles"
+			));
+			return;
+		}
+
 		let mut name = self.name[i].clone();
 		name.push_str(" copy");
 
@@ -441,20 +1746,67 @@ impl Scene {
 		self.rotation.push(self.rotation[i]);
 		self.scale.push(self.scale[i]);
 
+		self.corner_radius.push(self.corner_radius[i]);
+		self.flip_normals.push(self.flip_normals[i]);
+		self.cap_radius.push(self.cap_radius[i]);
+		self.double_sided.push(self.double_sided[i]);
+		self.mesh.push(self.mesh[i].clone());
+		self.csg_op.push(self.csg_op[i]);
+		self.csg_other.push(self.csg_other[i]);
+		self.turntable.push(self.turntable[i]);
+		self.turntable_speed.push(self.turntable_speed[i]);
+
+		self.uv_offset.push(self.uv_offset[i]);
+		self.uv_scale.push(self.uv_scale[i]);
+		self.uv_rotation.push(self.uv_rotation[i]);
+
 		self.mat_ty.push(self.mat_ty[i]);
 		self.mat_color.push(self.mat_color[i]);
+		self.mat_albedo_mode.push(self.mat_albedo_mode[i]);
+		self.mat_checker_color.push(self.mat_checker_color[i]);
+		self.mat_checker_scale.push(self.mat_checker_scale[i]);
 		self.mat_ior.push(self.mat_ior[i]);
 		self.mat_specular.push(self.mat_specular[i]);
 		self.mat_roughness.push(self.mat_roughness[i]);
+		self.mat_metallic.push(self.mat_metallic[i]);
 		self
 			.mat_emissive_strength
 			.push(self.mat_emissive_strength[i]);
+		self.mat_emissive_color.push(self.mat_emissive_color[i]);
+		self.mat_light_group.push(self.mat_light_group[i]);
 
 		self.transform.push(self.transform[i]);
 		self.inv_transform.push(self.inv_transform[i]);
 		self.normal_transform.push(self.normal_transform[i]);
 
-		self.selected = self.len() - 1;
+		self.select(self.len() - 1);
+	}
+
+	// adds an object for a mesh loaded from a file
+	pub fn import_mesh(&mut self, name: &str, mesh: MeshData) -> Result<(), String> {
+		if self.len() >= MAX_OBJECTS {
+			return Err(format!("the limit is {MAX_OBJECTS} objects"));
+		}
+		let triangles = self.mesh_triangles() + mesh.triangles.len();
+		if triangles > MAX_MESH_TRIANGLES {
+			return Err(format!(
+				"the scene would have {triangles} mesh triangles, the limit is \
+				 {MAX_MESH_TRIANGLES}"
+			));
+		}
+
+		self.new_object();
+		let i = self.len() - 1;
+		self.name[i] = name.to_owned();
+		self.ty[i] = ObjectType::Mesh;
+		self.mesh[i] = mesh;
+		self.set_changed(true);
+		Ok(())
+	}
+
+	// in all meshes together
+	pub fn mesh_triangles(&self) -> usize {
+		self.mesh.iter().map(|m| m.triangles.len()).sum()
 	}
 
 	pub fn delete_object(&mut self) {
@@ -470,45 +1822,168 @@ impl Scene {
 		self.rotation.remove(i);
 		self.scale.remove(i);
 
+		self.corner_radius.remove(i);
+		self.flip_normals.remove(i);
+		self.cap_radius.remove(i);
+		self.double_sided.remove(i);
+		self.mesh.remove(i);
+		self.csg_op.remove(i);
+		self.csg_other.remove(i);
+		self.turntable.remove(i);
+		self.turntable_speed.remove(i);
+
+		self.uv_offset.remove(i);
+		self.uv_scale.remove(i);
+		self.uv_rotation.remove(i);
+
 		self.mat_ty.remove(i);
 		self.mat_color.remove(i);
+		self.mat_albedo_mode.remove(i);
+		self.mat_checker_color.remove(i);
+		self.mat_checker_scale.remove(i);
 		self.mat_ior.remove(i);
 		self.mat_specular.remove(i);
 		self.mat_roughness.remove(i);
+		self.mat_metallic.remove(i);
 		self.mat_emissive_strength.remove(i);
+		self.mat_emissive_color.remove(i);
+		self.mat_light_group.remove(i);
 
 		self.transform.remove(i);
 		self.inv_transform.remove(i);
 		self.normal_transform.remove(i);
 
-		self.selected = i.saturating_sub(1);
+		// links to the deleted object are dropped, the ones past it follow
+		// their objects down by one
+		for other in &mut self.csg_other {
+			*other = match *other {
+				Some(j) if j == i => None,
+				Some(j) if j > i => Some(j - 1),
+				other => other,
+			};
+		}
+
+		self.select(i.saturating_sub(1));
 	}
 	// }}}
 
-	fn recalc_transforms(&mut self) {
+	// only for sanitizing, all properties must have the same length already
+	fn truncate_objects(&mut self, len: usize) {
+		self.name.truncate(len);
+		self.ty.truncate(len);
+		self.position.truncate(len);
+		self.rotation.truncate(len);
+		self.scale.truncate(len);
+
+		self.corner_radius.truncate(len);
+		self.flip_normals.truncate(len);
+		self.cap_radius.truncate(len);
+		self.double_sided.truncate(len);
+		self.mesh.truncate(len);
+		self.csg_op.truncate(len);
+		self.csg_other.truncate(len);
+		self.turntable.truncate(len);
+		self.turntable_speed.truncate(len);
+
+		self.uv_offset.truncate(len);
+		self.uv_scale.truncate(len);
+		self.uv_rotation.truncate(len);
+
+		self.mat_ty.truncate(len);
+		self.mat_color.truncate(len);
+		self.mat_albedo_mode.truncate(len);
+		self.mat_checker_color.truncate(len);
+		self.mat_checker_scale.truncate(len);
+		self.mat_ior.truncate(len);
+		self.mat_specular.truncate(len);
+		self.mat_roughness.truncate(len);
+		self.mat_metallic.truncate(len);
+		self.mat_emissive_strength.truncate(len);
+		self.mat_emissive_color.truncate(len);
+		self.mat_light_group.truncate(len);
+
+		self.transform.truncate(len);
+		self.inv_transform.truncate(len);
+		self.normal_transform.truncate(len);
+	}
+
+	// fill in per-object properties that are missing from older saves
+	pub fn migrate(&mut self) {
+		let len = self.len();
+		self.corner_radius.resize(len, 0.0);
+		self.flip_normals.resize(len, false);
+		self.cap_radius.resize(len, 0.5);
+		self.double_sided.resize(len, true);
+		self.mesh.resize(len, MeshData::default());
+		self.csg_op.resize(len, CsgOp::None);
+		self.csg_other.resize(len, None);
+		self.turntable.resize(len, false);
+		self.turntable_speed.resize(len, DEFAULT_TURNTABLE_SPEED);
+		self.uv_offset.resize(len, vec2(0.0, 0.0));
+		self.uv_scale.resize(len, vec2(1.0, 1.0));
+		self.uv_rotation.resize(len, 0.0);
+		self.mat_albedo_mode.resize(len, AlbedoMode::Solid);
+		self.mat_checker_color.resize(len, DEFAULT_CHECKER_COLOR);
+		self.mat_checker_scale.resize(len, DEFAULT_CHECKER_SCALE);
+		self.mat_metallic.resize(len, 1.0);
+		self.mat_light_group.resize(len, 0);
+
+		// lights used to give off their surface color
+		for i in self.mat_emissive_color.len()..len {
+			let color = self.mat_color.get(i).copied().unwrap_or([1.0, 1.0, 1.0]);
+			self.mat_emissive_color.push(color);
+		}
+		self.mat_emissive_color.truncate(len);
+
+		if self.light_groups.is_empty() {
+			self.light_groups.push(LightGroup::new("Default"));
+		}
+		self.light_groups.truncate(MAX_LIGHT_GROUPS);
+
+		// assignments to groups that don't exist go to the default group
+		let groups = self.light_groups.len() as u32;
+		for group in &mut self.mat_light_group {
+			if *group >= groups {
+				*group = 0;
+			}
+		}
+	}
+
+	pub fn recalc_transforms(&mut self) {
 		for i in 0..self.len() {
-			let pos = glm::translate(&identity(), &self.position[i]);
+			self.recalc_transform(i);
+		}
+	}
 
-			let mut rot = identity();
-			rot = glm::rotate_z(&rot, self.rotation[i].z);
-			rot = glm::rotate_y(&rot, self.rotation[i].y);
-			rot = glm::rotate_x(&rot, self.rotation[i].x);
+	pub fn recalc_transform(&mut self, i: usize) {
+		[
+			self.transform[i],
+			self.inv_transform[i],
+			self.normal_transform[i],
+		] = self.compute_transform(i);
+	}
 
-			let scl = glm::scale(&identity(), &self.scale[i]);
+	// transform, inverse and normal transform from the position, rotation and
+	// scale
+	fn compute_transform(&self, i: usize) -> [Mat4; 3] {
+		let pos = glm::translate(&identity(), &self.position[i]);
 
-			// rightmost transforms are applied first
-			// (due to how matrix multiplication works)
-			let mat = pos * rot * scl;
+		let mut rot = identity();
+		rot = glm::rotate_z(&rot, self.rotation[i].z);
+		rot = glm::rotate_y(&rot, self.rotation[i].y);
+		rot = glm::rotate_x(&rot, self.rotation[i].x);
 
-			self.transform[i] = mat;
-			self.inv_transform[i] = inverse(&mat);
+		let scl = glm::scale(&identity(), &self.scale[i]);
 
-			// normals are transformed:
-			// - without translation
-			// - with rotation
-			// - with inverted scale (reciprocal of scale factors)
-			self.normal_transform[i] = rot * inverse(&scl);
-		}
+		// rightmost transforms are applied first
+		// (due to how matrix multiplication works)
+		let mat = pos * rot * scl;
+
+		// normals are transformed:
+		// - without translation
+		// - with rotation
+		// - with inverted scale (reciprocal of scale factors)
+		[mat, inverse(&mat), rot * inverse(&scl)]
 	}
 
 	pub fn with_default_scene(mut self) -> Self {
@@ -516,15 +1991,38 @@ impl Scene {
 		self.new_object();
 		self.mat_roughness[self.selected] = 0.6;
 
-		// dark matte floor
+		// the floor is the ground plane from the world settings
+
+		self.migrate();
+		self.recalc_transforms();
+
+		self
+	}
+
+	// checks the self-intersection bias at both extremes: a 1000 unit floor
+	// shouldn't show acne and a 0.01 scale sphere shouldn't leak light. the
+	// floor is a plane, rays leaving a box from just inside it miss it anyway.
+	// it's matte, so that the sun is sampled from it. see
+	// `render::tests::bias_test_scene_has_no_acne`
+	pub fn with_bias_test_scene(mut self) -> Self {
+		self.new_object();
+		self.name[0] = "Floor".into();
+		self.ty[0] = ObjectType::Plane;
+		self.scale[0] = vec3(500.0, 1.0, 500.0);
+		self.mat_roughness[0] = 1.0;
+		self.mat_specular[0] = 0.0;
+
+		self.new_object();
+		self.name[1] = "Tiny sphere".into();
+		self.position[1] = vec3(0.0, 0.01, 0.0);
+		self.scale[1] = vec3(0.01, 0.01, 0.01);
+
 		self.new_object();
-		self.name[self.selected] = "Floor".to_string();
-		self.ty[self.selected] = ObjectType::Box;
-		self.position[self.selected] = vec3(0.0, -1.001, 0.0);
-		self.rotation[self.selected] = vec3(0.0, 0.0, 0.0);
-		self.scale[self.selected] = vec3(1000.0, 0.001, 1000.0);
-		self.mat_color[self.selected] = [0.1, 0.1, 0.1];
+		self.name[2] = "Sphere".into();
+		self.position[2] = vec3(2.0, 1.0, -2.0);
 
+		self.select(1);
+		self.migrate();
 		self.recalc_transforms();
 
 		self
@@ -540,3 +2038,294 @@ impl UpdateResponse for Scene {
 		self.response.changed |= changed;
 	}
 }
+
+// euler angles for `recalc_transforms` that turn local +Y onto `y` and local
+// +Z onto `z`, both unit length and perpendicular
+fn rotation_from_axes(y: Vec3, z: Vec3) -> Vec3 {
+	let x = glm::cross(&y, &z);
+	let m = glm::mat3(x.x, y.x, z.x, x.y, y.y, z.y, x.z, y.z, z.z);
+
+	// m = rz * ry * rx, the middle angle is from the bottom left. when it's
+	// a quarter turn, the other two turn around the same axis
+	let pitch = (-m[(2, 0)]).clamp(-1.0, 1.0).asin();
+	if m[(2, 0)].abs() < 1.0 - 1e-6 {
+		vec3(
+			m[(2, 1)].atan2(m[(2, 2)]),
+			pitch,
+			m[(1, 0)].atan2(m[(0, 0)]),
+		)
+	} else {
+		vec3(0.0, pitch, (-m[(0, 1)]).atan2(m[(1, 1)]))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use proptest::prelude::*;
+
+	use super::*;
+
+	// {{{ import
+	// numbered spheres, the n-th one at x = n
+	fn spheres(count: usize) -> Scene {
+		let mut scene = Scene::default();
+		for i in 0..count {
+			scene.new_object();
+			scene.position[i].x = i as f32;
+		}
+		scene
+	}
+
+	// the json of `scene` with every object property cut or padded to `len`
+	// objects, the padding repeats the last object. the UI can't make these
+	fn resized_json(scene: &Scene, len: usize) -> String {
+		let mut value = serde_json::to_value(scene).unwrap();
+		for (key, column) in value.as_object_mut().unwrap() {
+			let Some(column) = column.as_array_mut() else {
+				continue;
+			};
+			if key != "light_groups" && column.len() == scene.len() {
+				let last = column[scene.len() - 1].clone();
+				column.resize(len, last);
+			}
+		}
+		value.to_string()
+	}
+
+	#[test]
+	fn extra_objects_are_dropped() {
+		let (scene, warnings) =
+			Scene::from_json(&resized_json(&spheres(MAX_OBJECTS), MAX_OBJECTS + 10))
+				.unwrap();
+		assert_eq!(scene.len(), MAX_OBJECTS);
+		assert_eq!(scene.check_invariants(), Ok(()));
+		assert_eq!(scene.position[MAX_OBJECTS - 1].x, (MAX_OBJECTS - 1) as f32);
+		assert_eq!(warnings.len(), 1);
+		assert!(
+			warnings[0].starts_with("Dropped 10 objects"),
+			"{warnings:?}"
+		);
+	}
+
+	#[test]
+	fn full_scene_loads_without_warnings() {
+		let (scene, warnings) =
+			Scene::from_json(&resized_json(&spheres(MAX_OBJECTS), MAX_OBJECTS)).unwrap();
+		assert_eq!(scene.len(), MAX_OBJECTS);
+		assert!(warnings.is_empty(), "{warnings:?}");
+	}
+	// }}}
+
+	// {{{ selection
+	#[test]
+	fn importing_a_smaller_scene_clamps_the_selection() {
+		let mut scene = spheres(10);
+		scene.select(9);
+
+		// stored with the last of ten objects selected, but only three left
+		let (imported, _) = Scene::from_json(&resized_json(&scene, 3)).unwrap();
+		assert_eq!(imported.selected(), Some(2));
+
+		scene.replace_objects(imported);
+		scene.check_selection();
+		assert_eq!(scene.len(), 3);
+		assert_eq!(scene.selected(), Some(2));
+		assert_eq!(scene.check_invariants(), Ok(()));
+	}
+
+	#[test]
+	fn deleting_the_last_object_selects_the_one_before() {
+		let mut scene = spheres(3);
+		assert_eq!(scene.selected(), Some(2));
+
+		scene.delete_object();
+		scene.check_selection();
+		assert_eq!(scene.selected(), Some(1));
+		assert_eq!(scene.position[1].x, 1.0);
+
+		scene.delete_object();
+		scene.delete_object();
+		scene.check_selection();
+		assert_eq!(scene.len(), 0);
+		assert_eq!(scene.selected(), None);
+		assert_eq!(scene.check_invariants(), Ok(()));
+
+		// nothing to delete, and adding works from an empty scene
+		scene.delete_object();
+		scene.new_object();
+		assert_eq!(scene.selected(), Some(0));
+	}
+	// }}}
+
+	// {{{ arc array
+	// the copies go in as one change, or not at all
+	#[test]
+	fn arc_arrays_are_all_or_nothing() {
+		let mut scene = spheres(MAX_OBJECTS - 3);
+		scene.migrate();
+		scene.select(1);
+		scene.response.changed = false;
+		let before = serde_json::to_value(&scene).unwrap();
+
+		let arc = |count| ArcArray {
+			count,
+			..Default::default()
+		};
+		assert!(scene.array_on_arc(arc(4)).is_err());
+		assert_eq!(serde_json::to_value(&scene).unwrap(), before);
+		assert!(!scene.response.changed);
+
+		scene.array_on_arc(arc(3)).unwrap();
+		assert_eq!(scene.len(), MAX_OBJECTS);
+		assert_eq!(scene.selected(), Some(1));
+		assert!(scene.response.changed);
+		let names = &scene.name[MAX_OBJECTS - 3..];
+		assert_eq!(names, ["Sphere 1", "Sphere 2", "Sphere 3"]);
+		assert_eq!(scene.check_invariants(), Ok(()));
+	}
+	// }}}
+
+	// {{{ random edits
+	// what the scene window can do to the objects, on arbitrary indices that
+	// are wrapped into range like the UI's lists would have them
+	#[derive(Clone, Debug)]
+	enum Edit {
+		New,
+		Duplicate,
+		Delete,
+		Select(usize),
+		Rename(String),
+		Move([f32; 3], [f32; 3], [f32; 3]),
+		Link(CsgOp, usize),
+		Unlink,
+		AddLightGroup,
+		DeleteLightGroup(usize),
+		JoinLightGroup(usize),
+		ImportMesh(usize),  // triangles
+		ImportScene(usize), // objects, like pasting
+		Arc(usize, ArcFacing),
+		Rescale(f32),
+		Spin(f32),
+	}
+
+	fn edit() -> impl Strategy<Value = Edit> {
+		let vec = || prop::array::uniform3(-10.0f32..10.0);
+		let scale = prop::array::uniform3(0.1f32..5.0);
+		let csg_op = prop_oneof![
+			Just(CsgOp::Union),
+			Just(CsgOp::Intersect),
+			Just(CsgOp::Subtract),
+		];
+		let facing = prop_oneof![
+			Just(ArcFacing::Keep),
+			Just(ArcFacing::Center),
+			Just(ArcFacing::Tangent),
+		];
+		prop_oneof![
+			4 => Just(Edit::New),
+			3 => Just(Edit::Duplicate),
+			3 => Just(Edit::Delete),
+			3 => any::<usize>().prop_map(Edit::Select),
+			1 => ".{0,12}".prop_map(Edit::Rename),
+			2 => (vec(), vec(), scale).prop_map(|(p, r, s)| Edit::Move(p, r, s)),
+			3 => (csg_op, any::<usize>()).prop_map(|(op, j)| Edit::Link(op, j)),
+			1 => Just(Edit::Unlink),
+			1 => Just(Edit::AddLightGroup),
+			1 => any::<usize>().prop_map(Edit::DeleteLightGroup),
+			1 => any::<usize>().prop_map(Edit::JoinLightGroup),
+			1 => (1..40usize).prop_map(Edit::ImportMesh),
+			1 => (1..MAX_OBJECTS + 5).prop_map(Edit::ImportScene),
+			1 => (1..12usize, facing).prop_map(|(n, f)| Edit::Arc(n, f)),
+			1 => (0.1f32..4.0).prop_map(Edit::Rescale),
+			1 => (0.0f32..1.0).prop_map(Edit::Spin),
+		]
+	}
+
+	// a fan of triangles around the origin
+	fn fan(triangles: usize) -> MeshData {
+		let corners = (0..=triangles).map(|k| {
+			let a = k as f32 / triangles as f32 * std::f32::consts::PI;
+			[a.cos(), a.sin(), 0.0]
+		});
+		MeshData {
+			positions: std::iter::once([0.0; 3]).chain(corners).collect(),
+			triangles: (1..=triangles as u32).map(|k| [0, k, k + 1]).collect(),
+		}
+	}
+
+	fn apply(scene: &mut Scene, edit: Edit) {
+		let len = scene.len();
+		let i = scene.selected;
+		match edit {
+			Edit::New => scene.new_object(),
+			Edit::Duplicate => scene.duplicate_object(),
+			Edit::Delete => scene.delete_object(),
+			Edit::Select(j) => scene.select(j % len.max(1)),
+			Edit::Rename(name) if len > 0 => scene.name[i] = name,
+			Edit::Move(position, rotation, scale) if len > 0 => {
+				scene.position[i] = position.into();
+				scene.rotation[i] = rotation.into();
+				scene.scale[i] = scale.into();
+				scene.recalc_transform(i);
+			},
+			// the partner list leaves out the object itself
+			Edit::Link(op, j) if len > 1 && j % len != i => {
+				scene.csg_op[i] = op;
+				scene.csg_other[i] = Some(j % len);
+			},
+			Edit::Unlink if len > 0 => scene.csg_other[i] = None,
+			Edit::AddLightGroup if scene.light_groups.len() < MAX_LIGHT_GROUPS => {
+				let name = format!("Group {}", scene.light_groups.len());
+				scene.light_groups.push(LightGroup::new(name));
+			},
+			Edit::DeleteLightGroup(group) => {
+				scene.delete_light_group(group % scene.light_groups.len());
+			},
+			Edit::JoinLightGroup(group) if len > 0 => {
+				scene.mat_light_group[i] = (group % scene.light_groups.len()) as u32;
+			},
+			// errors leave the scene as it was
+			Edit::ImportMesh(triangles) => {
+				let _ = scene.import_mesh("fan", fan(triangles));
+			},
+			Edit::ImportScene(objects) => {
+				let source = if len > 0 { scene.clone() } else { spheres(1) };
+				let (imported, _) =
+					Scene::from_json(&resized_json(&source, objects)).unwrap();
+				scene.replace_objects(imported);
+			},
+			Edit::Arc(count, facing) => {
+				let _ = scene.array_on_arc(ArcArray {
+					count,
+					facing,
+					..Default::default()
+				});
+			},
+			Edit::Rescale(factor) => scene.rescale(factor),
+			Edit::Spin(dt) if len > 0 => {
+				scene.turntable[i] = true;
+				scene.advance_turntables(dt);
+			},
+			_ => {},
+		}
+	}
+
+	proptest! {
+		#![proptest_config(ProptestConfig::with_cases(64))]
+
+		#[test]
+		fn edits_keep_the_invariants(edits in prop::collection::vec(edit(), 1..80)) {
+			// where the app starts
+			let mut scene = Scene::default().with_default_scene();
+			for (step, edit) in edits.into_iter().enumerate() {
+				let description = format!("{edit:?}");
+				apply(&mut scene, edit);
+				scene.check_selection();
+				if let Err(e) = scene.check_invariants() {
+					return Err(TestCaseError::fail(format!("step {step}, {description}: {e}")));
+				}
+			}
+		}
+	}
+	// }}}
+}
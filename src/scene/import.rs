@@ -0,0 +1,986 @@
+// glTF 2.0 scenes (.gltf and .glb) dropped onto the window. Every node with a
+// mesh becomes a mesh object, its world transform becomes the position,
+// rotation and scale of the object. Shear can't be represented and is lost,
+// mirroring is baked into the mesh. Only vertex positions and the material
+// factors are read, textures are ignored.
+//
+// STL files from CAD programs come in here too, dropped or through the Import
+// STL button. They're single meshes with the default material.
+use base64::Engine;
+use nalgebra::{Matrix3, Rotation3};
+use nalgebra_glm::{self as glm, vec3, Mat4, Vec3};
+use serde::Deserialize;
+
+use super::{MaterialType, Scene, MAX_OBJECTS};
+use crate::{
+	mesh::{file_name, is_stl, MeshData, MAX_MESH_TRIANGLES},
+	util::{dropped_file_bytes, modal},
+};
+
+// {{{ file format
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Gltf {
+	scene: Option<usize>,
+	#[serde(default)]
+	scenes: Vec<GltfScene>,
+	#[serde(default)]
+	nodes: Vec<Node>,
+	#[serde(default)]
+	meshes: Vec<Mesh>,
+	#[serde(default)]
+	materials: Vec<Material>,
+	#[serde(default)]
+	accessors: Vec<Accessor>,
+	#[serde(default)]
+	buffer_views: Vec<BufferView>,
+	#[serde(default)]
+	buffers: Vec<Buffer>,
+}
+
+#[derive(Deserialize)]
+struct GltfScene {
+	#[serde(default)]
+	nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+	name: Option<String>,
+	mesh: Option<usize>,
+	#[serde(default)]
+	children: Vec<usize>,
+	matrix: Option<[f32; 16]>, // column-major, instead of the TRS properties
+	translation: Option<[f32; 3]>,
+	rotation: Option<[f32; 4]>, // quaternion, xyzw
+	scale: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct Mesh {
+	name: Option<String>,
+	primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+	attributes: Attributes,
+	indices: Option<usize>,
+	material: Option<usize>,
+	#[serde(default = "triangle_list")]
+	mode: u32,
+}
+
+fn triangle_list() -> u32 {
+	4
+}
+
+#[derive(Deserialize)]
+struct Attributes {
+	#[serde(rename = "POSITION")]
+	position: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+	#[serde(default)]
+	pbr_metallic_roughness: Pbr,
+	#[serde(default)]
+	emissive_factor: [f32; 3],
+	#[serde(default)]
+	extensions: MaterialExtensions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Pbr {
+	base_color_factor: [f32; 4],
+	metallic_factor: f32,
+	roughness_factor: f32,
+}
+
+impl Default for Pbr {
+	fn default() -> Self {
+		Self {
+			base_color_factor: [1.0; 4],
+			metallic_factor: 1.0,
+			roughness_factor: 1.0,
+		}
+	}
+}
+
+#[derive(Default, Deserialize)]
+struct MaterialExtensions {
+	#[serde(rename = "KHR_materials_emissive_strength")]
+	emissive_strength: Option<EmissiveStrength>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrength {
+	emissive_strength: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+	buffer_view: Option<usize>,
+	#[serde(default)]
+	byte_offset: usize,
+	component_type: u32,
+	count: usize,
+	#[serde(rename = "type")]
+	ty: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+	buffer: usize,
+	#[serde(default)]
+	byte_offset: usize,
+	byte_length: usize,
+	byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Buffer {
+	uri: Option<String>,
+}
+
+const COMPONENT_U8: u32 = 5121;
+const COMPONENT_U16: u32 = 5123;
+const COMPONENT_U32: u32 = 5125;
+const COMPONENT_F32: u32 = 5126;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4e4f534a;
+const GLB_CHUNK_BIN: u32 = 0x004e4942;
+// }}}
+
+// an object about to be added to the scene
+struct Imported {
+	name: String,
+	mesh: MeshData,
+	position: Vec3,
+	rotation: Vec3,
+	scale: Vec3,
+	material: Option<usize>,
+}
+
+// {{{ loading
+struct Loader {
+	gltf: Gltf,
+	buffers: Vec<Vec<u8>>,
+	objects: Vec<Imported>,
+	skipped_primitives: usize,
+}
+
+impl Loader {
+	// `path` is where the file is, external buffers are read relative to it
+	fn new(bytes: &[u8], path: Option<&std::path::Path>) -> Result<Self, String> {
+		let (json, mut bin) = if bytes.starts_with(GLB_MAGIC) {
+			split_glb(bytes)?
+		} else {
+			(bytes, None)
+		};
+		let gltf: Gltf = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+
+		let buffers = gltf
+			.buffers
+			.iter()
+			.map(|buffer| match &buffer.uri {
+				// only the first buffer can be the binary chunk
+				None => bin.take().ok_or_else(|| "a buffer has no data".to_owned()),
+				Some(uri) => load_uri(uri, path),
+			})
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self {
+			gltf,
+			buffers,
+			objects: Vec::new(),
+			skipped_primitives: 0,
+		})
+	}
+
+	fn load_scene(&mut self) -> Result<(), String> {
+		let roots = match self.gltf.scenes.get(self.gltf.scene.unwrap_or(0)) {
+			Some(scene) => scene.nodes.clone(),
+			// without scenes, every node that isn't a child is a root
+			None => {
+				let children: Vec<usize> = self
+					.gltf
+					.nodes
+					.iter()
+					.flat_map(|n| n.children.iter().copied())
+					.collect();
+				(0..self.gltf.nodes.len())
+					.filter(|i| !children.contains(i))
+					.collect()
+			},
+		};
+
+		for root in roots {
+			self.load_node(root, &glm::identity(), 0)?;
+		}
+		Ok(())
+	}
+
+	fn load_node(
+		&mut self,
+		index: usize,
+		parent: &Mat4,
+		depth: usize,
+	) -> Result<(), String> {
+		// also keeps cyclic files from overflowing the stack
+		if depth > 64 {
+			return Err("the node hierarchy is too deep".into());
+		}
+		let node = self
+			.gltf
+			.nodes
+			.get(index)
+			.ok_or("a node refers to a missing node")?;
+		let transform = parent * local_transform(node);
+		let children = node.children.clone();
+		let name = node.name.clone();
+
+		if let Some(mesh) = node.mesh {
+			self.load_mesh_object(mesh, name, &transform)?;
+		}
+		for child in children {
+			self.load_node(child, &transform, depth + 1)?;
+		}
+		Ok(())
+	}
+
+	fn load_mesh_object(
+		&mut self,
+		index: usize,
+		node_name: Option<String>,
+		transform: &Mat4,
+	) -> Result<(), String> {
+		let gltf_mesh = self
+			.gltf
+			.meshes
+			.get(index)
+			.ok_or("a node refers to a missing mesh")?;
+
+		// primitives are merged, the object takes the first one's material
+		let mut mesh = MeshData::default();
+		let mut material = None;
+		for primitive in &gltf_mesh.primitives {
+			let Some(position) = primitive.attributes.position else {
+				continue;
+			};
+			if primitive.mode != triangle_list() {
+				self.skipped_primitives += 1;
+				continue;
+			}
+
+			let positions = self.read_positions(position)?;
+			let indices = match primitive.indices {
+				Some(indices) => self.read_indices(indices)?,
+				None => (0..positions.len() as u32).collect(),
+			};
+			if indices.len() % 3 != 0 {
+				return Err("a triangle list has a partial triangle".into());
+			}
+			if indices.iter().any(|&i| i as usize >= positions.len()) {
+				return Err("a triangle refers to a missing vertex".into());
+			}
+
+			let offset = mesh.positions.len() as u32;
+			mesh.positions.extend(positions);
+			mesh.triangles.extend(
+				indices
+					.chunks(3)
+					.map(|t| [t[0], t[1], t[2]].map(|i| i + offset)),
+			);
+			material = material.or(primitive.material);
+		}
+		if mesh.is_empty() {
+			return Ok(());
+		}
+		if !mesh.is_valid() {
+			return Err("a mesh has invalid coordinates or indices".into());
+		}
+
+		let name = node_name
+			.or_else(|| gltf_mesh.name.clone())
+			.unwrap_or_else(|| format!("Mesh {index}"));
+		let object = place_mesh(name, mesh, transform, material)?;
+		self.objects.push(object);
+		Ok(())
+	}
+
+	// bytes of every element of an accessor
+	fn elements(&self, index: usize, size: usize) -> Result<Vec<&[u8]>, String> {
+		let accessor = self
+			.gltf
+			.accessors
+			.get(index)
+			.ok_or("a mesh refers to a missing accessor")?;
+		let view = accessor
+			.buffer_view
+			.and_then(|i| self.gltf.buffer_views.get(i))
+			.ok_or("sparse or missing accessor data isn't supported")?;
+		let buffer = self
+			.buffers
+			.get(view.buffer)
+			.ok_or("a buffer view refers to a missing buffer")?;
+		let data = view
+			.byte_offset
+			.checked_add(view.byte_length)
+			.and_then(|end| buffer.get(view.byte_offset..end))
+			.ok_or("a buffer view is out of bounds")?;
+
+		// elements can't overlap
+		let stride = view.byte_stride.unwrap_or(size);
+		if stride < size {
+			return Err("an accessor's stride is smaller than its elements".into());
+		}
+		// the count is checked before anything is allocated for it. every
+		// triangle has three corners
+		let count = accessor.count;
+		if count > 3 * MAX_MESH_TRIANGLES {
+			return Err(format!(
+				"an accessor has {count} elements, meshes can have at most \
+				 {MAX_MESH_TRIANGLES} triangles"
+			));
+		}
+		let end = count
+			.checked_sub(1)
+			.map_or(Some(0), |last| {
+				last
+					.checked_mul(stride)?
+					.checked_add(accessor.byte_offset)?
+					.checked_add(size)
+			})
+			.filter(|&end| end <= data.len());
+		if end.is_none() {
+			return Err("an accessor is out of bounds".into());
+		}
+
+		Ok(
+			(0..count)
+				.map(|i| {
+					let start = accessor.byte_offset + i * stride;
+					&data[start..start + size]
+				})
+				.collect(),
+		)
+	}
+
+	fn read_positions(&self, index: usize) -> Result<Vec<[f32; 3]>, String> {
+		let accessor = self
+			.gltf
+			.accessors
+			.get(index)
+			.ok_or("a mesh refers to a missing accessor")?;
+		if accessor.component_type != COMPONENT_F32 || accessor.ty != "VEC3" {
+			return Err("vertex positions need to be 3 floats".into());
+		}
+		let elements = self.elements(index, 12)?;
+		Ok(
+			elements
+				.iter()
+				.map(|e| [0, 4, 8].map(|o| f32::from_le_bytes(read_array(e, o))))
+				.collect(),
+		)
+	}
+
+	fn read_indices(&self, index: usize) -> Result<Vec<u32>, String> {
+		let accessor = self
+			.gltf
+			.accessors
+			.get(index)
+			.ok_or("a mesh refers to a missing accessor")?;
+		if accessor.ty != "SCALAR" {
+			return Err("indices need to be scalars".into());
+		}
+		let (size, read): (usize, fn(&[u8]) -> u32) = match accessor.component_type {
+			COMPONENT_U8 => (1, |e| e[0] as u32),
+			COMPONENT_U16 => (2, |e| u16::from_le_bytes(read_array(e, 0)) as u32),
+			COMPONENT_U32 => (4, |e| u32::from_le_bytes(read_array(e, 0))),
+			_ => return Err("indices need to be unsigned integers".into()),
+		};
+		Ok(self.elements(index, size)?.into_iter().map(read).collect())
+	}
+}
+
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+	bytes[offset..offset + N].try_into().unwrap()
+}
+
+// the JSON chunk and the binary chunk, if there is one
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<Vec<u8>>), String> {
+	let word = |offset: usize| {
+		bytes
+			.get(offset..offset + 4)
+			.map(|w| u32::from_le_bytes(read_array(w, 0)))
+			.ok_or_else(|| "the file is truncated".to_owned())
+	};
+	if word(4)? != 2 {
+		return Err("only glTF 2.0 is supported".into());
+	}
+
+	let mut json = None;
+	let mut bin = None;
+	let mut offset = 12;
+	while offset < bytes.len() {
+		let len = word(offset)? as usize;
+		let ty = word(offset + 4)?;
+		let chunk = bytes
+			.get(offset + 8..offset + 8 + len)
+			.ok_or("the file is truncated")?;
+		match ty {
+			GLB_CHUNK_JSON if json.is_none() => json = Some(chunk),
+			GLB_CHUNK_BIN if bin.is_none() => bin = Some(chunk.to_vec()),
+			_ => {},
+		}
+		offset += 8 + len;
+	}
+
+	Ok((json.ok_or("the file has no JSON chunk")?, bin))
+}
+
+fn load_uri(uri: &str, path: Option<&std::path::Path>) -> Result<Vec<u8>, String> {
+	if let Some(data) = uri.strip_prefix("data:") {
+		let (_, encoded) = data
+			.split_once(";base64,")
+			.ok_or("only base64 data URIs are supported")?;
+		return base64::engine::general_purpose::STANDARD
+			.decode(encoded)
+			.map_err(|e| e.to_string());
+	}
+
+	// the web only gets the dropped file itself
+	let dir = path
+		.and_then(|p| p.parent())
+		.ok_or_else(|| format!("can't read '{uri}' here, try a .glb file"))?;
+	std::fs::read(dir.join(uri)).map_err(|e| format!("'{uri}': {e}"))
+}
+// }}}
+
+// {{{ transforms
+fn local_transform(node: &Node) -> Mat4 {
+	if let Some(m) = node.matrix {
+		return glm::make_mat4(&m);
+	}
+	let [x, y, z] = node.translation.unwrap_or([0.0; 3]);
+	let [qx, qy, qz, qw] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+	let [sx, sy, sz] = node.scale.unwrap_or([1.0; 3]);
+
+	glm::translation(&vec3(x, y, z))
+		* glm::quat_to_mat4(&glm::quat(qx, qy, qz, qw))
+		* glm::scaling(&vec3(sx, sy, sz))
+}
+
+// splits the world transform into the object's position, rotation and scale.
+// the mesh is fitted into the unit cube like imported OBJ files, the fit is
+// moved into the transform
+fn place_mesh(
+	name: String,
+	mut mesh: MeshData,
+	transform: &Mat4,
+	material: Option<usize>,
+) -> Result<Imported, String> {
+	let mut linear: Matrix3<f32> = transform.fixed_view::<3, 3>(0, 0).clone_owned();
+	let translation = transform.fixed_view::<3, 1>(0, 3).clone_owned();
+
+	// a rotation can't mirror, flip the mesh instead
+	if linear.determinant() < 0.0 {
+		for p in &mut mesh.positions {
+			p[0] = -p[0];
+		}
+		let flipped = -linear.column(0);
+		linear.set_column(0, &flipped);
+	}
+
+	let (center, half) = mesh.fit_unit_cube()?;
+	let axes = vec3(
+		linear.column(0).norm(),
+		linear.column(1).norm(),
+		linear.column(2).norm(),
+	);
+	if axes.min() <= 0.0 {
+		return Err(format!("'{name}' has a scale of zero"));
+	}
+	let unscaled = linear * Matrix3::from_diagonal(&axes.map(|a| 1.0 / a));
+	// in f32, angles lose precision near a pitch of 90°
+	let rotation = Rotation3::from_matrix(&unscaled.cast::<f64>());
+	let (x, y, z) = rotation.euler_angles();
+
+	Ok(Imported {
+		name,
+		mesh,
+		position: linear * center + translation,
+		rotation: vec3(x as f32, y as f32, z as f32),
+		scale: axes * half,
+		material,
+	})
+}
+// }}}
+
+// {{{ importing
+impl Scene {
+	fn import_gltf(&mut self, name: &str, loader: Loader) -> Result<String, String> {
+		let count = loader.objects.len();
+		if count == 0 {
+			return Err("the file has no triangle meshes".into());
+		}
+		if self.len() + count > MAX_OBJECTS {
+			return Err(format!(
+				"the file has {count} mesh objects, but there's only room for {} \
+				 more (the limit is {MAX_OBJECTS} objects)",
+				MAX_OBJECTS - self.len()
+			));
+		}
+		let triangles: usize =
+			loader.objects.iter().map(|o| o.mesh.triangles.len()).sum();
+		if self.mesh_triangles() + triangles > MAX_MESH_TRIANGLES {
+			return Err(format!(
+				"the file has {triangles} triangles, but there's only room for {} \
+				 more (the limit is {MAX_MESH_TRIANGLES})",
+				MAX_MESH_TRIANGLES - self.mesh_triangles()
+			));
+		}
+
+		for object in loader.objects {
+			self.import_mesh(&object.name, object.mesh)?;
+			let i = self.len() - 1;
+			self.position[i] = object.position;
+			self.rotation[i] = object.rotation;
+			self.scale[i] = object.scale;
+
+			if let Some(material) =
+				object.material.and_then(|m| loader.gltf.materials.get(m))
+			{
+				self.apply_material(i, material);
+			}
+			self.recalc_transform(i);
+		}
+
+		let mut message = format!("Imported {count} objects from '{name}'");
+		if loader.skipped_primitives > 0 {
+			message += &format!(
+				", skipped {} parts that aren't triangle lists",
+				loader.skipped_primitives
+			);
+		}
+		Ok(message)
+	}
+
+	fn apply_material(&mut self, i: usize, material: &Material) {
+		let pbr = &material.pbr_metallic_roughness;
+		let [r, g, b, _] = pbr.base_color_factor;
+		self.mat_color[i] = [r, g, b];
+		self.mat_roughness[i] = pbr.roughness_factor.clamp(0.0, 1.0);
+
+		let strength = material
+			.extensions
+			.emissive_strength
+			.as_ref()
+			.map_or(1.0, |e| e.emissive_strength);
+		let emissive = Vec3::from(material.emissive_factor) * strength;
+		if emissive.max() > 0.0 {
+			// the brightest channel is the strength, the color is normalized
+			self.mat_ty[i] = MaterialType::Emissive;
+			self.mat_color[i] = (emissive / emissive.max()).into();
+			self.mat_emissive_color[i] = self.mat_color[i];
+			self.mat_emissive_strength[i] = emissive.max();
+		} else if pbr.metallic_factor > 0.0 {
+			self.mat_ty[i] = MaterialType::Metal;
+			self.mat_metallic[i] = pbr.metallic_factor.min(1.0);
+		}
+	}
+
+	pub(super) fn import_interface(&mut self, egui: &egui::Context) {
+		let error = &self.import_error;
+		modal(
+			egui,
+			"Import failed",
+			&mut self.import_modal,
+			|ui| {
+				ui.label(error);
+			},
+			|_| {},
+			|| {},
+		);
+	}
+
+	// the web can't open a file dialog, it only gets dropped files
+	pub(super) fn mesh_interface(&mut self, egui: &egui::Context) {
+		modal(
+			egui,
+			"Import mesh",
+			&mut self.mesh_modal,
+			|ui| {
+				ui.label("Drop an .obj or .stl file onto the window to import it.");
+			},
+			|_| {},
+			|| {},
+		);
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	pub(super) fn pick_mesh(&mut self) {
+		let Some(path) = rfd::FileDialog::new()
+			.set_title("Import mesh")
+			.add_filter("Meshes", &["obj", "stl"])
+			.pick_file()
+		else {
+			return;
+		};
+		let name = path.file_name().map_or_else(
+			|| path.to_string_lossy().into_owned(),
+			|n| n.to_string_lossy().into_owned(),
+		);
+		let imported = std::fs::read(&path)
+			.map_err(|e| e.to_string())
+			.and_then(|bytes| self.import_mesh_file(&name, &bytes));
+		self.finish_imports(vec![imported], false);
+	}
+
+	// OBJ or STL, by extension
+	fn import_mesh_file(
+		&mut self,
+		file_name: &str,
+		bytes: &[u8],
+	) -> Result<String, String> {
+		let lower = file_name.to_lowercase();
+		let (name, mesh) = if lower.ends_with(".obj") {
+			let mesh = std::str::from_utf8(bytes)
+				.map_err(|e| e.to_string())
+				.and_then(MeshData::from_obj);
+			(&file_name[..file_name.len() - ".obj".len()], mesh)
+		} else if lower.ends_with(".stl") {
+			let mesh = MeshData::from_stl(bytes);
+			(&file_name[..file_name.len() - ".stl".len()], mesh)
+		} else {
+			(
+				file_name,
+				Err("only OBJ and STL meshes are supported".to_owned()),
+			)
+		};
+		match mesh.and_then(|mesh| self.import_mesh(name, mesh)) {
+			Ok(()) => Ok(format!("Imported mesh '{name}'")),
+			Err(e) => Err(format!("'{file_name}' was not imported: {e}")),
+		}
+	}
+
+	// failed imports are shown in a modal, the rest as toasts
+	fn finish_imports(&mut self, results: Vec<Result<String, String>>, fit: bool) {
+		let (done, failed): (Vec<_>, Vec<_>) =
+			results.into_iter().partition(Result::is_ok);
+		let errors: Vec<String> = failed.into_iter().filter_map(Result::err).collect();
+
+		if !errors.is_empty() {
+			self.import_error = errors.join("\n");
+			self.import_modal = true;
+		}
+		if fit && !done.is_empty() {
+			self.open_fit_modal();
+		}
+		self
+			.pending_toasts
+			.extend(done.into_iter().filter_map(Result::ok));
+	}
+}
+
+// imports glTF and STL files dropped onto the window. failed imports leave the
+// scene unchanged
+pub fn import_dropped(egui: &egui::Context, scene: &mut Scene) {
+	let files = egui.input(|i| i.raw.dropped_files.clone());
+
+	let scenes: Vec<_> = files
+		.iter()
+		.filter(|f| is_gltf(f))
+		.map(|file| {
+			let name = file_name(file);
+			dropped_file_bytes(file)
+				.and_then(|bytes| Loader::new(&bytes, file.path.as_deref()))
+				.and_then(|mut loader| {
+					loader.load_scene()?;
+					Ok(loader)
+				})
+				.and_then(|loader| scene.import_gltf(&name, loader))
+				.map_err(|e| format!("'{name}' was not imported: {e}"))
+		})
+		.collect();
+	// imported scenes can be at any scale, single meshes are unit sized
+	scene.finish_imports(scenes, true);
+
+	let meshes: Vec<_> = files
+		.iter()
+		.filter(|f| is_stl(f))
+		.map(|file| {
+			let name = file_name(file);
+			dropped_file_bytes(file)
+				.and_then(|bytes| scene.import_mesh_file(&name, &bytes))
+		})
+		.collect();
+	scene.finish_imports(meshes, false);
+}
+
+// dropped files with these extensions are imported as scenes
+pub fn is_gltf(file: &egui::DroppedFile) -> bool {
+	let name = file_name(file).to_lowercase();
+	name.ends_with(".gltf") || name.ends_with(".glb")
+}
+// }}}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::scene::ObjectType;
+
+	const TRIANGLE_OBJ: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+	const TRIANGLE_STL: &str = "solid t
+facet normal 0 0 1
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+vertex 0 1 0
+endloop
+endfacet
+endsolid t
+";
+
+	// {{{ accessors
+	// one accessor on one view of a zeroed buffer
+	fn loader(accessor: &str, view: &str, buffer_len: usize) -> Loader {
+		let json = format!(
+			r#"{{ "accessors": [{accessor}], "bufferViews": [{view}], "buffers": [{{}}] }}"#
+		);
+		Loader {
+			gltf: serde_json::from_str(&json).unwrap(),
+			buffers: vec![vec![0; buffer_len]],
+			objects: Vec::new(),
+			skipped_primitives: 0,
+		}
+	}
+
+	#[test]
+	fn strided_elements_are_read() {
+		let loader = loader(
+			r#"{ "bufferView": 0, "byteOffset": 4, "componentType": 5126, "count": 3, "type": "VEC3" }"#,
+			r#"{ "buffer": 0, "byteOffset": 8, "byteLength": 56, "byteStride": 20 }"#,
+			64,
+		);
+		let elements = loader.elements(0, 12).unwrap();
+		assert_eq!(elements.len(), 3);
+		assert!(elements.iter().all(|e| e.len() == 12));
+
+		// the last element ends at the end of the view
+		let data = &loader.buffers[0][8..64];
+		let last = elements[2].as_ptr() as usize - data.as_ptr() as usize;
+		assert_eq!(last + 12, data.len());
+	}
+
+	#[test]
+	fn empty_accessors_have_no_elements() {
+		let loader = loader(
+			r#"{ "bufferView": 0, "componentType": 5126, "count": 0, "type": "VEC3" }"#,
+			r#"{ "buffer": 0, "byteLength": 0 }"#,
+			0,
+		);
+		assert_eq!(loader.elements(0, 12).unwrap().len(), 0);
+	}
+
+	#[test]
+	fn overlapping_elements_are_rejected() {
+		let loader = loader(
+			r#"{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3" }"#,
+			r#"{ "buffer": 0, "byteLength": 64, "byteStride": 4 }"#,
+			64,
+		);
+		assert!(loader.elements(0, 12).unwrap_err().contains("stride"));
+	}
+
+	#[test]
+	fn out_of_bounds_accessors_are_rejected() {
+		let cases = [
+			// one element too many
+			(r#""count": 5"#, r#""byteLength": 48"#),
+			// offsets past the end of the view or the buffer
+			(r#""count": 1, "byteOffset": 40"#, r#""byteLength": 48"#),
+			(r#""count": 1"#, r#""byteOffset": 60, "byteLength": 12"#),
+			// sums that overflow
+			(
+				r#""count": 1"#,
+				r#""byteOffset": 8, "byteLength": 18446744073709551615"#,
+			),
+			(
+				r#""count": 1, "byteOffset": 18446744073709551615"#,
+				r#""byteLength": 48"#,
+			),
+		];
+		for (accessor, view) in cases {
+			let loader = loader(
+				&format!(
+					r#"{{ "bufferView": 0, "componentType": 5126, "type": "VEC3", {accessor} }}"#
+				),
+				&format!(r#"{{ "buffer": 0, {view} }}"#),
+				64,
+			);
+			let error = loader.elements(0, 12).unwrap_err();
+			assert!(
+				error.contains("out of bounds"),
+				"{accessor}, {view}: {error}"
+			);
+		}
+	}
+
+	// a count that can't fit would otherwise be allocated, or overflow the
+	// offsets
+	#[test]
+	fn huge_counts_are_rejected_before_allocating() {
+		for count in [3 * MAX_MESH_TRIANGLES + 1, usize::MAX / 2, usize::MAX] {
+			let loader = loader(
+				&format!(
+					r#"{{ "bufferView": 0, "componentType": 5126, "count": {count}, "type": "VEC3" }}"#
+				),
+				r#"{ "buffer": 0, "byteLength": 64, "byteStride": 12 }"#,
+				64,
+			);
+			assert!(loader.elements(0, 12).is_err());
+		}
+	}
+	// }}}
+
+	// {{{ transforms
+	// an off-center tetrahedron
+	fn tetrahedron() -> MeshData {
+		MeshData {
+			positions: vec![
+				[1.0, 2.0, 3.0],
+				[3.0, 2.0, 3.0],
+				[1.0, 5.0, 3.0],
+				[1.0, 2.0, 4.0],
+			],
+			triangles: vec![[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]],
+		}
+	}
+
+	// the placed object has to put every vertex where the file's transform
+	// did
+	fn assert_placed(transform: &Mat4) {
+		let original = tetrahedron();
+		let imported =
+			place_mesh("t".to_owned(), original.clone(), transform, None).unwrap();
+
+		let mut scene = Scene::default();
+		scene.new_object();
+		scene.position[0] = imported.position;
+		scene.rotation[0] = imported.rotation;
+		scene.scale[0] = imported.scale;
+		let [object, ..] = scene.compute_transform(0);
+
+		for (before, after) in original.positions.iter().zip(&imported.mesh.positions) {
+			let want = transform * glm::vec4(before[0], before[1], before[2], 1.0);
+			let got = object * glm::vec4(after[0], after[1], after[2], 1.0);
+			assert!((want - got).norm() < 1e-4, "{want:?} != {got:?}");
+		}
+	}
+
+	fn trs(translation: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Mat4 {
+		local_transform(&Node {
+			name: None,
+			mesh: None,
+			children: Vec::new(),
+			matrix: None,
+			translation: Some(translation),
+			rotation: Some(rotation),
+			scale: Some(scale),
+		})
+	}
+
+	#[test]
+	fn identity_is_placed() {
+		assert_placed(&glm::identity());
+	}
+
+	#[test]
+	fn trs_is_placed() {
+		let q = glm::quat_normalize(&glm::quat(0.3, -0.5, 0.2, 0.8));
+		assert_placed(&trs(
+			[1.0, -2.0, 0.5],
+			[q.i, q.j, q.k, q.w],
+			[2.0, 0.5, 3.0],
+		));
+		// a quarter turn about y, euler angles near the poles
+		let h = std::f32::consts::FRAC_1_SQRT_2;
+		assert_placed(&trs([0.0; 3], [0.0, h, 0.0, h], [1.0; 3]));
+	}
+
+	#[test]
+	fn mirrored_nodes_are_placed() {
+		assert_placed(&trs(
+			[0.0, 1.0, 0.0],
+			[0.0, 0.0, 0.0, 1.0],
+			[-1.0, 1.0, 1.0],
+		));
+		assert_placed(&trs([0.0; 3], [0.0, 0.0, 0.0, 1.0], [1.0, -2.0, -3.0]));
+		assert_placed(&trs([0.0; 3], [0.0, 0.0, 0.0, 1.0], [-1.0, -1.0, -1.0]));
+	}
+
+	#[test]
+	fn matrices_match_trs() {
+		let q = glm::quat_normalize(&glm::quat(0.1, 0.7, -0.2, 0.6));
+		let expected = trs([3.0, 0.0, -1.0], [q.i, q.j, q.k, q.w], [1.0, 2.0, 0.5]);
+		let matrix = local_transform(&Node {
+			name: None,
+			mesh: None,
+			children: Vec::new(),
+			matrix: Some(expected.as_slice().try_into().unwrap()),
+			translation: Some([9.0; 3]), // ignored next to a matrix
+			rotation: None,
+			scale: None,
+		});
+		assert_eq!(matrix, expected);
+		assert_placed(&matrix);
+	}
+
+	#[test]
+	fn zero_scale_is_rejected() {
+		let flat = trs([0.0; 3], [0.0, 0.0, 0.0, 1.0], [1.0, 0.0, 1.0]);
+		assert!(place_mesh("flat".to_owned(), tetrahedron(), &flat, None).is_err());
+	}
+	// }}}
+
+	// {{{ mesh files
+	#[test]
+	fn mesh_files_are_imported_by_extension() {
+		let mut scene = Scene::default();
+		let obj = scene.import_mesh_file("tri.OBJ", TRIANGLE_OBJ.as_bytes());
+		assert_eq!(obj, Ok("Imported mesh 'tri'".to_owned()));
+		let stl = scene.import_mesh_file("flat.stl", TRIANGLE_STL.as_bytes());
+		assert_eq!(stl, Ok("Imported mesh 'flat'".to_owned()));
+
+		assert_eq!(scene.len(), 2);
+		assert!(scene.ty.iter().all(|ty| *ty == ObjectType::Mesh));
+		assert_eq!(scene.name, ["tri", "flat"]);
+		assert_eq!(scene.mesh_triangles(), 2);
+	}
+
+	#[test]
+	fn other_mesh_files_are_rejected() {
+		let mut scene = Scene::default();
+		// OBJ text in an STL file and the other way around
+		assert!(scene
+			.import_mesh_file("a.stl", TRIANGLE_OBJ.as_bytes())
+			.is_err());
+		assert!(scene
+			.import_mesh_file("b.obj", TRIANGLE_STL.as_bytes())
+			.is_err());
+		assert!(scene
+			.import_mesh_file("c.ply", TRIANGLE_OBJ.as_bytes())
+			.is_err());
+		assert_eq!(scene.len(), 0);
+	}
+	// }}}
+}
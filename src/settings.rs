@@ -1,6 +1,13 @@
 use egui::Slider;
+use nalgebra_glm as glm;
 
-use crate::util::{AngleControl, Reset, UpdateResponse};
+use crate::{
+	camera::{Projection, Stereo},
+	controls,
+	render::{RenderStats, Upload},
+	sky::SkyColors,
+	util::{Reset, UpdateResponse},
+};
 
 // {{{ state
 #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -8,25 +15,54 @@ use crate::util::{AngleControl, Reset, UpdateResponse};
 pub struct Settings {
 	pub world: WorldSettings,
 	pub render: RenderSettings,
+	pub post: PostSettings,
+	pub controls: ControlSettings,
+
+	// the settings window, the tab is kept across restarts
+	tab: SettingsTab,
+	#[serde(skip)]
+	search: String,
 
 	#[serde(skip)]
 	pub response: SettingsResponse,
 
 	#[serde(skip)]
 	data_modal: bool,
+	#[serde(skip)]
+	rebinding_restart: bool,
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct WorldSettings {
-	pub sun_size: f32,
+	pub sun_size: f32, // angular diameter in degrees, 0 is a point
 	pub sun_strength: f32,
 	pub sun_rotation: f32,
 	pub sun_elevation: f32,
 	pub sun_color: [f32; 3],
 	pub sky_color: [f32; 3],
+	pub lock_sun_to_camera: bool,
+
+	// constant light added where rays hit a surface, so that shadows don't go
+	// black. it's not physical and the sky doesn't show it
+	pub fill_color: [f32; 3],
+	pub fill_strength: f32, // 0 disables it
+
+	// derive the sky and sun colors from the sun elevation instead
+	pub physical_sky: bool,
+	pub haze: f32,
+
+	// older saves model the floor as a box, so they start without the plane
+	#[serde(default)]
+	pub ground_plane: bool,
+	pub ground_height: f32,
+	pub ground_color: [f32; 3],
+	pub ground_roughness: f32,
 }
 
+// degrees, a disc much larger than this stops looking like a sun
+const MAX_SUN_SIZE: f32 = 20.0;
+
 impl Default for WorldSettings {
 	fn default() -> Self {
 		Self {
@@ -36,6 +72,15 @@ impl Default for WorldSettings {
 			sun_elevation: 45.0_f32.to_radians(),
 			sun_color: [0.0, 0.0, 0.0],
 			sky_color: [0.6, 0.6, 0.6],
+			lock_sun_to_camera: false,
+			fill_color: [1.0, 1.0, 1.0],
+			fill_strength: 0.0,
+			physical_sky: false,
+			haze: 0.2,
+			ground_plane: true,
+			ground_height: -1.0,
+			ground_color: [0.1, 0.1, 0.1],
+			ground_roughness: 1.0,
 		}
 	}
 }
@@ -44,24 +89,399 @@ impl Default for WorldSettings {
 #[serde(default)]
 pub struct RenderSettings {
 	pub fov: f32,
+	pub fov_as_focal_length: bool, // only changes how the fov is shown
+	pub projection: Projection,
+	pub aperture: f32, // depth of field, copied to the camera like the fov
+	pub focal_distance: f32,
 	pub mode: RenderMode,
 	pub accumulate: bool,
 	pub samples_per_frame: u32,
+	pub max_samples: u32, // accumulation stops here, 0 never stops
+	#[serde(skip)]
+	pub paused: bool, // no new samples until something changes
 	pub highlight: bool,
+	#[serde(skip)]
+	pub solo: bool, // hide everything but the selected object from the camera
+	pub solo_lighting: bool, // hidden objects still cast light and shadows
 	pub lock_camera: bool,
 	pub max_bounces: u32,
+	pub roughness_multiplier: f32, // temporary viewing aid
+	pub ray_bias: f32,             // multiplier for the self-intersection bias
+	pub firefly_clamp: f32,        // max luminance of a sample, 0 disables
+	pub debug_palette: DebugPalette,
+	pub hover_tooltip: bool,
+	pub pixel_inspector: bool,
+	pub labels: bool,
+	pub label_occlusion: bool,
+	pub labels_in_screenshots: bool,
+	pub show_gizmo: bool,
+	pub freeze_and_continue: bool,
+	pub freeze_weight: u32,
+	pub aspect: AspectLock,
+	pub guides: CompositionGuides,
+	pub lod_aggressiveness: f32, // 0 disables LOD
+	pub tonemap: Tonemap,
+	pub exposure: f32, // stops, applied before tonemapping
+	pub stereo: bool,  // side by side, see camera::Stereo
+	pub stereo_separation: f32,
+	pub stereo_convergence: f32,
 }
 
 impl Default for RenderSettings {
 	fn default() -> Self {
 		Self {
 			fov: crate::camera::DEFAULT_FOV_DEG.to_radians(),
+			fov_as_focal_length: false,
+			projection: Projection::default(),
+			aperture: 0.0,
+			focal_distance: crate::camera::DEFAULT_FOCAL_DISTANCE,
 			mode: RenderMode::default(),
 			accumulate: true,
 			samples_per_frame: 1,
+			max_samples: 0,
+			paused: false,
 			highlight: false,
+			solo: false,
+			solo_lighting: true,
 			lock_camera: false,
 			max_bounces: 5,
+			roughness_multiplier: 1.0,
+			ray_bias: 1.0,
+			firefly_clamp: 0.0,
+			debug_palette: DebugPalette::default(),
+			hover_tooltip: false,
+			pixel_inspector: false,
+			labels: false,
+			label_occlusion: true,
+			labels_in_screenshots: false,
+			show_gizmo: true,
+			freeze_and_continue: false,
+			freeze_weight: 8,
+			aspect: AspectLock::default(),
+			guides: CompositionGuides::default(),
+			lod_aggressiveness: 0.0,
+			tonemap: Tonemap::default(),
+			exposure: 0.0,
+			stereo: false,
+			stereo_separation: 0.1,
+			stereo_convergence: 5.0,
+		}
+	}
+}
+
+// {{{ debug palette
+// false colors used by the debug render modes, see palette.glsl
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[repr(u32)]
+pub enum DebugPalette {
+	#[default]
+	Standard = 0,
+	Deuteranopia = 1,
+	Grayscale = 2,
+}
+
+impl std::fmt::Display for DebugPalette {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Standard => write!(f, "Standard"),
+			Self::Deuteranopia => write!(f, "Deuteranopia-safe"),
+			Self::Grayscale => write!(f, "Grayscale"),
+		}
+	}
+}
+// }}}
+
+// {{{ tonemapping
+// HDR to LDR in the final pass, see final.glsl. the debug views skip it
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[repr(u32)]
+pub enum Tonemap {
+	None = 0, // clipped
+	Reinhard = 1,
+	#[default]
+	AcesFilmic = 2,
+}
+
+impl std::fmt::Display for Tonemap {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::None => write!(f, "None (clip)"),
+			Self::Reinhard => write!(f, "Reinhard"),
+			Self::AcesFilmic => write!(f, "ACES filmic"),
+		}
+	}
+}
+// }}}
+
+// {{{ grading
+// hue, saturation, value and contrast of the finished image, see color.glsl.
+// only the final pass applies them, so changing them keeps the accumulation
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PostSettings {
+	pub hue_shift: f32, // degrees
+	pub saturation: f32,
+	pub value: f32,
+	pub contrast: f32,
+	pub in_exports: bool, // off leaves it out of screenshots and final renders
+}
+
+impl Default for PostSettings {
+	fn default() -> Self {
+		Self {
+			hue_shift: 0.0,
+			saturation: 1.0,
+			value: 1.0,
+			contrast: 1.0,
+			in_exports: true,
+		}
+	}
+}
+
+impl PostSettings {
+	pub const NEUTRAL: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+
+	pub fn is_neutral(&self) -> bool {
+		self.grading() == Self::NEUTRAL
+	}
+
+	// the `grading` uniform of final.glsl, neutral for images that are
+	// exported without it
+	pub fn uniform(&self, exporting: bool) -> [f32; 4] {
+		if exporting && !self.in_exports {
+			Self::NEUTRAL
+		} else {
+			self.grading()
+		}
+	}
+
+	fn grading(&self) -> [f32; 4] {
+		[
+			self.hue_shift / 360.0,
+			self.saturation,
+			self.value,
+			self.contrast,
+		]
+	}
+
+	fn reset_grading(&mut self) {
+		*self = Self {
+			in_exports: self.in_exports,
+			..Self::default()
+		};
+	}
+}
+// }}}
+
+// {{{ aspect lock
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub enum AspectLock {
+	#[default]
+	Window,
+	Widescreen,
+	Square,
+	Anamorphic,
+	Panorama,
+}
+
+impl AspectLock {
+	// width / height, None follows the window
+	pub fn ratio(self) -> Option<f32> {
+		match self {
+			Self::Window => None,
+			Self::Widescreen => Some(16.0 / 9.0),
+			Self::Square => Some(1.0),
+			Self::Anamorphic => Some(2.39),
+			Self::Panorama => Some(2.0),
+		}
+	}
+}
+
+impl std::fmt::Display for AspectLock {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Window => write!(f, "Fit window"),
+			Self::Widescreen => write!(f, "16:9"),
+			Self::Square => write!(f, "1:1"),
+			Self::Anamorphic => write!(f, "2.39:1"),
+			Self::Panorama => write!(f, "2:1 (panorama)"),
+		}
+	}
+}
+
+// overlays drawn over the viewport, they don't show up in the render
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CompositionGuides {
+	pub thirds: bool,
+	pub center: bool,
+	pub safe_area: bool,
+}
+// }}}
+
+impl WorldSettings {
+	// the colors that are rendered, the zenith also lights the scene as
+	// ambient light. without the physical sky the sky is a single color
+	pub fn sky_colors(&self) -> SkyColors {
+		if self.physical_sky {
+			crate::sky::derive(self.sun_elevation, self.haze)
+		} else {
+			SkyColors {
+				zenith: self.sky_color,
+				horizon: self.sky_color,
+				sun: self.sun_color,
+			}
+		}
+	}
+
+	// the sun disc is everything within this of the sun direction
+	pub fn sun_cos_radius(&self) -> f32 {
+		(self.sun_size * 0.5).to_radians().cos()
+	}
+
+	// a sun locked to the camera follows its yaw
+	pub fn sun_dir(&self, camera_yaw: f32) -> glm::Vec3 {
+		let rotation = if self.lock_sun_to_camera {
+			self.sun_rotation + camera_yaw
+		} else {
+			self.sun_rotation
+		};
+
+		let beta_cos = self.sun_elevation.cos();
+		glm::normalize(&glm::vec3(
+			rotation.cos() * beta_cos,
+			self.sun_elevation.sin(),
+			rotation.sin() * beta_cos,
+		))
+	}
+}
+
+// {{{ GPU-dependent defaults
+// renderer string fragments (lowercase) used to guess the class of GPU
+const LOW_END_RENDERERS: &[&str] = &[
+	// integrated
+	"intel",
+	"iris",
+	"uhd graphics",
+	"hd graphics",
+	"radeon(tm) graphics", // AMD APUs
+	"radeon graphics",
+	"vega 8",
+	"apple",
+	// mobile
+	"mali",
+	"adreno",
+	"powervr",
+	"videocore",
+	// software
+	"llvmpipe",
+	"softpipe",
+	"swiftshader",
+	"software",
+];
+const HIGH_END_RENDERERS: &[&str] = &[
+	"nvidia",
+	"geforce",
+	"quadro",
+	"rtx",
+	"radeon rx",
+	"radeon pro",
+];
+
+const LOW_END_MAX_BOUNCES: u32 = 3;
+const HIGH_END_MAX_BOUNCES: u32 = 8;
+
+impl RenderSettings {
+	// Picks a max_bounces default for the first launch based on the GL renderer
+	// string. The heuristic is deliberately simple:
+	// - integrated, mobile and software renderers get a conservative default,
+	//   since heavy glass scenes can bring them to a crawl
+	// - known discrete cards get a higher default
+	// - anything unrecognized (including masked WebGL renderer strings) keeps
+	//   the regular default
+	// Low-end matches take priority, so e.g. hybrid strings that mention both
+	// an Intel and an NVIDIA device stay on the safe side. Persisted settings
+	// always override this, so the user's choice is never touched.
+	pub fn default_max_bounces(gl_renderer: &str) -> u32 {
+		let renderer = gl_renderer.to_lowercase();
+
+		if LOW_END_RENDERERS.iter().any(|s| renderer.contains(s)) {
+			LOW_END_MAX_BOUNCES
+		} else if HIGH_END_RENDERERS.iter().any(|s| renderer.contains(s)) {
+			HIGH_END_MAX_BOUNCES
+		} else {
+			Self::default().max_bounces
+		}
+	}
+
+	// whether samples are actually accumulated in the current render mode
+	pub fn accumulating(&self) -> bool {
+		self.accumulate && self.mode.is_noisy()
+	}
+
+	// copied to the camera like the fov, there's no stereo panorama
+	pub fn stereo_rig(&self) -> Option<Stereo> {
+		let perspective = self.projection == Projection::Perspective;
+		(self.stereo && perspective).then_some(Stereo {
+			separation: self.stereo_separation,
+			convergence: self.stereo_convergence,
+		})
+	}
+
+	// whether the image has all the samples it's allowed, nothing is rendered
+	// then until something changes or the cap is raised
+	pub fn converged(&self, frame_index: u32) -> bool {
+		let samples = frame_index.saturating_sub(1) * self.samples_per_frame;
+		self.accumulating() && self.max_samples > 0 && samples >= self.max_samples
+	}
+
+	// nothing new to render, only the interface is redrawn on input
+	pub fn idle(&self, frame_index: u32) -> bool {
+		self.paused || self.converged(frame_index)
+	}
+}
+// }}}
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ControlSettings {
+	// walk mode keeps the camera at a fixed height above y=0 and disables
+	// vertical movement
+	pub walk_mode: bool,
+	pub eye_height: f32,
+	pub clamp_pitch: bool,
+	pub restart_key: egui::Key,
+
+	// stop in front of objects instead of flying through them
+	pub collision: bool,
+
+	// handheld wobble, amplitude in world units and frequency in Hz
+	pub shake: bool,
+	pub shake_amplitude: f32,
+	pub shake_frequency: f32,
+
+	// move the camera before drawing instead of after, for pen displays
+	pub low_latency: bool,
+}
+
+impl Default for ControlSettings {
+	fn default() -> Self {
+		Self {
+			walk_mode: false,
+			eye_height: 1.7,
+			clamp_pitch: true,
+			restart_key: egui::Key::Backspace,
+			collision: false,
+			shake: false,
+			shake_amplitude: 0.02,
+			shake_frequency: 0.7,
+			low_latency: false,
 		}
 	}
 }
@@ -81,6 +501,26 @@ pub enum RenderMode {
 	Roughness = 6,
 	RayDir = 7,
 	Noise = 8,
+	Wireframe = 9,
+	ObjectId = 10,
+	Uv = 11,
+	Lod = 12,
+	BvhHeatmap = 13,
+}
+
+impl RenderMode {
+	// noisy modes converge over multiple samples, the others are deterministic
+	// and look the same after a single one. the noise view shows the RNG state
+	// itself, so averaging it would be meaningless
+	pub fn is_noisy(self) -> bool {
+		matches!(self, Self::Realistic | Self::RayDir)
+	}
+
+	// everything but the two shaded views, their colors show values that
+	// exposure and tonemapping would distort
+	pub fn is_debug(self) -> bool {
+		!matches!(self, Self::Preview | Self::Realistic)
+	}
 }
 
 impl std::fmt::Display for RenderMode {
@@ -95,6 +535,11 @@ impl std::fmt::Display for RenderMode {
 			Self::Roughness => write!(f, "Roughness (debug)"),
 			Self::RayDir => write!(f, "Ray direction (debug)"),
 			Self::Noise => write!(f, "Noise (debug)"),
+			Self::Wireframe => write!(f, "Flat color and edges (debug)"),
+			Self::ObjectId => write!(f, "Object ID (debug)"),
+			Self::Uv => write!(f, "UV coordinates (debug)"),
+			Self::Lod => write!(f, "Level of detail (debug)"),
+			Self::BvhHeatmap => write!(f, "Mesh BVH visits (debug)"),
 		}
 	}
 }
@@ -105,8 +550,16 @@ impl std::fmt::Display for RenderMode {
 pub struct SettingsResponse {
 	pub focused: bool,
 	pub screenshot: bool,
+	pub final_render: bool,
 	pub save_data: bool,
 	pub clear_data: bool,
+	pub tutorial: bool,     // start the tour over
+	pub reset_layout: bool, // put the windows back where they start
+	pub reset_stats: bool,  // start counting uploads over
+
+	// restart accumulation without anything having changed
+	pub restart: bool,
+	pub presentation: bool,
 
 	pub changed: bool,
 }
@@ -116,8 +569,14 @@ impl Default for SettingsResponse {
 		Self {
 			focused: false,
 			screenshot: false,
+			final_render: false,
 			save_data: false,
 			clear_data: false,
+			tutorial: false,
+			reset_layout: false,
+			reset_stats: false,
+			restart: false,
+			presentation: false,
 			changed: true,
 		}
 	}
@@ -134,164 +593,1246 @@ impl Reset for SettingsResponse {
 // }}}
 
 impl Settings {
-	pub fn window(&mut self, egui: &egui::Context, frame_index: u32) {
+	// bring stored values back into the ranges the UI allows
+	pub fn sanitize(&mut self) {
+		let render = &mut self.render;
+		render.samples_per_frame = render.samples_per_frame.clamp(1, 32);
+		render.max_bounces = render.max_bounces.min(20);
+		render.freeze_weight = render.freeze_weight.clamp(1, 64);
+		render.roughness_multiplier = render.roughness_multiplier.clamp(0.0, 2.0);
+		render.ray_bias = render.ray_bias.clamp(0.1, 10.0);
+		render.firefly_clamp = render.firefly_clamp.clamp(0.0, 100.0);
+		render.lod_aggressiveness = render.lod_aggressiveness.clamp(0.0, 1.0);
+		render.aperture = render.aperture.clamp(0.0, 1.0);
+		render.focal_distance = render.focal_distance.clamp(0.1, 100.0);
+		render.exposure = render.exposure.clamp(-5.0, 5.0);
+		render.stereo_separation = render.stereo_separation.clamp(0.0, 1.0);
+		render.stereo_convergence = render.stereo_convergence.clamp(0.1, 100.0);
+		let post = &mut self.post;
+		post.hue_shift = post.hue_shift.clamp(-180.0, 180.0);
+		post.saturation = post.saturation.clamp(0.0, 2.0);
+		post.value = post.value.clamp(0.0, 2.0);
+		post.contrast = post.contrast.clamp(0.0, 2.0);
+		self.world.haze = self.world.haze.clamp(0.0, 1.0);
+		self.world.sun_size = self.world.sun_size.clamp(0.0, MAX_SUN_SIZE);
+		if !controls::fov_range().contains(&render.fov) {
+			render.fov = RenderSettings::default().fov;
+		}
+
+		let controls = &mut self.controls;
+		controls.shake_amplitude = controls.shake_amplitude.clamp(0.0, 0.2);
+		controls.shake_frequency = controls.shake_frequency.clamp(0.1, 5.0);
+	}
+	pub fn modal_open(&self) -> bool {
+		self.data_modal
+	}
+
+	pub fn window(&mut self, egui: &egui::Context, status: Status<'_>) {
 		egui::Window::new("Settings").show(egui, |ui| {
-			// {{{ performance stats
-			let frametime = ui.input(|i| i.unstable_dt);
+			self.status_lines(ui, &status);
+
 			ui.horizontal(|ui| {
+				ui.label("Search:");
+				let search = ui.text_edit_singleline(&mut self.search);
+				self.set_focused(search.has_focus());
+				if !self.search.is_empty() && ui.button("Clear").clicked() {
+					self.search.clear();
+				}
+			});
+
+			let search = self.search.trim().to_lowercase();
+			if search.is_empty() {
+				ui.horizontal(|ui| {
+					for tab in SettingsTab::ALL {
+						if ui
+							.selectable_label(self.tab == tab, tab.to_string())
+							.clicked()
+						{
+							self.tab = tab;
+						}
+					}
+				});
+				ui.separator();
+
+				let tab = self.tab;
+				for entry in ENTRIES.iter().filter(|e| e.tab == tab) {
+					(entry.draw)(self, ui, &status);
+				}
+			} else {
+				// every match, under the name of its tab
+				let mut found = false;
+				for tab in SettingsTab::ALL {
+					let mut matches = ENTRIES
+						.iter()
+						.filter(|e| e.tab == tab && e.label.to_lowercase().contains(&search))
+						.peekable();
+					if matches.peek().is_none() {
+						continue;
+					}
+
+					found = true;
+					ui.separator();
+					ui.strong(tab.to_string());
+					for entry in matches {
+						(entry.draw)(self, ui, &status);
+					}
+				}
+
+				if !found {
+					ui.label("No settings match");
+				}
+			}
+
+			// outside of the entries so that it stays open when switching tabs
+			crate::util::modal(
+				egui,
+				"Clear all data?",
+				&mut self.data_modal,
+				|ui| {
+					ui.label("This will delete:");
+					ui.label("- Scene objects and associated materials");
+					ui.label("- Camera parameters");
+					ui.label("- Saved settings");
+				},
+				crate::util::red_hover_button,
+				|| {
+					self.response.clear_data = true;
+				},
+			);
+		});
+	}
+
+	// performance stats and warnings, shown above the tabs
+	fn status_lines(&mut self, ui: &mut egui::Ui, status: &Status<'_>) {
+		let frametime = ui.input(|i| i.unstable_dt);
+		ui.horizontal(|ui| {
+			ui.label(format!(
+				"Frametime: {:.4}ms ({} FPS)",
+				(frametime * 1000.0),
+				(1.0 / frametime).round(),
+			));
+
+			if self.render.paused {
+				ui.label("(paused)");
+			} else if self.render.converged(status.frame_index) {
 				ui.label(format!(
-					"Frametime: {:.4}ms ({} FPS)",
-					(frametime * 1000.0),
-					(1.0 / frametime).round(),
+					"(converged at {} samples)",
+					self.render.max_samples
 				));
+			} else if self.render.accumulating() {
+				ui.label(format!(
+					"(sample {})",
+					status.frame_index * self.render.samples_per_frame
+				));
+			}
+
+			// easy to forget about, so make it obvious when it's on
+			if self.render.roughness_multiplier != 1.0 {
+				ui.colored_label(
+					ui.visuals().warn_fg_color,
+					format!("Roughness ×{:.2}", self.render.roughness_multiplier),
+				);
+			}
+			if !self.post.is_neutral() {
+				ui.colored_label(ui.visuals().warn_fg_color, "Graded")
+					.on_hover_text("Color grading is on, see the Post tab");
+			}
+		});
+
+		if let Some(([w, h], max)) = status.render_limit {
+			ui.colored_label(
+				ui.visuals().warn_fg_color,
+				format!("Render limited to {w}×{h}, the GPU allows up to {max}"),
+			);
+		}
+
+		if status.turntable && self.render.accumulating() {
+			ui.colored_label(
+				ui.visuals().warn_fg_color,
+				"A turntable is running, accumulation restarts every frame",
+			);
+		}
 
-				if self.render.mode == RenderMode::Realistic && self.render.accumulate {
-					ui.label(format!("(sample {})", frame_index * self.render.samples_per_frame));
+		if self.controls.shake && self.render.accumulating() && !self.render.lock_camera
+		{
+			ui.colored_label(
+				ui.visuals().warn_fg_color,
+				"Camera wobble is on, accumulation restarts every frame",
+			);
+		}
+
+		if let Some(name) = status.hovered {
+			ui.label(format!("Under cursor: {name}"));
+		}
+
+		egui::CollapsingHeader::new("GPU uploads")
+			.id_source("upload_stats")
+			.show(ui, |ui| self.upload_stats(ui, status.uploads));
+	}
+
+	// bytes sent to the GPU by kind, since the last reset
+	fn upload_stats(&mut self, ui: &mut egui::Ui, stats: &RenderStats) {
+		egui::Grid::new("upload_stats_grid")
+			.num_columns(5)
+			.striped(true)
+			.show(ui, |ui| {
+				ui.label("");
+				ui.label("Last frame");
+				ui.label("Per frame");
+				ui.label("Per change");
+				ui.label("Peak");
+				ui.end_row();
+
+				for upload in Upload::ALL {
+					let i = upload as usize;
+					ui.label(upload.to_string());
+					ui.label(format_bytes(stats.last_frame[i]));
+					ui.label(format_bytes(stats.per_frame(upload)));
+					ui.label(format_bytes(stats.per_change(upload)))
+						.on_hover_text(format!("{} changes", stats.changes[i]));
+					ui.label(format_bytes(stats.peak[i]));
+					ui.end_row();
 				}
 			});
-			// }}}
 
-			// {{{ world settings
-			ui.collapsing("World settings", |ui| {
+		ui.horizontal(|ui| {
+			ui.label(format!("{} frames", stats.frames));
+			if ui.button("Reset").clicked() {
+				self.response.reset_stats = true;
+			}
+		});
+	}
+}
+
+// {{{ tabs
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize,
+)]
+pub enum SettingsTab {
+	#[default]
+	World,
+	Render,
+	Post, // drawn over the finished image
+	Camera,
+	Controls,
+	System,
+}
+
+impl SettingsTab {
+	const ALL: [Self; 6] = [
+		Self::World,
+		Self::Render,
+		Self::Post,
+		Self::Camera,
+		Self::Controls,
+		Self::System,
+	];
+}
+
+impl std::fmt::Display for SettingsTab {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{self:?}")
+	}
+}
+// }}}
+
+// {{{ entries
+// Everything in the settings window below the status lines, in the order it's
+// shown. The search matches against `label`, so it should name every widget
+// the entry draws. Widgets that only make sense together (a checkbox and the
+// options it enables) share an entry.
+struct Entry {
+	tab: SettingsTab,
+	label: &'static str,
+	draw: fn(&mut Settings, &mut egui::Ui, &Status<'_>),
+}
+
+const ENTRIES: &[Entry] = &[
+	// {{{ world
+	Entry {
+		tab: SettingsTab::World,
+		label: "Physical sky haze",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(
+				&mut s.world.physical_sky,
+				"Physical sky (colors follow the sun elevation)",
+			);
+			s.update_response(checkbox);
+
+			if s.world.physical_sky {
+				ui.horizontal(|ui| {
+					ui.label("Haze:");
+					let slider = ui.add(controls::unit_slider(&mut s.world.haze));
+					s.update_response(slider);
+				});
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Sky color ambient light, sun color",
+		draw: |s, ui, _| {
+			// the derived colors are shown grayed out
+			let physical = s.world.physical_sky;
+			let mut derived = s.world.sky_colors();
+			ui.add_enabled_ui(!physical, |ui| {
 				ui.horizontal(|ui| {
 					ui.label("Sky color/Ambient light:");
-					let color = ui.color_edit_button_rgb(&mut self.world.sky_color);
-					self.update_response(color);
+					let color = if physical {
+						&mut derived.zenith
+					} else {
+						&mut s.world.sky_color
+					};
+					let color = crate::util::color_edit(ui, color);
+					s.update_response(color);
 				});
 
 				ui.horizontal(|ui| {
 					ui.label("Sun color:");
-					let color = ui.color_edit_button_rgb(&mut self.world.sun_color);
-					self.update_response(color);
+					let color = if physical {
+						&mut derived.sun
+					} else {
+						&mut s.world.sun_color
+					};
+					let color = crate::util::color_edit(ui, color);
+					s.update_response(color);
 				});
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Sun strength",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Sun strength:");
+				let slider = ui.add(Slider::new(&mut s.world.sun_strength, 0.0..=10.0));
+				s.update_response(slider);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Sun size (soft shadows)",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Sun size:");
+				let slider = ui
+					.add(
+						Slider::new(&mut s.world.sun_size, 0.0..=MAX_SUN_SIZE)
+							.suffix("°")
+							.logarithmic(true),
+					)
+					.on_hover_text(
+						"Angular diameter, larger suns cast softer shadows. \
+						 The real one is about 0.5°",
+					);
+				s.update_response(slider);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Sun elevation, sun rotation",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Sun elevation:");
+				let angle = ui.add(controls::angle_drag(ui, &mut s.world.sun_elevation));
+				s.update_response(angle);
+			});
+
+			ui.horizontal(|ui| {
+				ui.label("Sun rotation:");
+				let angle = ui.add(controls::angle_drag(ui, &mut s.world.sun_rotation));
+				s.update_response(angle);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Lock sun to camera",
+		draw: |s, ui, _| {
+			let checkbox =
+				ui.checkbox(&mut s.world.lock_sun_to_camera, "Lock sun to camera");
+			s.update_response(checkbox);
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Fill light color, fill light strength",
+		draw: |s, ui, _| {
+			ui.separator();
+
+			ui.horizontal(|ui| {
+				ui.label("Fill light color:");
+				let color = crate::util::color_edit(ui, &mut s.world.fill_color);
+				s.update_response(color);
+			});
+
+			ui.horizontal(|ui| {
+				ui.label("Fill light strength:");
+				let slider = ui
+					.add(Slider::new(&mut s.world.fill_strength, 0.0..=1.0))
+					.on_hover_text(
+						"Constant light on every surface so that shadows never go \
+						 black. Not physically based, the sky doesn't show it",
+					);
+				s.update_response(slider);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::World,
+		label: "Render ground plane, ground height, ground color, ground roughness",
+		draw: |s, ui, _| {
+			ui.separator();
+
+			let checkbox = ui.checkbox(&mut s.world.ground_plane, "Render ground plane");
+			s.update_response(checkbox);
 
+			if s.world.ground_plane {
 				ui.horizontal(|ui| {
-					ui.label("Sun strength:");
-					let slider =
-						ui.add(Slider::new(&mut self.world.sun_strength, 0.0..=10.0));
-					self.update_response(slider);
+					ui.label("Ground height:");
+					let drag =
+						ui.add(controls::position_drag(ui, &mut s.world.ground_height));
+					s.update_response(drag);
 				});
 
 				ui.horizontal(|ui| {
-					ui.label("Sun elevation:");
-					let angle = ui.drag_angle(&mut self.world.sun_elevation);
-					self.update_response(angle);
+					ui.label("Ground color:");
+					let color = crate::util::color_edit(ui, &mut s.world.ground_color);
+					s.update_response(color);
 				});
 
 				ui.horizontal(|ui| {
-					ui.label("Sun rotation:");
-					let angle = ui.drag_angle(&mut self.world.sun_rotation);
-					self.update_response(angle);
+					ui.label("Ground roughness:");
+					let slider =
+						ui.add(controls::roughness_slider(&mut s.world.ground_roughness));
+					s.update_response(slider);
 				});
+			}
+		},
+	},
+	// }}}
+	// {{{ render
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Render mode",
+		draw: |s, ui, _| s.render_mode_selector(ui),
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Debug palette",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Debug palette:");
+				egui::ComboBox::new("debug_palette_selector", "")
+					.selected_text(format!("{}", s.render.debug_palette))
+					.show_ui(
+						ui,
+						crate::selectable_values! {
+							target = s.render.debug_palette,
+							focused = s.response.focused,
+							changed = s.response.changed,
+							[
+								DebugPalette::Standard,
+								DebugPalette::Deuteranopia,
+								DebugPalette::Grayscale,
+							],
+						},
+					);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Accumulate samples",
+		draw: |s, ui, _| s.accumulate_checkbox(ui),
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Samples per frame",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Samples per frame");
+				let slider = ui.add(Slider::new(&mut s.render.samples_per_frame, 1..=32));
+				s.update_response(slider);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Pause rendering",
+		draw: |s, ui, _| {
+			// resuming continues the same image, so this isn't a change
+			let checkbox = ui
+				.checkbox(&mut s.render.paused, "Pause rendering")
+				.on_hover_text(
+					"Saves power, moving the camera or changing anything resumes",
+				);
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Stop after samples, reset accumulation",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Stop after");
+				// raising the cap continues where it stopped, so this doesn't
+				// count as a change
+				let drag = ui
+					.add(
+						egui::DragValue::new(&mut s.render.max_samples)
+							.speed(16)
+							.clamp_range(0..=u32::MAX),
+					)
+					.on_hover_text(
+						"Saves power once the image is good enough, 0 never stops",
+					);
+				s.set_focused(drag.has_focus());
+				ui.label("samples");
 			});
-			// }}}
 
-			// {{{ render settings
-			ui.collapsing("Render settings", |ui| {
+			if ui.button("Reset accumulation").clicked() {
+				s.response.restart = true;
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Continue from the current image after scene edits, weight",
+		draw: |s, ui, _| {
+			let checkbox = ui
+				.checkbox(
+					&mut s.render.freeze_and_continue,
+					"Continue from the current image after scene edits",
+				)
+				.on_hover_text(
+					"Biased while the image transitions to the new scene, \
+					 but converges to the correct result",
+				);
+			s.update_response(checkbox);
+
+			if s.render.freeze_and_continue {
 				ui.horizontal(|ui| {
-					ui.label("Render mode:");
-					// {{{ select render mode
-					egui::ComboBox::new("render_mode_selector", "")
-						.selected_text(format!("{}", self.render.mode))
-						.show_ui(
-							ui,
-							crate::selectable_values! {
-								target = self.render.mode,
-								focused = self.response.focused,
-								changed = self.response.changed,
-								[
-									RenderMode::Preview,
-									RenderMode::Realistic,
-									RenderMode::Position,
-									RenderMode::Normal,
-									RenderMode::Depth,
-									RenderMode::Fresnel,
-									RenderMode::Roughness,
-									RenderMode::RayDir,
-									RenderMode::Noise,
-								],
-							},
-						);
+					ui.label("Weight of the current image (samples):");
+					let slider = ui.add(Slider::new(&mut s.render.freeze_weight, 1..=64));
+					s.update_response(slider);
 				});
-				// }}}
-
-				{
-					let checkbox =
-						ui.checkbox(&mut self.render.accumulate, "Accumulate samples");
-					self.update_response(checkbox);
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Max ray bounces",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Max ray bounces:");
+				let slider = ui.add(Slider::new(&mut s.render.max_bounces, 0..=20));
+				s.update_response(slider);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Simplify distant objects (level of detail)",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Simplify distant objects:");
+				let slider = ui
+					.add(controls::unit_slider(&mut s.render.lod_aggressiveness))
+					.on_hover_text(
+						"Objects that are tiny on screen are skipped by bounced rays, \
+						 and left out entirely when even smaller",
+					);
+				s.update_response(slider);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Roughness multiplier",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Roughness multiplier:");
+				let slider = ui.add(
+					Slider::new(&mut s.render.roughness_multiplier, 0.0..=2.0).suffix("×"),
+				);
+				s.update_response(slider);
+				if ui.button("Reset").clicked() {
+					s.render.roughness_multiplier = 1.0;
+					s.set_changed(true);
 				}
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Ray bias",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Ray bias:");
+				let slider = ui
+					.add(
+						Slider::new(&mut s.render.ray_bias, 0.1..=10.0)
+							.logarithmic(true)
+							.suffix("×"),
+					)
+					.on_hover_text(
+						"Raise against speckled self-shadowing on large objects, \
+						 lower if light leaks through tiny ones",
+					);
+				s.update_response(slider);
+				if ui.button("Reset").clicked() {
+					s.render.ray_bias = 1.0;
+					s.set_changed(true);
+				}
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Firefly clamp",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Firefly clamp:");
+				let slider = ui
+					.add(
+						Slider::new(&mut s.render.firefly_clamp, 0.0..=100.0)
+							.logarithmic(true)
+							.smallest_positive(1.0)
+							.custom_formatter(|v, _| {
+								if v == 0.0 {
+									"Off".to_owned()
+								} else {
+									format!("{v:.1}")
+								}
+							}),
+					)
+					.on_hover_text(
+						"Limits the brightness of each sample. Lower values remove \
+						 the bright speckles that never average out, at the cost of \
+						 dimming small light sources and sharp highlights a little",
+					);
+				s.update_response(slider);
+				if ui.button("Off").clicked() {
+					s.render.firefly_clamp = 0.0;
+					s.set_changed(true);
+				}
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Highlight selected object",
+		draw: |s, ui, _| {
+			let checkbox =
+				ui.checkbox(&mut s.render.highlight, "Highlight selected object");
+			s.update_response(checkbox);
+		},
+	},
+	Entry {
+		tab: SettingsTab::Render,
+		label: "Solo selected object, hidden objects still light the scene",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(&mut s.render.solo, "Solo selected object (I)");
+			s.update_response(checkbox);
 
+			ui.add_enabled_ui(s.render.solo, |ui| {
+				ui.indent("solo_lighting", |ui| {
+					let checkbox = ui.checkbox(
+						&mut s.render.solo_lighting,
+						"Hidden objects still light the scene",
+					);
+					s.update_response(checkbox);
+				});
+			});
+		},
+	},
+	// }}}
+	// {{{ post
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Tonemapping",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Tonemapping:");
+				egui::ComboBox::new("tonemap_selector", "")
+					.selected_text(format!("{}", s.render.tonemap))
+					.show_ui(
+						ui,
+						crate::selectable_values! {
+							target = s.render.tonemap,
+							focused = s.response.focused,
+							changed = s.response.changed,
+							[Tonemap::None, Tonemap::Reinhard, Tonemap::AcesFilmic],
+						},
+					);
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Exposure",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Exposure:");
+				let slider = ui
+					.add(Slider::new(&mut s.render.exposure, -5.0..=5.0).suffix(" stops"))
+					.on_hover_text("Not applied to the debug views");
+				s.update_response(slider);
+				if ui.button("Reset").clicked() {
+					s.render.exposure = 0.0;
+					s.set_changed(true);
+				}
+			});
+		},
+	},
+	// display only, nothing below affects the render
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Grading: hue, saturation, value, contrast",
+		draw: |s, ui, _| {
+			let post = &mut s.post;
+			let sliders = [
+				(&mut post.hue_shift, -180.0..=180.0, "Hue shift:"),
+				(&mut post.saturation, 0.0..=2.0, "Saturation:"),
+				(&mut post.value, 0.0..=2.0, "Value:"),
+				(&mut post.contrast, 0.0..=2.0, "Contrast:"),
+			];
+			let mut focused = false;
+			for (value, range, label) in sliders {
 				ui.horizontal(|ui| {
-					ui.label("Samples per frame");
-					let slider = ui.add(Slider::new(&mut self.render.samples_per_frame, 1..=32));
-					self.update_response(slider);
+					ui.label(label);
+					let slider = ui
+						.add(Slider::new(value, range))
+						.on_hover_text("Not applied to the debug views");
+					focused |= slider.has_focus();
 				});
+			}
+			s.set_focused(focused);
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Reset grading, keep grading in exports",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				if ui.button("Reset grading").clicked() {
+					s.post.reset_grading();
+				}
+				let checkbox = ui
+					.checkbox(&mut s.post.in_exports, "Keep grading in exports")
+					.on_hover_text("Screenshots and final renders");
+				s.set_focused(checkbox.has_focus());
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Show scale gizmo for selected object",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(
+				&mut s.render.show_gizmo,
+				"Show scale gizmo for selected object",
+			);
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Show object info on hover",
+		draw: |s, ui, _| {
+			let checkbox =
+				ui.checkbox(&mut s.render.hover_tooltip, "Show object info on hover");
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Pixel inspector",
+		draw: |s, ui, _| {
+			let checkbox = ui
+				.checkbox(&mut s.render.pixel_inspector, "Pixel inspector")
+				.on_hover_text("Shows the rendered value under the pointer");
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Object labels, dim hidden, keep when hiding windows",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				let labels = ui.checkbox(&mut s.render.labels, "Object labels");
+				s.set_focused(labels.has_focus());
 
+				if s.render.labels {
+					let occlusion = ui
+						.checkbox(&mut s.render.label_occlusion, "Dim hidden")
+						.on_hover_text("Dims the labels of objects behind others");
+					let screenshots = ui.checkbox(
+						&mut s.render.labels_in_screenshots,
+						"Keep when hiding windows",
+					);
+					s.set_focused(occlusion.has_focus() || screenshots.has_focus());
+				}
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Post,
+		label: "Guides: thirds, center, safe area",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Guides:");
+				let guides = &mut s.render.guides;
+				let thirds = ui.checkbox(&mut guides.thirds, "Thirds");
+				let center = ui.checkbox(&mut guides.center, "Center");
+				let safe_area = ui.checkbox(&mut guides.safe_area, "Safe area");
+				s.set_focused(
+					thirds.has_focus() || center.has_focus() || safe_area.has_focus(),
+				);
+			});
+		},
+	},
+	// }}}
+	// {{{ camera
+	Entry {
+		tab: SettingsTab::Camera,
+		label: "Lock camera",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(
+				&mut s.render.lock_camera,
+				"Lock camera (L, useful when rendering)",
+			);
+			s.update_response(checkbox);
+		},
+	},
+	Entry {
+		tab: SettingsTab::Camera,
+		label: "Projection (perspective, 360° panorama)",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Projection:");
+				egui::ComboBox::new("projection_selector", "")
+					.selected_text(format!("{}", s.render.projection))
+					.show_ui(
+						ui,
+						crate::selectable_values! {
+							target = s.render.projection,
+							focused = s.response.focused,
+							changed = s.response.changed,
+							[Projection::Perspective, Projection::Panorama],
+						},
+					);
+			});
+			if s.render.projection == Projection::Panorama {
+				ui.label("Use a 2:1 aspect ratio for an undistorted panorama.");
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::Camera,
+		label: "Stereo (side by side), eye separation, convergence distance",
+		draw: |s, ui, _| {
+			ui.add_enabled_ui(s.render.projection == Projection::Perspective, |ui| {
+				let checkbox = ui
+					.checkbox(&mut s.render.stereo, "Stereo (side by side)")
+					.on_hover_text(
+						"The left eye's view on the left, the right eye's on the right",
+					);
+				s.update_response(checkbox);
+
+				if s.render.stereo {
+					ui.horizontal(|ui| {
+						ui.label("Eye separation:");
+						let slider =
+							ui.add(Slider::new(&mut s.render.stereo_separation, 0.0..=1.0));
+						s.update_response(slider);
+					});
+					ui.horizontal(|ui| {
+						ui.label("Convergence distance:");
+						let slider = ui
+							.add(
+								Slider::new(&mut s.render.stereo_convergence, 0.1..=100.0)
+									.logarithmic(true),
+							)
+							.on_hover_text("Objects at this distance look the same to both eyes");
+						s.update_response(slider);
+					});
+				}
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Camera,
+		label: "Field of view, focal length",
+		draw: |s, ui, _| {
+			// a panorama always covers the whole sphere
+			let perspective = s.render.projection == Projection::Perspective;
+			ui.add_enabled_ui(perspective, |ui| {
 				ui.horizontal(|ui| {
-					ui.label("Max ray bounces:");
-					let slider = ui.add(Slider::new(&mut self.render.max_bounces, 0..=20));
-					self.update_response(slider);
+					if s.render.fov_as_focal_length {
+						ui.label("Focal length:");
+						let slider = controls::focal_length_slider(ui, &mut s.render.fov);
+						s.update_response(slider);
+					} else {
+						ui.label("Field of view:");
+						let slider = ui.add(controls::fov_slider(&mut s.render.fov));
+						s.update_response(slider);
+					}
+
+					let toggle = ui
+						.toggle_value(&mut s.render.fov_as_focal_length, "mm")
+						.on_hover_text("Show as a 35mm-equivalent focal length");
+					s.set_focused(toggle.has_focus());
+				});
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Camera,
+		label: "Aperture, focal distance (depth of field)",
+		draw: |s, ui, _| {
+			// the lens is off in a panorama, see `RaytracingApp::paint`
+			let perspective = s.render.projection == Projection::Perspective;
+			ui.add_enabled_ui(perspective, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("Aperture:");
+					let slider = ui
+						.add(Slider::new(&mut s.render.aperture, 0.0..=1.0))
+						.on_hover_text(
+							"Lens radius for depth of field, 0 keeps everything sharp. \
+							 Only the realistic view is blurred",
+						);
+					s.update_response(slider);
 				});
 
-				{
-					let checkbox =
-						ui.checkbox(&mut self.render.highlight, "Highlight selected object");
-					self.update_response(checkbox);
-				}
+				ui.add_enabled_ui(s.render.aperture > 0.0, |ui| {
+					ui.horizontal(|ui| {
+						ui.label("Focal distance:");
+						let slider = ui.add(
+							Slider::new(&mut s.render.focal_distance, 0.1..=100.0)
+								.logarithmic(true),
+						);
+						s.update_response(slider);
+					});
+				});
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::Camera,
+		label: "Aspect ratio",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				ui.label("Aspect ratio:");
+				egui::ComboBox::new("aspect_lock_selector", "")
+					.selected_text(format!("{}", s.render.aspect))
+					.show_ui(
+						ui,
+						crate::selectable_values! {
+							target = s.render.aspect,
+							focused = s.response.focused,
+							changed = s.response.changed,
+							[
+								AspectLock::Window,
+								AspectLock::Widescreen,
+								AspectLock::Square,
+								AspectLock::Anamorphic,
+								AspectLock::Panorama,
+							],
+						},
+					);
+			});
+		},
+	},
+	// }}}
+	// {{{ controls
+	// these don't affect the render by themselves, camera movement takes care
+	// of resetting accumulation
+	Entry {
+		tab: SettingsTab::Controls,
+		label: "Walk mode, eye height",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(&mut s.controls.walk_mode, "Walk mode (F)");
+			s.set_focused(checkbox.has_focus());
 
-				{
-					let checkbox = ui.checkbox(
-						&mut self.render.lock_camera,
-						"Lock camera (useful when rendering)",
+			if s.controls.walk_mode {
+				ui.horizontal(|ui| {
+					ui.label("Eye height:");
+					let drag = ui.add(
+						controls::position_drag(ui, &mut s.controls.eye_height)
+							.clamp_range(0.0..=f32::MAX),
 					);
-					self.update_response(checkbox);
-				}
+					s.set_focused(drag.has_focus());
+				});
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::Controls,
+		label: "Limit looking up and down (pitch)",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(
+				&mut s.controls.clamp_pitch,
+				"Limit looking up and down to ±80°",
+			);
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Controls,
+		label: "Collide with objects",
+		draw: |s, ui, _| {
+			let checkbox = ui.checkbox(&mut s.controls.collision, "Collide with objects");
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Controls,
+		label: "Handheld wobble, amplitude, frequency",
+		draw: |s, ui, _| {
+			let checkbox = ui
+				.checkbox(&mut s.controls.shake, "Handheld wobble")
+				.on_hover_text("Meant for Preview shading, it keeps the camera moving");
+			s.set_focused(checkbox.has_focus());
+
+			if s.controls.shake {
+				ui.horizontal(|ui| {
+					ui.label("Wobble amplitude:");
+					let slider =
+						ui.add(Slider::new(&mut s.controls.shake_amplitude, 0.0..=0.2));
+					s.set_focused(slider.has_focus());
+				});
 
 				ui.horizontal(|ui| {
-					ui.label("Field of view:");
+					ui.label("Wobble frequency:");
 					let slider = ui.add(
-						Slider::new(
-							&mut self.render.fov,
-							(50.0_f32.to_radians())..=(120.0_f32.to_radians()),
-						)
-						.angle(),
+						Slider::new(&mut s.controls.shake_frequency, 0.1..=5.0).suffix(" Hz"),
 					);
-					self.update_response(slider);
+					s.set_focused(slider.has_focus());
 				});
-			});
-			// }}}
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::Controls,
+		label: "Low latency camera (pen displays)",
+		draw: |s, ui, _| {
+			let checkbox = ui
+				.checkbox(&mut s.controls.low_latency, "Low latency camera")
+				.on_hover_text(
+					"Shows camera movement in the same frame as the input. Frames \
+					 while moving take a single sample and look noisier",
+				);
+			s.set_focused(checkbox.has_focus());
+		},
+	},
+	Entry {
+		tab: SettingsTab::Controls,
+		label: "Restart render key, rebind",
+		draw: |s, ui, _| {
+			ui.horizontal(|ui| {
+				let key = s.controls.restart_key.name();
+				if ui.button(format!("Restart render ({key})")).clicked() {
+					s.response.restart = true;
+				}
+
+				if s.rebinding_restart {
+					ui.label("Press a key (Esc to cancel)");
+
+					let pressed = ui.input(|i| {
+						i.events.iter().find_map(|e| match e {
+							egui::Event::Key {
+								key, pressed: true, ..
+							} => Some(*key),
+							_ => None,
+						})
+					});
+					if let Some(key) = pressed {
+						if key != egui::Key::Escape {
+							s.controls.restart_key = key;
+						}
+						s.rebinding_restart = false;
+					}
 
+					// keep the key press from moving the camera
+					s.set_focused(true);
+				} else if ui.button("Rebind").clicked() {
+					s.rebinding_restart = true;
+				}
+			});
+		},
+	},
+	// }}}
+	// {{{ system
+	Entry {
+		tab: SettingsTab::System,
+		label: "Presentation mode",
+		draw: |s, ui, _| {
+			if ui.button("Presentation mode (P)").clicked() {
+				s.response.presentation = true;
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::System,
+		label: "Help, tour, tutorial",
+		draw: |s, ui, _| {
+			if ui.button("Restart the tour").clicked() {
+				s.response.tutorial = true;
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::System,
+		label: "Reset window layout",
+		draw: |s, ui, _| {
+			let button = ui
+				.button("Reset window layout")
+				.on_hover_text("Moves every window back to where it starts");
+			if button.clicked() {
+				s.response.reset_layout = true;
+			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::System,
+		label: "Temporarily hide windows (screenshot)",
+		draw: |s, ui, _| {
 			if ui.button("Temporarily hide windows").clicked() {
-				self.response.screenshot = true;
+				s.response.screenshot = true;
 			}
-
-			if ui.button("Manually save data").clicked() {
-				self.response.save_data = true;
+		},
+	},
+	Entry {
+		tab: SettingsTab::System,
+		label: "Final render, save image, export",
+		draw: |s, ui, _| {
+			let button = ui.button("Final render…").on_hover_text(
+				"Renders at a set size and sample count, then saves the image",
+			);
+			if button.clicked() {
+				s.response.final_render = true;
 			}
+		},
+	},
+	Entry {
+		tab: SettingsTab::System,
+		label: "Manually save data",
+		draw: |s, ui, status| {
+			ui.horizontal(|ui| {
+				if ui.button("Manually save data").clicked() {
+					s.response.save_data = true;
+				}
 
-			// {{{ clear data button
+				if status.unsaved {
+					ui.label("●").on_hover_text("Unsaved changes");
+				}
+				ui.label(match status.since_save {
+					Some(secs) => format!("saved {}", format_ago(secs)),
+					None => "not saved yet".to_owned(),
+				});
+			});
+		},
+	},
+	Entry {
+		tab: SettingsTab::System,
+		label: "Clear all data",
+		draw: |s, ui, _| {
 			if ui.button("Clear all data").clicked() {
-				self.data_modal = true;
+				s.data_modal = true;
 			}
+		},
+	},
+	// }}}
+];
+// }}}
 
-			crate::util::modal(
-				egui,
-				"Clear all data?",
-				&mut self.data_modal,
-				|ui| {
-					ui.label("This will delete:");
-					ui.label("- Scene objects and associated materials");
-					ui.label("- Camera parameters");
-					ui.label("- Saved settings");
-				},
-				crate::util::red_hover_button,
-				|| {
-					self.response.clear_data = true;
-				},
-			);
-			// }}}
+impl Settings {
+	// the only settings left in presentation mode, nothing here can edit the
+	// scene or the stored data
+	pub fn presentation_window(&mut self, egui: &egui::Context) {
+		egui::Window::new("Presentation").show(egui, |ui| {
+			self.render_mode_selector(ui);
+			self.accumulate_checkbox(ui);
+
+			ui.horizontal(|ui| {
+				if ui.button("Exit").clicked() {
+					self.response.presentation = true;
+				}
+				ui.label("or press P");
+			});
+		});
+	}
+
+	fn render_mode_selector(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Render mode:");
+			egui::ComboBox::new("render_mode_selector", "")
+				.selected_text(format!("{}", self.render.mode))
+				.show_ui(
+					ui,
+					crate::selectable_values! {
+						target = self.render.mode,
+						focused = self.response.focused,
+						changed = self.response.changed,
+						[
+							RenderMode::Preview,
+							RenderMode::Realistic,
+							RenderMode::Position,
+							RenderMode::Normal,
+							RenderMode::Depth,
+							RenderMode::Fresnel,
+							RenderMode::Roughness,
+							RenderMode::RayDir,
+							RenderMode::Noise,
+							RenderMode::Wireframe,
+							RenderMode::ObjectId,
+							RenderMode::Uv,
+							RenderMode::Lod,
+							RenderMode::BvhHeatmap,
+						],
+					},
+				);
 		});
 	}
+
+	fn accumulate_checkbox(&mut self, ui: &mut egui::Ui) {
+		let checkbox = ui.checkbox(&mut self.render.accumulate, "Accumulate samples");
+		self.update_response(checkbox);
+	}
+}
+
+// read-only information shown in the settings window
+pub struct Status<'a> {
+	pub frame_index: u32,
+	pub render_limit: Option<([u32; 2], u32)>, // render size, GPU limit
+	pub hovered: Option<&'a str>,
+	pub unsaved: bool,
+	pub since_save: Option<f64>, // seconds
+	pub turntable: bool,
+	pub uploads: &'a RenderStats,
+}
+
+fn format_bytes(bytes: usize) -> String {
+	match bytes {
+		0..=1023 => format!("{bytes} B"),
+		1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+		_ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+	}
+}
+
+fn format_ago(secs: f64) -> String {
+	let secs = secs as u64;
+	match secs {
+		0..=9 => "just now".to_owned(),
+		10..=59 => format!("{secs} s ago"),
+		60..=3599 => format!("{} min ago", secs / 60),
+		_ => format!("{} h ago", secs / 3600),
+	}
 }
 
 impl UpdateResponse for Settings {
@@ -0,0 +1,42 @@
+// vim:commentstring=//%s
+// Color grading for the final pass, included ahead of it (see
+// `fragment_shader!`). util.rs has the same functions for the images that are
+// saved from the CPU, keep the two in sync.
+precision mediump float;
+
+// hue in turns
+vec3 rgb_to_hsv(vec3 c) {
+	float max_c = max(c.r, max(c.g, c.b));
+	float d = max_c - min(c.r, min(c.g, c.b));
+	float s = max_c > 0.0 ? d / max_c : 0.0;
+	if (d <= 0.0) {
+		return vec3(0.0, s, max_c);
+	}
+
+	float h;
+	if (max_c == c.r) {
+		h = (c.g - c.b) / d;
+	} else if (max_c == c.g) {
+		h = (c.b - c.r) / d + 2.0;
+	} else {
+		h = (c.r - c.g) / d + 4.0;
+	}
+	return vec3(fract(h / 6.0), s, max_c);
+}
+
+vec3 hsv_to_rgb(vec3 c) {
+	vec3 k = vec3(1.0, 2.0 / 3.0, 1.0 / 3.0);
+	vec3 p = clamp(abs(fract(c.x + k) * 6.0 - 3.0) - 1.0, 0.0, 1.0);
+	return c.z * mix(vec3(1.0), p, c.y);
+}
+
+// `grading` is the hue shift in turns, then the saturation, value and
+// contrast multipliers. works on display (sRGB) values in [0, 1]
+vec3 grade(vec3 color, vec4 grading) {
+	vec3 hsv = rgb_to_hsv(color);
+	hsv.x = fract(hsv.x + grading.x);
+	hsv.y = clamp(hsv.y * grading.y, 0.0, 1.0);
+	hsv.z *= grading.z;
+	vec3 rgb = hsv_to_rgb(hsv);
+	return clamp((rgb - 0.5) * grading.w + 0.5, 0.0, 1.0);
+}
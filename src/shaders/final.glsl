@@ -3,9 +3,17 @@ precision mediump float;
 precision mediump usampler2D;
 
 uniform usampler2D image;
-uniform vec2 scr_size;
+uniform vec4 viewport; // left, bottom, width, height in window pixels
 uniform uint frame_index;
 uniform uint accumulate;
+uniform uint display_raw;
+uniform uint tonemap; // TONEMAP_*
+uniform float exposure; // multiplier, not stops
+uniform vec4 grading; // see grade() in color.glsl, (0, 1, 1, 1) is neutral
+
+const uint TONEMAP_NONE     = 0u;
+const uint TONEMAP_REINHARD = 1u;
+const uint TONEMAP_ACES     = 2u;
 
 out vec4 out_color;
 
@@ -20,18 +28,39 @@ vec3 aces_filmic(vec3 x) {
 }
 
 void main() {
-	vec2 uv = gl_FragCoord.xy / scr_size;
+	// the image can be smaller than the viewport, it's stretched to fit
+	vec2 uv = (gl_FragCoord.xy - viewport.xy) / viewport.zw;
 	uvec3 texel = texture(image, uv).rgb;
 	vec3 color = uintBitsToFloat(texel);
+
+	if (display_raw == 1u) {
+		out_color = vec4(color, 1.0);
+		return;
+	}
+
 	if (accumulate == 1u) {
 		color /= float(frame_index);
 	}
 
-	// HDR to LDR
-	color = aces_filmic(color);
+	// HDR to LDR, without a tonemap everything above 1 is clipped
+	color *= exposure;
+	switch (tonemap) {
+		case TONEMAP_NONE:
+			color = clamp(color, 0.0, 1.0);
+			break;
+		case TONEMAP_REINHARD:
+			color = color / (1.0 + color);
+			break;
+		case TONEMAP_ACES:
+			color = aces_filmic(color);
+			break;
+	}
 
 	// linear to sRGB
 	color = pow(color, vec3(1.0 / 2.2));
 
+	// like an adjustment layer in an image editor, on the display values
+	color = grade(color, grading);
+
 	out_color = vec4(color, 1.0);
 }
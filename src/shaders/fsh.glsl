@@ -30,12 +30,29 @@ const uint RENDER_FRESNEL    = 5u;
 const uint RENDER_ROUGHNESS  = 6u;
 const uint RENDER_RAY_DIR    = 7u;
 const uint RENDER_NOISE      = 8u;
+const uint RENDER_WIREFRAME  = 9u;
+const uint RENDER_OBJECT_ID  = 10u;
+const uint RENDER_UV         = 11u;
+const uint RENDER_LOD        = 12u;
+const uint RENDER_BVH_HEATMAP = 13u;
 
 const uint OBJ_TYPE_SPHERE   = 0u;
 const uint OBJ_TYPE_BOX      = 1u;
+const uint OBJ_TYPE_PLANE    = 2u;
+const uint OBJ_TYPE_CYLINDER = 3u;
+const uint OBJ_TYPE_CAPSULE  = 4u;
+const uint OBJ_TYPE_DISC     = 5u;
+const uint OBJ_TYPE_MESH     = 6u;
+
+const uint CSG_NONE      = 0u;
+const uint CSG_UNION     = 1u;
+const uint CSG_INTERSECT = 2u;
+const uint CSG_SUBTRACT  = 3u;
 
 const uint MAT_TYPE_SOLID    = 0u;
 const uint MAT_TYPE_EMISSIVE = 1u;
+const uint MAT_TYPE_METAL    = 2u;
+const uint MAT_TYPE_SHADOW_CATCHER = 3u;
 // }}}
 
 // 0x7f7f_fff = 0b0_11111110_11111111111111111111111 = 2139095039
@@ -52,43 +69,244 @@ const vec3 CAMERA_UP = vec3(0.0, 1.0, 0.0);
 uniform vec2 scr_size;
 uniform vec3 camera_pos;
 uniform vec3 camera_dir;
+uniform vec3 camera_right; // the lens lies in the plane of these two
+uniform vec3 camera_up;
+uniform float aperture; // lens radius, zero for a pinhole camera
+uniform float focal_distance;
+uniform uint stereo; // Stereo in camera.rs, the image is two views side by side
+uniform float stereo_separation;
+uniform float stereo_convergence;
 uniform uint frame_index;
+uniform float history_scale;
+
+// ranges that the position and depth views are scaled to
+uniform vec3 bounds_min;
+uniform vec3 bounds_max;
+uniform float max_depth;
 
 // {{{ UNIFORMS FOR SCENE
-const uint MAX_SCENE_SIZE = 50u;
+const uint MAX_SCENE_SIZE = 1024u; // MAX_OBJECTS in scene.rs
 
 // general
 uniform uint scene_selected;
 uniform uint scene_size;
-uniform uint scene_obj_type[MAX_SCENE_SIZE];
 
-// materials
-uniform uint scene_mat_type[MAX_SCENE_SIZE];
-uniform vec3 scene_mat_color[MAX_SCENE_SIZE];
-uniform float scene_mat_ior[MAX_SCENE_SIZE];
-uniform float scene_mat_specular[MAX_SCENE_SIZE];
-uniform float scene_mat_roughness[MAX_SCENE_SIZE];
-uniform float scene_mat_emissive_strength[MAX_SCENE_SIZE];
+// triangles of all meshes, three texels (the corners) each, and their BVH
+// nodes, two texels each. both have MESH_TEXTURE_WIDTH texels per row. each
+// mesh has a range of nodes (root, count), see upload_meshes in render.rs
+const int MESH_TEXTURE_WIDTH = 1024;
+const int MAX_BVH_DEPTH = 16;
+uniform highp sampler2D mesh_triangles;
+uniform highp sampler2D mesh_bvh;
+
+// BVH nodes tested so far, for the heatmap
+uint bvh_visits = 0u;
+
+// level of detail, classified on the CPU from the projected size
+const uint LOD_FULL         = 0u;
+const uint LOD_PRIMARY_ONLY = 1u; // skipped by secondary rays
+const uint LOD_SKIP         = 2u;
+
+// light groups, the scale is zero for disabled groups
+const uint MAX_LIGHT_GROUPS = 8u;
+uniform float scene_light_group_scale[MAX_LIGHT_GROUPS];
+
+// everything else about the objects, a row of OBJECT_TEXELS texels each. as
+// uniform arrays they went over WebGL2's limit of 1024 vectors. integers are
+// stored as floats, see object_texels in render.rs. the last texel is the
+// level of detail, which changes with the camera and is uploaded on its own
+const int OBJECT_TEXELS = 22;
+uniform highp sampler2D scene_objects;
+
+vec4 object_texel(uint i, int texel) {
+	return texelFetch(scene_objects, ivec2(texel, int(i)), 0);
+}
+
+mat4 object_matrix(uint i, int first) {
+	return mat4(
+		object_texel(i, first),
+		object_texel(i, first + 1),
+		object_texel(i, first + 2),
+		object_texel(i, first + 3)
+	);
+}
 
 // transforms
-uniform mat4 scene_transform[MAX_SCENE_SIZE];
-uniform mat4 scene_inv_transform[MAX_SCENE_SIZE];
-uniform mat4 scene_normal_transform[MAX_SCENE_SIZE];
+mat4 scene_transform(uint i) {
+	return object_matrix(i, 0);
+}
+
+mat4 scene_inv_transform(uint i) {
+	return object_matrix(i, 4);
+}
+
+mat4 scene_normal_transform(uint i) {
+	return object_matrix(i, 8);
+}
+
+// shapes
+uint scene_obj_type(uint i) {
+	return uint(object_texel(i, 12).x);
+}
+
+uint scene_flip_normals(uint i) {
+	return uint(object_texel(i, 12).y);
+}
+
+uint scene_double_sided(uint i) {
+	return uint(object_texel(i, 12).z);
+}
+
+float scene_corner_radius(uint i) {
+	return object_texel(i, 12).w;
+}
+
+float scene_cap_radius(uint i) {
+	return object_texel(i, 13).x;
+}
+
+// range of BVH nodes (root, count) in mesh_bvh
+uvec2 scene_mesh_range(uint i) {
+	return uvec2(object_texel(i, 13).yz);
+}
+
+// operation, partner and whether the object is some other object's partner.
+// the operation is CSG_NONE unless the link is usable, see Scene::csg_link
+uvec3 scene_csg(uint i) {
+	return uvec3(object_texel(i, 14).xyz);
+}
+
+// texture coordinates, uv' = mat2(matrix) * uv + offset
+vec4 scene_uv_matrix(uint i) {
+	return object_texel(i, 15);
+}
+
+vec2 scene_uv_offset(uint i) {
+	return object_texel(i, 16).xy;
+}
+
+// materials
+uint scene_mat_type(uint i) {
+	return uint(object_texel(i, 14).w);
+}
+
+uint scene_mat_light_group(uint i) {
+	return uint(object_texel(i, 13).w);
+}
+
+float scene_mat_ior(uint i) {
+	return object_texel(i, 16).z;
+}
+
+float scene_mat_specular(uint i) {
+	return object_texel(i, 16).w;
+}
+
+vec3 scene_mat_color(uint i) {
+	return object_texel(i, 17).rgb;
+}
+
+float scene_mat_roughness(uint i) {
+	return object_texel(i, 17).a;
+}
+
+vec3 scene_mat_emissive_color(uint i) {
+	return object_texel(i, 18).rgb;
+}
+
+float scene_mat_emissive_strength(uint i) {
+	return object_texel(i, 18).a;
+}
+
+// rgb is the second color of the checkerboard, a is the number of squares
+// across the object or 0 for a solid color
+vec4 scene_mat_checker(uint i) {
+	return object_texel(i, 19);
+}
+
+float scene_mat_metallic(uint i) {
+	return object_texel(i, 20).x;
+}
+
+uint scene_lod(uint i) {
+	return uint(object_texel(i, 21).x);
+}
 // }}}
 
 // {{{ UNIFORMS FOR SETTINGS
 // world
-uniform vec3 sky_color;
+uniform vec3 sky_color; // at the zenith, also the ambient light
+uniform vec3 horizon_color;
 uniform vec3 sun_color;
 uniform vec3 sun_dir;
 uniform float sun_strength;
+uniform float sun_cos_radius; // of the sun's angular radius, 1 for a point
+uniform vec3 fill_color; // constant, only on surfaces
+uniform float fill_strength;
+uniform uint ground_plane;
+uniform float ground_height;
+uniform vec3 ground_color;
+uniform float ground_roughness;
 
 // render
 uniform uint render_mode;
 uniform uint accumulate;
 uniform uint samples_per_frame;
 uniform uint highlight_selected;
+uniform uint solo_object; // 0xffffffff when nothing is soloed
+uniform uint solo_lighting;
 uniform uint max_bounces;
+uniform float roughness_multiplier;
+uniform float ray_bias;
+uniform float firefly_clamp; // max luminance of a sample, 0 disables
+uniform uint debug_palette; // PALETTE_* from palette.glsl
+
+// set while tracing the single ray for picking an object, see Raytracer::pick
+uniform uint picking;
+uniform ivec2 pick_texel;
+// }}}
+
+// {{{ MATERIALS
+// the ground plane isn't a scene object, it gets an index past the end of the
+// scene so that material lookups can tell it apart
+const uint GROUND_OBJ = MAX_SCENE_SIZE;
+
+uint mat_type(uint i) {
+	return (i == GROUND_OBJ) ? MAT_TYPE_SOLID : scene_mat_type(i);
+}
+
+vec3 mat_color(uint i) {
+	return (i == GROUND_OBJ) ? ground_color : scene_mat_color(i);
+}
+
+float mat_ior(uint i) {
+	return (i == GROUND_OBJ) ? 1.333 : scene_mat_ior(i);
+}
+
+float mat_specular(uint i) {
+	return (i == GROUND_OBJ) ? 1.0 : scene_mat_specular(i);
+}
+
+float mat_roughness(uint i) {
+	float r = (i == GROUND_OBJ) ? ground_roughness : scene_mat_roughness(i);
+	return clamp(r * roughness_multiplier, 0.0, 1.0);
+}
+
+float mat_metallic(uint i) {
+	return (i == GROUND_OBJ) ? 0.0 : scene_mat_metallic(i);
+}
+
+float mat_emissive_strength(uint i) {
+	if (i == GROUND_OBJ) {
+		return 0.0;
+	}
+	return scene_mat_emissive_strength(i)
+		* scene_light_group_scale[scene_mat_light_group(i)];
+}
+
+vec3 mat_emissive_color(uint i) {
+	return (i == GROUND_OBJ) ? vec3(0.0) : scene_mat_emissive_color(i);
+}
 // }}}
 
 // {{{ SAMPLING
@@ -98,28 +316,47 @@ uint pcg(uint p) {
 	return (word >> 22u) ^ word;
 }
 
-float hash(float p) {
-	uint pu = floatBitsToUint(p);
-	return float(pcg(pu)) * RECIP_UINT_MAX;
+// {{{ random streams
+// The noise prepass gives every pixel four independent 32-bit states per
+// frame. rng_next() hands out random numbers in [0, 1] by mixing one of the
+// states with a running counter, so every call returns a fresh, uncorrelated
+// value.
+//
+// Consumption order, per sample:
+// - 2 numbers for the antialiasing offset
+// - 2 numbers for the position on the lens, only with depth of field
+// - per bounce:
+//   - 3 numbers for the diffuse direction
+//   - 1 number for choosing between the specular and diffuse lobe
+// New features should take their numbers from rng_next() too, after the
+// existing ones, instead of hashing a value that was already used.
+uvec4 rng_state;
+uint rng_counter;
+
+void rng_init(uvec4 state) {
+	rng_state = state;
+	rng_counter = 0u;
+}
+
+float rng_next() {
+	uint i = rng_counter++;
+	uint lane = rng_state[i & 3u];
+	return float(pcg(lane ^ pcg(i))) * RECIP_UINT_MAX;
 }
 
-vec2 hash2(float p) {
-	uint pu = floatBitsToUint(p);
-	uint x = pcg(pu);
-	uint y = pcg(pu + 1u);
-	return vec2(uvec2(x, y)) * RECIP_UINT_MAX;
+vec2 rng_next2() {
+	float x = rng_next();
+	return vec2(x, rng_next());
 }
 
-vec3 hash3(float p) {
-	uint pu = floatBitsToUint(p);
-	uint x = pcg(pu);
-	uint y = pcg(pu + 1u);
-	uint z = pcg(pu + 2u);
-	return vec3(uvec3(x, y, z)) * RECIP_UINT_MAX;
+vec3 rng_next3() {
+	vec2 xy = rng_next2();
+	return vec3(xy, rng_next());
 }
+// }}}
 
-vec3 cos_dist_in_hemi(float seed, vec3 normal) {
-	vec3 res = normalize(normal + (hash3(seed) * 2.0 - 1.0));
+vec3 cos_dist_in_hemi(vec3 normal) {
+	vec3 res = normalize(normal + (rng_next3() * 2.0 - 1.0));
 
 	if (dot(res, normal) < 0.0) {
 		res = -res;
@@ -127,6 +364,25 @@ vec3 cos_dist_in_hemi(float seed, vec3 normal) {
 
 	return res;
 }
+
+// microfacet normal from the GGX distribution around `normal`, alpha is the
+// squared roughness. zero gives the normal itself (a perfect mirror)
+vec3 ggx_normal(vec3 normal, float alpha) {
+	vec2 u = rng_next2();
+	float a2 = alpha * alpha;
+	float cos_theta = sqrt((1.0 - u.x) / (1.0 + (a2 - 1.0) * u.x));
+	float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+	float phi = TWO_PI * u.y;
+
+	vec3 helper = abs(normal.x) > 0.5 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(helper, normal));
+	vec3 bitangent = cross(normal, tangent);
+
+	return normalize(
+		(tangent * cos(phi) + bitangent * sin(phi)) * sin_theta
+		+ normal * cos_theta
+	);
+}
 // }}}
 
 // {{{ MISC
@@ -186,6 +442,42 @@ float schlick_fresnel(
 	// adjust reflect multiplier for object reflectivity
 	return mix(min_refl, max_refl, r0 + (1.0 - r0) * x*x*x*x*x);
 }
+
+// Schlick with a colored reflectance, for metals the albedo is the reflectance
+// head on and it goes towards white at grazing angles
+vec3 metal_fresnel(vec3 f0, vec3 incident, vec3 normal) {
+	float x = 1.0 - clamp(-dot(normal, incident), 0.0, 1.0);
+	return f0 + (1.0 - f0) * x*x*x*x*x;
+}
+// }}}
+
+// {{{ SELF-INTERSECTION BIAS
+// Bounced rays start slightly above the surface they leave so that rounding
+// errors don't make them hit it again (shadow acne). Floats get coarser
+// further from the origin and the intersection error grows with the distance
+// travelled, so the offset is relative to the larger of the two. It's capped
+// to a fraction of the object's smallest dimension so that it never gets
+// large enough to leak light through thin or tiny objects.
+// Primitives only have to report the hit in world space with an outward
+// normal and the distance along the ray, everything else happens here.
+// BIAS_ABSOLUTE, BIAS_RELATIVE and BIAS_MAX_OBJECT_FRACTION are put in front
+// of the shader by render.rs, light_path.rs uses them too.
+
+// smallest scale factor of an object
+float object_size(uint i) {
+	if (i == GROUND_OBJ) {
+		return FLT_MAX;
+	}
+	mat4 m = scene_transform(i);
+	return min(length(m[0].xyz), min(length(m[1].xyz), length(m[2].xyz)));
+}
+
+float hit_bias(RayHit hit) {
+	vec3 p = abs(hit.pos);
+	float magnitude = max(max(p.x, p.y), max(p.z, hit.distance));
+	float bias = (BIAS_ABSOLUTE + BIAS_RELATIVE * magnitude) * ray_bias;
+	return min(bias, object_size(hit.obj) * BIAS_MAX_OBJECT_FRACTION);
+}
 // }}}
 
 // {{{ INTERSECTION TESTS
@@ -194,7 +486,7 @@ const RayHit NO_HIT = RayHit(false, 0u, vec3(0.0), vec3(0.0), FLT_MAX);
 // adapted from The Cherno's series
 RayHit intersect_sphere(Ray ray, uint i) {
 	// {{{
-	Ray local_ray = transform(ray, scene_inv_transform[i]);
+	Ray local_ray = transform(ray, scene_inv_transform(i));
 
 	// quadratic formula
 	// a is dot(dir, dir) which is 1 because dir is normalized
@@ -217,10 +509,10 @@ RayHit intersect_sphere(Ray ray, uint i) {
 	// float local_t = (local_tn > 0.0) ? local_tx : local_tn
 
 	vec3 local_pos = pos_from_ray(local_ray, local_tn);
-	vec3 pos = transform(local_pos, scene_transform[i]);
+	vec3 pos = transform(local_pos, scene_transform(i));
 	// in local space, the sphere is centered on the origin and has radius 1
 	// the local position of the ray hit is automatically equal to the local normal
-	vec3 normal = transform_n(local_pos, scene_normal_transform[i]);
+	vec3 normal = transform_n(local_pos, scene_normal_transform(i));
 	float distance = distance(ray.origin, pos);
 
 	return RayHit(true, i, pos, normal, distance);
@@ -230,7 +522,7 @@ RayHit intersect_sphere(Ray ray, uint i) {
 // adapted from https://iquilezles.org/articles/intersectors/
 RayHit intersect_box(Ray ray, uint i) {
 	// {{{
-	Ray local_ray = transform(ray, scene_inv_transform[i]);
+	Ray local_ray = transform(ray, scene_inv_transform(i));
 
 	vec3 inv = 1.0 / local_ray.dir;
 	vec3 n = inv * local_ray.origin;
@@ -244,10 +536,10 @@ RayHit intersect_box(Ray ray, uint i) {
 
 	if (local_tn > local_tx || local_tx < 0.0 || local_tn < 0.0) return NO_HIT;
 
-	vec3 pos = transform(pos_from_ray(local_ray, local_tn), scene_transform[i]);
+	vec3 pos = transform(pos_from_ray(local_ray, local_tn), scene_transform(i));
 	vec3 normal = transform_n(
 		step(vec3(local_tn), t1) * -sign(local_ray.dir),
-		scene_normal_transform[i]
+		scene_normal_transform(i)
 	);
 	float distance = distance(ray.origin, pos);
 
@@ -255,18 +547,518 @@ RayHit intersect_box(Ray ray, uint i) {
 	// }}}
 }
 
+// the XZ square of the unit cube, or the circle inside it for discs, so
+// scale.x and scale.z are the half-extents. double-sided ones face the ray,
+// single-sided ones can only be seen (and only emit light) towards local +Y
+RayHit intersect_plane(Ray ray, uint i, bool disc) {
+	// {{{
+	Ray local_ray = transform(ray, scene_inv_transform(i));
+	if (local_ray.dir.y == 0.0) return NO_HIT;
+	if (scene_double_sided(i) == 0u && local_ray.dir.y > 0.0) return NO_HIT;
+
+	float local_t = -local_ray.origin.y / local_ray.dir.y;
+	if (local_t < 0.0) return NO_HIT;
+
+	vec3 local_pos = pos_from_ray(local_ray, local_t);
+	bool outside = disc
+		? dot(local_pos.xz, local_pos.xz) > 1.0
+		: abs(local_pos.x) > 1.0 || abs(local_pos.z) > 1.0;
+	if (outside) return NO_HIT;
+
+	vec3 pos = transform(local_pos, scene_transform(i));
+	vec3 normal = transform_n(
+		vec3(0.0, -sign(local_ray.dir.y), 0.0),
+		scene_normal_transform(i)
+	);
+	float distance = distance(ray.origin, pos);
+
+	return RayHit(true, i, pos, normal, distance);
+	// }}}
+}
+
+// radius 1 around the Y axis and capped at y = ±1, like the unit cube. the
+// side is tested first, the cap facing the ray is only hit if the side isn't
+RayHit intersect_cylinder(Ray ray, uint i) {
+	// {{{
+	Ray local_ray = transform(ray, scene_inv_transform(i));
+	vec3 o = local_ray.origin;
+	vec3 d = local_ray.dir;
+
+	vec3 local_pos;
+	vec3 local_normal;
+	bool hit = false;
+
+	// side, skipped for rays along the axis where `a` would divide by zero
+	float a = dot(d.xz, d.xz);
+	float b = dot(o.xz, d.xz);
+	float c = dot(o.xz, o.xz) - 1.0;
+	if (a > 1e-12) {
+		float h = b * b - a * c;
+		if (h < 0.0) return NO_HIT;
+
+		float local_t = (-b - sqrt(h)) / a;
+		local_pos = pos_from_ray(local_ray, local_t);
+		if (local_t >= 0.0 && abs(local_pos.y) <= 1.0) {
+			// on the surface, so the length is 1 even for tangent rays
+			local_normal = vec3(local_pos.x, 0.0, local_pos.z);
+			hit = true;
+		}
+	} else if (c > 0.0) {
+		return NO_HIT;
+	}
+
+	// cap
+	if (!hit) {
+		if (d.y == 0.0) return NO_HIT;
+
+		float local_t = (-sign(d.y) - o.y) / d.y;
+		local_pos = pos_from_ray(local_ray, local_t);
+		if (local_t < 0.0 || dot(local_pos.xz, local_pos.xz) > 1.0) return NO_HIT;
+		local_normal = vec3(0.0, -sign(d.y), 0.0);
+	}
+
+	vec3 pos = transform(local_pos, scene_transform(i));
+	vec3 normal = transform_n(local_normal, scene_normal_transform(i));
+	float distance = distance(ray.origin, pos);
+
+	return RayHit(true, i, pos, normal, distance);
+	// }}}
+}
+
+// a sphere swept along the Y axis, fitting the unit cube: the caps have radius
+// r and their centers are at y = ±(1 - r), so r = 1 is a sphere. the result is
+// the nearest of the side and both caps
+RayHit intersect_capsule(Ray ray, uint i) {
+	// {{{
+	Ray local_ray = transform(ray, scene_inv_transform(i));
+	vec3 o = local_ray.origin;
+	vec3 d = local_ray.dir;
+	float r = scene_cap_radius(i);
+	float h = 1.0 - r;
+
+	float local_t = FLT_MAX;
+
+	// side, skipped for rays along the axis where `a` would divide by zero
+	float a = dot(d.xz, d.xz);
+	float b = dot(o.xz, d.xz);
+	float c = dot(o.xz, o.xz) - r * r;
+	if (a > 1e-12) {
+		float e = b * b - a * c;
+		// missing the infinite cylinder misses the caps too
+		if (e < 0.0) return NO_HIT;
+
+		float t = (-b - sqrt(e)) / a;
+		if (t >= 0.0 && abs(o.y + t * d.y) <= h) local_t = t;
+	} else if (c > 0.0) {
+		return NO_HIT;
+	}
+
+	// caps
+	for (int s = -1; s <= 1; s += 2) {
+		vec3 oc = o - vec3(0.0, float(s) * h, 0.0);
+		float cb = dot(oc, d);
+		float e = cb * cb - dot(oc, oc) + r * r;
+		if (e < 0.0) continue;
+
+		float t = -cb - sqrt(e);
+		if (t >= 0.0 && t < local_t) local_t = t;
+	}
+	if (local_t == FLT_MAX) return NO_HIT;
+
+	vec3 local_pos = pos_from_ray(local_ray, local_t);
+	vec3 local_normal = local_pos - vec3(0.0, clamp(local_pos.y, -h, h), 0.0);
+
+	vec3 pos = transform(local_pos, scene_transform(i));
+	vec3 normal = transform_n(local_normal / r, scene_normal_transform(i));
+	float distance = distance(ray.origin, pos);
+
+	return RayHit(true, i, pos, normal, distance);
+	// }}}
+}
+
+vec3 mesh_corner(int texel) {
+	ivec2 uv = ivec2(texel % MESH_TEXTURE_WIDTH, texel / MESH_TEXTURE_WIDTH);
+	return texelFetch(mesh_triangles, uv, 0).xyz;
+}
+
+vec4 bvh_texel(int texel) {
+	ivec2 uv = ivec2(texel % MESH_TEXTURE_WIDTH, texel / MESH_TEXTURE_WIDTH);
+	return texelFetch(mesh_bvh, uv, 0);
+}
+
+// distance to a BVH node's box along the ray, FLT_MAX when it's missed.
+// `inv_d` is 1 / the ray direction
+float bvh_node_distance(int node, vec3 o, vec3 inv_d) {
+	vec3 t1 = (bvh_texel(node * 2).xyz - o) * inv_d;
+	vec3 t2 = (bvh_texel(node * 2 + 1).xyz - o) * inv_d;
+	vec3 lo = min(t1, t2);
+	vec3 hi = max(t1, t2);
+	float tn = max(max(lo.x, lo.y), max(lo.z, 0.0));
+	float tx = min(min(hi.x, hi.y), hi.z);
+	return tn <= tx ? tn : FLT_MAX;
+}
+
+// Möller-Trumbore, updates `local_t` and `local_normal` if the triangle is
+// nearer than the current hit
+void intersect_triangle(
+	int k, vec3 o, vec3 d,
+	inout float local_t, inout vec3 local_normal
+) {
+	vec3 v0 = mesh_corner(k * 3);
+	vec3 e1 = mesh_corner(k * 3 + 1) - v0;
+	vec3 e2 = mesh_corner(k * 3 + 2) - v0;
+
+	vec3 p = cross(d, e2);
+	float det = dot(e1, p);
+	if (abs(det) < 1e-12) return;
+	float inv = 1.0 / det;
+
+	vec3 s = o - v0;
+	float u = dot(s, p) * inv;
+	if (u < 0.0 || u > 1.0) return;
+	vec3 q = cross(s, e1);
+	float v = dot(d, q) * inv;
+	if (v < 0.0 || u + v > 1.0) return;
+
+	float t = dot(e2, q) * inv;
+	if (t >= 0.0 && t < local_t) {
+		local_t = t;
+		local_normal = cross(e1, e2);
+	}
+}
+
+// the BVH is walked nearest child first. the children that still have to be
+// visited go on a small stack, one per level at most (see geometry/bvh.rs).
+// the normal faces the ray, like double-sided planes
+RayHit intersect_mesh(Ray ray, uint i) {
+	// {{{
+	Ray local_ray = transform(ray, scene_inv_transform(i));
+	vec3 o = local_ray.origin;
+	vec3 d = local_ray.dir;
+	vec3 inv_d = 1.0 / d;
+
+	float local_t = FLT_MAX;
+	vec3 local_normal = vec3(0.0);
+
+	int node = int(scene_mesh_range(i).x);
+	if (scene_mesh_range(i).y == 0u) return NO_HIT;
+	bvh_visits++;
+	if (bvh_node_distance(node, o, inv_d) == FLT_MAX) return NO_HIT;
+
+	int stack[MAX_BVH_DEPTH];
+	float stack_t[MAX_BVH_DEPTH];
+	int size = 0;
+	while (true) {
+		vec4 a = bvh_texel(node * 2);
+		int count = int(bvh_texel(node * 2 + 1).w);
+
+		if (count > 0) {
+			int first = int(a.w);
+			for (int k = first; k < first + count; k++) {
+				intersect_triangle(k, o, d, local_t, local_normal);
+			}
+		} else {
+			int near = node + 1;
+			int far = int(a.w);
+			float near_t = bvh_node_distance(near, o, inv_d);
+			float far_t = bvh_node_distance(far, o, inv_d);
+			bvh_visits += 2u;
+			if (far_t < near_t) {
+				int n = near;
+				near = far;
+				far = n;
+				float t = near_t;
+				near_t = far_t;
+				far_t = t;
+			}
+
+			if (near_t < local_t) {
+				if (far_t < local_t && size < MAX_BVH_DEPTH) {
+					stack[size] = far;
+					stack_t[size] = far_t;
+					size++;
+				}
+				node = near;
+				continue;
+			}
+		}
+
+		// skip the nodes that are behind the nearest hit by now
+		while (size > 0 && stack_t[size - 1] >= local_t) size--;
+		if (size == 0) break;
+		size--;
+		node = stack[size];
+	}
+	if (local_t == FLT_MAX) return NO_HIT;
+
+	local_normal = normalize(local_normal);
+	local_normal *= -sign(dot(local_normal, d));
+
+	vec3 pos = transform(pos_from_ray(local_ray, local_t), scene_transform(i));
+	vec3 normal = transform_n(local_normal, scene_normal_transform(i));
+	float distance = distance(ray.origin, pos);
+
+	return RayHit(true, i, pos, normal, distance);
+	// }}}
+}
+
+// signed distance to a box with rounded edges, centered on the origin
+// b is the half-extent and r is the corner radius
+float sd_rounded_box(vec3 p, vec3 b, float r) {
+	vec3 q = abs(p) - (b - r);
+	return length(max(q, 0.0)) + min(max(q.x, max(q.y, q.z)), 0.0) - r;
+}
+
+// the ray is intersected with the bounding box first, then sphere traced
+// against the SDF. this is done in a local space that only has rotation and
+// translation removed so the corners stay round under non-uniform scale
+RayHit intersect_rounded_box(Ray ray, uint i) {
+	// {{{
+	mat4 m = scene_transform(i);
+	vec3 b = vec3(length(m[0].xyz), length(m[1].xyz), length(m[2].xyz));
+	float r = scene_corner_radius(i) * min(min(b.x, b.y), b.z);
+
+	Ray local_ray = transform(ray, scene_inv_transform(i));
+	local_ray.origin *= b;
+	local_ray.dir = normalize(local_ray.dir * b);
+
+	// bounding box
+	vec3 inv = 1.0 / local_ray.dir;
+	vec3 n = inv * local_ray.origin;
+	vec3 k = abs(inv) * b;
+	vec3 t1 = -n - k;
+	vec3 t2 = -n + k;
+
+	float local_tn = max(max(t1.x, t1.y), t1.z);
+	float local_tx = min(min(t2.x, t2.y), t2.z);
+
+	if (local_tn > local_tx || local_tx < 0.0 || local_tn < 0.0) return NO_HIT;
+
+	// sphere tracing, the tolerance grows with distance to stay within float
+	// precision on far away objects
+	float t = local_tn;
+	vec3 local_pos;
+	bool hit = false;
+	for (int s = 0; s < 64; s++) {
+		local_pos = pos_from_ray(local_ray, t);
+		float d = sd_rounded_box(local_pos, b, r);
+		if (d < max(t, 1.0) * 0.00001) {
+			hit = true;
+			break;
+		}
+		t += d;
+		if (t > local_tx) break;
+	}
+	if (!hit) return NO_HIT;
+
+	// analytic gradient of the SDF, smooth across the rounded region
+	vec3 q = abs(local_pos) - (b - r);
+	vec3 local_normal = sign(local_pos) * max(q, 0.0);
+	if (max(q.x, max(q.y, q.z)) <= 0.0) {
+		// the point is on a flat face
+		local_normal = sign(local_pos) * step(vec3(max(q.x, max(q.y, q.z))), q);
+	}
+
+	vec3 pos = transform(local_pos / b, m);
+	// the normal transform divides by scale, which cancels out here
+	vec3 normal = transform_n(normalize(local_normal) * b, scene_normal_transform(i));
+
+	// the local space is only rotated and translated, so distances match
+	return RayHit(true, i, pos, normal, t);
+	// }}}
+}
+
+// {{{ constructive solid geometry
+// where the line of a ray enters and leaves a sphere or a box with sharp
+// corners, as distances along the world ray, with outward normals
+struct Interval {
+	bool hit;
+	float tn;
+	float tx;
+	vec3 nn;
+	vec3 nx;
+};
+
+const Interval NO_INTERVAL = Interval(false, 0.0, 0.0, vec3(0.0), vec3(0.0));
+
+Interval csg_interval(Ray ray, uint i) {
+	Ray local_ray = transform(ray, scene_inv_transform(i));
+
+	float tn, tx;
+	vec3 nn, nx;
+	if (scene_obj_type(i) == OBJ_TYPE_SPHERE) {
+		float b = dot(local_ray.origin, local_ray.dir);
+		float d = b * b - dot(local_ray.origin, local_ray.origin) + 1.0;
+		if (d < 0.0) return NO_INTERVAL;
+
+		tn = -b - sqrt(d);
+		tx = -b + sqrt(d);
+		nn = pos_from_ray(local_ray, tn);
+		nx = pos_from_ray(local_ray, tx);
+	} else {
+		// per axis, written out so that rays parallel to a face and starting
+		// on the center plane don't turn into 0 * inf
+		vec3 lo = (-1.0 - local_ray.origin) / local_ray.dir;
+		vec3 hi = (1.0 - local_ray.origin) / local_ray.dir;
+		vec3 t1 = min(lo, hi);
+		vec3 t2 = max(lo, hi);
+
+		tn = max(max(t1.x, t1.y), t1.z);
+		tx = min(min(t2.x, t2.y), t2.z);
+		if (tn > tx) return NO_INTERVAL;
+
+		nn = step(vec3(tn), t1) * -sign(local_ray.dir);
+		nx = step(t2, vec3(tx)) * sign(local_ray.dir);
+	}
+
+	vec3 pn = transform(pos_from_ray(local_ray, tn), scene_transform(i));
+	vec3 px = transform(pos_from_ray(local_ray, tx), scene_transform(i));
+	return Interval(
+		true,
+		dot(pn - ray.origin, ray.dir),
+		dot(px - ray.origin, ray.dir),
+		transform_n(nn, scene_normal_transform(i)),
+		transform_n(nx, scene_normal_transform(i))
+	);
+}
+
+bool csg_inside(uint op, bool a, bool b) {
+	switch (op) {
+		case CSG_UNION:
+			return a || b;
+		case CSG_INTERSECT:
+			return a && b;
+		case CSG_SUBTRACT:
+			return a && !b;
+	}
+	return a;
+}
+
+bool in_interval(Interval iv, float t) {
+	return iv.hit && iv.tn < t && t < iv.tx;
+}
+
+// the nearest boundary of either object where the ray goes in or out of the
+// result. the normal is turned to face the ray where it goes in and away from
+// it where it comes out, so cuts get the inverted normals of the partner.
+// the whole result has the material of object i
+RayHit intersect_csg(Ray ray, uint i) {
+	// {{{
+	uint op = scene_csg(i).x;
+	Interval ivs[2] = Interval[2](
+		csg_interval(ray, i),
+		csg_interval(ray, scene_csg(i).y)
+	);
+
+	float best = FLT_MAX;
+	vec3 normal = vec3(0.0);
+	for (int k = 0; k < 4; k++) {
+		bool first = k < 2;
+		bool entering = (k & 1) == 0;
+		Interval iv = ivs[first ? 0 : 1];
+		if (!iv.hit) continue;
+
+		float t = entering ? iv.tn : iv.tx;
+		if (t < 0.0 || t >= best) continue;
+
+		bool o = in_interval(ivs[first ? 1 : 0], t);
+		bool before = first
+			? csg_inside(op, !entering, o)
+			: csg_inside(op, o, !entering);
+		bool after = first
+			? csg_inside(op, entering, o)
+			: csg_inside(op, o, entering);
+		if (before == after) continue;
+
+		vec3 n = entering ? iv.nn : iv.nx;
+		best = t;
+		normal = (after == (dot(n, ray.dir) < 0.0)) ? n : -n;
+	}
+	if (best == FLT_MAX) return NO_HIT;
+
+	return RayHit(true, i, pos_from_ray(ray, best), normal, best);
+	// }}}
+}
+// }}}
+
 RayHit intersect_obj(Ray ray, uint i) {
-	switch (scene_obj_type[i]) {
+	// the orientation of the normal follows from going in or out of the
+	// result, so it's never inside out and isn't flipped
+	if (scene_csg(i).x != CSG_NONE) {
+		return intersect_csg(ray, i);
+	}
+
+	RayHit hit = NO_HIT;
+	switch (scene_obj_type(i)) {
 		case OBJ_TYPE_SPHERE:
-			return intersect_sphere(ray, i);
+			hit = intersect_sphere(ray, i);
+			break;
 		case OBJ_TYPE_BOX:
-			return intersect_box(ray, i);
+			// zero radius takes the exact (and much cheaper) path
+			if (scene_corner_radius(i) > 0.0) {
+				hit = intersect_rounded_box(ray, i);
+			} else {
+				hit = intersect_box(ray, i);
+			}
+			break;
+		case OBJ_TYPE_PLANE:
+			hit = intersect_plane(ray, i, false);
+			break;
+		case OBJ_TYPE_DISC:
+			hit = intersect_plane(ray, i, true);
+			break;
+		case OBJ_TYPE_CYLINDER:
+			hit = intersect_cylinder(ray, i);
+			break;
+		case OBJ_TYPE_CAPSULE:
+			hit = intersect_capsule(ray, i);
+			break;
+		case OBJ_TYPE_MESH:
+			hit = intersect_mesh(ray, i);
+			break;
+	}
+
+	// after the normal transform, which is what turns mirrored objects
+	// inside out in the first place
+	if (scene_flip_normals(i) == 1u) {
+		hit.normal = -hit.normal;
 	}
+	return hit;
+}
+
+// infinite horizontal plane, visible from both sides
+RayHit intersect_ground(Ray ray) {
+	if (ground_plane == 0u || ray.dir.y == 0.0) return NO_HIT;
+
+	float t = (ground_height - ray.origin.y) / ray.dir.y;
+	if (t < 0.0) return NO_HIT;
+
+	vec3 normal = vec3(0.0, -sign(ray.dir.y), 0.0);
+	return RayHit(true, GROUND_OBJ, pos_from_ray(ray, t), normal, t);
 }
 
-RayHit intersect_world(Ray ray) {
+// while an object is soloed the others (and the ground) are hidden from the
+// camera, and from everything else too unless they keep lighting the scene
+bool solo_hidden(uint i, bool camera) {
+	return solo_object != 0xffffffffu
+	    && i != solo_object
+	    && (camera || solo_lighting == 0u);
+}
+
+// objects with a LOD above `max_lod` are skipped. `camera` is set for the rays
+// leaving the camera
+RayHit intersect_world(Ray ray, uint max_lod, bool camera) {
 	RayHit hit = NO_HIT;
+	if (!solo_hidden(GROUND_OBJ, camera)) {
+		hit = intersect_ground(ray);
+	}
 	for (uint i = 0u; i < scene_size; i++) {
+		// partners are only drawn as part of the objects that link to them
+		bool partner = scene_csg(i).z == 1u;
+		if (scene_lod(i) > max_lod || solo_hidden(i, camera) || partner) {
+			continue;
+		}
+
 		RayHit new_hit = intersect_obj(ray, i);
 		if (hit.distance > new_hit.distance) {
 			hit = new_hit;
@@ -277,66 +1069,364 @@ RayHit intersect_world(Ray ray) {
 // }}}
 
 // {{{ COLOR CALCULATIONS
+// horizon to zenith gradient, flat below the horizon
+vec3 sky(vec3 dir) {
+	return mix(horizon_color, sky_color, sqrt(max(dir.y, 0.0)));
+}
+
+// the material color with its pattern, if it has one. the checkerboard is
+// evaluated in object space so that it follows the object. the point is taken
+// slightly inside the surface, faces lying on the border between two squares
+// would flicker between the colors otherwise
+vec3 albedo(RayHit hit) {
+	uint i = hit.obj;
+	if (i == GROUND_OBJ || scene_mat_checker(i).a == 0.0) {
+		return mat_color(i);
+	}
+
+	vec4 checker = scene_mat_checker(i);
+	vec3 n = normalize(transpose(mat3(scene_transform(i))) * hit.normal);
+	vec3 p = transform(hit.pos, scene_inv_transform(i)) - n * (0.01 / checker.a);
+	ivec3 square = ivec3(floor((p * 0.5 + 0.5) * checker.a));
+	bool odd = ((square.x + square.y + square.z) & 1) != 0;
+	return odd ? checker.rgb : mat_color(i);
+}
+
+// {{{ sun
+// sun_strength sets the light the sun gives, not its brightness, so a larger
+// disc is dimmer. the disc used to be fixed at 1 - cos_radius = 0.005, where
+// this gives 100 times the strength like before
+vec3 sun_radiance() {
+	return sun_color * sun_strength / (2.0 * (1.0 - sun_cos_radius));
+}
+
+bool in_sun_disc(vec3 dir) {
+	return sun_cos_radius < 1.0 && dot(dir, sun_dir) >= sun_cos_radius;
+}
+
+// uniformly distributed over the sun disc
+vec3 sample_sun_dir() {
+	vec2 u = rng_next2();
+	float cos_theta = mix(1.0, sun_cos_radius, u.x);
+	float sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+	float phi = TWO_PI * u.y;
+
+	vec3 helper = abs(sun_dir.x) > 0.5 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(helper, sun_dir));
+	vec3 bitangent = cross(sun_dir, tangent);
+
+	return normalize(
+		(tangent * cos(phi) + bitangent * sin(phi)) * sin_theta
+		+ sun_dir * cos_theta
+	);
+}
+// }}}
+
+// {{{ shadow catchers
+// nothing but shadow catchers between a point and the given direction
+bool unoccluded(vec3 origin, vec3 dir) {
+	RayHit hit = intersect_world(Ray(origin, dir), LOD_FULL, false);
+	return !hit.hit || mat_type(hit.obj) == MAT_TYPE_SHADOW_CATCHER;
+}
+
+// how much of the light on a shadow catcher isn't blocked, with the sun and
+// the sky weighted by how much each lights the surface (the sun disc covers
+// about 1/100 of the solid angle the sky's radiance would). `soft` adds a
+// random sky sample, the preview only looks at the sun
+float catcher_visibility(RayHit hit, bool soft) {
+	vec3 origin = hit.pos + hit.normal * hit_bias(hit);
+	vec3 luma = vec3(0.2126, 0.7152, 0.0722);
+
+	// a point on the disc gives soft shadows, the preview keeps them sharp
+	vec3 to_sun = soft ? sample_sun_dir() : sun_dir;
+	float cos_sun = dot(hit.normal, sun_dir);
+	float sun = max(cos_sun, 0.0) * dot(sun_color, luma) * sun_strength;
+	float sun_vis = (sun > 0.0 && !unoccluded(origin, to_sun)) ? 0.0 : 1.0;
+	if (!soft) return sun_vis;
+
+	float ambient = dot(sky(hit.normal), luma);
+	float sky_vis = unoccluded(origin, cos_dist_in_hemi(hit.normal)) ? 1.0 : 0.0;
+
+	float total = sun + ambient;
+	return total > 0.0 ? (sun * sun_vis + ambient * sky_vis) / total : 1.0;
+}
+// }}}
+
+// light from a random point on the sun on a diffuse surface, the albedo is
+// already in the contribution. a cosine weighted bounce used to find the same
+// on average, see sun_radiance
+vec3 direct_sun(vec3 origin, vec3 normal) {
+	vec3 dir = sample_sun_dir();
+	float cos_theta = dot(normal, dir);
+	if (cos_theta <= 0.0 || !unoccluded(origin, dir)) {
+		return vec3(0.0);
+	}
+	return sun_color * sun_strength * cos_theta;
+}
+
 // heart of the renderer
-vec3 path_trace(Ray ray, float seed) {
+vec3 path_trace(Ray ray) {
 	vec3 light = vec3(0.0);
 	vec3 contribution = vec3(1.0);
 
+	// diffuse bounces look for the sun themselves, the next ray then ignores it
+	// so that it isn't counted twice
+	bool sun_sampled = false;
+
 	for (uint ray_n = 0u; ray_n <= max_bounces; ray_n++) {
-		RayHit hit = intersect_world(ray);
+		RayHit hit = intersect_world(
+			ray,
+			ray_n == 0u ? LOD_PRIMARY_ONLY : LOD_FULL,
+			ray_n == 0u
+		);
 
 		if (!hit.hit) {
-			light += contribution * sky_color;
-			light += contribution
-			       * sun_color
-						 * sun_strength * 100.0
-						 * step(0.005, max(dot(ray.dir, sun_dir) - 0.99, 0.0));
+			light += contribution * sky(ray.dir);
+			if (!sun_sampled && in_sun_disc(ray.dir)) {
+				light += contribution * sun_radiance();
+			}
 			break;
 		}
 
 		uint i = hit.obj;
-		uint m = scene_mat_type[i];
+		uint m = mat_type(i);
+		bool highlighted = highlight_selected == 1u && i == scene_selected;
+
+		// see-through apart from its shadows, the ray carries on as if the
+		// catcher wasn't there
+		if (m == MAT_TYPE_SHADOW_CATCHER && !highlighted) {
+			contribution *= catcher_visibility(hit, true);
+			ray.origin = hit.pos + ray.dir * hit_bias(hit);
+			continue;
+		}
 
-		if (highlight_selected == 1u && i == scene_selected) {
+		if (highlighted) {
 			light += contribution * vec3(0.4, 0.2, 0.1);
-		} else if (m == MAT_TYPE_SOLID) {
-			contribution *= scene_mat_color[i];
+		} else if (m == MAT_TYPE_SOLID || m == MAT_TYPE_METAL) {
+			// the fill light reaches every surface, lit or not
+			light += contribution * albedo(hit) * fill_color * fill_strength;
+			// metal reflections are tinted by fresnel instead, below
+			if (m == MAT_TYPE_SOLID) {
+				contribution *= albedo(hit);
+			}
 		} else if (m == MAT_TYPE_EMISSIVE) {
+			// seen from the camera it has its surface color, everything it
+			// lights gets the light color
+			vec3 color = (ray_n == 0u) ? albedo(hit) : mat_emissive_color(i);
 			light += contribution
-			       * scene_mat_color[i]
-			       * scene_mat_emissive_strength[i];
+			       * color
+			       * mat_emissive_strength(i);
 			break;
 		}
 
-		float r = scene_mat_roughness[i];
+		float r = mat_roughness(i);
 		r *= r; // square roughness, makes it feel more linear perceptually
 
-		vec3 diffuse = cos_dist_in_hemi(seed, hit.normal);
+		vec3 diffuse = cos_dist_in_hemi(hit.normal);
 		vec3 specular = reflect(ray.dir, hit.normal);
 		specular = normalize(mix(specular, diffuse, r*r));
 
 		// fresnel
-		float specular_chance = scene_mat_specular[i];
+		float specular_chance = mat_specular(i);
 		if (specular_chance > 0.0f) {
 			specular_chance = schlick_fresnel(
 				1.0,
-				scene_mat_ior[i],
+				mat_ior(i),
 				ray.dir,
 				hit.normal,
-				scene_mat_specular[i],
+				mat_specular(i),
 				1.0
 			);
 		}
 
-		ray.origin = hit.pos + hit.normal * 0.0001;
-		ray.dir = (hash(seed) < specular_chance) ? specular : diffuse;
+		ray.origin = hit.pos + hit.normal * hit_bias(hit);
+		bool diffuse_bounce;
+		if (m == MAT_TYPE_METAL) {
+			// reflections off steep microfacets can point into the surface,
+			// those bounce diffusely instead
+			vec3 facet = ggx_normal(hit.normal, r);
+			vec3 glossy = reflect(ray.dir, facet);
+			diffuse_bounce =
+				rng_next() >= mat_metallic(i) || dot(glossy, hit.normal) <= 0.0;
+			if (diffuse_bounce) {
+				contribution *= albedo(hit);
+				ray.dir = diffuse;
+			} else {
+				contribution *= metal_fresnel(albedo(hit), ray.dir, facet);
+				ray.dir = glossy;
+			}
+		} else {
+			diffuse_bounce = rng_next() >= specular_chance;
+			ray.dir = diffuse_bounce ? diffuse : specular;
+		}
+
+		// a small sun is rarely hit by chance, so it's sampled directly. only
+		// where the next ray would be traced, like finding it by chance
+		sun_sampled = diffuse_bounce && ray_n < max_bounces;
+		if (sun_sampled) {
+			light += contribution * direct_sun(ray.origin, hit.normal);
+		}
+	}
+
+	return (render_mode == RENDER_RAY_DIR)
+		? palette_vector(ray.dir * 0.5 + 0.5, debug_palette)
+		: light;
+}
+
+// Texture coordinates from the object space hit position, so they stay
+// attached to the object when it moves or rotates:
+// - spheres: longitude and latitude, u = 0.5 facing +Z, v = 0 at the bottom
+// - boxes: each face is projected along its axis, [-1, 1] maps to [0, 1]
+// - planes and discs: local XZ, [-1, 1] maps to [0, 1]
+// - cylinders: around the side like a sphere's longitude, caps like planes
+// - capsules: longitude around, v = 0 at the bottom and 1 at the top
+// - meshes: projected from above, local XZ like planes
+// - ground plane: world space XZ, one unit per UV
+vec2 object_uv(RayHit hit) {
+	if (hit.obj == GROUND_OBJ) {
+		return hit.pos.xz;
+	}
+
+	vec3 p = transform(hit.pos, scene_inv_transform(hit.obj));
+	vec2 uv;
+	switch (scene_obj_type(hit.obj)) {
+		case OBJ_TYPE_SPHERE:
+			p = normalize(p);
+			uv = vec2(
+				atan(p.x, p.z) * RECIP_TWO_PI + 0.5,
+				asin(clamp(p.y, -1.0, 1.0)) * RECIP_PI + 0.5
+			);
+			break;
+		case OBJ_TYPE_BOX:
+			// the face is along the axis with the largest coordinate
+			vec3 a = abs(p);
+			if (a.x >= a.y && a.x >= a.z) {
+				uv = vec2(p.z * -sign(p.x), p.y);
+			} else if (a.y >= a.z) {
+				uv = vec2(p.x, p.z * -sign(p.y));
+			} else {
+				uv = vec2(p.x * sign(p.z), p.y);
+			}
+			uv = uv * 0.5 + 0.5;
+			break;
+		case OBJ_TYPE_PLANE:
+		case OBJ_TYPE_DISC:
+			uv = p.xz * 0.5 + 0.5;
+			break;
+		case OBJ_TYPE_CYLINDER:
+			if (abs(p.y) < 0.9999) {
+				uv = vec2(atan(p.x, p.z) * RECIP_TWO_PI + 0.5, p.y * 0.5 + 0.5);
+			} else {
+				uv = p.xz * 0.5 + 0.5;
+			}
+			break;
+		case OBJ_TYPE_CAPSULE:
+			uv = vec2(atan(p.x, p.z) * RECIP_TWO_PI + 0.5, p.y * 0.5 + 0.5);
+			break;
+		case OBJ_TYPE_MESH:
+			uv = p.xz * 0.5 + 0.5;
+			break;
+	}
+
+	vec4 m = scene_uv_matrix(hit.obj);
+	return mat2(m.xy, m.zw) * uv + scene_uv_offset(hit.obj);
+}
+
+// direct sun and sky light only, no shadows
+vec3 preview_color(RayHit hit) {
+	float cos_sun = -dot(hit.normal, sun_dir);
+	vec3 color = albedo(hit) * 0.01;
+	color *= sky_color + cos_sun * sun_color * sun_strength * 100.0;
+	return color;
+}
+
+// flat per-object color with darkened edges, so that adjacent boxes can be
+// told apart. edge widths scale with distance to stay roughly constant on screen
+vec3 wireframe_color(Ray primary, RayHit hit) {
+	float width = 0.003 * hit.distance;
+
+	if (hit.obj == GROUND_OBJ) {
+		return vec3(0.5);
 	}
 
-	return (render_mode == RENDER_RAY_DIR) ? (ray.dir * 0.5 + 0.5) : light;
+	vec3 color = palette_id(hit.obj, debug_palette);
+	mat4 m = scene_transform(hit.obj);
+	vec3 scale = vec3(length(m[0].xyz), length(m[1].xyz), length(m[2].xyz));
+	vec3 local_pos = transform(hit.pos, scene_inv_transform(hit.obj));
+
+	bool edge = false;
+	switch (scene_obj_type(hit.obj)) {
+		case OBJ_TYPE_SPHERE:
+			// silhouette only, spheres don't have edges
+			edge = abs(dot(primary.dir, hit.normal)) < 0.25;
+			break;
+		case OBJ_TYPE_BOX:
+			// world space distance to each pair of faces. the smallest one is the
+			// face that was hit, the second smallest is the distance to an edge
+			vec3 d = (1.0 - abs(local_pos)) * scale;
+			float lo = min(min(d.x, d.y), d.z);
+			float hi = max(max(d.x, d.y), d.z);
+			float mid = d.x + d.y + d.z - lo - hi;
+			edge = mid < width;
+			break;
+		case OBJ_TYPE_PLANE:
+			// world space distance to the nearest side
+			vec2 e = (1.0 - abs(local_pos.xz)) * scale.xz;
+			edge = min(e.x, e.y) < width;
+			break;
+		case OBJ_TYPE_CYLINDER:
+			// rims around the caps and the silhouette of the side
+			float rim = abs(local_pos.y) < 0.9999
+				? (1.0 - abs(local_pos.y)) * scale.y
+				: (1.0 - length(local_pos.xz)) * min(scale.x, scale.z);
+			edge = rim < width || abs(dot(primary.dir, hit.normal)) < 0.25;
+			break;
+		case OBJ_TYPE_CAPSULE:
+		case OBJ_TYPE_MESH:
+			edge = abs(dot(primary.dir, hit.normal)) < 0.25;
+			break;
+		case OBJ_TYPE_DISC:
+			edge = (1.0 - length(local_pos.xz)) * min(scale.x, scale.z) < width;
+			break;
+	}
+
+	return edge ? color * 0.2 : color;
 }
 
-// switch between render modes
-vec3 get_color(Ray primary, float seed) {
+// thin lens depth of field: the ray starts from a random point on the lens and
+// is aimed at the point the pinhole ray meets the focal plane, so only things
+// at the focal distance stay sharp. the ray origin changes with every sample,
+// which is why this can't happen in the ray directions prepass
+Ray lens_ray(Ray ray) {
+	vec3 forward = cross(camera_up, camera_right);
+	vec3 focus = pos_from_ray(ray, focal_distance / dot(ray.dir, forward));
+
+	vec2 u = rng_next2();
+	float angle = TWO_PI * u.y;
+	vec2 lens = vec2(cos(angle), sin(angle)) * sqrt(u.x) * aperture;
+
+	ray.origin += camera_right * lens.x + camera_up * lens.y;
+	ray.dir = normalize(focus - ray.origin);
+	return ray;
+}
+
+const float BVH_HEATMAP_MAX = 64.0;
+
+// rare paths that find a small, bright light are far brighter than the
+// average and take very long to average out. scaling each sample down before
+// it's accumulated removes them at the cost of a little energy
+vec3 clamp_firefly(vec3 color) {
+	float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+	if (firefly_clamp > 0.0 && render_mode == RENDER_REALISTIC
+	    && luminance > firefly_clamp) {
+		return color * (firefly_clamp / luminance);
+	}
+	return color;
+}
+
+// switch between render modes. depth of field is only in the realistic mode,
+// the debug views stay sharp
+vec3 get_color(Ray primary) {
 	if (render_mode == RENDER_REALISTIC || render_mode == RENDER_RAY_DIR) {
 		vec3 color = vec3(0.0);
 
@@ -345,63 +1435,143 @@ vec3 get_color(Ray primary, float seed) {
 			Ray ray = primary;
 
 			// "antialias" by skewing the ray direction by a small random offset
-			vec2 ofs = (hash2(seed) * 2.0 - 1.0) / 1000.0;
+			vec2 ofs = (rng_next2() * 2.0 - 1.0) / 1000.0;
 			ray.dir += (cross(camera_dir, CAMERA_UP) * ofs.x);
 			ray.dir += (CAMERA_UP * ofs.y);
 
-			color += path_trace(ray, seed);
-			seed = hash(seed);
+			if (aperture > 0.0) {
+				ray = lens_ray(ray);
+			}
+
+			color += clamp_firefly(path_trace(ray));
 		}
 		color /= float(samples_per_frame);
 
 		return color;
 	}
 
+	// the first three streams as red, green and blue. each channel should look
+	// like uniform white noise, without any pattern or correlation between them
 	if (render_mode == RENDER_NOISE) {
-		return vec3(seed);
+		return vec3(rng_state.rgb) * RECIP_UINT_MAX;
+	}
+
+	// the LOD view shows the skipped objects too
+	RayHit hit = intersect_world(
+		primary,
+		render_mode == RENDER_LOD ? LOD_SKIP : LOD_PRIMARY_ONLY,
+		true
+	);
+
+	// misses count too, the box of a mesh can be entered without a hit.
+	// BVH_HEATMAP_MAX visits or more is the end of the palette
+	if (render_mode == RENDER_BVH_HEATMAP) {
+		return palette_scalar(min(float(bvh_visits) / BVH_HEATMAP_MAX, 1.0), debug_palette);
 	}
 
-	RayHit hit = intersect_world(primary);
+	// the preview has no shadows otherwise, catchers show the sun's on top of
+	// whatever is behind them
+	float shadow = 1.0;
+	if (render_mode == RENDER_PREVIEW) {
+		for (int k = 0; k < 4; k++) {
+			if (!hit.hit || mat_type(hit.obj) != MAT_TYPE_SHADOW_CATCHER) break;
+			shadow *= catcher_visibility(hit, false);
+			primary.origin = hit.pos + primary.dir * hit_bias(hit);
+			hit = intersect_world(primary, LOD_PRIMARY_ONLY, true);
+		}
+	}
 
 	if (!hit.hit) {
-		return sky_color;
+		return sky(primary.dir) * shadow;
 	}
 	
 	switch (render_mode) {
 		case RENDER_PREVIEW:
-			float cos_sun = -dot(hit.normal, sun_dir);
-			vec3 color = scene_mat_color[hit.obj] * 0.01;
-			color *= sky_color + cos_sun * sun_color * sun_strength * 100.0;
-			return color;
+			return preview_color(hit) * shadow;
 		case RENDER_POSITION:
-			return hit.pos / 2.0 + 0.5;
+			vec3 extent = max(bounds_max - bounds_min, vec3(0.0001));
+			vec3 p = clamp((hit.pos - bounds_min) / extent, 0.0, 1.0);
+			return palette_vector(p, debug_palette);
 		case RENDER_NORMAL:
-			return hit.normal / 2.0 + 0.5;
+			return palette_vector(hit.normal / 2.0 + 0.5, debug_palette);
 		case RENDER_DEPTH:
-			return vec3(hit.distance / 100.0);
+			float depth = min(hit.distance / max(max_depth, 0.0001), 1.0);
+			return palette_scalar(depth, debug_palette);
 		case RENDER_FRESNEL:
-			return vec3(fresnel(primary.dir, hit.normal));
+			return palette_scalar(fresnel(primary.dir, hit.normal), debug_palette);
 		case RENDER_ROUGHNESS:
-			float r = scene_mat_roughness[hit.obj];
-			return vec3(max(r - fresnel(primary.dir, hit.normal), 0.0));
+			float r = mat_roughness(hit.obj);
+			r = max(r - fresnel(primary.dir, hit.normal), 0.0);
+			return palette_scalar(r, debug_palette);
+		case RENDER_WIREFRAME:
+			return wireframe_color(primary, hit);
+		case RENDER_OBJECT_ID:
+			return palette_id(hit.obj, debug_palette);
+		case RENDER_UV:
+			return palette_vector(vec3(fract(object_uv(hit)), 0.0), debug_palette);
+		case RENDER_LOD:
+			// yellow: only primary rays, red: skipped entirely
+			uint lod = hit.obj == GROUND_OBJ ? LOD_FULL : scene_lod(hit.obj);
+			vec3 shading = preview_color(hit);
+			if (lod == LOD_PRIMARY_ONLY) {
+				return mix(shading, vec3(1.0, 0.8, 0.0), 0.6);
+			} else if (lod == LOD_SKIP) {
+				return mix(shading, vec3(1.0, 0.0, 0.0), 0.6);
+			}
+			return shading;
 	}
 }
 // }}}
 
+// pinhole ray from the prepass, depth of field is applied per sample on top
 Ray get_primary_ray(vec2 uv) {
 	uvec3 texel = texture(ray_dirs, uv).rgb;
-	return Ray(camera_pos, vec3(uintBitsToFloat(texel)));
+	vec3 dir = vec3(uintBitsToFloat(texel));
+	if (stereo == 0u || stereo_separation == 0.0) {
+		return Ray(camera_pos, dir);
+	}
+
+	// each eye aims at the point the camera's ray meets the convergence plane
+	// by whole pixels like the views in the ray directions
+	float side = uv.x * scr_size.x < floor(scr_size.x * 0.5) ? -0.5 : 0.5;
+	vec3 eye = camera_pos + camera_right * (stereo_separation * side);
+	vec3 target = camera_pos + dir * (stereo_convergence / dot(dir, camera_dir));
+	return Ray(eye, normalize(target - eye));
 }
 
 void main() {
+	// the index of the object under the texel instead of a color, the ground
+	// and the sky aren't objects
+	if (picking == 1u) {
+		Ray ray = get_primary_ray((vec2(pick_texel) + 0.5) / scr_size);
+		RayHit hit = intersect_world(ray, LOD_PRIMARY_ONLY, true);
+		bool object = hit.hit && hit.obj != GROUND_OBJ;
+		out_color = uvec4(object ? hit.obj : 0xffffffffu, 0u, 0u, 0u);
+		return;
+	}
+
 	vec2 uv = gl_FragCoord.xy / scr_size;
-	float seed = float(texture(noise, uv).r) * RECIP_UINT_MAX;
+	uvec4 state = texture(noise, uv);
+	for (uint i = 0u; i < 4u; i++) {
+		if (state[i] == 0u) {
+			// the noise prepass should never output zero, but if it happens,
+			// don't let a whole region of pixels share the same random numbers
+			// numbered within the view like in the prepass
+			vec2 coord = gl_FragCoord.xy;
+			float width = stereo == 1u ? floor(scr_size.x * 0.5) : scr_size.x;
+			coord.x -= coord.x < width ? 0.0 : width;
+			uint coords = uint(coord.y * width + coord.x);
+			state[i] = pcg(coords ^ frame_index ^ (i << 28u));
+		}
+	}
+	rng_init(state);
 	Ray primary = get_primary_ray(uv);
 
-	vec3 color = get_color(primary, seed);
+	vec3 color = get_color(primary);
 
+	// only set for render modes that are worth accumulating
 	if (frame_index > 1u && accumulate == 1u) {
-		color += uintBitsToFloat(texture(image, uv).rgb);
+		color += uintBitsToFloat(texture(image, uv).rgb) * history_scale;
 	}
 
 	out_color = floatBitsToUint(vec4(color, 1.0));
@@ -5,9 +5,17 @@ precision mediump usampler2D;
 uniform usampler2D noise;
 uniform uint frame_index;
 uniform vec2 scr_size;
+uniform uint stereo; // pixels are numbered within each view
+
+// set when the textures are reset, starts over from the coordinates
+uniform uint reseed;
+uniform uint seed;
 
 out uvec4 out_color;
 
+const uint STREAM_SALT = 0x9e3779b9u;
+const uint STREAM_INCREMENT[4] = uint[4](0u, 0x68e31da4u, 0xb5297a4du, 0x1b56c4e9u);
+
 uint pcg_hash(uint p) {
 	uint state = p * 747796405u + 2891336453u;
 	uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
@@ -16,14 +24,32 @@ uint pcg_hash(uint p) {
 
 void main() {
 	vec2 uv = gl_FragCoord.xy / scr_size;
-	uint value;
 
-	if (frame_index == 1u) {
-		value = uint(gl_FragCoord.y * scr_size.x + gl_FragCoord.x);
-	} else {
-		value = texture(noise, uv).r;
+	// both views start from the same noise, so that they only differ by the
+	// separation of the eyes
+	vec2 coord = gl_FragCoord.xy;
+	float width = scr_size.x;
+	if (stereo == 1u) {
+		width = floor(width * 0.5);
+		coord.x -= coord.x < width ? 0.0 : width;
+	}
+	uint coords = uint(coord.y * width + coord.x);
+	uvec4 prev = texture(noise, uv);
+
+	// four independent streams. each channel starts from a different hash of
+	// the coordinates and steps with its own increment, so they never line up
+	uvec4 value;
+	for (uint i = 0u; i < 4u; i++) {
+		uint start = pcg_hash(coords ^ seed ^ (STREAM_SALT * (i + 1u)));
+
+		// an all-zero state never recovers into proper noise
+		bool restart = frame_index == 1u || reseed == 1u || prev[i] == 0u;
+		uint v = restart ? start : prev[i];
+		v = pcg_hash(v + STREAM_INCREMENT[i]);
+
+		// zero is the only degenerate state, keep away from it
+		value[i] = max(v, 1u);
 	}
-	value = pcg_hash(value);
 
-	out_color = uvec4(value, 0u, 0u, 0u);
+	out_color = value;
 }
@@ -0,0 +1,85 @@
+// vim:commentstring=//%s
+// False color mappings for the debug views, included ahead of the shaders
+// that use them (see `fragment_shader!`). Every debug view maps its output
+// through these so that the palette setting applies everywhere; the regular
+// shading never does.
+precision mediump float;
+
+const uint PALETTE_STANDARD     = 0u;
+const uint PALETTE_DEUTERANOPIA = 1u;
+const uint PALETTE_GRAYSCALE    = 2u;
+
+const float PALETTE_RECIP_UINT_MAX = 1.0 / float(0xFFFFFFFFu);
+
+float palette_luminance(vec3 c) {
+	return dot(c, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// cividis, linearly interpolated between samples of the sRGB colormap.
+// readable with red-green color blindness and monotonic in lightness
+const vec3 CIVIDIS[5] = vec3[5](
+	vec3(0.000, 0.135, 0.304),
+	vec3(0.256, 0.302, 0.420),
+	vec3(0.487, 0.482, 0.471),
+	vec3(0.741, 0.679, 0.437),
+	vec3(0.995, 0.909, 0.217)
+);
+
+vec3 cividis(float t) {
+	t = clamp(t, 0.0, 1.0) * 4.0;
+	int i = min(int(t), 3);
+	vec3 srgb = mix(CIVIDIS[i], CIVIDIS[i + 1], t - float(i));
+	return pow(srgb, vec3(2.2));
+}
+
+// values in [0, 1], e.g. depth or fresnel
+vec3 palette_scalar(float t, uint palette) {
+	if (palette == PALETTE_DEUTERANOPIA) {
+		return cividis(t);
+	}
+	return vec3(t);
+}
+
+// vectors with each component in [0, 1], e.g. normals or positions
+vec3 palette_vector(vec3 v, uint palette) {
+	if (palette == PALETTE_DEUTERANOPIA) {
+		// only lightness and blue versus yellow are told apart reliably, so x
+		// picks the hue, y the lightness and z the saturation
+		vec3 hue = mix(vec3(0.1, 0.3, 1.0), vec3(1.0, 0.85, 0.1), v.x);
+		vec3 color = mix(vec3(palette_luminance(hue)), hue, v.z * 0.7 + 0.3);
+		return color * (v.y * 0.8 + 0.2);
+	} else if (palette == PALETTE_GRAYSCALE) {
+		return vec3(palette_luminance(v));
+	}
+	return v;
+}
+
+uint palette_hash(uint p) {
+	uint state = p * 747796405u + 2891336453u;
+	uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+	return (word >> 22u) ^ word;
+}
+
+// a distinct color for each object
+vec3 palette_id(uint id, uint palette) {
+	uint x = palette_hash(id);
+	uint y = palette_hash(x);
+	uint z = palette_hash(y);
+
+	if (palette == PALETTE_DEUTERANOPIA) {
+		// spread along cividis, neighbours differ in lightness
+		return cividis(float(x) * PALETTE_RECIP_UINT_MAX);
+	} else if (palette == PALETTE_GRAYSCALE) {
+		// lightness alone repeats quickly, so also hatch in one of four
+		// directions
+		float level = 0.25 + 0.65 * float(y) * PALETTE_RECIP_UINT_MAX;
+		vec2 p = gl_FragCoord.xy;
+		uint dir = z & 3u;
+		float stripe = dir == 0u ? p.x
+		             : dir == 1u ? p.y
+		             : dir == 2u ? p.x + p.y
+		             : p.x - p.y;
+		return vec3(mod(stripe, 8.0) < 2.0 ? level * 0.5 : level);
+	}
+	return vec3(uvec3(x, y, z)) * PALETTE_RECIP_UINT_MAX * 0.8 + 0.2;
+}
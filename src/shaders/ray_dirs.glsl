@@ -7,18 +7,44 @@ uniform vec2 scr_size;
 // camera
 uniform mat4 inv_proj;
 uniform mat4 inv_view;
+uniform uint projection; // Projection in camera.rs
+uniform uint stereo;     // both halves get the same directions
+
+const uint PROJECTION_PERSPECTIVE = 0u;
+const uint PROJECTION_PANORAMA    = 1u;
+
+const float PI = 3.14159265358979;
 
 // not actually a color, the RGB components are float bits encoded as uints
 // which represent a vec3 that represents the ray direction for each pixel
 // the alpha channel is not used; RGBA32UI is chosen because RGB32UI isn't
 // color-renderable
+// these are the base directions of a pinhole camera, all starting at the eye.
+// depth of field moves the origin per sample, so it's left to the main shader
 out uvec4 out_color;
 
 void main() {
 	// adapted from The Cherno's series
-	vec2 uv = gl_FragCoord.xy / scr_size * 2.0 - 1.0;
-	vec4 target = inv_proj * vec4(uv, 1, 1);
-	vec3 dir = vec3(inv_view * vec4(normalize(vec3(target) / target.w), 0));
+	vec2 coord = gl_FragCoord.xy;
+	vec2 size = scr_size;
+	if (stereo == 1u) {
+		// the width is even, both views are whole pixels wide
+		size.x = floor(size.x * 0.5);
+		coord.x -= coord.x < size.x ? 0.0 : size.x;
+	}
+	vec2 uv = coord / size * 2.0 - 1.0;
+	vec3 local_dir;
+	if (projection == PROJECTION_PANORAMA) {
+		// pixel centers are symmetric around the middle, so the left and right
+		// edges meet without a seam at the back
+		float lon = uv.x * PI;
+		float lat = uv.y * PI / 2.0;
+		local_dir = vec3(cos(lat) * sin(lon), sin(lat), -cos(lat) * cos(lon));
+	} else {
+		vec4 target = inv_proj * vec4(uv, 1, 1);
+		local_dir = normalize(vec3(target) / target.w);
+	}
+	vec3 dir = vec3(inv_view * vec4(local_dir, 0));
 
 	out_color = uvec4(floatBitsToUint(dir), 0u);
 }
@@ -0,0 +1,81 @@
+// Sky colors derived from the sun elevation, a fitted gradient rather than a
+// real scattering model. A high sun gives a blue sky and a white sun, a low
+// one a warm sun and an orange to purple horizon. Haze washes out the
+// horizon and dims the sun further.
+
+pub struct SkyColors {
+	pub zenith: [f32; 3],
+	pub horizon: [f32; 3],
+	pub sun: [f32; 3],
+}
+
+const ZENITH_DAY: [f32; 3] = [0.18, 0.36, 0.85];
+const ZENITH_NIGHT: [f32; 3] = [0.005, 0.008, 0.02];
+const HORIZON_DAY: [f32; 3] = [0.65, 0.75, 0.9];
+const HORIZON_SUNSET: [f32; 3] = [0.85, 0.45, 0.3];
+const HORIZON_TWILIGHT: [f32; 3] = [0.35, 0.25, 0.45];
+const HAZE_COLOR: [f32; 3] = [0.75, 0.75, 0.75];
+
+// optical depth at the zenith relative to white light, scattering takes out
+// blue first. haze scatters all wavelengths alike
+const RAYLEIGH_DEPTH: [f32; 3] = [0.1, 0.2, 0.42];
+const HAZE_DEPTH: f32 = 0.35;
+
+// keeps the sun from going black right at the horizon
+const MAX_AIR_MASS: f32 = 12.0;
+
+// `elevation` in radians, `haze` in [0, 1]
+pub fn derive(elevation: f32, haze: f32) -> SkyColors {
+	let h = elevation.sin();
+	let day = smoothstep(-0.1, 0.25, h);
+	let sunset = 1.0 - smoothstep(0.0, 0.35, h.abs());
+	let brightness = lerp(0.05, 1.0, smoothstep(-0.2, 0.1, h));
+
+	let zenith = mix(ZENITH_NIGHT, ZENITH_DAY, day);
+	let zenith = mix(zenith, scale(HAZE_COLOR, day), haze * 0.5);
+
+	let warm = mix(HORIZON_TWILIGHT, HORIZON_SUNSET, smoothstep(-0.1, 0.05, h));
+	let horizon = scale(mix(HORIZON_DAY, warm, sunset), brightness);
+	let horizon = mix(horizon, scale(HAZE_COLOR, brightness), haze);
+
+	// relative to the zenith, so a high sun stays white
+	let air_mass = air_mass(elevation) - 1.0;
+	let visible = smoothstep(-0.02, 0.02, h);
+	let sun =
+		RAYLEIGH_DEPTH.map(|d| (-(d + HAZE_DEPTH * haze) * air_mass).exp() * visible);
+
+	SkyColors {
+		zenith,
+		horizon,
+		sun,
+	}
+}
+
+// Kasten and Young's approximation, 1 at the zenith
+fn air_mass(elevation: f32) -> f32 {
+	let degrees = elevation.to_degrees().max(0.0);
+	let m =
+		1.0 / (elevation.max(0.0).sin() + 0.50572 * (degrees + 6.07995).powf(-1.6364));
+	m.min(MAX_AIR_MASS)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+	let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+	t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+	a + (b - a) * t
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+	[
+		lerp(a[0], b[0], t),
+		lerp(a[1], b[1], t),
+		lerp(a[2], b[2], t),
+	]
+}
+
+fn scale(c: [f32; 3], s: f32) -> [f32; 3] {
+	c.map(|x| x * s)
+}
@@ -0,0 +1,173 @@
+// First-run tour. Each step waits for the user to actually do what it asks,
+// seen in the camera, the scene and the responses the windows report anyway,
+// and outlines the part of the app it's about. The progress is stored on its
+// own key, so the tour is only shown once even if the data is cleared. It can
+// be restarted from the System tab.
+use egui::{Id, LayerId, Order, Rect, Stroke};
+use nalgebra_glm::Vec3;
+
+use crate::{
+	app::PersistentData, scene::SceneResponse, settings::SettingsResponse,
+};
+
+pub const STORAGE_KEY: &str = "raytracer_tutorial";
+
+const OUTLINE_WIDTH: f32 = 3.0;
+const OUTLINE_MARGIN: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum Step {
+	Look,
+	Move,
+	NewObject,
+	EditObject,
+	ChangeSetting,
+}
+
+impl Step {
+	const ALL: [Self; 5] = [
+		Self::Look,
+		Self::Move,
+		Self::NewObject,
+		Self::EditObject,
+		Self::ChangeSetting,
+	];
+
+	fn text(self) -> &'static str {
+		match self {
+			Self::Look => "Hold the right mouse button and drag in the view to look around.",
+			Self::Move => "Move with W, A, S and D. Q and E go down and up.",
+			Self::NewObject => "This is the Scene window, click New object.",
+			Self::EditObject => "Now change the new object, e.g. drag its position or pick a color.",
+			Self::ChangeSetting => {
+				"The Settings window changes how the image is made. Try another render mode."
+			},
+		}
+	}
+
+	// the window it's about, the others are about the view
+	fn window(self) -> Option<&'static str> {
+		match self {
+			Self::Look | Self::Move => None,
+			Self::NewObject | Self::EditObject => Some("Scene"),
+			Self::ChangeSetting => Some("Settings"),
+		}
+	}
+
+	fn next(self) -> Option<Self> {
+		let i = Self::ALL.iter().position(|&s| s == self)?;
+		Self::ALL.get(i + 1).copied()
+	}
+}
+
+// what's stored, none once the tour was finished or skipped
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Tutorial {
+	step: Option<Step>,
+
+	// what the steps compare against, from the last update
+	#[serde(skip)]
+	last_camera: Option<(Vec3, Vec3)>,
+	#[serde(skip)]
+	last_len: usize,
+}
+
+// new users start at the beginning
+impl Default for Tutorial {
+	fn default() -> Self {
+		Self {
+			step: Some(Step::Look),
+			last_camera: None,
+			last_len: 0,
+		}
+	}
+}
+
+impl Tutorial {
+	pub fn restart(&mut self) {
+		*self = Self::default();
+	}
+
+	pub fn active(&self) -> bool {
+		self.step.is_some()
+	}
+
+	// advances when this frame shows that the current step was done. returns
+	// a message once the tour is over
+	pub fn observe(
+		&mut self,
+		data: &PersistentData,
+		scene: SceneResponse,
+		settings: SettingsResponse,
+	) -> Option<&'static str> {
+		let camera = (data.camera.pos, data.camera.forward_dir);
+		let last_camera = self.last_camera.replace(camera).unwrap_or(camera);
+		let last_len = std::mem::replace(&mut self.last_len, data.scene.len());
+
+		let done = match self.step? {
+			Step::Look => camera.1 != last_camera.1,
+			Step::Move => camera.0 != last_camera.0,
+			Step::NewObject => data.scene.len() > last_len,
+			Step::EditObject => scene.changed && data.scene.len() == last_len,
+			Step::ChangeSetting => settings.changed,
+		};
+		if !done {
+			return None;
+		}
+
+		self.step = self.step.and_then(Step::next);
+		self
+			.step
+			.is_none()
+			.then_some("That's the tour, it can be restarted from the System tab")
+	}
+
+	// the current step next to an outline of what it's about. `view` is the
+	// rendered part of the window
+	pub fn draw(&mut self, egui: &egui::Context, data: &PersistentData, view: Rect) {
+		let Some(step) = self.step else {
+			return;
+		};
+
+		let target = match step.window() {
+			Some(title) => egui.memory(|m| m.area_rect(Id::new(title))),
+			None => Some(view),
+		};
+		if let Some(rect) = target {
+			let painter = egui.layer_painter(LayerId::new(
+				Order::Foreground,
+				Id::new("tutorial_outline"),
+			));
+			let color = egui.style().visuals.selection.bg_fill;
+			painter.rect_stroke(
+				rect.expand(OUTLINE_MARGIN),
+				4.0,
+				Stroke::new(OUTLINE_WIDTH, color),
+			);
+		}
+
+		let number = Step::ALL.iter().position(|&s| s == step).unwrap_or(0) + 1;
+		let mut skip = false;
+		egui::Window::new("Tour")
+			.collapsible(false)
+			.resizable(false)
+			.anchor(egui::Align2::LEFT_BOTTOM, [16.0, -16.0])
+			.show(egui, |ui| {
+				ui.label(format!("Step {number} of {}", Step::ALL.len()));
+				ui.label(step.text());
+				if step.window().is_none() && data.settings.render.lock_camera {
+					ui.colored_label(
+						ui.visuals().warn_fg_color,
+						"The camera is locked, press L to unlock it.",
+					);
+				}
+				if ui.button("Skip the tour").clicked() {
+					skip = true;
+				}
+			});
+
+		if skip {
+			self.step = None;
+		}
+	}
+}
@@ -1,5 +1,4 @@
 use egui::{Color32, Ui};
-use nalgebra::Const;
 
 // {{{ UI
 #[macro_export]
@@ -66,52 +65,240 @@ pub fn modal(
 }
 // }}}
 
-// {{{ angle controller UI modifier
-pub trait AngleControl {
-	fn angle(self) -> Self;
+// {{{ color editing
+// color button whose context menu takes exact values as `#rrggbb`, `#rgb` or
+// `rgb(r, g, b)`. like egui's button, the color itself is linear, while the
+// text is sRGB since that's what palettes and other tools use. invalid text
+// leaves the color unchanged
+pub fn color_edit(ui: &mut Ui, color: &mut [f32; 3]) -> egui::Response {
+	let mut resp = ui.color_edit_button_rgb(color);
+	let hex = color_to_hex(*color);
+	let id = resp.id.with("color_text");
+
+	let mut entered = None;
+	resp.context_menu(|ui| {
+		ui.horizontal(|ui| {
+			ui.label(format!("Current: {hex}"));
+			if ui.button("Copy").clicked() {
+				ui.output_mut(|o| o.copied_text = hex.clone());
+			}
+		});
+
+		let mut text = ui
+			.data_mut(|d| d.get_temp::<String>(id))
+			.unwrap_or_else(|| hex.clone());
+		let edit = ui.text_edit_singleline(&mut text);
+		let submitted =
+			edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+		let parsed = parse_color(&text);
+		ui.horizontal(|ui| {
+			let apply = ui.add_enabled(parsed.is_some(), egui::Button::new("Apply"));
+			if apply.clicked() || submitted {
+				entered = parsed;
+				ui.close_menu();
+			}
+			if parsed.is_none() {
+				ui.colored_label(ui.visuals().warn_fg_color, "Invalid color");
+			}
+		});
+
+		ui.data_mut(|d| d.insert_temp(id, text));
+	});
+
+	// start from the current value the next time the menu opens
+	if !resp.context_menu_opened() {
+		ui.data_mut(|d| d.remove::<String>(id));
+	}
+
+	if let Some(new) = entered {
+		*color = new;
+		resp.mark_changed();
+	}
+	resp
 }
 
-impl AngleControl for egui::Slider<'_> {
-	fn angle(self) -> Self {
-		self
-			.suffix("°")
-			.custom_formatter(|n, _| n.to_degrees().round().to_string())
-			.custom_parser(|s| s.parse().map(|n: f64| n.to_radians()).ok())
+fn color_to_hex(color: [f32; 3]) -> String {
+	let srgb = Color32::from(egui::Rgba::from_rgb(color[0], color[1], color[2]));
+	format!("#{:02x}{:02x}{:02x}", srgb.r(), srgb.g(), srgb.b())
+}
+
+// `#rrggbb`, `#rgb`, `#rrggbbaa` (the alpha is ignored), the same without the
+// `#`, or `rgb(r, g, b)` in sRGB -> linear color
+fn parse_color(text: &str) -> Option<[f32; 3]> {
+	let text = text.trim();
+
+	let srgb = if let Some(args) =
+		text.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')'))
+	{
+		let channels = args
+			.split(',')
+			.map(|c| c.trim().parse::<u8>().ok())
+			.collect::<Option<Vec<_>>>()?;
+		<[u8; 3]>::try_from(channels).ok()?
+	} else {
+		let hex = text.strip_prefix('#').unwrap_or(text);
+		if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+			return None;
+		}
+		let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+		match hex.len() {
+			3 => {
+				let short = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok();
+				[short(0)? * 17, short(1)? * 17, short(2)? * 17]
+			},
+			6 | 8 => [channel(0)?, channel(2)?, channel(4)?],
+			_ => return None,
+		}
+	};
+
+	let linear = egui::Rgba::from(Color32::from_rgb(srgb[0], srgb[1], srgb[2]));
+	Some([linear.r(), linear.g(), linear.b()])
+}
+// }}}
+
+// {{{ toasts
+const TOAST_DURATION: f32 = 2.5;
+
+// short-lived notifications shown at the bottom of the screen
+#[derive(Default)]
+pub struct Toasts {
+	toasts: Vec<(String, f32)>,
+}
+
+impl Toasts {
+	pub fn add(&mut self, text: impl Into<String>) {
+		self.toasts.push((text.into(), TOAST_DURATION));
+	}
+
+	pub fn active(&self) -> bool {
+		!self.toasts.is_empty()
+	}
+
+	pub fn show(&mut self, egui: &egui::Context, visible: bool) {
+		let dt = egui.input(|i| i.unstable_dt);
+		for (_, time) in &mut self.toasts {
+			*time -= dt;
+		}
+		self.toasts.retain(|(_, time)| *time > 0.0);
+
+		if !visible || self.toasts.is_empty() {
+			return;
+		}
+
+		egui::Area::new(egui::Id::new("toasts"))
+			.anchor(egui::Align2::CENTER_BOTTOM, [0.0, -16.0])
+			.interactable(false)
+			.show(egui, |ui| {
+				for (text, _) in &self.toasts {
+					egui::Frame::popup(ui.style()).show(ui, |ui| {
+						ui.label(text);
+					});
+				}
+			});
 	}
 }
+// }}}
 
-impl AngleControl for egui::DragValue<'_> {
-	fn angle(self) -> Self {
-		self
-			.suffix("°")
-			.custom_formatter(|n, _| n.to_degrees().round().to_string())
-			.custom_parser(|s| s.parse().map(|n: f64| n.to_radians()).ok())
+// contents of a file dropped onto the window. the web gets the contents,
+// native only the path
+pub fn dropped_file_bytes(file: &egui::DroppedFile) -> Result<Vec<u8>, String> {
+	match (&file.bytes, &file.path) {
+		(Some(bytes), _) => Ok(bytes.to_vec()),
+		(None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+		(None, None) => Err("the file is empty".to_owned()),
 	}
 }
 // }}}
+
+// {{{ value noise
+// Smooth 1D noise in [-1, 1]: random values at the integers, interpolated with
+// smoothstep in between. Continuous in `t` and fully determined by `seed`.
+pub fn value_noise(seed: u32, t: f32) -> f32 {
+	let i = t.floor();
+	let f = t - i;
+	let f = f * f * (3.0 - 2.0 * f);
+
+	let a = lattice_value(seed, i as i32);
+	let b = lattice_value(seed, i as i32 + 1);
+	a + (b - a) * f
+}
+
+// two octaves of value noise, less regular than a single one
+pub fn fractal_noise(seed: u32, t: f32) -> f32 {
+	(value_noise(seed, t) * 2.0 + value_noise(seed ^ 0x5bd1_e995, t * 2.0)) / 3.0
+}
+
+fn lattice_value(seed: u32, i: i32) -> f32 {
+	let hash = pcg_hash(i as u32 ^ pcg_hash(seed));
+	hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+// same hash as the noise shader
+pub fn pcg_hash(p: u32) -> u32 {
+	let state = p.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+	let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277_803_737);
+	(word >> 22) ^ word
+}
 // }}}
 
-// slice of nalgebra vectors or matrices -> slice of f32s
-pub fn flatten_matrices<T, const R: usize, const C: usize>(
-	src: &[nalgebra::Matrix<
-		T,
-		Const<R>,
-		Const<C>,
-		nalgebra::ArrayStorage<T, R, C>,
-	>],
-) -> &[T] {
-	unsafe {
-		let ptr = src.as_ptr() as *const T;
-		std::slice::from_raw_parts(ptr, src.len() * R * C)
+// {{{ color grading
+// same as color.glsl, which applies it to the image on screen. hue is in turns
+pub fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+	let max = r.max(g).max(b);
+	let d = max - r.min(g).min(b);
+	let s = if max > 0.0 { d / max } else { 0.0 };
+	if d <= 0.0 {
+		return [0.0, s, max];
 	}
+
+	let h = if max == r {
+		(g - b) / d
+	} else if max == g {
+		(b - r) / d + 2.0
+	} else {
+		(r - g) / d + 4.0
+	};
+	[fract(h / 6.0), s, max]
 }
 
-pub fn fill_50<T: Copy + Default>(sl: &[T]) -> [T; 50] {
-	let mut a: [T; 50] = [T::default(); 50];
-	a[0..sl.len()].copy_from_slice(sl);
-	a
+pub fn hsv_to_rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+	[1.0, 2.0 / 3.0, 1.0 / 3.0].map(|k| {
+		let p = ((fract(h + k) * 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0);
+		v * (1.0 + (p - 1.0) * s)
+	})
 }
 
+// GLSL's, which rounds down. `f32::fract` rounds towards zero
+fn fract(x: f32) -> f32 {
+	x - x.floor()
+}
+
+// `grading` is the hue shift in turns, then the saturation, value and
+// contrast multipliers. works on display (sRGB) values in [0, 1]
+pub fn grade(color: [f32; 3], [hue, sat, value, contrast]: [f32; 4]) -> [f32; 3] {
+	let [h, s, v] = rgb_to_hsv(color);
+	let hsv = [fract(h + hue), (s * sat).clamp(0.0, 1.0), v * value];
+	hsv_to_rgb(hsv).map(|c| ((c - 0.5) * contrast + 0.5).clamp(0.0, 1.0))
+}
+// }}}
+
+// {{{ focal length
+// 35mm-equivalent, the fov is vertical so it's measured against the 24mm side
+// of a 36×24mm frame
+const FRAME_HEIGHT_MM: f32 = 24.0;
+
+// radians -> mm
+pub fn fov_to_focal_length(fov: f32) -> f32 {
+	FRAME_HEIGHT_MM / 2.0 / (fov / 2.0).tan()
+}
+
+// mm -> radians
+pub fn focal_length_to_fov(focal_length: f32) -> f32 {
+	2.0 * (FRAME_HEIGHT_MM / 2.0 / focal_length).atan()
+}
+// }}}
+
 // for objects that need to "reset" to a non-`Default` state
 pub trait Reset where Self: Sized {
 	fn reset_state() -> Self;
@@ -134,3 +321,262 @@ pub trait UpdateResponse {
 		// }
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// {{{ colors
+	#[test]
+	fn hex_round_trips() {
+		for hex in ["#000000", "#ffffff", "#336699", "#0a7f3c", "#fe01b2"] {
+			let color = parse_color(hex).unwrap();
+			assert_eq!(color_to_hex(color), hex);
+		}
+	}
+
+	#[test]
+	fn short_and_alpha_hex() {
+		assert_eq!(color_to_hex(parse_color("#369").unwrap()), "#336699");
+		assert_eq!(color_to_hex(parse_color("fA0").unwrap()), "#ffaa00");
+		assert_eq!(color_to_hex(parse_color("#33669980").unwrap()), "#336699");
+		assert_eq!(parse_color("#336699ff"), parse_color("#336699"));
+	}
+
+	#[test]
+	fn rgb_function() {
+		let color = parse_color(" rgb(51, 102, 153) ").unwrap();
+		assert_eq!(color_to_hex(color), "#336699");
+	}
+
+	#[test]
+	fn hex_is_srgb() {
+		// mid grey in sRGB is darker in linear
+		let [r, g, b] = parse_color("#808080").unwrap();
+		assert!((r - 0.2158).abs() < 1e-3);
+		assert_eq!((r, r), (g, b));
+	}
+
+	#[test]
+	fn malformed_colors_are_rejected() {
+		for text in [
+			"",
+			"#",
+			"#12",
+			"#1234",
+			"#12345",
+			"#1234567",
+			"#123456789",
+			"#ggg",
+			"#12345z",
+			"#+1+2+3",
+			"#１２３",
+			"rgb(1, 2)",
+			"rgb(1, 2, 3, 4)",
+			"rgb(256, 0, 0)",
+			"rgb(-1, 0, 0)",
+			"rgb(1, 2, 3",
+			"red",
+		] {
+			assert_eq!(parse_color(text), None, "{text:?}");
+		}
+	}
+	// }}}
+
+	// {{{ color grading
+	fn assert_close(a: [f32; 3], b: [f32; 3]) {
+		let close = a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-5);
+		assert!(close, "{a:?} != {b:?}");
+	}
+
+	const PRIMARIES: [([f32; 3], f32); 6] = [
+		([1.0, 0.0, 0.0], 0.0),
+		([1.0, 1.0, 0.0], 1.0 / 6.0),
+		([0.0, 1.0, 0.0], 2.0 / 6.0),
+		([0.0, 1.0, 1.0], 3.0 / 6.0),
+		([0.0, 0.0, 1.0], 4.0 / 6.0),
+		([1.0, 0.0, 1.0], 5.0 / 6.0),
+	];
+
+	#[test]
+	fn primaries_have_known_hues() {
+		for (rgb, hue) in PRIMARIES {
+			assert_close(rgb_to_hsv(rgb), [hue, 1.0, 1.0]);
+			assert_close(hsv_to_rgb([hue, 1.0, 1.0]), rgb);
+			// darker and paler
+			let pale = rgb.map(|c| 0.25 + c * 0.5);
+			assert_close(rgb_to_hsv(pale), [hue, 2.0 / 3.0, 0.75]);
+		}
+	}
+
+	#[test]
+	fn greys_have_no_hue_or_saturation() {
+		for v in [0.0, 0.2, 0.5, 1.0] {
+			assert_eq!(rgb_to_hsv([v; 3]), [0.0, 0.0, v]);
+			// any hue
+			for h in [0.0, 0.3, 0.9] {
+				assert_close(hsv_to_rgb([h, 0.0, v]), [v; 3]);
+			}
+		}
+	}
+
+	#[test]
+	fn hue_wraps_at_a_full_turn() {
+		// 360° is red again, so is -360°
+		for h in [1.0, 2.0, -1.0] {
+			assert_close(hsv_to_rgb([h, 1.0, 1.0]), [1.0, 0.0, 0.0]);
+		}
+		// hues below zero count back from a full turn, like GLSL's fract
+		assert_close(hsv_to_rgb([-0.25, 1.0, 1.0]), hsv_to_rgb([0.75, 1.0, 1.0]));
+		assert_close(
+			hsv_to_rgb([-1.0 / 6.0, 0.5, 0.8]),
+			hsv_to_rgb([5.0 / 6.0, 0.5, 0.8]),
+		);
+
+		// just below red
+		let [h, ..] = rgb_to_hsv([1.0, 0.0, 0.01]);
+		assert!(h > 0.99 && h < 1.0, "{h}");
+	}
+
+	#[test]
+	fn hsv_round_trips() {
+		let steps = [0.0, 0.1, 0.35, 0.5, 0.8, 1.0];
+		for r in steps {
+			for g in steps {
+				for b in steps {
+					assert_close(hsv_to_rgb(rgb_to_hsv([r, g, b])), [r, g, b]);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn grading_shifts_hues() {
+		let neutral = [0.0, 1.0, 1.0, 1.0];
+		assert_close(grade([0.2, 0.4, 0.6], neutral), [0.2, 0.4, 0.6]);
+
+		for (shift, rgb) in [
+			(1.0 / 3.0, [0.0, 1.0, 0.0]),
+			(-1.0 / 3.0, [0.0, 0.0, 1.0]),
+			(1.0, [1.0, 0.0, 0.0]),
+			(-2.0, [1.0, 0.0, 0.0]),
+		] {
+			assert_close(grade([1.0, 0.0, 0.0], [shift, 1.0, 1.0, 1.0]), rgb);
+		}
+	}
+
+	#[test]
+	fn grading_saturation_value_and_contrast() {
+		// no saturation leaves the brightest channel
+		assert_close(grade([0.8, 0.4, 0.2], [0.0, 0.0, 1.0, 1.0]), [0.8; 3]);
+		assert_close(
+			grade([0.8, 0.4, 0.2], [0.0, 1.0, 0.5, 1.0]),
+			[0.4, 0.2, 0.1],
+		);
+		// contrast spreads around middle grey and clamps
+		assert_close(
+			grade([0.6, 0.5, 0.2], [0.0, 1.0, 1.0, 2.0]),
+			[0.7, 0.5, 0.0],
+		);
+		assert_close(
+			grade([1.0, 0.5, 0.5], [0.0, 2.0, 2.0, 1.0]),
+			[1.0, 0.0, 0.0],
+		);
+	}
+	// }}}
+
+	// {{{ focal length
+	fn assert_degrees(radians: f32, degrees: f32) {
+		let d = radians.to_degrees();
+		assert!((d - degrees).abs() < 0.01, "{d}° != {degrees}°");
+	}
+
+	#[test]
+	fn focal_lengths_have_known_fovs() {
+		// a normal lens, 39.6° across the 36mm side
+		let fov = focal_length_to_fov(50.0);
+		assert_degrees(fov, 26.99);
+		let horizontal = 2.0 * ((fov / 2.0).tan() * 36.0 / FRAME_HEIGHT_MM).atan();
+		assert_degrees(horizontal, 39.6);
+
+		// half the frame height away covers 90°
+		assert_degrees(focal_length_to_fov(12.0), 90.0);
+		assert_degrees(focal_length_to_fov(24.0), 53.13);
+		assert_degrees(focal_length_to_fov(200.0), 6.87);
+		assert!((fov_to_focal_length(90f32.to_radians()) - 12.0).abs() < 1e-4);
+	}
+
+	#[test]
+	fn focal_lengths_round_trip() {
+		for mm in [8.0, 14.0, 35.0, 50.0, 85.0, 300.0] {
+			let back = fov_to_focal_length(focal_length_to_fov(mm));
+			assert!((back - mm).abs() < mm * 1e-5, "{mm}mm -> {back}mm");
+		}
+		for degrees in [10.0, 50.0, 70.0, 120.0, 170.0] {
+			let fov = f32::to_radians(degrees);
+			assert_degrees(focal_length_to_fov(fov_to_focal_length(fov)), degrees);
+		}
+	}
+
+	#[test]
+	fn longer_lenses_see_less() {
+		let fovs: Vec<_> = (1..100).map(|mm| focal_length_to_fov(mm as f32)).collect();
+		assert!(fovs.windows(2).all(|w| w[1] < w[0]));
+		assert!(fovs
+			.iter()
+			.all(|&fov| fov > 0.0 && fov < std::f32::consts::PI));
+	}
+	// }}}
+
+	// {{{ noise
+	#[test]
+	fn pcg_hash_is_fixed() {
+		// the shaders use the same constants, a change here breaks that match
+		assert_eq!(pcg_hash(0), 129_708_002);
+		assert_eq!(pcg_hash(1), 2_831_084_092);
+		assert_ne!(pcg_hash(1), pcg_hash(2));
+	}
+
+	#[test]
+	fn noise_is_deterministic() {
+		for t in [-12.3, -0.5, 0.0, 0.25, 7.75, 1000.1] {
+			assert_eq!(value_noise(7, t), value_noise(7, t));
+			assert_eq!(fractal_noise(7, t), fractal_noise(7, t));
+		}
+		let differs =
+			(0..10).any(|i| value_noise(1, i as f32) != value_noise(2, i as f32));
+		assert!(differs, "the seed should change the noise");
+	}
+
+	#[test]
+	fn noise_stays_in_range() {
+		for i in -400..400 {
+			let t = i as f32 * 0.137;
+			assert!((-1.0..=1.0).contains(&value_noise(3, t)), "{t}");
+			assert!((-1.0..=1.0).contains(&fractal_noise(3, t)), "{t}");
+		}
+	}
+
+	// smoothstepped between lattice points, so small steps make small changes,
+	// also across the lattice points themselves
+	#[test]
+	fn noise_is_continuous() {
+		const STEP: f32 = 1e-3;
+		for i in -100..100 {
+			for t in [i as f32 * 0.31, i as f32] {
+				for noise in [value_noise, fractal_noise] {
+					let change = (noise(5, t + STEP) - noise(5, t - STEP)).abs();
+					assert!(change < 0.02, "jump of {change} at {t}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn value_noise_hits_the_lattice() {
+		for i in -5..5 {
+			assert_eq!(value_noise(9, i as f32), lattice_value(9, i));
+		}
+	}
+	// }}}
+}
@@ -0,0 +1,373 @@
+use egui::{Pos2, Rect};
+use nalgebra_glm as glm;
+
+use crate::camera::Camera;
+
+// Converts between the coordinate spaces of the viewport. Everything that maps
+// the pointer onto the render goes through this, so that it agrees with the
+// size of the render targets set up by the paint callback.
+// - points: egui's coordinates, the viewport is `rect`
+// - pixels: physical pixels, `pixels_per_point` of them to a point
+// - texels: render target coordinates, origin at the bottom left like GL
+// - NDC: [-1, 1] on both axes, y up
+// In stereo the render is two views side by side. NDC are of a single view,
+// points on either half map to the left one and back.
+#[derive(Clone, Copy, Debug)]
+pub struct ViewportMapping {
+	pub rect: Rect,
+	texels_per_point: egui::Vec2,
+	limited: bool, // by `with_texture_limit`
+	stereo: bool,
+}
+
+impl Default for ViewportMapping {
+	fn default() -> Self {
+		Self::new(Rect::ZERO, 1.0)
+	}
+}
+
+impl ViewportMapping {
+	// the render targets have a texel for every pixel, unless limited
+	pub fn new(rect: Rect, pixels_per_point: f32) -> Self {
+		Self {
+			rect,
+			texels_per_point: egui::Vec2::splat(pixels_per_point),
+			limited: false,
+			stereo: false,
+		}
+	}
+
+	pub fn with_stereo(mut self, stereo: bool) -> Self {
+		self.stereo = stereo;
+		self
+	}
+
+	// the part of the viewport NDC are relative to
+	fn view_rect(&self) -> Rect {
+		let mut rect = self.rect;
+		if self.stereo {
+			rect.set_width(rect.width() / 2.0);
+		}
+		rect
+	}
+
+	// keeps both sides of the render targets within `max` texels, the render
+	// is upscaled to the viewport instead
+	pub fn with_texture_limit(mut self, max: u32) -> Self {
+		let size = self.texture_size();
+		let largest = size.x.max(size.y);
+		if largest > max as f32 {
+			self.texels_per_point *= max as f32 / largest;
+			self.limited = true;
+		}
+		self
+	}
+
+	// render targets of exactly this size, stretched to the viewport. the
+	// half texel keeps the truncation in `texture_size` from losing one
+	pub fn with_texture_size(mut self, [w, h]: [u32; 2]) -> Self {
+		let size = self.rect.size();
+		if size.x <= 0.0 || size.y <= 0.0 {
+			return self;
+		}
+		self.texels_per_point =
+			egui::vec2((w as f32 + 0.5) / size.x, (h as f32 + 0.5) / size.y);
+		self.limited = false;
+		self
+	}
+
+	pub fn limited(&self) -> bool {
+		self.limited
+	}
+
+	// the largest centered region of `bounds` with the given aspect ratio
+	// (width / height), or all of it if there's none
+	pub fn letterboxed(
+		bounds: Rect,
+		aspect: Option<f32>,
+		pixels_per_point: f32,
+	) -> Self {
+		let Some(aspect) = aspect else {
+			return Self::new(bounds, pixels_per_point);
+		};
+
+		let size = bounds.size();
+		let size = if size.x / size.y > aspect {
+			egui::vec2(size.y * aspect, size.y)
+		} else {
+			egui::vec2(size.x, size.x / aspect)
+		};
+
+		// whole pixels so that the edges don't blur against the overlay
+		let min = (bounds.center() - size / 2.0).to_vec2() * pixels_per_point;
+		let min = min.round() / pixels_per_point;
+		let size = (size * pixels_per_point).floor() / pixels_per_point;
+		Self::new(Rect::from_min_size(min.to_pos2(), size), pixels_per_point)
+	}
+
+	// size of the render targets. in stereo the width is an even number of
+	// whole texels, so that both views are the same whole texels wide
+	pub fn texture_size(&self) -> glm::Vec2 {
+		let mut size = self.rect.size() * self.texels_per_point;
+		if self.stereo {
+			size.x = (size.x / 2.0).floor() * 2.0;
+		}
+		glm::vec2(size.x, size.y)
+	}
+
+	// size of a single view in the render targets
+	pub fn view_texture_size(&self) -> glm::Vec2 {
+		let mut size = self.texture_size();
+		if self.stereo {
+			size.x /= 2.0;
+		}
+		size
+	}
+
+	pub fn contains(&self, p: Pos2) -> bool {
+		self.rect.contains(p)
+	}
+
+	pub fn point_to_ndc(&self, p: Pos2) -> glm::Vec2 {
+		let rect = self.view_rect();
+		let mut p = p;
+		if p.x >= rect.max.x && self.stereo {
+			p.x -= rect.width();
+		}
+
+		let uv = (p - rect.min) / rect.size();
+		glm::vec2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0)
+	}
+
+	pub fn ndc_to_point(&self, ndc: glm::Vec2) -> Pos2 {
+		let rect = self.view_rect();
+		Pos2::new(
+			rect.min.x + (ndc.x + 1.0) / 2.0 * rect.width(),
+			rect.min.y + (1.0 - ndc.y) / 2.0 * rect.height(),
+		)
+	}
+
+	// world space point -> point, none if it's behind the camera
+	pub fn project(&self, camera: &Camera, p: glm::Vec3) -> Option<Pos2> {
+		Some(self.ndc_to_point(camera.project(p)?))
+	}
+
+	// texel containing a point, clamped to the render targets. in stereo
+	// it's in the left view
+	pub fn point_to_texel(&self, p: Pos2) -> [i32; 2] {
+		// same truncation as the texture allocation
+		let size = self.view_texture_size();
+		let (w, h) = (size.x as i32, size.y as i32);
+
+		let mut local = (p - self.rect.min) * self.texels_per_point;
+		if self.stereo && local.x >= size.x {
+			local.x -= size.x;
+		}
+		[
+			(local.x as i32).clamp(0, (w - 1).max(0)),
+			(h - 1 - local.y as i32).clamp(0, (h - 1).max(0)),
+		]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mapping() -> ViewportMapping {
+		ViewportMapping::new(
+			Rect::from_min_size(Pos2::new(100.0, 50.0), egui::vec2(400.0, 200.0)),
+			1.0,
+		)
+	}
+
+	fn close(a: glm::Vec2, b: glm::Vec2) -> bool {
+		(a - b).norm() < 1e-5
+	}
+
+	#[test]
+	fn corners_map_to_ndc() {
+		let m = mapping();
+		assert!(close(
+			m.point_to_ndc(m.rect.left_top()),
+			glm::vec2(-1.0, 1.0)
+		));
+		assert!(close(
+			m.point_to_ndc(m.rect.right_bottom()),
+			glm::vec2(1.0, -1.0)
+		));
+		assert!(close(m.point_to_ndc(m.rect.center()), glm::vec2(0.0, 0.0)));
+	}
+
+	#[test]
+	fn ndc_round_trip() {
+		let m = mapping();
+		for p in [
+			Pos2::new(100.0, 50.0),
+			Pos2::new(321.5, 77.25),
+			Pos2::new(499.0, 249.0),
+		] {
+			let back = m.ndc_to_point(m.point_to_ndc(p));
+			assert!((back - p).length() < 1e-3, "{p:?} came back as {back:?}");
+		}
+	}
+
+	#[test]
+	fn texels_are_gl_ordered_and_clamped() {
+		let m = mapping();
+		assert_eq!(m.texture_size(), glm::vec2(400.0, 200.0));
+
+		// origin at the bottom left
+		assert_eq!(m.point_to_texel(Pos2::new(100.0, 249.5)), [0, 0]);
+		assert_eq!(m.point_to_texel(Pos2::new(100.0, 50.0)), [0, 199]);
+		assert_eq!(m.point_to_texel(Pos2::new(499.5, 50.0)), [399, 199]);
+
+		// outside the viewport sticks to the edge
+		assert_eq!(m.point_to_texel(Pos2::new(0.0, 1000.0)), [0, 0]);
+		assert_eq!(m.point_to_texel(Pos2::new(1000.0, 0.0)), [399, 199]);
+	}
+
+	#[test]
+	fn texture_limit_scales_texels() {
+		let m = mapping().with_texture_limit(100);
+		assert!(m.limited());
+		assert_eq!(m.texture_size(), glm::vec2(100.0, 50.0));
+		assert_eq!(m.point_to_texel(m.rect.right_top()), [99, 49]);
+
+		let m = mapping().with_texture_limit(1000);
+		assert!(!m.limited());
+	}
+
+	#[test]
+	fn fixed_texture_size_is_stretched() {
+		// a final render at half the size of the viewport
+		let m = mapping().with_texture_size([200, 100]);
+		let size = m.texture_size();
+		assert_eq!((size.x as u32, size.y as u32), (200, 100));
+		assert_eq!(m.point_to_texel(m.rect.left_bottom()), [0, 0]);
+		assert_eq!(m.point_to_texel(m.rect.right_top()), [199, 99]);
+		assert_eq!(m.point_to_texel(m.rect.center()), [100, 49]);
+
+		// the pointer still maps to the same ndc
+		assert!(close(m.point_to_ndc(m.rect.center()), glm::vec2(0.0, 0.0)));
+	}
+
+	#[test]
+	fn stereo_views_are_whole_texels() {
+		// 401 texels wide don't split in two
+		let m = mapping().with_texture_size([401, 100]).with_stereo(true);
+		let size = m.texture_size();
+		assert_eq!((size.x as u32, size.y as u32), (400, 100));
+		let view = m.view_texture_size();
+		assert_eq!((view.x as u32, view.y as u32), (200, 100));
+
+		// the same point in either view is the same texel
+		let left = Pos2::new(150.0, 100.0);
+		let right = left + egui::vec2(200.0, 0.0);
+		assert_eq!(m.point_to_texel(left), m.point_to_texel(right));
+		assert_eq!(m.point_to_texel(m.rect.right_top())[0], 199);
+	}
+
+	// {{{ pixels per point
+	fn scaled(pixels_per_point: f32) -> ViewportMapping {
+		ViewportMapping::new(mapping().rect, pixels_per_point)
+	}
+
+	#[test]
+	fn render_targets_have_a_texel_per_pixel() {
+		for (scale, size) in [(1.0, [400, 200]), (1.5, [600, 300]), (2.0, [800, 400])] {
+			let texture = scaled(scale).texture_size();
+			assert_eq!([texture.x as u32, texture.y as u32], size, "at {scale}");
+		}
+	}
+
+	#[test]
+	fn points_map_to_texels_at_any_scale() {
+		// a point past the top left corner, and the bottom right point
+		let p = Pos2::new(110.0, 60.0);
+		let last = Pos2::new(499.5, 249.5);
+		for (scale, at_p, at_last) in [
+			(1.0, [10, 189], [399, 0]),
+			(1.5, [15, 284], [599, 0]),
+			(2.0, [20, 379], [799, 0]),
+		] {
+			let m = scaled(scale);
+			assert_eq!(m.point_to_texel(p), at_p, "at {scale}");
+			assert_eq!(m.point_to_texel(last), at_last, "at {scale}");
+			assert_eq!(m.point_to_texel(m.rect.left_bottom()), [0, 0], "at {scale}");
+
+			// the center of a texel maps back to a point inside it
+			let [x, y] = at_p;
+			let h = m.texture_size().y;
+			let center = Pos2::new(
+				m.rect.min.x + (x as f32 + 0.5) / scale,
+				m.rect.min.y + (h - y as f32 - 0.5) / scale,
+			);
+			assert_eq!(m.point_to_texel(center), at_p, "at {scale}");
+
+			// and ndc don't depend on it
+			assert!(close(m.point_to_ndc(m.rect.center()), glm::vec2(0.0, 0.0)));
+		}
+	}
+
+	#[test]
+	fn scaled_texture_limit() {
+		// 800×400 pixels at 2.0, halved to fit
+		let m = scaled(2.0).with_texture_limit(400);
+		assert!(m.limited());
+		assert_eq!(m.texture_size(), glm::vec2(400.0, 200.0));
+		assert_eq!(m.point_to_texel(Pos2::new(110.0, 60.0)), [10, 189]);
+	}
+
+	#[test]
+	fn letterboxing_snaps_to_pixels() {
+		let bounds = Rect::from_min_size(Pos2::ZERO, egui::vec2(801.0, 400.0));
+		let m = ViewportMapping::letterboxed(bounds, Some(1.0), 1.5);
+		// 200.5 points in is 300.75 pixels, rounded to 301
+		for v in [m.rect.min.x, m.rect.min.y, m.rect.width(), m.rect.height()] {
+			let pixels = v * 1.5;
+			assert!((pixels - pixels.round()).abs() < 1e-3, "{v} points");
+		}
+		assert!((m.rect.min.x - 301.0 / 1.5).abs() < 1e-3);
+		assert_eq!(m.texture_size(), glm::vec2(600.0, 600.0));
+	}
+	// }}}
+
+	#[test]
+	fn contains() {
+		let m = mapping();
+		assert!(m.contains(Pos2::new(100.0, 50.0)));
+		assert!(m.contains(Pos2::new(300.0, 150.0)));
+		assert!(!m.contains(Pos2::new(99.0, 150.0)));
+		assert!(!m.contains(Pos2::new(300.0, 251.0)));
+	}
+
+	#[test]
+	fn letterboxing() {
+		let bounds = Rect::from_min_size(Pos2::ZERO, egui::vec2(800.0, 400.0));
+
+		// wider bounds than the ratio leave bars on the sides
+		let m = ViewportMapping::letterboxed(bounds, Some(1.0), 1.0);
+		assert_eq!(
+			m.rect,
+			Rect::from_min_size(Pos2::new(200.0, 0.0), egui::vec2(400.0, 400.0))
+		);
+
+		// taller ones above and below
+		let m = ViewportMapping::letterboxed(bounds, Some(4.0), 1.0);
+		assert_eq!(
+			m.rect,
+			Rect::from_min_size(Pos2::new(0.0, 100.0), egui::vec2(800.0, 200.0))
+		);
+
+		// the bars aren't part of the viewport
+		assert!(!m.contains(Pos2::new(400.0, 50.0)));
+		assert!(close(
+			m.point_to_ndc(Pos2::new(400.0, 100.0)),
+			glm::vec2(0.0, 1.0)
+		));
+
+		// no ratio is all of it
+		assert_eq!(ViewportMapping::letterboxed(bounds, None, 1.0).rect, bounds);
+	}
+}